@@ -0,0 +1,154 @@
+//! hreflang alternates (`<link rel="alternate" hreflang=…>`) and their optional sub-runs.
+
+use crate::{run_one, Cli};
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Serialize, Clone)]
+pub struct Alternate {
+    pub hreflang: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    url: &'a str,
+    captured_at: String,
+    alternates: &'a [Alternate],
+}
+
+/// hreflang alternates declared in the saved HTML, resolved against `base`.
+pub fn from_html_file(html_path: &Path, base: &str) -> Vec<Alternate> {
+    let html = std::fs::read_to_string(html_path).unwrap_or_default();
+    let base = url::Url::parse(base).ok();
+    let mut out: Vec<Alternate> = Vec::new();
+    for (hreflang, href) in link_alternates(&html) {
+        let url = match &base {
+            Some(b) => match b.join(&href) {
+                Ok(u) => u.to_string(),
+                Err(_) => continue,
+            },
+            None => href,
+        };
+        if out.iter().any(|a| a.hreflang == hreflang && a.url == url) {
+            continue;
+        }
+        out.push(Alternate {
+            hreflang,
+            url,
+            result_json: None,
+            exit_code: None,
+            error: None,
+        });
+    }
+    out
+}
+
+/// Render every alternate into `<run_dir>/alternates/<hreflang>/` and write
+/// `alternates.json` linking the sub-runs. Returns the manifest path.
+pub async fn capture(
+    args: &Cli,
+    run_dir: &Path,
+    page_url: &str,
+    alternates: &mut [Alternate],
+) -> Result<String> {
+    let dir = run_dir.join("alternates");
+    for alt in alternates.iter_mut() {
+        // The page usually lists itself as one of its alternates.
+        if alt.url == page_url {
+            continue;
+        }
+        let mut sub = args.clone();
+        sub.url = Some(alt.url.clone());
+        sub.run_dir = Some(dir.join(dir_name(&alt.hreflang)));
+        sub.capture_alternates = false;
+        sub.golden = None;
+        match Box::pin(run_one(&sub)).await {
+            Ok(rec) => {
+                alt.result_json = Some(rec.result_json.display().to_string());
+                alt.exit_code = Some(rec.exit_code);
+            }
+            Err(e) => alt.error = Some(format!("{:#}", e)),
+        }
+    }
+    let manifest = run_dir.join("alternates.json");
+    std::fs::write(
+        &manifest,
+        serde_json::to_vec_pretty(&Manifest {
+            url: page_url,
+            captured_at: chrono::Local::now().to_rfc3339(),
+            alternates,
+        })?,
+    )?;
+    Ok(manifest.display().to_string())
+}
+
+/// (hreflang, href) pairs of `<link rel="alternate" hreflang=… href=…>` tags.
+fn link_alternates(html: &str) -> Vec<(String, String)> {
+    let lower = html.to_ascii_lowercase();
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = lower[pos..].find("<link") {
+        let start = pos + i;
+        let end = lower[start..]
+            .find('>')
+            .map(|e| start + e)
+            .unwrap_or(lower.len());
+        pos = end.max(start + 5);
+        let tag = &html[start..end];
+        let rel = tag_attr(tag, "rel")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !rel.split_whitespace().any(|r| r == "alternate") {
+            continue;
+        }
+        if let (Some(lang), Some(href)) = (tag_attr(tag, "hreflang"), tag_attr(tag, "href")) {
+            if !lang.is_empty() && !href.is_empty() {
+                out.push((lang.to_string(), href.replace("&amp;", "&")));
+            }
+        }
+    }
+    out
+}
+
+/// Value of attribute `name` in a raw start tag (quoted or bare).
+fn tag_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let at = from + i;
+        from = at + name.len();
+        let boundary = lower[..at]
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = lower[from..].trim_start();
+        if !boundary || !rest.starts_with('=') {
+            continue;
+        }
+        let value_at = tag.len() - rest.len() + 1;
+        let raw = tag[value_at..].trim_start();
+        return Some(match raw.chars().next() {
+            Some(q @ ('"' | '\'')) => raw[1..].split(q).next().unwrap_or(""),
+            _ => raw
+                .split(|c: char| c.is_ascii_whitespace())
+                .next()
+                .unwrap_or(""),
+        });
+    }
+    None
+}
+
+fn dir_name(hreflang: &str) -> String {
+    hreflang
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
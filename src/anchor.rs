@@ -0,0 +1,73 @@
+//! Fragment targeting: for `https://example.com/page#section` the capture starts at the
+//! `#section` element instead of the top of the page.
+//!
+//! The target is scrolled to the top of the viewport for the screenshot and marked with
+//! `data-ankabot-anchor` so `--full-page` captures start there too. For the PDF, a
+//! print-only style hides everything laid out before it.
+
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Anchor {
+    /// The fragment as it appears in the URL, without `#`.
+    pub fragment: String,
+    /// Whether an element with that id (or `<a name>`) was on the page.
+    pub found: bool,
+}
+
+/// Marks the target and every element before it in document order (the earlier siblings
+/// of the target and of each ancestor), then scrolls the target into view.
+const TARGET_JS: &str = r#"((frag) => {
+  let decoded = frag;
+  try { decoded = decodeURIComponent(frag); } catch (e) {}
+  const el = document.getElementById(frag) || document.getElementById(decoded)
+    || document.getElementsByName(decoded)[0];
+  if (!el) return false;
+  el.setAttribute('data-ankabot-anchor', '');
+  for (let node = el; node && node !== document.body; node = node.parentElement) {
+    for (let sib = node.previousElementSibling; sib; sib = sib.previousElementSibling) {
+      sib.setAttribute('data-ankabot-before-anchor', '');
+    }
+  }
+  const style = document.createElement('style');
+  style.textContent = '@media print { [data-ankabot-before-anchor] { display: none !important; } }';
+  (document.head || document.documentElement).appendChild(style);
+  el.scrollIntoView({ block: 'start', behavior: 'instant' });
+  return true;
+})"#;
+
+/// Document offset of the marked anchor in CSS px, or 0 without one.
+pub const TOP_JS: &str = r#"(() => {
+  const el = document.querySelector('[data-ankabot-anchor]');
+  return el ? el.getBoundingClientRect().top + window.scrollY : 0;
+})()"#;
+
+/// The fragment worth targeting in `url`: not hash-routes (`#/inbox`, `#!/inbox`) or text
+/// fragments (`#:~:text=`), which aren't element ids.
+fn fragment(url: &str) -> Option<String> {
+    let frag = url::Url::parse(url).ok()?.fragment()?.to_string();
+    (!frag.is_empty() && !frag.starts_with(['/', '!', ':'])).then_some(frag)
+}
+
+/// Scroll to the fragment of `url` and set up the captures to start there.
+pub fn target(tab: &headless_chrome::Tab, url: &str) -> Result<Option<Anchor>> {
+    let Some(fragment) = fragment(url) else {
+        return Ok(None);
+    };
+    let found = tab
+        .evaluate(
+            &format!("{}({})", TARGET_JS, serde_json::to_string(&fragment)?),
+            false,
+        )?
+        .value
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !found {
+        eprintln!(
+            "warning: anchor #{} not found; capturing from the top",
+            fragment
+        );
+    }
+    Ok(Some(Anchor { fragment, found }))
+}
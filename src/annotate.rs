@@ -0,0 +1,158 @@
+//! `--annotate`: a copy of the screenshot with the elements a reviewer looks for first
+//! boxed and labeled: consent banners, CAPTCHAs, login forms and the main content.
+//!
+//! The boxes are drawn by the page itself, as a temporary overlay like the screenshot
+//! stamp, so labels use real fonts and `--full-page` captures need no coordinate mapping.
+//! Regions are reported in CSS px, relative to the document (or to the viewport for
+//! fixed-position elements).
+
+use crate::Cli;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    ConsentBanner,
+    Captcha,
+    LoginForm,
+    MainContent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Region {
+    pub kind: Kind,
+    /// Short description of the element, e.g. `div#onetrust-banner-sdk`.
+    pub element: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Fixed-position element: coordinates are relative to the viewport.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub fixed: bool,
+}
+
+/// Finds the regions and draws them into `#__ankabot_annotations`. Consent and CAPTCHA
+/// selectors cover the common CMPs and the vendors `block` knows about.
+const DETECT_JS: &str = r#"(() => {
+  const CONSENT = [
+    '#onetrust-banner-sdk', '#CybotCookiebotDialog', '#didomi-host', '#usercentrics-root',
+    '.qc-cmp2-container', '#truste-consent-track', '#cookie-law-info-bar', '.cc-window',
+    '#cmpbox', '.fc-consent-root', '[id*="cookie" i][id*="banner" i]',
+    '[class*="cookie-banner" i]', '[class*="cookie-consent" i]', '[id*="consent" i]',
+    '[class*="consent-banner" i]', '[aria-label*="cookie" i]',
+  ];
+  const CAPTCHA = [
+    'iframe[src*="recaptcha"]', 'iframe[src*="hcaptcha.com"]',
+    'iframe[src*="challenges.cloudflare.com"]', 'iframe[src*="captcha-delivery.com"]',
+    '.g-recaptcha', '.h-captcha', '.cf-turnstile', '#px-captcha', '[id*="captcha" i]',
+  ];
+  const COLORS = {
+    consent_banner: '#d97706', captcha: '#dc2626', login_form: '#2563eb', main_content: '#16a34a',
+  };
+  const LABELS = {
+    consent_banner: 'consent banner', captcha: 'CAPTCHA', login_form: 'login form',
+    main_content: 'main content',
+  };
+
+  const visible = (el) => {
+    const r = el.getBoundingClientRect();
+    if (r.width < 4 || r.height < 4) return false;
+    const s = getComputedStyle(el);
+    return s.visibility !== 'hidden' && s.display !== 'none' && +s.opacity !== 0;
+  };
+  const fixed = (el) => {
+    for (let n = el; n && n !== document.documentElement; n = n.parentElement) {
+      const p = getComputedStyle(n).position;
+      if (p === 'fixed' || p === 'sticky') return p === 'fixed';
+    }
+    return false;
+  };
+  const describe = (el) => {
+    let d = el.tagName.toLowerCase();
+    if (el.id) d += '#' + el.id;
+    else if (typeof el.className === 'string' && el.className.trim())
+      d += '.' + el.className.trim().split(/\s+/).slice(0, 2).join('.');
+    return d;
+  };
+  // Matches nested in another match only count once, as the outermost element.
+  const outermost = (els) => els.filter((el) => !els.some((o) => o !== el && o.contains(el)));
+  const all = (sels) => {
+    const found = new Set();
+    for (const sel of sels) {
+      try { document.querySelectorAll(sel).forEach((el) => found.add(el)); } catch (e) {}
+    }
+    return outermost([...found].filter(visible));
+  };
+
+  const consent = all(CONSENT);
+  if (!consent.length) {
+    // Unknown CMPs: an overlay that talks about cookies.
+    for (const el of document.querySelectorAll('body *')) {
+      const p = getComputedStyle(el).position;
+      if ((p === 'fixed' || p === 'sticky') && visible(el)
+          && /cookie|consent|gdpr/i.test(el.innerText || '')) consent.push(el);
+    }
+  }
+  const captcha = all(CAPTCHA);
+  const login = outermost([...document.querySelectorAll('input[type="password"]')]
+    .filter(visible)
+    .map((i) => i.closest('form') || i.parentElement));
+  // Marked by `maincontent::detect`.
+  const main = document.querySelector('[data-ankabot-main]');
+
+  const regions = [];
+  const add = (kind, els, limit) => {
+    for (const el of els.slice(0, limit)) {
+      const r = el.getBoundingClientRect();
+      const f = fixed(el);
+      regions.push({
+        kind, element: describe(el),
+        x: r.left + (f ? 0 : scrollX), y: r.top + (f ? 0 : scrollY),
+        width: r.width, height: r.height, fixed: f,
+      });
+    }
+  };
+  add('main_content', main ? [main] : [], 1);
+  add('login_form', login, 3);
+  add('captcha', captcha, 5);
+  add('consent_banner', outermost(consent), 3);
+
+  const layer = document.createElement('div');
+  layer.id = '__ankabot_annotations';
+  for (const r of regions) {
+    const box = document.createElement('div');
+    box.style.cssText = `position:${r.fixed ? 'fixed' : 'absolute'};left:${r.x}px;top:${r.y}px;` +
+      `width:${r.width}px;height:${r.height}px;box-sizing:border-box;` +
+      `border:3px solid ${COLORS[r.kind]};z-index:2147483647;pointer-events:none;`;
+    const label = document.createElement('span');
+    label.textContent = LABELS[r.kind];
+    label.style.cssText = `position:absolute;left:-3px;top:${r.y < 18 ? 0 : -18}px;` +
+      `padding:1px 6px;font:bold 12px/16px sans-serif;color:#fff;white-space:nowrap;` +
+      `background:${COLORS[r.kind]};`;
+    box.appendChild(label);
+    layer.appendChild(box);
+  }
+  document.documentElement.appendChild(layer);
+  return JSON.stringify(regions);
+})()"#;
+
+/// Screenshot the page with the detected regions drawn on it into `path`; the overlay is
+/// removed afterwards.
+pub fn capture(tab: &headless_chrome::Tab, args: &Cli, path: &Path) -> Result<Vec<Region>> {
+    crate::maincontent::detect(tab)?;
+    let json = tab
+        .evaluate(DETECT_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "[]".to_string());
+    let shot = crate::screenshot(tab, args);
+    tab.evaluate(
+        "document.getElementById('__ankabot_annotations')?.remove()",
+        false,
+    )?;
+    std::fs::write(path, shot?).with_context(|| format!("writing {}", path.display()))?;
+    Ok(serde_json::from_str(&json)?)
+}
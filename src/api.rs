@@ -0,0 +1,84 @@
+//! `--capture-api REGEX`: save the bodies of the XHR/fetch and JSON responses whose URL
+//! matches into `api/`, for SPA data that never reaches the DOM.
+
+use crate::assets;
+use crate::network::{self, NetEntry};
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+
+/// Resource types that carry API traffic; other types count only with a JSON body.
+const API_TYPES: &[&str] = &["XHR", "Fetch", "EventSource"];
+
+#[derive(Serialize)]
+pub struct ApiRecord {
+    pub url: String,
+    pub method: String,
+    pub status: u32,
+    pub mime_type: String,
+    pub file: Option<String>,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn parse_filter(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+/// Write every matching response body into `dir`, numbered in request order, and list
+/// them in `index.json`. Repeated calls (polling) are each kept.
+///
+/// Like `--save-assets`, bodies come from Chrome's network buffer; a body that was
+/// evicted is still listed, with an error.
+pub fn capture(
+    tab: &headless_chrome::Tab,
+    entries: &[NetEntry],
+    filter: &Regex,
+    dir: &Path,
+) -> Result<Vec<ApiRecord>> {
+    std::fs::create_dir_all(dir)?;
+    let mut records = Vec::new();
+    for entry in entries {
+        let Some(resp) = &entry.response else {
+            continue;
+        };
+        let url = &entry.request.url;
+        let is_api = API_TYPES.contains(&entry.resource_type.as_str())
+            || resp.mime_type.to_ascii_lowercase().contains("json");
+        // Redirect hops have no body of their own.
+        if !is_api || (300..400).contains(&resp.status) || !filter.is_match(url) {
+            continue;
+        }
+        let mut rec = ApiRecord {
+            url: url.clone(),
+            method: entry.request.method.clone(),
+            status: resp.status,
+            mime_type: resp.mime_type.clone(),
+            file: None,
+            bytes: 0,
+            error: None,
+        };
+        let body = match &entry.failed {
+            Some(err) => Err(anyhow::anyhow!("{}", err)),
+            None => network::response_body(tab, &entry.request_id),
+        };
+        match body {
+            Ok(data) => {
+                let name = format!(
+                    "{:03}-{}",
+                    records.len() + 1,
+                    assets::file_name(url, &resp.mime_type)
+                );
+                std::fs::write(dir.join(&name), &data)?;
+                rec.bytes = data.len() as u64;
+                rec.file = Some(name);
+            }
+            Err(e) => rec.error = Some(e.to_string()),
+        }
+        records.push(rec);
+    }
+    std::fs::write(dir.join("index.json"), serde_json::to_vec_pretty(&records)?)?;
+    Ok(records)
+}
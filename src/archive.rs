@@ -0,0 +1,107 @@
+//! `--archive`: pack a finished run directory into a single `.zip` or `.tar.gz` next to it,
+//! for shipping captures between machines.
+//!
+//! Entries are stored under the run directory's name, so unpacking recreates it. The file
+//! is written under a temporary name and renamed when complete, so a watcher never picks
+//! up a half-written archive.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Timelike};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Zip,
+    #[value(name = "tar.gz")]
+    TarGz,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::TarGz => "tar.gz",
+        }
+    }
+}
+
+/// Pack `dir` into `<dir>.zip` / `<dir>.tar.gz` and return its path.
+pub fn pack(dir: &Path, format: Format) -> Result<PathBuf> {
+    let name = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("run directory has no name")?;
+    let out = dir.with_file_name(format!("{name}.{}", format.extension()));
+    let partial = dir.with_file_name(format!("{name}.{}.partial", format.extension()));
+    let file = std::fs::File::create(&partial)
+        .with_context(|| format!("creating {}", partial.display()))?;
+
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    match format {
+        Format::Zip => {
+            let mut zip = ZipWriter::new(std::io::BufWriter::new(file));
+            let mut deflated = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .large_file(true);
+            // Zip has no time zone; stamp entries with the local time, as unzip expects.
+            let now = chrono::Local::now();
+            if let Ok(t) = zip::DateTime::from_date_and_time(
+                now.year() as u16,
+                now.month() as u8,
+                now.day() as u8,
+                now.hour() as u8,
+                now.minute() as u8,
+                now.second() as u8,
+            ) {
+                deflated = deflated.last_modified_time(t);
+            }
+            for path in &files {
+                zip.start_file(entry_name(dir, name, path), deflated)?;
+                std::io::copy(&mut std::fs::File::open(path)?, &mut zip)?;
+            }
+            zip.finish()?;
+        }
+        Format::TarGz => {
+            let gz = flate2::write::GzEncoder::new(
+                std::io::BufWriter::new(file),
+                flate2::Compression::default(),
+            );
+            let mut tar = tar::Builder::new(gz);
+            for path in &files {
+                tar.append_path_with_name(path, entry_name(dir, name, path))?;
+            }
+            tar.into_inner()?.finish()?;
+        }
+    }
+    std::fs::rename(&partial, &out).with_context(|| format!("writing {}", out.display()))?;
+    Ok(out)
+}
+
+/// Regular files below `dir`, sorted so archives of identical runs list entries alike.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if kind.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// `name/relative/path`, with forward slashes on every platform.
+fn entry_name(dir: &Path, name: &str, path: &Path) -> String {
+    let rel = path.strip_prefix(dir).unwrap_or(path);
+    let parts: Vec<_> = std::iter::once(name.into())
+        .chain(rel.components().map(|c| c.as_os_str().to_string_lossy()))
+        .collect();
+    parts.join("/")
+}
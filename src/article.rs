@@ -0,0 +1,85 @@
+//! Combine the pages of a paginated article into one document (`--merge-pages`).
+
+use crate::golden::html_to_text;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Concatenate the content region of each saved page into `out_html` (one `<section>` per
+/// page) and its visible text into `out_txt`. Pages that can't be read are skipped.
+pub fn merge_pages(pages: &[PathBuf], out_html: &Path, out_txt: &Path) -> Result<()> {
+    let mut html = String::from(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>Merged article</title></head><body>\n",
+    );
+    let mut text = String::new();
+    let mut last_text = String::new();
+    for (i, path) in pages.iter().enumerate() {
+        let Ok(page) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let region = content_region(&page);
+        let region_text = html_to_text(region);
+        // Some sites serve the full article on every page; don't repeat it.
+        if region_text.is_empty() || region_text == last_text {
+            continue;
+        }
+        html.push_str(&format!(
+            "<section data-page=\"{}\">\n{}\n</section>\n",
+            i + 1,
+            strip_scripts(region)
+        ));
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(&region_text);
+        last_text = region_text;
+    }
+    html.push_str("</body></html>\n");
+    std::fs::write(out_html, html)?;
+    std::fs::write(out_txt, text)?;
+    Ok(())
+}
+
+/// Inner HTML of the first `<article>`, else `<main>`, else `<body>`, else the whole page.
+pub fn content_region(html: &str) -> &str {
+    ["article", "main", "body"]
+        .iter()
+        .find_map(|tag| inner(html, tag))
+        .unwrap_or(html)
+}
+
+fn inner<'a>(html: &'a str, tag: &str) -> Option<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", tag);
+    let mut from = 0;
+    let start = loop {
+        let i = from + lower[from..].find(&open)?;
+        let after = lower
+            .as_bytes()
+            .get(i + open.len())
+            .copied()
+            .unwrap_or(b'>');
+        if after == b'>' || after.is_ascii_whitespace() {
+            break i;
+        }
+        from = i + open.len();
+    };
+    let body_start = start + lower[start..].find('>')? + 1;
+    let end = lower.rfind(&format!("</{}>", tag))?;
+    (end >= body_start).then(|| &html[body_start..end])
+}
+
+/// `html` without its `<script>` elements.
+pub fn strip_scripts(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut i = 0;
+    while let Some(s) = lower[i..].find("<script").map(|s| i + s) {
+        out.push_str(&html[i..s]);
+        i = lower[s..]
+            .find("</script>")
+            .map(|e| s + e + "</script>".len())
+            .unwrap_or(html.len());
+    }
+    out.push_str(&html[i..]);
+    out
+}
@@ -0,0 +1,131 @@
+//! `--save-assets`: a local mirror of the page's subresources (`assets/`).
+
+use crate::network::{self, NetEntry};
+use anyhow::Result;
+use serde::Serialize;
+use std::{collections::HashSet, path::Path};
+
+/// Resource types worth mirroring; documents, XHR and the like are left to the HAR.
+const MIRRORED_TYPES: &[&str] = &["Stylesheet", "Script", "Image", "Font"];
+
+#[derive(Serialize)]
+pub struct AssetRecord {
+    pub url: String,
+    pub file: Option<String>,
+    pub resource_type: String,
+    pub mime_type: String,
+    pub status: u32,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Write every stylesheet, script, image and font the page fetched into `dir` and map
+/// their URLs to file names in `index.json`.
+///
+/// Bodies come from Chrome's network buffer, so nothing is fetched a second time; an
+/// asset whose body was evicted is still listed, with an error.
+pub fn save_assets(
+    tab: &headless_chrome::Tab,
+    entries: &[NetEntry],
+    dir: &Path,
+) -> Result<Vec<AssetRecord>> {
+    std::fs::create_dir_all(dir)?;
+    let mut seen = HashSet::new();
+    let mut records = Vec::new();
+    for entry in entries {
+        let Some(resp) = &entry.response else {
+            continue;
+        };
+        let url = &entry.request.url;
+        if !MIRRORED_TYPES.contains(&entry.resource_type.as_str())
+            || url.starts_with("data:")
+            || !seen.insert(url.clone())
+        {
+            continue;
+        }
+        let mut rec = AssetRecord {
+            url: url.clone(),
+            file: None,
+            resource_type: entry.resource_type.clone(),
+            mime_type: resp.mime_type.clone(),
+            status: resp.status,
+            bytes: 0,
+            error: None,
+        };
+        let body = match &entry.failed {
+            Some(err) => Err(anyhow::anyhow!("{}", err)),
+            None => network::response_body(tab, &entry.request_id),
+        };
+        match body {
+            Ok(data) => {
+                let name = format!(
+                    "{:03}-{}",
+                    records.len() + 1,
+                    file_name(url, &resp.mime_type)
+                );
+                std::fs::write(dir.join(&name), &data)?;
+                rec.bytes = data.len() as u64;
+                rec.file = Some(name);
+            }
+            Err(e) => rec.error = Some(e.to_string()),
+        }
+        records.push(rec);
+    }
+    std::fs::write(dir.join("index.json"), serde_json::to_vec_pretty(&records)?)?;
+    Ok(records)
+}
+
+/// A filesystem-safe name from the URL's last path segment, with an extension guessed
+/// from the MIME type when the URL has none.
+pub fn file_name(url: &str, mime: &str) -> String {
+    let last = url::Url::parse(url)
+        .ok()
+        .and_then(|u| {
+            u.path_segments()
+                .and_then(|mut s| s.next_back().map(|s| s.to_string()))
+        })
+        .unwrap_or_default();
+    let mut name: String = last
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(80)
+        .collect();
+    if name.trim_matches('.').is_empty() {
+        name = "asset".to_string();
+    }
+    if !name.contains('.') {
+        if let Some(ext) = ext_for_mime(mime) {
+            name.push('.');
+            name.push_str(ext);
+        }
+    }
+    name
+}
+
+fn ext_for_mime(mime: &str) -> Option<&'static str> {
+    let m = mime.to_ascii_lowercase();
+    Some(match m.split(';').next().unwrap_or("").trim() {
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" | "application/x-javascript" => "js",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "font/woff2" => "woff2",
+        "font/woff" | "application/font-woff" => "woff",
+        "font/ttf" => "ttf",
+        "font/otf" => "otf",
+        "application/json" => "json",
+        _ => return None,
+    })
+}
@@ -0,0 +1,124 @@
+//! `--axtree`: the accessibility tree Chrome built for the captured page, from
+//! `Accessibility.getFullAXTree`, written as `axtree.json` for automated a11y checks.
+//!
+//! The protocol wraps every value in a typed `AXValue`; here each node is flattened to its
+//! role, name, description, value and a property map of plain JSON values, with parent and
+//! child ids kept so the tree can be walked.
+
+use anyhow::{Context, Result};
+use headless_chrome::protocol::cdp::types::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+#[derive(Serialize)]
+struct Node {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<serde_json::Value>,
+    /// Not exposed to assistive technology; `ignored_reasons` says why.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    ignored: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ignored_reasons: BTreeMap<String, serde_json::Value>,
+    /// `focusable`, `checked`, `level`, `required`, … as reported by Chrome.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, serde_json::Value>,
+    /// The DOM node's backend id (`DOM.BackendNodeId`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dom_node_id: Option<u64>,
+}
+
+/// `Accessibility.getFullAXTree`, deserialized loosely: the generated protocol types use
+/// closed enums for value types and property names, which break on newer Chrome builds.
+#[derive(Serialize, Debug)]
+struct GetFullAxTree {}
+
+impl Method for GetFullAxTree {
+    const NAME: &'static str = "Accessibility.getFullAXTree";
+    type ReturnObject = RawTree;
+}
+
+#[derive(Deserialize, Debug)]
+struct RawTree {
+    nodes: Vec<RawNode>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RawNode {
+    node_id: String,
+    #[serde(default)]
+    ignored: bool,
+    #[serde(default)]
+    ignored_reasons: Vec<RawProperty>,
+    role: Option<RawValue>,
+    name: Option<RawValue>,
+    description: Option<RawValue>,
+    value: Option<RawValue>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+    parent_id: Option<String>,
+    #[serde(default)]
+    child_ids: Vec<String>,
+    #[serde(rename = "backendDOMNodeId")]
+    backend_dom_node_id: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawProperty {
+    name: String,
+    value: RawValue,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawValue {
+    value: Option<serde_json::Value>,
+}
+
+fn props(list: Vec<RawProperty>) -> BTreeMap<String, serde_json::Value> {
+    list.into_iter()
+        .filter_map(|p| Some((p.name, p.value.value?)))
+        .collect()
+}
+
+/// Write the main frame's accessibility tree to `path`.
+pub fn capture(tab: &headless_chrome::Tab, path: &Path) -> Result<()> {
+    let raw = tab.call_method(GetFullAxTree {})?;
+    let nodes = raw
+        .nodes
+        .into_iter()
+        .map(|n| Node {
+            id: n.node_id,
+            parent: n.parent_id,
+            children: n.child_ids,
+            role: n.role.and_then(|v| v.value),
+            name: n.name.and_then(|v| v.value),
+            description: n.description.and_then(|v| v.value),
+            value: n.value.and_then(|v| v.value),
+            ignored: n.ignored,
+            ignored_reasons: props(n.ignored_reasons),
+            properties: props(n.properties),
+            dom_node_id: n.backend_dom_node_id,
+        })
+        .collect();
+    let file =
+        std::fs::File::create(path).with_context(|| format!("writing {}", path.display()))?;
+    serde_json::to_writer(std::io::BufWriter::new(file), &Tree { nodes })?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! JSONL job manifest (`--manifest jobs.jsonl`): one URL per line with optional
+//! per-job overrides of the command-line options.
+
+use crate::{crawl::slug, progress::Progress, schedule::Pool, summary::SummaryRow, Cli};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// One manifest line. Unset fields fall back to the command-line options.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    pub url: String,
+    pub wait_selector: Option<String>,
+    pub wait_xpath: Option<String>,
+    pub wait_js: Option<String>,
+    pub wait_ready: Option<String>,
+    pub max_wait_ms: Option<u64>,
+    pub network_idle_ms: Option<u64>,
+    pub proxy: Option<String>,
+    pub profile: Option<String>,
+    pub user_data_dir: Option<PathBuf>,
+    pub import_cookies: Option<PathBuf>,
+    pub locale: Option<String>,
+    pub tz: Option<String>,
+    pub geo: Option<String>,
+    pub window: Option<String>,
+    pub mobile: Option<bool>,
+    pub force_chrome: Option<bool>,
+    pub run_dir: Option<PathBuf>,
+    /// Jobs with a higher priority start first (default 0).
+    #[serde(default)]
+    pub priority: i32,
+    /// Added to (and overriding) the command-line `--tag`s.
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Job {
+    /// The base options with this job's overrides applied.
+    fn apply(self, base: &Cli) -> Cli {
+        let mut a = base.clone();
+        a.url = Some(self.url);
+        if let Some(sel) = self.wait_selector {
+            a.wait_selector = vec![sel];
+        }
+        a.wait_xpath = self.wait_xpath.or(a.wait_xpath);
+        a.wait_js = self.wait_js.or(a.wait_js);
+        a.wait_ready = self.wait_ready.unwrap_or(a.wait_ready);
+        a.max_wait_ms = self.max_wait_ms.unwrap_or(a.max_wait_ms);
+        a.network_idle_ms = self.network_idle_ms.unwrap_or(a.network_idle_ms);
+        a.proxy = self.proxy.or(a.proxy);
+        a.profile = self.profile.unwrap_or(a.profile);
+        a.user_data_dir = self.user_data_dir.or(a.user_data_dir);
+        a.import_cookies = self.import_cookies.or(a.import_cookies);
+        a.locale = self.locale.or(a.locale);
+        a.tz = self.tz.or(a.tz);
+        a.geo = self.geo.or(a.geo);
+        a.window = self.window.unwrap_or(a.window);
+        a.mobile = self.mobile.unwrap_or(a.mobile);
+        a.force_chrome = self.force_chrome.unwrap_or(a.force_chrome);
+        a.run_dir = self.run_dir;
+        a.tags.retain(|(k, _)| !self.tags.contains_key(k));
+        a.tags.extend(self.tags);
+        a
+    }
+}
+
+/// Parse a manifest; blank lines and `#` comments are skipped. Each entry keeps its
+/// line number so errors point at the offending line.
+pub fn read_manifest(path: &Path) -> Result<Vec<(usize, Result<Job>)>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("reading manifest {}", path.display()))?;
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(i, l)| {
+            let job = serde_json::from_str::<Job>(l)
+                .map_err(|e| anyhow!("manifest line {}: {}", i + 1, e));
+            (i + 1, job)
+        })
+        .collect())
+}
+
+/// `--partition-by-origin`: the job's profile directory narrowed to the target's origin,
+/// e.g. `~/.ankabot/profiles/default/origins/https-example-com`. State still persists
+/// between batches for the same origin; different origins never share it.
+fn origin_profile(job: &Cli, url: &str) -> PathBuf {
+    let origin = url::Url::parse(url)
+        .map(|u| u.origin().ascii_serialization().replace("://", "-"))
+        .unwrap_or_else(|_| url.to_string());
+    crate::profile_dir(&job.profile, job.user_data_dir.clone())
+        .join("origins")
+        .join(slug(&origin))
+}
+
+pub async fn run(args: &Cli, manifest: &Path) -> Result<i32> {
+    let jobs = read_manifest(manifest)?;
+    let batch_dir = args.run_dir.clone().unwrap_or_else(|| {
+        let ts = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        args.out_root.join(format!("batch-{}", ts))
+    });
+    let progress = Progress::new(jobs.len() as u64, args.workers, args.no_tui);
+    let mut pool = Pool::new(args.workers, args.host_concurrency);
+    // Rows are collected as jobs finish and written in manifest order.
+    let mut rows: Vec<(usize, SummaryRow)> = Vec::new();
+    for (seq, (line, job)) in jobs.into_iter().enumerate() {
+        let job = match job {
+            Ok(j) => j,
+            Err(e) => {
+                rows.push((
+                    seq,
+                    SummaryRow::failed(&format!("line {}", line), &e.to_string()),
+                ));
+                progress.finish(0, false);
+                continue;
+            }
+        };
+        let url = job.url.clone();
+        let priority = job.priority;
+        let mut job_args = job.apply(args);
+        if job_args.run_dir.is_none() {
+            job_args.run_dir = Some(batch_dir.join(format!("{:04}-{}", seq + 1, slug(&url))));
+        }
+        if args.partition_by_origin {
+            job_args.user_data_dir = Some(origin_profile(&job_args, &url));
+        }
+        pool.push(priority, job_args, (seq, url));
+    }
+
+    pool.fill(|w, url| progress.start(w, url));
+    while let Some(done) = pool.next_done().await {
+        let (seq, url) = done.meta;
+        let row = match done.result {
+            Ok(rec) => {
+                progress.finish(done.worker, rec.exit_code == 0);
+                SummaryRow::from_result(&url, rec.exit_code, &rec.result)
+            }
+            Err(e) => {
+                progress.finish(done.worker, false);
+                SummaryRow::failed(&url, &format!("{:#}", e))
+            }
+        };
+        rows.push((seq, row));
+        pool.fill(|w, url| progress.start(w, url));
+    }
+    progress.done();
+    rows.sort_by_key(|(seq, _)| *seq);
+    let rows: Vec<SummaryRow> = rows.into_iter().map(|(_, r)| r).collect();
+    crate::summary::write(&batch_dir, &rows)?;
+    Ok(crate::summary::exit_code(&rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn job(line: &str) -> Job {
+        serde_json::from_str(line).unwrap()
+    }
+
+    #[test]
+    fn job_overrides_only_what_it_sets() {
+        let base = Cli::parse_from([
+            "ankabot",
+            "https://base.example/",
+            "--proxy",
+            "http://proxy:8080",
+            "--max-wait-ms",
+            "9000",
+        ]);
+        let a = job(r#"{"url": "https://a.example/", "max_wait_ms": 1000, "mobile": true}"#)
+            .apply(&base);
+        assert_eq!(a.url.as_deref(), Some("https://a.example/"));
+        assert_eq!(a.max_wait_ms, 1000);
+        assert!(a.mobile);
+        assert_eq!(a.proxy.as_deref(), Some("http://proxy:8080"));
+        assert_eq!(a.network_idle_ms, base.network_idle_ms);
+        assert_eq!(a.run_dir, None);
+    }
+
+    #[test]
+    fn manifest_skips_blank_and_comment_lines() {
+        let path =
+            std::env::temp_dir().join(format!("ankabot-manifest-{}.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            "# jobs\n{\"url\": \"https://a/\"}\n\n{\"url\": \"https://b/\", \"bogus\": 1}\nnot json\n",
+        )
+        .unwrap();
+        let jobs = read_manifest(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<usize> = jobs.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [2, 4, 5]);
+        assert_eq!(jobs[0].1.as_ref().unwrap().url, "https://a/");
+        let unknown = jobs[1].1.as_ref().unwrap_err().to_string();
+        assert!(unknown.starts_with("manifest line 4:"), "{unknown}");
+        assert!(jobs[2].1.is_err());
+    }
+
+    #[test]
+    fn job_tags_extend_and_override_cli_tags() {
+        let base = Cli::parse_from([
+            "ankabot",
+            "https://base.example/",
+            "--tag",
+            "team=web",
+            "--tag",
+            "env=prod",
+        ]);
+        let a = job(r#"{"url": "https://a.example/", "tags": {"env": "staging", "id": "7"}}"#)
+            .apply(&base);
+        let mut tags = a.tags.clone();
+        tags.sort();
+        assert_eq!(
+            tags,
+            [
+                ("env".to_string(), "staging".to_string()),
+                ("id".to_string(), "7".to_string()),
+                ("team".to_string(), "web".to_string()),
+            ]
+        );
+    }
+}
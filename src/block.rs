@@ -0,0 +1,375 @@
+//! Block detection: anti-bot vendors, challenge pages and geo-blocks.
+//!
+//! A vendor showing up in the headers or markup only sets `anti_bot_vendor`; plenty of
+//! sites sit behind Cloudflare and render fine. A page is *blocked* when the signals are
+//! conclusive: a 451, a geo-block message on an error status, or a vendor fingerprint
+//! together with an error status or a challenge/CAPTCHA still on screen after the waits.
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Challenge {
+    /// An interstitial that runs JS checks ("Checking your browser…").
+    #[serde(rename = "js_challenge")]
+    Js,
+    /// A CAPTCHA or press-and-hold widget.
+    Captcha,
+    /// A hard deny page.
+    AccessDenied,
+    /// The content isn't served in the client's region.
+    GeoBlock,
+}
+
+/// Why a page counts as blocked; also the error `render_with_chrome` bails out with.
+#[derive(Serialize, Clone, Debug)]
+pub struct Verdict {
+    pub vendor: Option<String>,
+    pub challenge: Challenge,
+    /// The signals that matched, e.g. `header cf-ray`, `status 403`.
+    pub evidence: Vec<String>,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let challenge = serde_json::to_value(self.challenge).unwrap_or_default();
+        write!(
+            f,
+            "blocked by {} ({})",
+            self.vendor.as_deref().unwrap_or("the site"),
+            challenge.as_str().unwrap_or_default()
+        )
+    }
+}
+
+impl std::error::Error for Verdict {}
+
+/// What the detector looks at: the main document's response and the rendered page.
+pub struct Page<'a> {
+    pub status: Option<u16>,
+    /// Response headers, names in any case (`set-cookie` carries the vendor cookies).
+    pub headers: &'a [(String, String)],
+    pub html: &'a str,
+    /// Visible text (`innerText`); empty for the HTTP fast path.
+    pub text: &'a str,
+}
+
+struct Vendor {
+    name: &'static str,
+    /// Headers only the vendor sets.
+    headers: &'static [&'static str],
+    /// Substrings of `name: value` header lines, mostly cookie names in `set-cookie`.
+    header_values: &'static [&'static str],
+    /// Substrings of the (lowercased) HTML.
+    markup: &'static [&'static str],
+}
+
+const VENDORS: &[Vendor] = &[
+    Vendor {
+        name: "cloudflare",
+        headers: &["cf-ray", "cf-mitigated"],
+        header_values: &["server: cloudflare", "__cf_bm", "cf_clearance"],
+        markup: &[
+            "challenges.cloudflare.com",
+            "/cdn-cgi/challenge-platform",
+            "cf-browser-verification",
+            "cf_chl_opt",
+        ],
+    },
+    Vendor {
+        name: "akamai",
+        headers: &["akamai-grn"],
+        header_values: &["server: akamaighost", "_abck", "bm_sz"],
+        markup: &["errors.edgesuite.net", "reference&#32;&#35;"],
+    },
+    Vendor {
+        name: "perimeterx",
+        headers: &["x-px-blocked"],
+        header_values: &["_pxhd", "_px3"],
+        markup: &["px-captcha", "_pxappid", "perimeterx"],
+    },
+    Vendor {
+        name: "datadome",
+        headers: &["x-datadome", "x-datadome-cid"],
+        header_values: &["datadome="],
+        markup: &["captcha-delivery.com", "geo.captcha-delivery"],
+    },
+    Vendor {
+        name: "imperva",
+        headers: &["x-iinfo"],
+        header_values: &["incap_ses", "visid_incap", "x-cdn: imperva"],
+        markup: &["_incapsula_resource", "incapsula incident id"],
+    },
+    Vendor {
+        name: "aws_waf",
+        headers: &["x-amzn-waf-action"],
+        header_values: &["aws-waf-token"],
+        markup: &["awswaf", "aws-waf-token"],
+    },
+    Vendor {
+        name: "sucuri",
+        headers: &["x-sucuri-id", "x-sucuri-block"],
+        header_values: &["server: sucuri"],
+        markup: &["sucuri website firewall"],
+    },
+    Vendor {
+        name: "kasada",
+        headers: &["x-kpsdk-ct"],
+        header_values: &[],
+        markup: &["kpsdk"],
+    },
+];
+
+/// Challenge widgets that only appear on interstitials.
+const CAPTCHA_MARKERS: &[&str] = &["cf-turnstile", "px-captcha", "captcha-delivery.com"];
+/// General-purpose CAPTCHAs, which ordinary forms use too.
+const FORM_CAPTCHA_MARKERS: &[&str] = &["g-recaptcha", "hcaptcha.com", "h-captcha"];
+const CAPTCHA_TEXT: &[&str] = &[
+    "press and hold",
+    "complete the captcha",
+    "solve the captcha",
+];
+const JS_CHALLENGE_TEXT: &[&str] = &[
+    "checking your browser",
+    "verifying you are human",
+    "checking if the site connection is secure",
+    "enable javascript and cookies to continue",
+    "just a moment...",
+];
+const DENIED_TEXT: &[&str] = &[
+    "access denied",
+    "sorry, you have been blocked",
+    "request blocked",
+    "you don't have permission to access",
+    "the requested url was rejected",
+];
+const GEO_TEXT: &[&str] = &[
+    "not available in your country",
+    "not available in your region",
+    "unavailable in your country",
+    "unavailable in your region",
+    "not available in your location",
+];
+
+/// Whether `name` is a header only an anti-bot vendor or WAF sets.
+pub fn is_vendor_header(name: &str) -> bool {
+    VENDORS
+        .iter()
+        .any(|v| v.headers.iter().any(|h| h.eq_ignore_ascii_case(name)))
+}
+
+/// The anti-bot vendor whose fingerprint is on the page, with the matching signals.
+pub fn vendor(page: &Page) -> Option<(&'static str, Vec<String>)> {
+    let html = page.html.to_ascii_lowercase();
+    let header_lines: Vec<String> = page
+        .headers
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k.to_ascii_lowercase(), v.to_ascii_lowercase()))
+        .collect();
+    VENDORS.iter().find_map(|vendor| {
+        let mut evidence = Vec::new();
+        for h in vendor.headers {
+            if page.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(h)) {
+                evidence.push(format!("header {}", h));
+            }
+        }
+        for v in vendor.header_values {
+            if header_lines.iter().any(|l| l.contains(v)) {
+                evidence.push(format!("header value {}", v));
+            }
+        }
+        for m in vendor.markup {
+            if html.contains(m) {
+                evidence.push(format!("markup {}", m));
+            }
+        }
+        (!evidence.is_empty()).then_some((vendor.name, evidence))
+    })
+}
+
+fn found(list: &[&'static str], hay: &str) -> Option<&'static str> {
+    list.iter().find(|m| hay.contains(**m)).copied()
+}
+
+/// A conclusive block, or `None` when the page may just be a page.
+pub fn detect(page: &Page) -> Option<Verdict> {
+    let html = page.html.to_ascii_lowercase();
+    let text = page.text.to_ascii_lowercase();
+    let vendor = vendor(page);
+    let mut evidence: Vec<String> = vendor.iter().flat_map(|(_, e)| e.clone()).collect();
+    let error_status = page.status.filter(|s| matches!(s, 403 | 429 | 451 | 503));
+    if let Some(s) = error_status {
+        evidence.push(format!("status {}", s));
+    }
+    // Error pages are short; a real article that mentions "access denied" is not.
+    let short = text.len() < 2000 && html.len() < 200_000;
+
+    let geo = found(GEO_TEXT, &text).filter(|_| short);
+    if page.status == Some(451) || (geo.is_some() && error_status.is_some()) {
+        if let Some(m) = geo {
+            evidence.push(format!("text \"{}\"", m));
+        }
+        return Some(Verdict {
+            vendor: vendor.map(|(v, _)| v.to_string()),
+            challenge: Challenge::GeoBlock,
+            evidence,
+        });
+    }
+
+    // A login form with a reCAPTCHA is still a page, unless it came with an error status.
+    let captcha = found(CAPTCHA_MARKERS, &html)
+        .or_else(|| found(CAPTCHA_TEXT, &text))
+        .or_else(|| found(FORM_CAPTCHA_MARKERS, &html).filter(|_| error_status.is_some()))
+        .filter(|_| short);
+    let js = found(JS_CHALLENGE_TEXT, &text).filter(|_| short);
+    let denied = found(DENIED_TEXT, &text).filter(|_| short);
+    let challenge = if let Some(m) = captcha {
+        evidence.push(format!("captcha {}", m));
+        Challenge::Captcha
+    } else if let Some(m) = js {
+        evidence.push(format!("text \"{}\"", m));
+        Challenge::Js
+    } else if let Some(m) = denied {
+        evidence.push(format!("text \"{}\"", m));
+        Challenge::AccessDenied
+    } else {
+        Challenge::AccessDenied
+    };
+
+    let on_screen = captcha.is_some() || js.is_some();
+    let conclusive = match &vendor {
+        Some(_) => error_status.is_some() || on_screen,
+        // Without a vendor fingerprint only a short deny page on a 403 is conclusive.
+        None => page.status == Some(403) && denied.is_some(),
+    };
+    conclusive.then(|| Verdict {
+        vendor: vendor.map(|(v, _)| v.to_string()),
+        challenge,
+        evidence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page<'a>(
+        status: u16,
+        headers: &'a [(String, String)],
+        html: &'a str,
+        text: &'a str,
+    ) -> Page<'a> {
+        Page {
+            status: Some(status),
+            headers,
+            html,
+            text,
+        }
+    }
+
+    #[test]
+    fn verdicts() {
+        let cf = [("CF-Ray".to_string(), "8a1b2c3d4e5f-FRA".to_string())];
+        let none: [(String, String); 0] = [];
+        let body = "The court found that access denied to the archive was unlawful. ".repeat(40);
+        let article = format!("<article>{body}</article>");
+        let login = r#"<form action="/login"><div class="g-recaptcha"></div></form>"#;
+        for (name, p, want) in [
+            (
+                "cloudflare 403 with cf-ray",
+                page(403, &cf, "<html></html>", "Sorry, you have been blocked"),
+                Some(Challenge::AccessDenied),
+            ),
+            (
+                "cloudflare 200 renders normally",
+                page(200, &cf, "<html><main>Hi</main></html>", "Hi"),
+                None,
+            ),
+            (
+                "login form with reCAPTCHA on a 200",
+                page(200, &none, login, "Sign in"),
+                None,
+            ),
+            (
+                "reCAPTCHA on a 429",
+                page(429, &cf, login, "Sign in"),
+                Some(Challenge::Captcha),
+            ),
+            (
+                "long article mentioning access denied",
+                page(403, &none, &article, &body),
+                None,
+            ),
+            (
+                "451",
+                page(451, &none, "<html></html>", "Unavailable"),
+                Some(Challenge::GeoBlock),
+            ),
+            (
+                "geo message on a 403",
+                page(
+                    403,
+                    &none,
+                    "<html></html>",
+                    "This video is not available in your country",
+                ),
+                Some(Challenge::GeoBlock),
+            ),
+            (
+                "geo message on a 200",
+                page(200, &none, "<html></html>", "Not available in your country"),
+                None,
+            ),
+            (
+                "403 without vendor or deny text",
+                page(403, &none, "<html></html>", "Forbidden"),
+                None,
+            ),
+            (
+                "403 without vendor with deny text",
+                page(403, &none, "<html></html>", "Access Denied"),
+                Some(Challenge::AccessDenied),
+            ),
+            (
+                "503 without vendor with deny text",
+                page(503, &none, "<html></html>", "Access Denied"),
+                None,
+            ),
+            (
+                "cloudflare interstitial on a 200",
+                page(
+                    200,
+                    &cf,
+                    "<html></html>",
+                    "Checking your browser before accessing",
+                ),
+                Some(Challenge::Js),
+            ),
+        ] {
+            assert_eq!(detect(&p).map(|v| v.challenge), want, "{name}");
+        }
+    }
+
+    #[test]
+    fn cloudflare_verdict_names_vendor_and_evidence() {
+        let cf = [("cf-ray".to_string(), "8a1b2c3d4e5f-FRA".to_string())];
+        let v = detect(&page(403, &cf, "<html></html>", "")).unwrap();
+        assert_eq!(v.vendor.as_deref(), Some("cloudflare"));
+        assert_eq!(v.evidence, ["header cf-ray", "status 403"]);
+    }
+
+    #[test]
+    fn vendor_matches_headers_cookies_and_markup() {
+        let cookie = [("Set-Cookie".to_string(), "_abck=1; path=/".to_string())];
+        let (name, evidence) = vendor(&page(200, &cookie, "", "")).unwrap();
+        assert_eq!(
+            (name, evidence),
+            ("akamai", vec!["header value _abck".to_string()])
+        );
+
+        let html = r#"<script src="https://geo.captcha-delivery.com/c.js"></script>"#;
+        assert_eq!(vendor(&page(200, &[], html, "")).unwrap().0, "datadome");
+        assert!(vendor(&page(200, &[], "<html></html>", "")).is_none());
+    }
+}
@@ -0,0 +1,69 @@
+//! `--breakpoints`: one screenshot per viewport width, for checking responsive layouts in
+//! a single run.
+//!
+//! The page is not reloaded; each width is applied with the same device metrics override
+//! as the main capture, so media queries, `srcset` and resize handlers see a real resize.
+
+use crate::{apply_device_metrics, wait_images_and_fonts, Cli};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Longest wait for images and fonts a new width pulls in.
+const SETTLE_MAX: Duration = Duration::from_secs(5);
+
+/// Resolves after two animation frames, i.e. once layout for the new width has run.
+const SETTLE_JS: &str =
+    "new Promise((r) => requestAnimationFrame(() => requestAnimationFrame(() => r(true))))";
+
+#[derive(Serialize)]
+pub struct Shot {
+    pub width: u32,
+    pub height: u32,
+    /// Document height at this width, in CSS px.
+    pub document_height: u64,
+    /// Path relative to the breakpoints directory.
+    pub file: String,
+}
+
+/// Capture a screenshot at each of `widths` into `dir` with an `index.json`, then restore
+/// the `--window` size.
+pub fn capture(tab: &headless_chrome::Tab, args: &Cli, widths: &[u32], dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let (win_w, win_h) = args.window_size();
+    let mut shots = Vec::new();
+    for &width in widths {
+        apply_device_metrics(tab, args, width, win_h)?;
+        tab.evaluate(SETTLE_JS, true)?;
+        // Best effort: a slow image at one width shouldn't fail the run.
+        let _ = wait_images_and_fonts(tab, Instant::now() + SETTLE_MAX);
+        let bytes = if args.full_page {
+            crate::fullpage::capture(tab, args.screenshot_format, args.screenshot_quality)?
+        } else {
+            tab.capture_screenshot(
+                args.screenshot_format.cdp(),
+                args.screenshot_quality,
+                None,
+                true,
+            )?
+        };
+        let file = format!("{}.{}", width, args.screenshot_format.extension());
+        std::fs::write(dir.join(&file), bytes)
+            .with_context(|| format!("writing breakpoint screenshot {}", file))?;
+        let document_height = tab
+            .evaluate("document.documentElement.scrollHeight", false)?
+            .value
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        shots.push(Shot {
+            width,
+            height: win_h,
+            document_height,
+            file,
+        });
+    }
+    apply_device_metrics(tab, args, win_w, win_h)?;
+    std::fs::write(dir.join("index.json"), serde_json::to_vec_pretty(&shots)?)?;
+    Ok(())
+}
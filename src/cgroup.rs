@@ -0,0 +1,411 @@
+//! `--chrome-memory-limit-mb` / `--chrome-cpu-quota`: run the launched Chrome (and every
+//! process it forks) in its own cgroup on Linux, so one runaway page can't take down the
+//! host, and report whether the limits were hit.
+//!
+//! Both the unified (v2) hierarchy and the legacy v1 `memory`/`cpu` controllers are
+//! supported. The group is created next to ankabot's own cgroup, so ankabot needs write
+//! access there (root, or a delegated cgroup such as a `systemd-run --scope -p Delegate=yes`).
+//! When a v2 group has to hand it controllers, ankabot moves itself into an `ankabot-<pid>`
+//! leaf once per process and puts every Chrome group beside it; [`LeafGuard`] removes the
+//! leaf again on exit.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+const ROOT: &str = "/sys/fs/cgroup";
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// What result.json reports about a limited Chrome.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LimitReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limit_mb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_quota: Option<f64>,
+    /// Peak memory of the group, when the kernel tracks it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_peak_mb: Option<u64>,
+    /// Chrome ran into the memory limit (allocations were throttled or failed).
+    pub memory_limit_hit: bool,
+    /// Processes the kernel OOM-killed inside the group (renderers, usually).
+    pub oom_kills: u64,
+    /// Total time the group was throttled by the CPU quota.
+    pub cpu_throttled_ms: u64,
+}
+
+impl LimitReport {
+    pub fn limit_hit(&self) -> bool {
+        self.memory_limit_hit || self.oom_kills > 0
+    }
+}
+
+enum Layout {
+    V2(PathBuf),
+    V1 {
+        memory: Option<PathBuf>,
+        cpu: Option<PathBuf>,
+    },
+}
+
+pub struct ChromeCgroup {
+    layout: Layout,
+    memory_limit_mb: Option<u64>,
+    cpu_quota: Option<f64>,
+}
+
+static SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// Process-wide v2 state, behind one lock so parallel workers don't race on the move into
+/// the leaf or on `cgroup.subtree_control`.
+struct V2State {
+    /// ankabot's own cgroup as it was before any move into the leaf.
+    base: Option<PathBuf>,
+    leaf: Option<PathBuf>,
+    /// Controllers ankabot enabled in `base`, handed back on exit.
+    added: Vec<String>,
+}
+
+static V2: Mutex<V2State> = Mutex::new(V2State {
+    base: None,
+    leaf: None,
+    added: Vec::new(),
+});
+
+/// Hands the v2 controllers back and removes the `ankabot-<pid>` leaf when dropped.
+pub struct LeafGuard;
+
+impl Drop for LeafGuard {
+    fn drop(&mut self) {
+        let mut state = V2.lock().unwrap_or_else(|e| e.into_inner());
+        let (Some(base), Some(leaf)) = (state.base.clone(), state.leaf.take()) else {
+            return;
+        };
+        // Best effort: the base can only take the process back once it hands no
+        // controllers to children, which fails while another process still uses them.
+        let remove: Vec<String> = state.added.drain(..).map(|c| format!("-{}", c)).collect();
+        let _ = fs::write(base.join("cgroup.subtree_control"), remove.join(" "));
+        if fs::write(base.join("cgroup.procs"), std::process::id().to_string()).is_ok() {
+            let _ = fs::remove_dir(&leaf);
+        }
+    }
+}
+
+impl ChromeCgroup {
+    /// Create a fresh group with the given limits; `cpu_quota` is in cores (1.5 = one and a
+    /// half CPUs).
+    pub fn create(memory_limit_mb: Option<u64>, cpu_quota: Option<f64>) -> Result<Self> {
+        let name = format!(
+            "ankabot-{}-chrome-{}",
+            std::process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        let layout = if Path::new(ROOT).join("cgroup.controllers").exists() {
+            create_v2(&name, memory_limit_mb, cpu_quota)?
+        } else {
+            create_v1(&name, memory_limit_mb, cpu_quota)?
+        };
+        Ok(Self {
+            layout,
+            memory_limit_mb,
+            cpu_quota,
+        })
+    }
+
+    /// Move `pid` and all of its descendants into the group. Run it twice so processes
+    /// forked while the first pass was moving their parents are caught too.
+    pub fn adopt(&self, pid: u32) -> Result<()> {
+        for _ in 0..2 {
+            for p in std::iter::once(pid).chain(descendants(pid)) {
+                for dir in self.dirs() {
+                    // A process may have exited since it was listed.
+                    let _ = fs::write(dir.join("cgroup.procs"), p.to_string());
+                }
+            }
+        }
+        let procs = fs::read_to_string(self.dirs()[0].join("cgroup.procs")).unwrap_or_default();
+        if !procs.lines().any(|l| l.trim() == pid.to_string()) {
+            return Err(anyhow!(
+                "could not move Chrome (pid {}) into its cgroup",
+                pid
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> LimitReport {
+        let mut r = LimitReport {
+            memory_limit_mb: self.memory_limit_mb,
+            cpu_quota: self.cpu_quota,
+            ..Default::default()
+        };
+        match &self.layout {
+            Layout::V2(dir) => {
+                let events = keyed(&dir.join("memory.events"));
+                r.memory_limit_hit = events.get("max").is_some_and(|&n| n > 0);
+                r.oom_kills = events.get("oom_kill").copied().unwrap_or(0);
+                r.memory_peak_mb = read_u64(&dir.join("memory.peak")).map(|b| b >> 20);
+                r.cpu_throttled_ms = keyed(&dir.join("cpu.stat"))
+                    .get("throttled_usec")
+                    .map_or(0, |us| us / 1000);
+            }
+            Layout::V1 { memory, cpu } => {
+                if let Some(dir) = memory {
+                    let peak = read_u64(&dir.join("memory.max_usage_in_bytes"));
+                    // With swap accounting the failure counters can stay at zero even
+                    // though usage was pinned at the limit, so a peak at it counts too.
+                    r.memory_limit_hit = read_u64(&dir.join("memory.failcnt"))
+                        .is_some_and(|n| n > 0)
+                        || peak
+                            .zip(read_u64(&dir.join("memory.limit_in_bytes")))
+                            .is_some_and(|(p, l)| p >= l);
+                    r.oom_kills = keyed(&dir.join("memory.oom_control"))
+                        .get("oom_kill")
+                        .copied()
+                        .unwrap_or(0);
+                    r.memory_peak_mb = peak.map(|b| b >> 20);
+                }
+                if let Some(dir) = cpu {
+                    r.cpu_throttled_ms = keyed(&dir.join("cpu.stat"))
+                        .get("throttled_time")
+                        .map_or(0, |ns| ns / 1_000_000);
+                }
+            }
+        }
+        r
+    }
+
+    fn dirs(&self) -> Vec<&Path> {
+        match &self.layout {
+            Layout::V2(dir) => vec![dir],
+            Layout::V1 { memory, cpu } => memory.iter().chain(cpu).map(|p| p.as_path()).collect(),
+        }
+    }
+}
+
+impl Drop for ChromeCgroup {
+    /// Kill whatever Chrome left behind and remove the group.
+    fn drop(&mut self) {
+        let dirs: Vec<PathBuf> = self.dirs().into_iter().map(Path::to_path_buf).collect();
+        for dir in &dirs {
+            if fs::write(dir.join("cgroup.kill"), "1").is_err() {
+                for pid in fs::read_to_string(dir.join("cgroup.procs"))
+                    .unwrap_or_default()
+                    .lines()
+                {
+                    let _ = std::process::Command::new("kill")
+                        .args(["-9", pid.trim()])
+                        .status();
+                }
+            }
+        }
+        for dir in &dirs {
+            for _ in 0..20 {
+                if fs::remove_dir(dir).is_ok() || !dir.exists() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+    }
+}
+
+/// The path of this process's cgroup within `controller`'s hierarchy ("" for v2).
+fn own_path(controller: &str) -> Result<String> {
+    let own = fs::read_to_string("/proc/self/cgroup")
+        .context("reading /proc/self/cgroup (cgroup limits need Linux)")?;
+    own.lines()
+        .filter_map(|l| {
+            let mut parts = l.splitn(3, ':');
+            let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+            controllers
+                .split(',')
+                .any(|c| c == controller)
+                .then(|| path.trim_start_matches('/').to_string())
+        })
+        .next()
+        .ok_or_else(|| anyhow!("no {} cgroup for this process", controller))
+}
+
+fn create_v2(name: &str, memory_mb: Option<u64>, cpu_quota: Option<f64>) -> Result<Layout> {
+    let mut state = V2.lock().unwrap_or_else(|e| e.into_inner());
+    let base = match &state.base {
+        Some(b) => b.clone(),
+        None => {
+            let b = Path::new(ROOT).join(own_path("")?);
+            state.base = Some(b.clone());
+            b
+        }
+    };
+    let wanted: Vec<&str> = [
+        ("memory", memory_mb.is_some()),
+        ("cpu", cpu_quota.is_some()),
+    ]
+    .iter()
+    .filter(|(_, on)| *on)
+    .map(|(c, _)| *c)
+    .collect();
+    let enabled = fs::read_to_string(base.join("cgroup.subtree_control")).unwrap_or_default();
+    let missing: Vec<&str> = wanted
+        .iter()
+        .copied()
+        .filter(|c| !enabled.split_whitespace().any(|e| e == *c))
+        .collect();
+    if !missing.is_empty() {
+        // A v2 group can only hand controllers to children while it holds no processes
+        // itself, so ankabot first moves into a leaf of its own.
+        let moved = match &state.leaf {
+            Some(_) => Ok(()),
+            None => {
+                remove_stale_leaves(&base);
+                let leaf = base.join(format!("ankabot-{}", std::process::id()));
+                let moved = fs::create_dir_all(&leaf).and_then(|_| {
+                    fs::write(leaf.join("cgroup.procs"), std::process::id().to_string())
+                });
+                if moved.is_ok() {
+                    state.leaf = Some(leaf);
+                }
+                moved
+            }
+        };
+        moved
+            .and_then(|_| {
+                let add: Vec<String> = missing.iter().map(|c| format!("+{}", c)).collect();
+                fs::write(base.join("cgroup.subtree_control"), add.join(" "))
+            })
+            .with_context(|| {
+                format!(
+                    "enabling the {} controller(s) in {} (run ankabot as root or in a \
+                     delegated cgroup)",
+                    missing.join("+"),
+                    base.display()
+                )
+            })?;
+        state.added.extend(missing.iter().map(|c| c.to_string()));
+    }
+    drop(state);
+    let dir = base.join(name);
+    fs::create_dir(&dir).with_context(|| format!("creating cgroup {}", dir.display()))?;
+    if let Some(mb) = memory_mb {
+        fs::write(dir.join("memory.max"), (mb << 20).to_string())?;
+        // Without this the kernel would page Chrome out to swap instead of enforcing the limit.
+        let _ = fs::write(dir.join("memory.swap.max"), "0");
+    }
+    if let Some(q) = cpu_quota {
+        let quota = (q * CPU_PERIOD_US as f64).round() as u64;
+        fs::write(dir.join("cpu.max"), format!("{} {}", quota, CPU_PERIOD_US))?;
+    }
+    Ok(Layout::V2(dir))
+}
+
+/// Remove the empty leaves of ankabot processes that exited without cleaning up.
+fn remove_stale_leaves(base: &Path) {
+    for entry in fs::read_dir(base).into_iter().flatten().flatten() {
+        let name = entry.file_name();
+        let pid = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("ankabot-"))
+            .and_then(|p| p.parse::<u32>().ok());
+        if pid.is_some_and(|p| !Path::new("/proc").join(p.to_string()).exists()) {
+            let _ = fs::remove_dir(entry.path());
+        }
+    }
+}
+
+fn create_v1(name: &str, memory_mb: Option<u64>, cpu_quota: Option<f64>) -> Result<Layout> {
+    let group = |controller: &str| -> Result<PathBuf> {
+        let dir = Path::new(ROOT)
+            .join(controller)
+            .join(own_path(controller)?)
+            .join(name);
+        fs::create_dir(&dir).with_context(|| {
+            format!(
+                "creating cgroup {} (run ankabot as root or in a delegated cgroup)",
+                dir.display()
+            )
+        })?;
+        Ok(dir)
+    };
+    let memory = match memory_mb {
+        Some(mb) => {
+            let dir = group("memory")?;
+            fs::write(dir.join("memory.limit_in_bytes"), (mb << 20).to_string())?;
+            let _ = fs::write(
+                dir.join("memory.memsw.limit_in_bytes"),
+                (mb << 20).to_string(),
+            );
+            Some(dir)
+        }
+        None => None,
+    };
+    let cpu = match cpu_quota {
+        Some(q) => {
+            let dir = group("cpu")?;
+            fs::write(dir.join("cpu.cfs_period_us"), CPU_PERIOD_US.to_string())?;
+            let quota = (q * CPU_PERIOD_US as f64).round() as u64;
+            fs::write(dir.join("cpu.cfs_quota_us"), quota.to_string())?;
+            Some(dir)
+        }
+        None => None,
+    };
+    Ok(Layout::V1 { memory, cpu })
+}
+
+/// All live descendants of `pid`, from the parent links in /proc/*/stat.
+pub fn descendants(pid: u32) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for entry in fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let Some(p) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        // The command name may contain spaces and parentheses; fields resume after the last ')'.
+        let ppid = stat
+            .rfind(')')
+            .and_then(|i| stat[i + 1..].split_whitespace().nth(1))
+            .and_then(|s| s.parse::<u32>().ok());
+        if let Some(ppid) = ppid {
+            children.entry(ppid).or_default().push(p);
+        }
+    }
+    let mut out = Vec::new();
+    let mut stack = vec![pid];
+    while let Some(p) = stack.pop() {
+        for &c in children.get(&p).into_iter().flatten() {
+            out.push(c);
+            stack.push(c);
+        }
+    }
+    out
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// `key value` lines (memory.events, cpu.stat, memory.oom_control).
+fn keyed(path: &Path) -> HashMap<String, u64> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| {
+            let (k, v) = l.split_once(' ')?;
+            Some((k.to_string(), v.trim().parse().ok()?))
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+//! `--require-chrome`: a version requirement on the browser a run launches, checked
+//! before anything is rendered.
+
+use anyhow::{anyhow, Result};
+use headless_chrome::{Browser, LaunchOptions};
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// Comma-separated comparisons such as `>=120,<126` or `=121.0.6167`. A bare version
+/// means `=`. Only as many components as a comparison gives are compared, so `<126`
+/// excludes every 126.x build.
+#[derive(Clone, Debug)]
+pub struct ChromeReq {
+    raw: String,
+    clauses: Vec<(Op, Vec<u32>)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Ge,
+    Gt,
+    Le,
+    Lt,
+    Eq,
+}
+
+impl FromStr for ChromeReq {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut clauses = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (op, rest) = [
+                (">=", Op::Ge),
+                ("<=", Op::Le),
+                (">", Op::Gt),
+                ("<", Op::Lt),
+                ("=", Op::Eq),
+            ]
+            .iter()
+            .find_map(|(p, op)| part.strip_prefix(p).map(|r| (*op, r)))
+            .unwrap_or((Op::Eq, part));
+            let version = parse_version(rest.trim())
+                .ok_or_else(|| format!("invalid version '{}' in '{}'", rest.trim(), s))?;
+            clauses.push((op, version));
+        }
+        if clauses.is_empty() {
+            return Err("empty version requirement".to_string());
+        }
+        Ok(Self {
+            raw: s.to_string(),
+            clauses,
+        })
+    }
+}
+
+impl fmt::Display for ChromeReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl ChromeReq {
+    pub fn matches(&self, version: &[u32]) -> bool {
+        self.clauses.iter().all(|(op, want)| {
+            let have: Vec<u32> = (0..want.len())
+                .map(|i| version.get(i).copied().unwrap_or(0))
+                .collect();
+            let ord = have.cmp(want);
+            match op {
+                Op::Ge => ord != Ordering::Less,
+                Op::Gt => ord == Ordering::Greater,
+                Op::Le => ord != Ordering::Greater,
+                Op::Lt => ord == Ordering::Less,
+                Op::Eq => ord == Ordering::Equal,
+            }
+        })
+    }
+}
+
+fn parse_version(s: &str) -> Option<Vec<u32>> {
+    s.split('.').map(|n| n.parse().ok()).collect()
+}
+
+/// The version in a `Browser.getVersion` product string ("HeadlessChrome/120.0.6099.109").
+fn product_version(product: &str) -> Option<Vec<u32>> {
+    parse_version(product.rsplit('/').next()?)
+}
+
+/// Check the launched browser against `req`, with a message naming both on a mismatch.
+fn check(browser: &Browser, req: &ChromeReq) -> Result<()> {
+    let product = browser.get_version()?.product;
+    match product_version(&product) {
+        Some(v) if req.matches(&v) => Ok(()),
+        _ => Err(anyhow!(
+            "browser {} does not satisfy --require-chrome \"{}\"",
+            product,
+            req
+        )),
+    }
+}
+
+/// Launch with `opts` and check the browser against `req`. With the `fetcher` feature and
+/// `may_fetch`, a mismatching browser is replaced by a downloaded compatible build.
+pub fn launch_checked(opts: LaunchOptions, req: &ChromeReq, may_fetch: bool) -> Result<Browser> {
+    let browser = Browser::new(opts.clone())?;
+    let Err(e) = check(&browser, req) else {
+        return Ok(browser);
+    };
+    #[cfg(feature = "fetcher")]
+    if may_fetch {
+        use headless_chrome::browser::{FetcherOptions, Revision};
+        eprintln!("{:#}; fetching a compatible build", e);
+        drop(browser);
+        let mut opts = opts;
+        opts.path = None;
+        opts.fetcher_options =
+            FetcherOptions::default().with_revision(Revision::Specific(compatible_revision(req)?));
+        let browser = Browser::new(opts)?;
+        check(&browser, req)?;
+        return Ok(browser);
+    }
+    let _ = may_fetch;
+    Err(e)
+}
+
+/// Chromium snapshot revision of the newest Chrome for Testing release that satisfies
+/// `req`, for headless_chrome's fetcher to download.
+#[cfg(feature = "fetcher")]
+pub fn compatible_revision(req: &ChromeReq) -> Result<String> {
+    const KNOWN_GOOD: &str =
+        "https://googlechromelabs.github.io/chrome-for-testing/known-good-versions.json";
+    let body = ureq::get(KNOWN_GOOD).call()?.body_mut().read_to_string()?;
+    let known: serde_json::Value = serde_json::from_str(&body)?;
+    known["versions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| {
+            let version = parse_version(v["version"].as_str()?)?;
+            let revision = v["revision"].as_str()?.to_string();
+            req.matches(&version).then_some((version, revision))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, revision)| revision)
+        .ok_or_else(|| anyhow!("no Chrome release satisfies --require-chrome \"{}\"", req))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(s: &str) -> ChromeReq {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn range_of_majors() {
+        let r = req(">=120,<126");
+        assert!(r.matches(&[120, 0, 6099, 109]));
+        assert!(r.matches(&[125, 9, 9999, 0]));
+        assert!(!r.matches(&[119, 0, 6045, 199]));
+        // `<126` compares the major only, so every 126.x build is out.
+        assert!(!r.matches(&[126, 0, 0, 0]));
+    }
+
+    #[test]
+    fn bare_version_means_equal_to_its_components() {
+        let r = req("121.0.6167");
+        assert!(r.matches(&[121, 0, 6167, 85]));
+        assert!(!r.matches(&[121, 0, 6168, 0]));
+        assert!(req("=121").matches(&[121, 0, 6167, 85]));
+    }
+
+    #[test]
+    fn strict_and_inclusive_bounds() {
+        assert!(req(">120").matches(&[121]));
+        assert!(!req(">120").matches(&[120, 5]));
+        assert!(req("<=120").matches(&[120, 5]));
+        assert!(!req("<=120.0.5").matches(&[120, 0, 6]));
+        // Missing components of the browser's version count as 0.
+        assert!(req(">=120.0.0").matches(&[120]));
+    }
+
+    #[test]
+    fn spaces_and_empty_parts_are_ignored() {
+        assert!(req(" >= 120 , , < 126 ").matches(&[123, 1]));
+    }
+
+    #[test]
+    fn invalid_requirements_are_rejected() {
+        assert_eq!(
+            "".parse::<ChromeReq>().unwrap_err(),
+            "empty version requirement"
+        );
+        assert!(",".parse::<ChromeReq>().is_err());
+        for bad in [">=abc", "~120", "120.x", ">=", "=>120", "120..1"] {
+            assert!(bad.parse::<ChromeReq>().is_err(), "{bad} accepted");
+        }
+    }
+
+    #[test]
+    fn display_keeps_the_requirement_as_written() {
+        assert_eq!(req(">=120,<126").to_string(), ">=120,<126");
+    }
+
+    #[test]
+    fn product_string_version() {
+        assert_eq!(
+            product_version("HeadlessChrome/120.0.6099.109"),
+            Some(vec![120, 0, 6099, 109])
+        );
+        assert_eq!(product_version("Chrome/beta"), None);
+    }
+}
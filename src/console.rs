@@ -0,0 +1,217 @@
+//! `console.log`: the page's console messages (`console.*` calls, from
+//! `Runtime.consoleAPICalled`) and the browser's own log entries (failed loads,
+//! interventions, deprecations, from `Log.entryAdded`), one timestamped line each.
+//!
+//! Lines are written as the events arrive rather than at the end, so a run that times out
+//! or crashes still leaves everything logged up to that point. Errors and warnings are
+//! also counted for result.json, keeping the first few.
+
+use anyhow::{Context, Result};
+use headless_chrome::protocol::cdp::{types::Event, Log, Runtime};
+use serde::Serialize;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+/// How many error and warning lines result.json keeps.
+const MAX_SAMPLES: usize = 5;
+
+/// Error and warning totals, with the first few of their log lines; flattened into
+/// result.json as `console_errors`, `console_warnings` and `console_messages`.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ConsoleCounts {
+    #[serde(rename = "console_errors")]
+    pub errors: usize,
+    #[serde(rename = "console_warnings")]
+    pub warnings: usize,
+    #[serde(rename = "console_messages", skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct ConsoleCapture {
+    counts: Arc<Mutex<ConsoleCounts>>,
+}
+
+impl ConsoleCapture {
+    pub fn counts(&self) -> ConsoleCounts {
+        self.counts.lock().unwrap().clone()
+    }
+}
+
+/// `--wait-console`: the first `console.*` message matching a pattern.
+#[derive(Clone)]
+pub struct ConsoleWatch {
+    hit: Arc<Mutex<Option<String>>>,
+    logged: Arc<Condvar>,
+}
+
+impl ConsoleWatch {
+    /// Start watching `tab`'s console; call before navigating, as ready markers are often
+    /// logged early.
+    pub fn attach(tab: &headless_chrome::Tab, pattern: regex::Regex) -> Result<Self> {
+        let hit = Arc::new(Mutex::new(None));
+        let logged = Arc::new(Condvar::new());
+        let (sink, notify) = (hit.clone(), logged.clone());
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            let Event::RuntimeConsoleAPICalled(e) = event else {
+                return;
+            };
+            let text = e
+                .params
+                .args
+                .iter()
+                .map(remote_text)
+                .collect::<Vec<_>>()
+                .join(" ");
+            if let Ok(mut hit) = sink.lock() {
+                if hit.is_none() && pattern.is_match(&text) {
+                    *hit = Some(text);
+                    notify.notify_all();
+                }
+            }
+        }))?;
+        tab.call_method(Runtime::Enable(None))?;
+        Ok(Self { hit, logged })
+    }
+
+    /// Block until a matching message is logged or `deadline` passes.
+    pub fn wait(&self, deadline: Instant) -> Option<String> {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let hit = self.hit.lock().unwrap();
+        let (hit, _) = self
+            .logged
+            .wait_timeout_while(hit, left, |hit| hit.is_none())
+            .unwrap();
+        hit.clone()
+    }
+
+    /// Forget the match before navigating to another page.
+    pub fn reset(&self) {
+        *self.hit.lock().unwrap() = None;
+    }
+}
+
+#[derive(PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+    Other,
+}
+
+/// Start writing `tab`'s console to `path` (truncating it).
+pub fn attach(tab: &headless_chrome::Tab, path: &Path) -> Result<ConsoleCapture> {
+    let file =
+        std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let file = Arc::new(Mutex::new(file));
+    let counts = Arc::new(Mutex::new(ConsoleCounts::default()));
+    let sink = counts.clone();
+    tab.add_event_listener(Arc::new(move |event: &Event| {
+        let Some((severity, line)) = format_event(event) else {
+            return;
+        };
+        if let Ok(mut f) = file.lock() {
+            let _ = f.write_all(line.as_bytes());
+        }
+        if severity == Severity::Other {
+            return;
+        }
+        if let Ok(mut c) = sink.lock() {
+            match severity {
+                Severity::Error => c.errors += 1,
+                _ => c.warnings += 1,
+            }
+            if c.messages.len() < MAX_SAMPLES {
+                c.messages.push(line.trim_end().to_string());
+            }
+        }
+    }))?;
+    tab.call_method(Runtime::Enable(None))?;
+    tab.call_method(Log::Enable(None))?;
+    Ok(ConsoleCapture { counts })
+}
+
+fn severity(level: &str) -> Severity {
+    match level {
+        "error" | "assert" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => Severity::Other,
+    }
+}
+
+/// `<time> [<kind>] <text> (<url>:<line>)` plus a newline, for console events.
+fn format_event(event: &Event) -> Option<(Severity, String)> {
+    let (timestamp, severity, kind, text, location) = match event {
+        Event::RuntimeConsoleAPICalled(e) => {
+            let p = &e.params;
+            let text = p.args.iter().map(remote_text).collect::<Vec<_>>().join(" ");
+            let location = p
+                .stack_trace
+                .as_ref()
+                .and_then(|s| s.call_frames.first())
+                .map(|f| (f.url.clone(), f.line_number + 1));
+            let kind = name(&p.Type);
+            (
+                p.timestamp,
+                severity(&kind),
+                format!("console.{kind}"),
+                text,
+                location,
+            )
+        }
+        Event::LogEntryAdded(e) => {
+            let entry = &e.params.entry;
+            let location = entry
+                .url
+                .clone()
+                .filter(|u| !u.is_empty())
+                .map(|u| (u, entry.line_number.map_or(0, |l| l + 1)));
+            let level = name(&entry.level);
+            (
+                entry.timestamp,
+                severity(&level),
+                format!("{} {level}", name(&entry.source)),
+                entry.text.clone(),
+                location,
+            )
+        }
+        _ => return None,
+    };
+    let time = chrono::DateTime::from_timestamp_millis(timestamp as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let location = match location {
+        Some((url, line)) if line > 0 => format!(" ({url}:{line})"),
+        Some((url, _)) if !url.is_empty() => format!(" ({url})"),
+        _ => String::new(),
+    };
+    // One line per message: later lines of multi-line output are indented.
+    Some((
+        severity,
+        format!(
+            "{time} [{kind}] {}{location}\n",
+            text.replace('\n', "\n    ")
+        ),
+    ))
+}
+
+/// A console argument as DevTools would print it: strings bare, other values by their
+/// JSON or description.
+fn remote_text(o: &Runtime::RemoteObject) -> String {
+    match (&o.value, &o.unserializable_value, &o.description) {
+        (Some(serde_json::Value::String(s)), _, _) => s.clone(),
+        (Some(v), _, _) => v.to_string(),
+        (None, Some(u), _) => u.clone(),
+        (None, None, Some(d)) => d.clone(),
+        _ => name(&o.Type),
+    }
+}
+
+/// The protocol's name for an enum value.
+fn name<T: serde::Serialize>(v: &T) -> String {
+    serde_json::to_value(v)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
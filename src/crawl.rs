@@ -0,0 +1,183 @@
+//! Same-host crawl driver (`--crawl`), one run directory per visited page.
+
+use crate::{frontier::Frontier, progress::Progress, schedule::Pool, summary::SummaryRow, Cli};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+const SKIP_EXTENSIONS: &[&str] = &[
+    ".pdf", ".jpg", ".jpeg", ".png", ".gif", ".webp", ".svg", ".zip", ".gz", ".mp4", ".mp3",
+    ".css", ".js", ".xml", ".json",
+];
+
+pub async fn run(args: &Cli) -> Result<i32> {
+    let seed = url::Url::parse(args.url())?;
+    let host = seed.host_str().unwrap_or("page").to_string();
+    let crawl_id = args.crawl_id.clone().unwrap_or_else(|| host.clone());
+    let frontier = Frontier::open(&args.out_root, &crawl_id)?;
+    frontier.enqueue(seed.as_str(), 0, None)?;
+    let crawl_dir = args
+        .run_dir
+        .clone()
+        .unwrap_or_else(|| args.out_root.join(format!("crawl-{}", slug(&crawl_id))));
+
+    let done_at_start = frontier.done_count()?;
+    let remaining = |f: &Frontier| -> Result<u64> {
+        let budget = args.crawl_limit.saturating_sub(done_at_start);
+        let done = f.done_count()? - done_at_start;
+        Ok(budget.min(done + f.pending_count()?) as u64)
+    };
+    let progress = Progress::new(remaining(&frontier)?, args.workers, args.no_tui);
+    let budget = args.crawl_limit.saturating_sub(done_at_start);
+    let mut pool = Pool::new(args.workers, args.host_concurrency);
+    let mut claimed = 0;
+    let mut rows = Vec::new();
+    loop {
+        // Claim only a small buffer from the frontier so unvisited URLs stay queued in
+        // the database (and resumable) rather than in memory.
+        while claimed < budget && pool.queued() < args.workers.max(1) {
+            let Some(item) = frontier.next()? else {
+                break;
+            };
+            claimed += 1;
+            let seq = done_at_start + claimed;
+            let mut page_args = args.clone();
+            page_args.crawl = false;
+            page_args.url = Some(item.url.clone());
+            page_args.run_dir = Some(crawl_dir.join(format!("{:04}-{}", seq, slug(&item.url))));
+            // Shallower pages first.
+            pool.push(-(item.depth as i32), page_args, item);
+        }
+        pool.fill(|w, url| progress.start(w, url));
+        let Some(done) = pool.next_done().await else {
+            break;
+        };
+        let item = done.meta;
+
+        match done.result {
+            Ok(rec) => {
+                let result = &rec.result;
+                let status = result
+                    .get("http_status")
+                    .and_then(|v| v.as_u64())
+                    .map(|s| s as u16);
+                let run_dir = rec
+                    .result_json
+                    .parent()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                frontier.mark_visited(&item.url, status, &run_dir)?;
+                rows.push(SummaryRow::from_result(&item.url, rec.exit_code, result));
+                progress.finish(done.worker, rec.exit_code == 0);
+
+                if item.depth < args.crawl_depth {
+                    let html_path = result
+                        .get("html_path")
+                        .or_else(|| result.pointer("/artifacts/html"))
+                        .and_then(|v| v.as_str())
+                        .map(PathBuf::from);
+                    let base = result
+                        .get("final_url")
+                        .and_then(|v| v.as_str())
+                        .and_then(|u| url::Url::parse(u).ok())
+                        .unwrap_or_else(|| seed.clone());
+                    for link in html_path
+                        .as_deref()
+                        .map(|p| same_host_links(p, &base, &host))
+                        .unwrap_or_default()
+                    {
+                        frontier.enqueue(&link, item.depth + 1, Some(&item.url))?;
+                    }
+                }
+            }
+            Err(e) => {
+                let msg = format!("{:#}", e);
+                frontier.mark_failed(&item.url, &msg)?;
+                rows.push(SummaryRow::failed(&item.url, &msg));
+                progress.finish(done.worker, false);
+            }
+        }
+        progress.set_total(remaining(&frontier)?);
+    }
+    progress.done();
+    crate::summary::write(&crawl_dir, &rows)?;
+    if let Some(out) = &args.merge_pdf {
+        let parts = crate::mergepdf::crawl_parts(&frontier.visited()?);
+        let merged = crate::mergepdf::merge(&parts, &format!("Crawl of {}", seed), out)?;
+        eprintln!("merged {} page PDFs into {}", merged, out.display());
+    }
+    Ok(crate::summary::exit_code(&rows))
+}
+
+/// Links in `<a href>` tags of the saved HTML that stay on `host`, without fragments.
+fn same_host_links(html_path: &Path, base: &url::Url, host: &str) -> Vec<String> {
+    let html = match std::fs::read_to_string(html_path) {
+        Ok(h) => h,
+        Err(_) => return Vec::new(),
+    };
+    let mut out = Vec::new();
+    for href in anchor_hrefs(&html) {
+        let Ok(mut u) = base.join(&href) else {
+            continue;
+        };
+        u.set_fragment(None);
+        let path = u.path().to_ascii_lowercase();
+        if !matches!(u.scheme(), "http" | "https")
+            || u.host_str() != Some(host)
+            || SKIP_EXTENSIONS.iter().any(|e| path.ends_with(e))
+        {
+            continue;
+        }
+        let s = u.to_string();
+        if !out.contains(&s) {
+            out.push(s);
+        }
+    }
+    out
+}
+
+/// Raw href values of `<a>` tags.
+pub fn anchor_hrefs(html: &str) -> Vec<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = lower[pos..].find("<a") {
+        let start = pos + i;
+        let end = lower[start..]
+            .find('>')
+            .map(|e| start + e)
+            .unwrap_or(lower.len());
+        pos = end.max(start + 2);
+        let next = lower.as_bytes().get(start + 2).copied().unwrap_or(b'>');
+        if !next.is_ascii_whitespace() {
+            continue;
+        }
+        let tag = &html[start..end];
+        let tag_lower = &lower[start..end];
+        if let Some(h) = tag_lower.find("href=") {
+            let rest = &tag[h + 5..];
+            let value = match rest.chars().next() {
+                Some(q @ ('"' | '\'')) => rest[1..].split(q).next().unwrap_or(""),
+                _ => rest
+                    .split(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .next()
+                    .unwrap_or(""),
+            };
+            if !value.is_empty() && !value.starts_with("javascript:") {
+                out.push(value.replace("&amp;", "&"));
+            }
+        }
+    }
+    out
+}
+
+pub fn slug(s: &str) -> String {
+    let s = s
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let mut out: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    out.truncate(60);
+    out.trim_matches('-').to_string()
+}
@@ -0,0 +1,94 @@
+//! Content hash for cross-run deduplication: the visible text of the page's main content
+//! region, with volatile tokens (timestamps, counters, nonces) dropped before hashing.
+
+use crate::{article, golden};
+use sha2::{Digest, Sha256};
+
+/// sha256 (hex) of the normalized main-content text of `html`.
+pub fn content_hash(html: &str) -> String {
+    let text = golden::html_to_text(article::content_region(html));
+    let normalized = text
+        .split_whitespace()
+        .filter(|w| !is_volatile(w))
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Words that change between otherwise identical captures: anything with a digit
+/// (dates, times, view counts, cache busters) and long random-looking tokens.
+fn is_volatile(word: &str) -> bool {
+    word.chars().any(|c| c.is_ascii_digit())
+        || (word.len() >= 16
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(nav: &str, article: &str) -> String {
+        format!(
+            "<html><body><nav>{nav}</nav><article>{article}</article>\
+             <footer>© 2024</footer></body></html>"
+        )
+    }
+
+    #[test]
+    fn timestamp_and_counter_churn_hash_equal() {
+        let a = page(
+            "Home",
+            "<h1>Release notes</h1><p>Updated 2024-05-31 14:25:01 · 1,204 views</p>\
+             <p>The importer now keeps tags.</p>",
+        );
+        let b = page(
+            "Home",
+            "<h1>Release notes</h1><p>Updated 2024-06-01 09:00:12 · 1,377 views</p>\
+             <p>The importer now keeps tags.</p>",
+        );
+        assert_eq!(content_hash(&a), content_hash(&b));
+    }
+
+    #[test]
+    fn nonces_case_and_whitespace_hash_equal() {
+        let a = page("", "<p>Session abcdefABCDEF_ghij-klmn ready</p>");
+        let b = page("", "<p>session   QWERTYuiopasdfgh_zxcv\n READY</p>");
+        assert_eq!(content_hash(&a), content_hash(&b));
+    }
+
+    #[test]
+    fn text_outside_the_main_region_is_ignored() {
+        let a = page("Sale ends soon", "<p>Same story.</p>");
+        let b = page("Log in", "<p>Same story.</p>");
+        assert_eq!(content_hash(&a), content_hash(&b));
+    }
+
+    #[test]
+    fn real_content_change_hashes_differently() {
+        let a = page("", "<p>The importer now keeps tags.</p>");
+        let b = page("", "<p>The importer now drops tags.</p>");
+        assert_ne!(content_hash(&a), content_hash(&b));
+        let added = page("", "<p>The importer now keeps tags.</p><p>And folders.</p>");
+        assert_ne!(content_hash(&a), content_hash(&added));
+    }
+
+    #[test]
+    fn volatile_words() {
+        for w in [
+            "2024-05-31",
+            "14:25",
+            "1,204",
+            "v2",
+            "a1b2",
+            "abcdefghijklmnop",
+        ] {
+            assert!(is_volatile(w), "{w}");
+        }
+        for w in ["importer", "tags.", "short_token", "well-known-words!"] {
+            assert!(!is_volatile(w), "{w}");
+        }
+    }
+}
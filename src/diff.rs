@@ -0,0 +1,71 @@
+//! Structured diff between two stored runs (status fields, text, screenshot).
+
+use crate::{
+    golden::{self, GoldenDelta},
+    index::IndexedRun,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Serialize)]
+pub struct RunDiff {
+    pub url: String,
+    pub from: IndexedRun,
+    pub to: IndexedRun,
+    /// Status-level changes (final URL, HTTP status, WAF/challenge flags).
+    pub changes: Vec<GoldenDelta>,
+    /// Word-level Jaccard similarity of the visible DOM text (1.0 = identical).
+    pub text_similarity: f64,
+    /// Hamming distance (0-64) between the screenshots' difference hashes; absent when
+    /// either run has no screenshot.
+    pub visual_hash_distance: Option<u32>,
+}
+
+pub fn diff_runs(from: IndexedRun, to: IndexedRun) -> Result<RunDiff> {
+    let from_dir = Path::new(&from.run_dir);
+    let to_dir = Path::new(&to.run_dir);
+    let a = read_result(from_dir)?;
+    let b = read_result(to_dir)?;
+    let visual_hash_distance = match (
+        golden::artifact(&a, from_dir, "screenshot_path").and_then(|p| dhash(&p)),
+        golden::artifact(&b, to_dir, "screenshot_path").and_then(|p| dhash(&p)),
+    ) {
+        (Some(x), Some(y)) => Some((x ^ y).count_ones()),
+        _ => None,
+    };
+    Ok(RunDiff {
+        url: to.url.clone(),
+        changes: golden::field_deltas(&a, &b),
+        text_similarity: golden::similarity(
+            &golden::run_text(&a, from_dir),
+            &golden::run_text(&b, to_dir),
+        ),
+        visual_hash_distance,
+        from,
+        to,
+    })
+}
+
+fn read_result(dir: &Path) -> Result<serde_json::Value> {
+    let path = dir.join("result.json");
+    Ok(serde_json::from_slice(
+        &std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?,
+    )?)
+}
+
+/// 64-bit difference hash: grayscale 9x8 thumbnail, one bit per horizontal gradient.
+pub fn dhash(path: &Path) -> Option<u64> {
+    let img = image::open(path).ok()?;
+    let small = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    Some(hash)
+}
@@ -0,0 +1,158 @@
+//! Where the main document was served from: the peer's IP and port, and the host's CNAME
+//! chain, as `remote` in result.json, to tell CDN edges and geo-routing apart when
+//! captures differ between workers.
+//!
+//! The chain comes from a single A query to the first `nameserver` in /etc/resolv.conf. A
+//! recursive resolver answers with every CNAME it followed. With no resolver reachable the
+//! chain and addresses are left out, and the peer IP is still reported.
+
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const TYPE_A: u16 = 1;
+const TYPE_CNAME: u16 = 5;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Remote {
+    pub host: String,
+    /// The address the document actually came from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Canonical names followed from `host`, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cname_chain: Vec<String>,
+    /// A records the resolver returned at the end of the chain.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+}
+
+/// Describe the host of `url`, served from `ip`:`port`.
+pub fn describe(url: &str, ip: Option<&str>, port: Option<u16>) -> Option<Remote> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_string();
+    let ip = ip
+        .map(|ip| ip.trim_start_matches('[').trim_end_matches(']'))
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string);
+    let (cname_chain, addresses) = match host.parse::<IpAddr>() {
+        Ok(_) => (Vec::new(), Vec::new()),
+        Err(_) => resolve(&host).unwrap_or_default(),
+    };
+    Some(Remote {
+        host,
+        ip,
+        port,
+        cname_chain,
+        addresses,
+    })
+}
+
+fn nameserver() -> Option<IpAddr> {
+    std::fs::read_to_string("/etc/resolv.conf")
+        .ok()?
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("nameserver"))
+        .find_map(|ns| ns.trim().split('%').next()?.parse().ok())
+}
+
+/// (CNAME chain, A records) for `host`.
+fn resolve(host: &str) -> Option<(Vec<String>, Vec<String>)> {
+    let server = SocketAddr::new(nameserver()?, 53);
+    let bind: SocketAddr = if server.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        "[::]:0".parse().ok()?
+    };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT)).ok()?;
+    let id = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .subsec_nanos() as u16;
+    socket.send_to(&query(id, host)?, server).ok()?;
+    let mut buf = [0u8; 4096];
+    loop {
+        let (n, from) = socket.recv_from(&mut buf).ok()?;
+        if from == server && n >= 12 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            return parse_answers(&buf[..n]);
+        }
+    }
+}
+
+/// A recursive A query for `host`.
+fn query(id: u16, host: &str) -> Option<Vec<u8>> {
+    let mut q = Vec::with_capacity(32 + host.len());
+    q.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired; one question.
+    q.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
+        }
+        q.push(label.len() as u8);
+        q.extend_from_slice(label.as_bytes());
+    }
+    q.push(0);
+    q.extend_from_slice(&TYPE_A.to_be_bytes());
+    q.extend_from_slice(&1u16.to_be_bytes());
+    Some(q)
+}
+
+fn parse_answers(msg: &[u8]) -> Option<(Vec<String>, Vec<String>)> {
+    let u16_at = |pos: usize| Some(u16::from_be_bytes([*msg.get(pos)?, *msg.get(pos + 1)?]));
+    // Any error code (NXDOMAIN, SERVFAIL, …) means there is nothing to report.
+    if msg[3] & 0x0f != 0 {
+        return None;
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(msg, pos)?.1 + 4;
+    }
+    let mut chain = Vec::new();
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        pos = read_name(msg, pos)?.1;
+        let rtype = u16_at(pos)?;
+        let len = u16_at(pos + 8)? as usize;
+        let data = pos + 10;
+        let rdata = msg.get(data..data + len)?;
+        match rtype {
+            TYPE_CNAME => chain.push(read_name(msg, data)?.0),
+            TYPE_A if len == 4 => {
+                addresses.push(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]).to_string())
+            }
+            _ => {}
+        }
+        pos = data + len;
+    }
+    Some((chain, addresses))
+}
+
+/// The (possibly compressed) domain name at `pos`, and the offset just past it.
+fn read_name(msg: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bounds the number of compression pointers followed, against loops.
+    for _ in 0..128 {
+        let len = *msg.get(pos)? as usize;
+        match len {
+            0 => {
+                return Some((labels.join("."), end.unwrap_or(pos + 1)));
+            }
+            l if l & 0xc0 == 0xc0 => {
+                end.get_or_insert(pos + 2);
+                pos = ((l & 0x3f) << 8) | *msg.get(pos + 1)? as usize;
+            }
+            l => {
+                labels.push(String::from_utf8_lossy(msg.get(pos + 1..pos + 1 + l)?).into_owned());
+                pos += 1 + l;
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,244 @@
+//! `--dom-snapshot`: the rendered DOM with layout boxes and computed styles, from
+//! `DOMSnapshot.captureSnapshot`, written as `dom_snapshot.json`.
+//!
+//! CDP returns parallel arrays that index into a shared string table. They are resolved
+//! here into one record per node, so consumers don't need to know the protocol: each node
+//! has its parent index, name, attributes and, when it was laid out, its boxes.
+
+use anyhow::{Context, Result};
+use headless_chrome::protocol::cdp::types::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Computed styles recorded for every layout box: the ones that decide where a box is,
+/// whether it is seen, and how prominent it is.
+const STYLES: &[&str] = &[
+    "display",
+    "position",
+    "visibility",
+    "opacity",
+    "z-index",
+    "overflow",
+    "float",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "color",
+    "background-color",
+    "background-image",
+];
+
+#[derive(Serialize)]
+struct Snapshot {
+    documents: Vec<Document>,
+}
+
+#[derive(Serialize)]
+struct Document {
+    url: String,
+    title: String,
+    frame_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_width: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scroll_x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scroll_y: Option<f64>,
+    nodes: Vec<Node>,
+}
+
+#[derive(Serialize)]
+struct Node {
+    /// Index of the parent in `nodes`; absent for the document node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<u32>,
+    /// DOM node type (1 element, 3 text, 9 document, …).
+    #[serde(rename = "type")]
+    node_type: u32,
+    name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    value: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, String>,
+    /// Current value of form controls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_value: Option<String>,
+    /// Index into `documents` of an iframe's content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_document: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    boxes: Vec<LayoutBox>,
+}
+
+#[derive(Serialize)]
+struct LayoutBox {
+    /// `[x, y, width, height]` in CSS px, relative to the document.
+    bounds: Vec<f64>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paint_order: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stacking_context: bool,
+    styles: BTreeMap<&'static str, String>,
+}
+
+/// `DOMSnapshot.captureSnapshot` with its result kept close to the wire format. The
+/// generated protocol types use unsigned indices, but Chrome sends -1 for "none" (the
+/// document's parent, absent strings), which they can't deserialize.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CaptureSnapshot {
+    computed_styles: &'static [&'static str],
+    include_paint_order: bool,
+}
+
+impl Method for CaptureSnapshot {
+    const NAME: &'static str = "DOMSnapshot.captureSnapshot";
+    type ReturnObject = RawSnapshot;
+}
+
+#[derive(Deserialize, Debug)]
+struct RawSnapshot {
+    documents: Vec<RawDocument>,
+    strings: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RawDocument {
+    #[serde(rename = "documentURL")]
+    document_url: i64,
+    title: i64,
+    frame_id: i64,
+    nodes: RawNodes,
+    layout: RawLayout,
+    scroll_offset_x: Option<f64>,
+    scroll_offset_y: Option<f64>,
+    content_width: Option<f64>,
+    content_height: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct RawNodes {
+    parent_index: Vec<i64>,
+    node_type: Vec<i64>,
+    node_name: Vec<i64>,
+    node_value: Vec<i64>,
+    attributes: Vec<Vec<i64>>,
+    input_value: RareData,
+    content_document_index: RareData,
+}
+
+/// Values for the few nodes that have one.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct RareData {
+    index: Vec<i64>,
+    value: Vec<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct RawLayout {
+    node_index: Vec<i64>,
+    styles: Vec<Vec<i64>>,
+    bounds: Vec<Vec<f64>>,
+    text: Vec<i64>,
+    stacking_contexts: RareData,
+    paint_orders: Vec<i64>,
+}
+
+/// Capture a snapshot of the current page (including iframes) into `path`.
+pub fn capture(tab: &headless_chrome::Tab, path: &Path) -> Result<()> {
+    let snap = tab.call_method(CaptureSnapshot {
+        computed_styles: STYLES,
+        include_paint_order: true,
+    })?;
+    let s = |i: i64| -> String {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| snap.strings.get(i))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let rare = |d: &RareData| -> HashMap<i64, i64> {
+        d.index
+            .iter()
+            .copied()
+            .zip(d.value.iter().copied())
+            .collect()
+    };
+
+    let mut documents = Vec::new();
+    for doc in &snap.documents {
+        let n = &doc.nodes;
+        let inputs = rare(&n.input_value);
+        let content_docs = rare(&n.content_document_index);
+        let mut nodes: Vec<Node> = (0..n.node_name.len())
+            .map(|i| {
+                let at = |v: &[i64]| v.get(i).copied().unwrap_or(-1);
+                Node {
+                    parent: u32::try_from(at(&n.parent_index)).ok(),
+                    node_type: u32::try_from(at(&n.node_type)).unwrap_or(0),
+                    name: s(at(&n.node_name)),
+                    value: s(at(&n.node_value)),
+                    attributes: n
+                        .attributes
+                        .get(i)
+                        .map(|a| a.chunks_exact(2).map(|kv| (s(kv[0]), s(kv[1]))).collect())
+                        .unwrap_or_default(),
+                    input_value: inputs.get(&(i as i64)).map(|&v| s(v)),
+                    content_document: content_docs
+                        .get(&(i as i64))
+                        .and_then(|&d| u32::try_from(d).ok()),
+                    boxes: Vec::new(),
+                }
+            })
+            .collect();
+
+        let layout = &doc.layout;
+        let stacking: std::collections::HashSet<i64> =
+            layout.stacking_contexts.index.iter().copied().collect();
+        for (l, &node) in layout.node_index.iter().enumerate() {
+            let Some(target) = usize::try_from(node).ok().and_then(|i| nodes.get_mut(i)) else {
+                continue;
+            };
+            let styles = layout
+                .styles
+                .get(l)
+                .map(|vals| STYLES.iter().zip(vals).map(|(&k, &v)| (k, s(v))).collect())
+                .unwrap_or_default();
+            target.boxes.push(LayoutBox {
+                bounds: layout.bounds.get(l).cloned().unwrap_or_default(),
+                text: layout.text.get(l).map(|&t| s(t)).unwrap_or_default(),
+                paint_order: layout
+                    .paint_orders
+                    .get(l)
+                    .and_then(|&p| u32::try_from(p).ok()),
+                stacking_context: stacking.contains(&(l as i64)),
+                styles,
+            });
+        }
+
+        documents.push(Document {
+            url: s(doc.document_url),
+            title: s(doc.title),
+            frame_id: s(doc.frame_id),
+            content_width: doc.content_width,
+            content_height: doc.content_height,
+            scroll_x: doc.scroll_offset_x,
+            scroll_y: doc.scroll_offset_y,
+            nodes,
+        });
+    }
+
+    let file =
+        std::fs::File::create(path).with_context(|| format!("writing {}", path.display()))?;
+    serde_json::to_writer(std::io::BufWriter::new(file), &Snapshot { documents })?;
+    Ok(())
+}
@@ -0,0 +1,242 @@
+//! `--epub`: the page's main content, title and images packaged as an EPUB 3 book.
+
+use crate::{extract, markdown::MAIN_CONTENT_JS, network::NetEntry};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, io::Write, path::Path};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Sources of the visible images inside the content root, in document order.
+const IMAGE_SOURCES_JS: &str = r#"((root) => {
+  const out = [];
+  for (const img of root.querySelectorAll('img')) {
+    const src = img.currentSrc || img.src;
+    if (!src || out.includes(src) || getComputedStyle(img).display === 'none') continue;
+    out.push(src);
+  }
+  return JSON.stringify(out);
+})"#;
+
+/// Clone the content root into well-formed XHTML: interactive, embedded and hidden
+/// elements dropped, images pointed at their files in the book (or dropped when not
+/// packaged), links made absolute.
+const XHTML_JS: &str = r#"((root, images) => {
+  const HIDDEN = 'data-ankabot-hidden';
+  const SRC = 'data-ankabot-src';
+  for (const el of root.querySelectorAll('*')) {
+    const s = getComputedStyle(el);
+    if (s.display === 'none' || s.visibility === 'hidden') el.setAttribute(HIDDEN, '');
+    if (el.tagName === 'IMG') el.setAttribute(SRC, el.currentSrc || el.src || '');
+    if (el.tagName === 'A' && el.getAttribute('href')) el.setAttribute('data-ankabot-href', el.href);
+  }
+  const clone = root.cloneNode(true);
+  for (const el of root.querySelectorAll(`[${HIDDEN}], [${SRC}], [data-ankabot-href]`)) {
+    el.removeAttribute(HIDDEN);
+    el.removeAttribute(SRC);
+    el.removeAttribute('data-ankabot-href');
+  }
+
+  clone.querySelectorAll(`[${HIDDEN}], script, style, noscript, template, iframe, frame, object, embed,
+    form, button, input, select, textarea, canvas, video, audio, svg, math, source, nav, aside, footer,
+    dialog, link, meta`).forEach((el) => el.remove());
+  // Custom elements aren't valid XHTML; keep their content.
+  for (const el of Array.from(clone.querySelectorAll('*')).filter((e) => e.tagName.includes('-'))) {
+    el.replaceWith(...el.childNodes);
+  }
+  for (const img of clone.querySelectorAll('img')) {
+    const file = images[img.getAttribute(SRC)];
+    if (!file) { img.remove(); continue; }
+    const alt = img.getAttribute('alt') || '';
+    for (const a of Array.from(img.attributes)) img.removeAttribute(a.name);
+    img.setAttribute('src', file);
+    img.setAttribute('alt', alt);
+  }
+  for (const el of clone.querySelectorAll('*')) {
+    const href = el.getAttribute('data-ankabot-href');
+    for (const a of Array.from(el.attributes)) {
+      if (/^(on|data-)|^(style|srcset|sizes|loading|decoding|fetchpriority|contenteditable|tabindex)$/i.test(a.name)) {
+        el.removeAttribute(a.name);
+      }
+    }
+    if (href) el.setAttribute('href', href);
+  }
+  const wrap = document.createElement('div');
+  while (clone.firstChild) wrap.appendChild(clone.firstChild);
+  const h1 = document.querySelector('h1');
+  return JSON.stringify({
+    title: (document.title || (h1 && h1.innerText) || '').trim(),
+    lang: document.documentElement.lang || 'en',
+    has_h1: !!wrap.querySelector('h1'),
+    xhtml: new XMLSerializer().serializeToString(wrap),
+  });
+})"#;
+
+#[derive(Deserialize)]
+struct Content {
+    title: String,
+    lang: String,
+    has_h1: bool,
+    xhtml: String,
+}
+
+/// Package the main content of the current document, its title and its images into an
+/// EPUB 3 file at `path`. `url` becomes the book's identifier and source.
+pub fn write_epub(
+    tab: &headless_chrome::Tab,
+    entries: &[NetEntry],
+    url: &str,
+    path: &Path,
+) -> Result<()> {
+    let eval = |expr: String| -> Result<String> {
+        tab.evaluate(&expr, false)?
+            .value
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .ok_or_else(|| anyhow!("EPUB extraction returned nothing"))
+    };
+    let sources: Vec<String> =
+        serde_json::from_str(&eval(format!("{}({})", IMAGE_SOURCES_JS, MAIN_CONTENT_JS))?)?;
+
+    // Images EPUB readers can't be relied on to display (e.g. AVIF) are left out.
+    let mut images = Vec::new();
+    let mut files = HashMap::new();
+    for src in sources {
+        let Ok((bytes, ext)) = extract::image_bytes(tab, entries, &src) else {
+            continue;
+        };
+        let Some(media_type) = media_type(ext) else {
+            continue;
+        };
+        let name = format!("images/{:03}.{}", images.len() + 1, ext);
+        files.insert(src, name.clone());
+        images.push((name, media_type, bytes));
+    }
+
+    let content: Content = serde_json::from_str(&eval(format!(
+        "{}({}, {})",
+        XHTML_JS,
+        MAIN_CONTENT_JS,
+        serde_json::to_string(&files)?
+    ))?)?;
+    let title = if content.title.is_empty() {
+        url.to_string()
+    } else {
+        content.title
+    };
+    let (title, lang, url) = (
+        xml_escape(&title),
+        xml_escape(&content.lang),
+        xml_escape(url),
+    );
+
+    let heading = if content.has_h1 {
+        String::new()
+    } else {
+        format!("<h1>{}</h1>\n", title)
+    };
+    let chapter = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" lang="{lang}" xml:lang="{lang}">
+<head><meta charset="UTF-8"/><title>{title}</title></head>
+<body>
+{heading}{body}
+</body>
+</html>
+"#,
+        body = content.xhtml,
+    );
+    let nav = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{lang}" xml:lang="{lang}">
+<head><meta charset="UTF-8"/><title>{title}</title></head>
+<body>
+<nav epub:type="toc"><ol><li><a href="content.xhtml">{title}</a></li></ol></nav>
+</body>
+</html>
+"#
+    );
+    let image_items: String = images
+        .iter()
+        .enumerate()
+        .map(|(i, (name, media_type, _))| {
+            format!(
+                "    <item id=\"img{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                i + 1,
+                name,
+                media_type
+            )
+        })
+        .collect();
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid" xml:lang="{lang}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">{url}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{lang}</dc:language>
+    <dc:source>{url}</dc:source>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="content" href="content.xhtml" media-type="application/xhtml+xml"/>
+{image_items}  </manifest>
+  <spine>
+    <itemref idref="content"/>
+  </spine>
+</package>
+"#
+    );
+
+    let mut zip = ZipWriter::new(std::fs::File::create(path)?);
+    // The mimetype entry must come first and be stored uncompressed.
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, bytes: &[u8]| -> Result<()> {
+        zip.start_file(name, deflated)?;
+        zip.write_all(bytes)?;
+        Ok(())
+    };
+    add("META-INF/container.xml", CONTAINER_XML.as_bytes())?;
+    add("OEBPS/content.opf", opf.as_bytes())?;
+    add("OEBPS/nav.xhtml", nav.as_bytes())?;
+    add("OEBPS/content.xhtml", chapter.as_bytes())?;
+    for (name, _, bytes) in &images {
+        add(&format!("OEBPS/{}", name), bytes)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// EPUB core media type for an image extension from [`extract::image_bytes`].
+fn media_type(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "jpg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
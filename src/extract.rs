@@ -0,0 +1,295 @@
+//! Opt-in extraction passes over the rendered page.
+
+use crate::network::{self, NetEntry};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct ImageCandidate {
+    src: String,
+    alt: String,
+    width: u64,
+    height: u64,
+}
+
+#[derive(Serialize)]
+pub struct ImageRecord {
+    pub file: Option<String>,
+    pub src: String,
+    pub alt: String,
+    pub width: u64,
+    pub height: u64,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+const IMAGE_CANDIDATES_JS: &str = r#"((minPx) => {
+  const seen = new Set();
+  const out = [];
+  for (const img of document.images) {
+    const src = img.currentSrc || img.src;
+    if (!src || seen.has(src)) continue;
+    if (img.naturalWidth < minPx || img.naturalHeight < minPx) continue;
+    seen.add(src);
+    out.push({ src, alt: img.alt || '', width: img.naturalWidth, height: img.naturalHeight });
+  }
+  return JSON.stringify(out);
+})"#;
+
+const FETCH_DATA_URL_JS: &str = r#"((src) => fetch(src, { credentials: 'include' })
+  .then(r => r.blob())
+  .then(b => new Promise((res, rej) => {
+    const fr = new FileReader();
+    fr.onload = () => res(fr.result);
+    fr.onerror = () => rej(fr.error);
+    fr.readAsDataURL(b);
+  })))"#;
+
+/// Save content images at least `min_px` in both dimensions into `dir` and write `index.json`.
+///
+/// Bytes come from Chrome's network buffer when the image was loaded by the page, falling
+/// back to an in-page fetch (same cookies/session) so nothing is re-requested from outside.
+pub fn extract_images(
+    tab: &headless_chrome::Tab,
+    entries: &[NetEntry],
+    dir: &Path,
+    min_px: u64,
+) -> Result<Vec<ImageRecord>> {
+    std::fs::create_dir_all(dir)?;
+    let raw = tab
+        .evaluate(&format!("{}({})", IMAGE_CANDIDATES_JS, min_px), false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "[]".to_string());
+    let candidates: Vec<ImageCandidate> = serde_json::from_str(&raw)?;
+
+    let mut records = Vec::new();
+    for (i, c) in candidates.into_iter().enumerate() {
+        let bytes = image_bytes(tab, entries, &c.src);
+        let mut rec = ImageRecord {
+            file: None,
+            src: c.src,
+            alt: c.alt,
+            width: c.width,
+            height: c.height,
+            bytes: 0,
+            error: None,
+        };
+        match bytes {
+            Ok((data, ext)) => {
+                let name = format!("{:03}.{}", i + 1, ext);
+                std::fs::write(dir.join(&name), &data)?;
+                rec.bytes = data.len() as u64;
+                rec.file = Some(name);
+            }
+            Err(e) => rec.error = Some(e.to_string()),
+        }
+        records.push(rec);
+    }
+    std::fs::write(dir.join("index.json"), serde_json::to_vec_pretty(&records)?)?;
+    Ok(records)
+}
+
+/// Bytes and file extension of an image the page shows: decoded from a data URL, taken from
+/// Chrome's network buffer, or fetched in-page with the page's cookies.
+pub fn image_bytes(
+    tab: &headless_chrome::Tab,
+    entries: &[NetEntry],
+    src: &str,
+) -> Result<(Vec<u8>, &'static str)> {
+    use base64::Engine as _;
+
+    if let Some(data) = src.strip_prefix("data:") {
+        let (meta, payload) = data.split_once(',').unwrap_or(("", data));
+        let bytes = if meta.ends_with(";base64") {
+            base64::engine::general_purpose::STANDARD.decode(payload)?
+        } else {
+            payload.as_bytes().to_vec()
+        };
+        return Ok((bytes, ext_for(meta, src)));
+    }
+
+    let loaded = entries
+        .iter()
+        .rev()
+        .find(|e| e.request.url == src && e.response.is_some() && e.failed.is_none());
+    if let Some(entry) = loaded {
+        if let Ok(bytes) = network::response_body(tab, &entry.request_id) {
+            let mime = entry
+                .response
+                .as_ref()
+                .map(|r| r.mime_type.as_str())
+                .unwrap_or("");
+            return Ok((bytes, ext_for(mime, src)));
+        }
+    }
+
+    let data_url = tab
+        .evaluate(&format!("{}({:?})", FETCH_DATA_URL_JS, src), true)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .ok_or_else(|| anyhow::anyhow!("in-page fetch returned no data"))?;
+    let (meta, payload) = data_url
+        .strip_prefix("data:")
+        .and_then(|d| d.split_once(','))
+        .ok_or_else(|| anyhow::anyhow!("unexpected data URL"))?;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(payload)?;
+    Ok((bytes, ext_for(meta, src)))
+}
+
+fn ext_for(mime: &str, src: &str) -> &'static str {
+    let m = mime.to_ascii_lowercase();
+    let s = src.to_ascii_lowercase();
+    let s = s.split(['?', '#']).next().unwrap_or("");
+    if m.contains("png") || s.ends_with(".png") {
+        "png"
+    } else if m.contains("webp") || s.ends_with(".webp") {
+        "webp"
+    } else if m.contains("gif") || s.ends_with(".gif") {
+        "gif"
+    } else if m.contains("svg") || s.ends_with(".svg") {
+        "svg"
+    } else if m.contains("avif") || s.ends_with(".avif") {
+        "avif"
+    } else {
+        "jpg"
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct Contacts {
+    pub emails: Vec<ContactLink>,
+    pub phones: Vec<ContactLink>,
+    pub organizations: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContactLink {
+    pub value: String,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+struct DomContacts {
+    mailto: Vec<ContactLink>,
+    tel: Vec<ContactLink>,
+    ld_json: Vec<String>,
+    microdata: Vec<serde_json::Value>,
+}
+
+const CONTACTS_JS: &str = r#"(() => {
+  const links = (scheme) => Array.from(document.querySelectorAll(`a[href^="${scheme}" i]`)).map(a => ({
+    value: decodeURIComponent(a.getAttribute('href').slice(scheme.length).split('?')[0]).trim(),
+    text: (a.innerText || '').trim(),
+  }));
+  const ld_json = Array.from(document.querySelectorAll('script[type="application/ld+json"]')).map(s => s.textContent);
+  const microdata = [];
+  for (const el of document.querySelectorAll('[itemscope][itemtype]')) {
+    const type = el.getAttribute('itemtype').split('/').pop();
+    const item = { '@type': type };
+    for (const p of el.querySelectorAll('[itemprop]')) {
+      if (p.parentElement.closest('[itemscope]') !== el) continue;
+      const v = p.getAttribute('content') || p.getAttribute('href') || (p.innerText || '').trim();
+      if (v && !(p.getAttribute('itemprop') in item)) item[p.getAttribute('itemprop')] = v;
+    }
+    microdata.push(item);
+  }
+  return JSON.stringify({ mailto: links('mailto:'), tel: links('tel:'), ld_json, microdata });
+})()"#;
+
+/// schema.org types treated as a business contact card.
+const ORG_TYPES: &[&str] = &["Organization", "LocalBusiness", "Corporation"];
+const ORG_FIELDS: &[&str] = &[
+    "@type",
+    "name",
+    "legalName",
+    "url",
+    "email",
+    "telephone",
+    "faxNumber",
+    "address",
+    "contactPoint",
+    "sameAs",
+    "openingHours",
+    "geo",
+];
+
+/// Pull mailto/tel links and schema.org Organization/LocalBusiness data from the DOM
+/// into `path` (contacts.json).
+pub fn extract_contacts(tab: &headless_chrome::Tab, path: &Path) -> Result<Contacts> {
+    let raw = tab
+        .evaluate(CONTACTS_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .ok_or_else(|| anyhow::anyhow!("contact scan returned nothing"))?;
+    let dom: DomContacts = serde_json::from_str(&raw)?;
+
+    let mut contacts = Contacts {
+        emails: dedup_links(dom.mailto),
+        phones: dedup_links(dom.tel),
+        organizations: Vec::new(),
+    };
+    let ld: Vec<serde_json::Value> = dom
+        .ld_json
+        .iter()
+        .filter_map(|text| serde_json::from_str(text).ok())
+        .collect();
+    let mut nodes = Vec::new();
+    for item in ld.iter().chain(&dom.microdata) {
+        collect_org_nodes(item, &mut nodes);
+    }
+    for node in nodes {
+        let card: serde_json::Map<String, serde_json::Value> = ORG_FIELDS
+            .iter()
+            .filter_map(|k| node.get(*k).map(|v| (k.to_string(), v.clone())))
+            .collect();
+        let card = serde_json::Value::Object(card);
+        if !contacts.organizations.contains(&card) {
+            contacts.organizations.push(card);
+        }
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(&contacts)?)?;
+    Ok(contacts)
+}
+
+fn dedup_links(list: Vec<ContactLink>) -> Vec<ContactLink> {
+    let mut out: Vec<ContactLink> = Vec::new();
+    for l in list {
+        if !l.value.is_empty() && !out.iter().any(|o| o.value.eq_ignore_ascii_case(&l.value)) {
+            out.push(l);
+        }
+    }
+    out
+}
+
+/// Walk JSON-LD (including `@graph` and nested values) for organization-typed nodes.
+fn collect_org_nodes<'a>(v: &'a serde_json::Value, out: &mut Vec<&'a serde_json::Value>) {
+    match v {
+        serde_json::Value::Array(items) => items.iter().for_each(|i| collect_org_nodes(i, out)),
+        serde_json::Value::Object(map) => {
+            let is_org = match map.get("@type") {
+                Some(serde_json::Value::String(t)) => is_org_type(t),
+                Some(serde_json::Value::Array(ts)) => {
+                    ts.iter().any(|t| t.as_str().is_some_and(is_org_type))
+                }
+                _ => false,
+            };
+            if is_org {
+                out.push(v);
+            }
+            for (k, child) in map {
+                if k != "@type" {
+                    collect_org_nodes(child, out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_org_type(t: &str) -> bool {
+    let t = t.rsplit(['/', ':']).next().unwrap_or(t);
+    ORG_TYPES.contains(&t) || t.ends_with("Business") || t.ends_with("Store")
+}
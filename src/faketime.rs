@@ -0,0 +1,59 @@
+//! `--fake-time`: run the page's clock from a fixed instant, so relative dates ("3 minutes
+//! ago") and countdowns render the same on every capture.
+//!
+//! The clock keeps ticking from that instant rather than standing still: debounce and
+//! throttle helpers measure elapsed time with `Date.now()` and never fire on a frozen
+//! clock. `performance.now()` is untouched, since it isn't wall-clock time.
+
+use chrono::{DateTime, Utc};
+
+/// Parse an RFC 3339 timestamp, e.g. `2024-01-01T00:00:00Z`.
+pub fn parse(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| {
+            format!(
+                "expected an RFC 3339 time like 2024-01-01T00:00:00Z ({})",
+                e
+            )
+        })
+}
+
+/// Script for every new document that shifts `Date` (and the `Intl` formatters that read
+/// the current time implicitly) to start at `t`.
+pub fn script(t: DateTime<Utc>) -> String {
+    format!(
+        r#"(() => {{
+  const RealDate = Date;
+  const realNow = RealDate.now.bind(RealDate);
+  const offset = {fake} - realNow();
+  const now = () => realNow() + offset;
+  const FakeDate = new Proxy(RealDate, {{
+    construct(target, args, newTarget) {{
+      return Reflect.construct(target, args.length ? args : [now()], newTarget);
+    }},
+    apply() {{
+      return new RealDate(now()).toString();
+    }},
+  }});
+  RealDate.now = now;
+  window.Date = FakeDate;
+  try {{
+    const proto = Intl.DateTimeFormat.prototype;
+    const format = Object.getOwnPropertyDescriptor(proto, 'format').get;
+    Object.defineProperty(proto, 'format', {{
+      configurable: true,
+      get() {{
+        const f = format.call(this);
+        return (d) => f(d === undefined ? now() : d);
+      }},
+    }});
+    const toParts = proto.formatToParts;
+    proto.formatToParts = function (d) {{
+      return toParts.call(this, d === undefined ? now() : d);
+    }};
+  }} catch (e) {{}}
+}})();"#,
+        fake = t.timestamp_millis()
+    )
+}
@@ -0,0 +1,105 @@
+//! `--filmstrip-ms`: periodic viewport screenshots from navigation start until the page is
+//! ready, to see what a slow page looked like while it loaded.
+//!
+//! Frames are taken on a separate thread so they keep coming while the main thread is
+//! blocked in navigation and the readiness waits. They are small JPEGs whatever
+//! `--screenshot-format` says: they're for looking at, not archiving.
+
+use anyhow::{anyhow, Result};
+use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const QUALITY: u32 = 50;
+/// Bounds the disk a page that never gets ready can take (2 minutes at 500 ms).
+const MAX_FRAMES: usize = 240;
+
+#[derive(Serialize)]
+struct Frame {
+    /// Milliseconds since navigation start.
+    t_ms: u64,
+    /// Path relative to the filmstrip directory.
+    file: String,
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    interval_ms: u64,
+    frames: &'a [Frame],
+}
+
+pub struct Filmstrip {
+    dir: PathBuf,
+    interval: Duration,
+    stop: Sender<()>,
+    worker: JoinHandle<Vec<Frame>>,
+}
+
+impl Filmstrip {
+    /// Start taking a frame every `interval` into `dir`.
+    pub fn start(
+        tab: Arc<headless_chrome::Tab>,
+        dir: &Path,
+        interval: Duration,
+    ) -> Result<Filmstrip> {
+        std::fs::create_dir_all(dir)?;
+        let (stop, stopped) = mpsc::channel::<()>();
+        let out = dir.to_path_buf();
+        let worker = std::thread::spawn(move || {
+            let start = Instant::now();
+            let mut frames = Vec::new();
+            while frames.len() < MAX_FRAMES {
+                let t = start.elapsed();
+                // Before the first paint there is nothing to capture; try again next tick.
+                if let Ok(jpeg) = tab.capture_screenshot(
+                    CaptureScreenshotFormatOption::Jpeg,
+                    Some(QUALITY),
+                    None,
+                    true,
+                ) {
+                    let file = format!("{:04}.jpg", frames.len() + 1);
+                    if std::fs::write(out.join(&file), jpeg).is_ok() {
+                        frames.push(Frame {
+                            t_ms: t.as_millis() as u64,
+                            file,
+                        });
+                    }
+                }
+                let next = (t + interval).saturating_sub(start.elapsed());
+                match stopped.recv_timeout(next) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+            frames
+        });
+        Ok(Filmstrip {
+            dir: dir.to_path_buf(),
+            interval,
+            stop,
+            worker,
+        })
+    }
+
+    /// Stop taking frames and write `index.json`; returns its path.
+    pub fn finish(self) -> Result<PathBuf> {
+        let _ = self.stop.send(());
+        let frames = self
+            .worker
+            .join()
+            .map_err(|_| anyhow!("filmstrip thread panicked"))?;
+        let index = self.dir.join("index.json");
+        std::fs::write(
+            &index,
+            serde_json::to_vec_pretty(&Index {
+                interval_ms: self.interval.as_millis() as u64,
+                frames: &frames,
+            })?,
+        )?;
+        Ok(index)
+    }
+}
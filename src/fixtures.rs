@@ -0,0 +1,112 @@
+//! Local fixture server for hermetic runs (`--target-fixture NAME`).
+//!
+//! Each fixture exercises one branch of the pipeline without touching the network:
+//! `static` takes the HTTP fast path, `spa` needs JavaScript and settles on network
+//! idle, `slow` delays the document, `hang` never finishes loading (timeout path) and
+//! `challenge` mimics a WAF interstitial.
+
+use anyhow::{anyhow, Result};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use std::{convert::Infallible, net::SocketAddr, time::Duration};
+
+pub const FIXTURES: &[&str] = &["static", "slow", "spa", "hang", "challenge"];
+
+/// Start the fixture server on an ephemeral localhost port and return the URL of `name`.
+pub async fn start(name: &str) -> Result<String> {
+    if !FIXTURES.contains(&name) {
+        return Err(anyhow!(
+            "unknown fixture '{}' (expected one of: {})",
+            name,
+            FIXTURES.join(", ")
+        ));
+    }
+    let make_svc = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle)) });
+    let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_svc);
+    let addr = server.local_addr();
+    tokio::spawn(async move {
+        let _ = server.await;
+    });
+    Ok(format!("http://{}/{}", addr, name))
+}
+
+async fn handle(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let resp = match req.uri().path() {
+        "/static" => html(StatusCode::OK, &static_page()),
+        "/slow" => {
+            tokio::time::sleep(Duration::from_millis(2000)).await;
+            html(StatusCode::OK, &static_page())
+        }
+        "/spa" => html(StatusCode::OK, SPA_PAGE),
+        "/api/items" => {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Response::builder()
+                .header("content-type", "application/json")
+                .body(Body::from(r#"["alpha","beta","gamma"]"#))
+                .unwrap()
+        }
+        "/hang" => html(
+            StatusCode::OK,
+            r#"<!doctype html><html><body><h1>Hanging</h1><img src="/stall.png"></body></html>"#,
+        ),
+        "/stall.png" => {
+            tokio::time::sleep(Duration::from_secs(120)).await;
+            Response::new(Body::empty())
+        }
+        "/challenge" => {
+            let mut r = html(StatusCode::SERVICE_UNAVAILABLE, CHALLENGE_PAGE);
+            r.headers_mut().insert(
+                "server",
+                hyper::header::HeaderValue::from_static("cloudflare"),
+            );
+            r.headers_mut().insert(
+                "cf-ray",
+                hyper::header::HeaderValue::from_static("0000000000000000-FIX"),
+            );
+            r
+        }
+        _ => html(StatusCode::NOT_FOUND, "<html><body>not found</body></html>"),
+    };
+    Ok(resp)
+}
+
+fn html(status: StatusCode, body: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn static_page() -> String {
+    let items: String = (1..=20)
+        .map(|i| {
+            format!("<li><a href=\"/static?item={i}\">Item {i}</a> lorem ipsum dolor sit amet</li>")
+        })
+        .collect();
+    format!(
+        "<!doctype html><html><head><title>Static fixture</title>\
+         <link rel=\"alternate\" hreflang=\"en\" href=\"/static\">\
+         <link rel=\"alternate\" hreflang=\"de\" href=\"/static?lang=de\"></head>\
+         <body><main><h1>Static fixture</h1><ul>{items}</ul></main></body></html>"
+    )
+}
+
+const SPA_PAGE: &str = r#"<!doctype html>
+<html><head><title>SPA fixture</title></head>
+<body><div id="app"></div>
+<script>
+setTimeout(() => {
+  fetch('/api/items').then(r => r.json()).then(items => {
+    document.getElementById('app').innerHTML =
+      '<main><h1>SPA fixture</h1><ul>' + items.map(i => '<li>' + i + '</li>').join('') + '</ul></main>';
+  });
+}, 200);
+</script></body></html>"#;
+
+const CHALLENGE_PAGE: &str = r#"<!doctype html>
+<html><head><title>Just a moment...</title></head>
+<body><h1>Checking your browser before accessing the site.</h1>
+<p>This process is automatic. Your browser will redirect shortly.</p></body></html>"#;
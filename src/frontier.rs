@@ -0,0 +1,140 @@
+//! SQLite-backed crawl frontier (`<out_root>/ankabot.db`).
+//!
+//! Every discovered URL is a row keyed by (crawl, url) that moves through
+//! `queued` -> `in_progress` -> `visited` | `failed`, so a crawl can be resumed after a
+//! restart and inspected with plain SQL.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+pub struct Frontier {
+    conn: Connection,
+    crawl: String,
+}
+
+pub struct FrontierItem {
+    pub url: String,
+    pub depth: u32,
+}
+
+pub fn db_path(out_root: &Path) -> std::path::PathBuf {
+    out_root.join("ankabot.db")
+}
+
+impl Frontier {
+    pub fn open(out_root: &Path, crawl: &str) -> Result<Self> {
+        std::fs::create_dir_all(out_root)?;
+        let conn = Connection::open(db_path(out_root))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS frontier (
+                crawl         TEXT NOT NULL,
+                url           TEXT NOT NULL,
+                depth         INTEGER NOT NULL,
+                state         TEXT NOT NULL,
+                parent        TEXT,
+                discovered_at TEXT NOT NULL,
+                visited_at    TEXT,
+                http_status   INTEGER,
+                run_dir       TEXT,
+                error         TEXT,
+                PRIMARY KEY (crawl, url)
+            );
+            CREATE INDEX IF NOT EXISTS frontier_state ON frontier (crawl, state);",
+        )?;
+        // Anything left in flight by a previous process goes back to the queue.
+        conn.execute(
+            "UPDATE frontier SET state = 'queued' WHERE crawl = ?1 AND state = 'in_progress'",
+            params![crawl],
+        )?;
+        Ok(Self {
+            conn,
+            crawl: crawl.to_string(),
+        })
+    }
+
+    /// Queue `url` unless this crawl has already seen it. Returns true if newly added.
+    pub fn enqueue(&self, url: &str, depth: u32, parent: Option<&str>) -> Result<bool> {
+        let n = self.conn.execute(
+            "INSERT OR IGNORE INTO frontier (crawl, url, depth, state, parent, discovered_at)
+             VALUES (?1, ?2, ?3, 'queued', ?4, ?5)",
+            params![self.crawl, url, depth, parent, now()],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Claim the shallowest queued URL (oldest first within a depth).
+    pub fn next(&self) -> Result<Option<FrontierItem>> {
+        let item = self
+            .conn
+            .query_row(
+                "SELECT url, depth FROM frontier WHERE crawl = ?1 AND state = 'queued'
+                 ORDER BY depth, discovered_at, rowid LIMIT 1",
+                params![self.crawl],
+                |r| {
+                    Ok(FrontierItem {
+                        url: r.get(0)?,
+                        depth: r.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        if let Some(it) = &item {
+            self.conn.execute(
+                "UPDATE frontier SET state = 'in_progress' WHERE crawl = ?1 AND url = ?2",
+                params![self.crawl, it.url],
+            )?;
+        }
+        Ok(item)
+    }
+
+    pub fn mark_visited(&self, url: &str, http_status: Option<u16>, run_dir: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE frontier SET state = 'visited', visited_at = ?3, http_status = ?4, run_dir = ?5
+             WHERE crawl = ?1 AND url = ?2",
+            params![self.crawl, url, now(), http_status, run_dir],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_failed(&self, url: &str, error: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE frontier SET state = 'failed', visited_at = ?3, error = ?4
+             WHERE crawl = ?1 AND url = ?2",
+            params![self.crawl, url, now(), error],
+        )?;
+        Ok(())
+    }
+
+    /// URL and run directory of every page this crawl has visited, across restarts.
+    pub fn visited(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT url, run_dir FROM frontier
+             WHERE crawl = ?1 AND state = 'visited' AND run_dir IS NOT NULL",
+        )?;
+        let rows = stmt.query_map(params![self.crawl], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Number of URLs this crawl still has queued or in flight.
+    pub fn pending_count(&self) -> Result<u32> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM frontier WHERE crawl = ?1 AND state IN ('queued', 'in_progress')",
+            params![self.crawl],
+            |r| r.get(0),
+        )?)
+    }
+
+    /// Number of URLs this crawl has finished (visited or failed).
+    pub fn done_count(&self) -> Result<u32> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM frontier WHERE crawl = ?1 AND state IN ('visited', 'failed')",
+            params![self.crawl],
+            |r| r.get(0),
+        )?)
+    }
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
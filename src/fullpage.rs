@@ -0,0 +1,161 @@
+//! `--full-page`: a screenshot of the whole document instead of the viewport.
+//!
+//! Chrome renders a capture into a single GPU texture, and anything taller than its limit
+//! (16384 device pixels on most builds) comes back truncated or blank. Short pages are
+//! captured in one go with `captureBeyondViewport`; taller ones are captured in segments
+//! well under the limit and stitched together here.
+
+use crate::ScreenshotFormat;
+use anyhow::{anyhow, Result};
+use headless_chrome::protocol::cdp::Page::{
+    CaptureScreenshot, CaptureScreenshotFormatOption, GetLayoutMetrics, Viewport,
+};
+use image::{codecs::jpeg::JpegEncoder, codecs::webp::WebPEncoder, RgbaImage};
+use std::io::Cursor;
+
+/// Height of one capture, in device pixels.
+const SEGMENT_PX: f64 = 8192.0;
+/// Tallest stitched image, in device pixels; bounds the memory a runaway page can take.
+const MAX_HEIGHT_PX: f64 = 32_000.0;
+/// WebP can't encode images taller than this.
+const WEBP_MAX_PX: f64 = 16_383.0;
+
+/// Scroll through the document a viewport at a time so lazy-loaded images and
+/// intersection-observer content below the fold are requested, then return to the top
+/// (or to the URL's anchor, see `anchor`).
+const SCROLL_THROUGH_JS: &str = r#"(async () => {
+  const step = Math.max(window.innerHeight, 200);
+  for (let y = 0; y < document.documentElement.scrollHeight && y < 200000; y += step) {
+    window.scrollTo(0, y);
+    await new Promise((r) => setTimeout(r, 100));
+  }
+  const anchor = document.querySelector('[data-ankabot-anchor]');
+  if (anchor) anchor.scrollIntoView({ block: 'start', behavior: 'instant' });
+  else window.scrollTo(0, 0);
+})()"#;
+
+pub fn scroll_through(tab: &headless_chrome::Tab) -> Result<()> {
+    tab.evaluate(SCROLL_THROUGH_JS, true)?;
+    Ok(())
+}
+
+/// Capture the whole document in `format`, from the URL's anchor down when it has one.
+pub fn capture(
+    tab: &headless_chrome::Tab,
+    format: ScreenshotFormat,
+    quality: Option<u32>,
+) -> Result<Vec<u8>> {
+    let content = tab.call_method(GetLayoutMetrics(None))?.css_content_size;
+    let dpr = tab
+        .evaluate("window.devicePixelRatio", false)?
+        .value
+        .and_then(|v| v.as_f64())
+        .unwrap_or(1.0);
+    let top = tab
+        .evaluate(crate::anchor::TOP_JS, false)?
+        .value
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0)
+        .floor();
+    let width = content.width.ceil();
+    let max_px = if format == ScreenshotFormat::Webp {
+        WEBP_MAX_PX
+    } else {
+        MAX_HEIGHT_PX
+    };
+    let mut height = (content.height.ceil() - top).max(1.0);
+    if height * dpr > max_px {
+        let cut = (max_px / dpr).floor();
+        eprintln!(
+            "warning: page is {} px tall; full-page screenshot cut at {} px",
+            height, cut
+        );
+        height = cut;
+    }
+
+    let segment = (SEGMENT_PX / dpr).floor();
+    if height <= segment {
+        return shot(tab, format.cdp(), quality, width, top, height);
+    }
+
+    // Segments are lossless PNGs; the stitched image is encoded once at the end.
+    let mut parts = Vec::new();
+    let mut y = 0.0;
+    while y < height {
+        let h = segment.min(height - y);
+        let png = shot(
+            tab,
+            CaptureScreenshotFormatOption::Png,
+            None,
+            width,
+            top + y,
+            h,
+        )?;
+        parts.push(image::load_from_memory(&png)?.to_rgba8());
+        y += h;
+    }
+    let canvas_w = parts.iter().map(|p| p.width()).max().unwrap_or(0);
+    let canvas_h = parts.iter().map(|p| p.height()).sum();
+    let mut canvas = RgbaImage::new(canvas_w, canvas_h);
+    let mut top = 0;
+    for part in &parts {
+        image::imageops::replace(&mut canvas, part, 0, top as i64);
+        top += part.height();
+    }
+    drop(parts);
+    encode(canvas, format, quality)
+}
+
+fn shot(
+    tab: &headless_chrome::Tab,
+    format: CaptureScreenshotFormatOption,
+    quality: Option<u32>,
+    width: f64,
+    y: f64,
+    height: f64,
+) -> Result<Vec<u8>> {
+    use base64::Engine as _;
+
+    let data = tab
+        .call_method(CaptureScreenshot {
+            format: Some(format),
+            quality,
+            clip: Some(Viewport {
+                x: 0.0,
+                y,
+                width,
+                height,
+                scale: 1.0,
+            }),
+            from_surface: Some(true),
+            capture_beyond_viewport: Some(true),
+            optimize_for_speed: None,
+        })?
+        .data;
+    Ok(base64::engine::general_purpose::STANDARD.decode(data)?)
+}
+
+fn encode(img: RgbaImage, format: ScreenshotFormat, quality: Option<u32>) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        ScreenshotFormat::Png => {
+            img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)?
+        }
+        ScreenshotFormat::Jpeg => {
+            let rgb = image::DynamicImage::ImageRgba8(img).to_rgb8();
+            JpegEncoder::new_with_quality(&mut out, quality.unwrap_or(80) as u8)
+                .encode_image(&rgb)?
+        }
+        // The image crate only writes lossless WebP, so --screenshot-quality doesn't apply
+        // to stitched captures.
+        ScreenshotFormat::Webp => WebPEncoder::new_lossless(&mut out)
+            .encode(
+                img.as_raw(),
+                img.width(),
+                img.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| anyhow!("encoding WebP screenshot: {}", e))?,
+    }
+    Ok(out)
+}
@@ -0,0 +1,384 @@
+use crate::diff::dhash;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+/// result.json fields that must match the golden capture exactly.
+const GOLDEN_FIELDS: &[&str] = &[
+    "final_url",
+    "http_status",
+    "requires_javascript",
+    "waf_detected",
+    "anti_bot_vendor",
+    "js_challenge_page",
+];
+
+#[derive(Serialize)]
+pub struct GoldenVerdict {
+    pub golden_run: String,
+    pub verdict: &'static str,
+    pub text_similarity: f64,
+    pub deltas: Vec<GoldenDelta>,
+}
+
+#[derive(Serialize)]
+pub struct GoldenDelta {
+    pub field: String,
+    pub golden: serde_json::Value,
+    pub current: serde_json::Value,
+    /// Hamming distance (0-64) between the screenshots' difference hashes, for the
+    /// `screenshot_dhash` delta when both runs have a screenshot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<u32>,
+}
+
+impl GoldenVerdict {
+    pub fn passed(&self) -> bool {
+        self.deltas.is_empty()
+    }
+}
+
+/// Compare the current result (serialized Output) against the capture stored in `golden_dir`.
+pub fn compare(
+    golden_dir: &Path,
+    current: &serde_json::Value,
+    min_similarity: f64,
+    max_visual_distance: u32,
+) -> Result<GoldenVerdict> {
+    let golden_json = golden_dir.join("result.json");
+    let golden: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(&golden_json)
+            .with_context(|| format!("reading golden {}", golden_json.display()))?,
+    )?;
+
+    let mut deltas = field_deltas(&golden, current);
+    deltas.extend(extracted_deltas(
+        &extracted_fields(&golden, golden_dir),
+        &extracted_fields(current, Path::new("")),
+    ));
+
+    let golden_text = run_text(&golden, golden_dir);
+    let current_text = run_text(current, Path::new(""));
+    let text_similarity = similarity(&golden_text, &current_text);
+    if text_similarity < min_similarity {
+        deltas.push(GoldenDelta {
+            field: "dom_text".to_string(),
+            golden: serde_json::json!(golden_text.len()),
+            current: serde_json::json!(current_text.len()),
+            distance: None,
+        });
+    }
+
+    // Antialiasing, font hinting and caret blinks change the bytes of almost every live
+    // capture, so screenshots are compared perceptually.
+    let golden_shot = artifact(&golden, golden_dir, "screenshot_path").and_then(|p| dhash(&p));
+    let current_shot = artifact(current, Path::new(""), "screenshot_path").and_then(|p| dhash(&p));
+    // A run the HTTP fast path served has no screenshot; that says nothing about how the
+    // page looks, so only two screenshots are compared.
+    if let (Some(g), Some(c)) = (golden_shot, current_shot) {
+        let distance = (g ^ c).count_ones();
+        if distance > max_visual_distance {
+            deltas.push(GoldenDelta {
+                field: "screenshot_dhash".to_string(),
+                golden: serde_json::json!(format!("{g:016x}")),
+                current: serde_json::json!(format!("{c:016x}")),
+                distance: Some(distance),
+            });
+        }
+    }
+
+    let verdict = if deltas.is_empty() { "pass" } else { "fail" };
+    Ok(GoldenVerdict {
+        golden_run: golden_dir.display().to_string(),
+        verdict,
+        text_similarity,
+        deltas,
+    })
+}
+
+/// Deltas of the status fields (final URL, HTTP status, WAF/challenge flags) between two
+/// result.json values.
+pub fn field_deltas(golden: &serde_json::Value, current: &serde_json::Value) -> Vec<GoldenDelta> {
+    let mut deltas = Vec::new();
+    for field in GOLDEN_FIELDS {
+        let g = golden.get(*field).cloned().unwrap_or_default();
+        let c = current.get(*field).cloned().unwrap_or_default();
+        if g != c {
+            deltas.push(GoldenDelta {
+                field: field.to_string(),
+                golden: g,
+                current: c,
+                distance: None,
+            });
+        }
+    }
+    deltas
+}
+
+/// The extraction output of a result.json, flattened to one value per field:
+/// `metadata.title`, `metadata.social.og:title`, `main_content.selector`, and the
+/// sorted `contacts.emails` / `contacts.phones` from contacts.json.
+pub fn extracted_fields(v: &serde_json::Value, dir: &Path) -> BTreeMap<String, serde_json::Value> {
+    let mut fields = BTreeMap::new();
+    if let Some(meta) = v.get("metadata") {
+        flatten("metadata", meta, &mut fields);
+    }
+    // The region's box shifts with every layout tweak; which element it is is the contract.
+    if let Some(sel) = v.pointer("/main_content/selector") {
+        fields.insert("main_content.selector".to_string(), sel.clone());
+    }
+    let contacts = artifact(v, dir, "contacts_path")
+        .and_then(|p| std::fs::read(p).ok())
+        .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok());
+    if let Some(contacts) = contacts {
+        for kind in ["emails", "phones"] {
+            let mut values: Vec<&str> = contacts[kind]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c["value"].as_str())
+                .collect();
+            values.sort_unstable();
+            values.dedup();
+            fields.insert(format!("contacts.{kind}"), serde_json::json!(values));
+        }
+    }
+    fields
+}
+
+fn flatten(prefix: &str, v: &serde_json::Value, out: &mut BTreeMap<String, serde_json::Value>) {
+    match v {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                flatten(&format!("{prefix}.{k}"), v, out);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), v.clone());
+        }
+    }
+}
+
+/// Deltas between two sets of [`extracted_fields`]; a field only one side has counts.
+pub fn extracted_deltas(
+    golden: &BTreeMap<String, serde_json::Value>,
+    current: &BTreeMap<String, serde_json::Value>,
+) -> Vec<GoldenDelta> {
+    let keys: std::collections::BTreeSet<&String> = golden.keys().chain(current.keys()).collect();
+    keys.into_iter()
+        .filter_map(|k| {
+            let g = golden.get(k).cloned().unwrap_or_default();
+            let c = current.get(k).cloned().unwrap_or_default();
+            (g != c).then(|| GoldenDelta {
+                field: k.clone(),
+                golden: g,
+                current: c,
+                distance: None,
+            })
+        })
+        .collect()
+}
+
+/// Visible text of the HTML a result.json points at (empty when unavailable).
+pub fn run_text(v: &serde_json::Value, dir: &Path) -> String {
+    artifact(v, dir, "html_path")
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|h| html_to_text(&h))
+        .unwrap_or_default()
+}
+
+/// Resolve an artifact path from a result.json, falling back to the same file name inside
+/// `dir` when the recorded absolute path no longer exists (e.g. a copied golden run).
+pub fn artifact(v: &serde_json::Value, dir: &Path, key: &str) -> Option<std::path::PathBuf> {
+    let recorded = std::path::PathBuf::from(v.get(key)?.as_str()?);
+    if recorded.exists() {
+        return Some(recorded);
+    }
+    let local = dir.join(recorded.file_name()?);
+    local.exists().then_some(local)
+}
+
+/// Crude visible-text extraction: drops script/style bodies and tags, collapses whitespace.
+pub fn html_to_text(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len() / 4);
+    let mut i = 0;
+    while i < html.len() {
+        if lower[i..].starts_with("<script") || lower[i..].starts_with("<style") {
+            let close = if lower[i..].starts_with("<script") {
+                "</script>"
+            } else {
+                "</style>"
+            };
+            i = lower[i..]
+                .find(close)
+                .map(|e| i + e + close.len())
+                .unwrap_or(html.len());
+            out.push(' ');
+        } else if html.as_bytes()[i] == b'<' {
+            i = html[i..].find('>').map(|e| i + e + 1).unwrap_or(html.len());
+            out.push(' ');
+        } else {
+            let next = html[i..].find('<').map(|e| i + e).unwrap_or(html.len());
+            out.push_str(&html[i..next]);
+            i = next;
+        }
+    }
+    let out = out
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"");
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Jaccard similarity over word sets; 1.0 when both texts are empty.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let sa: HashSet<&str> = a.split_whitespace().collect();
+    let sb: HashSet<&str> = b.split_whitespace().collect();
+    if sa.is_empty() && sb.is_empty() {
+        return 1.0;
+    }
+    let inter = sa.intersection(&sb).count() as f64;
+    let union = sa.union(&sb).count() as f64;
+    inter / union
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn similarity_is_jaccard_over_words() {
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("a b c", "c b a a"), 1.0);
+        assert_eq!(similarity("a b", "c d"), 0.0);
+        assert_eq!(similarity("a b c", "a b d"), 0.5);
+        assert_eq!(similarity("a", ""), 0.0);
+    }
+
+    #[test]
+    fn html_to_text_drops_scripts_styles_and_tags() {
+        let html = "<html><head><style>p { color: red }</style><SCRIPT>var x = '<p>';</SCRIPT>\
+                    </head><body><p>Fish&nbsp;&amp; chips</p>\n<div>  &lt;3 </div></body></html>";
+        assert_eq!(html_to_text(html), "Fish & chips <3");
+    }
+
+    #[test]
+    fn field_deltas_report_changed_and_missing_status_fields() {
+        let golden = json!({"final_url": "https://a/", "http_status": 200, "waf_detected": false});
+        let same = golden.clone();
+        assert!(field_deltas(&golden, &same).is_empty());
+
+        let current = json!({"final_url": "https://a/login", "http_status": 200});
+        let fields: Vec<_> = field_deltas(&golden, &current)
+            .into_iter()
+            .map(|d| d.field)
+            .collect();
+        assert_eq!(fields, ["final_url", "waf_detected"]);
+    }
+
+    #[test]
+    fn extracted_fields_flatten_metadata_and_main_content() {
+        let v = json!({
+            "metadata": {"title": "Pricing", "social": {"og:title": "Pricing | Acme"}},
+            "main_content": {"selector": "main > article", "x": 0.0, "height": 812.5},
+        });
+        let fields = extracted_fields(&v, Path::new(""));
+        assert_eq!(fields["metadata.title"], "Pricing");
+        assert_eq!(fields["metadata.social.og:title"], "Pricing | Acme");
+        assert_eq!(fields["main_content.selector"], "main > article");
+        assert_eq!(fields.len(), 3);
+    }
+
+    #[test]
+    fn extracted_deltas_cover_changes_and_one_sided_fields() {
+        let golden = extracted_fields(
+            &json!({"metadata": {"title": "Pricing", "lang": "en"}}),
+            Path::new(""),
+        );
+        let current = extracted_fields(
+            &json!({"metadata": {"title": "Plans", "canonical": "https://acme/plans"}}),
+            Path::new(""),
+        );
+        let deltas = extracted_deltas(&golden, &current);
+        let fields: Vec<_> = deltas.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["metadata.canonical", "metadata.lang", "metadata.title"]
+        );
+        assert_eq!(deltas[1].current, serde_json::Value::Null);
+        assert!(extracted_deltas(&golden, &golden).is_empty());
+    }
+
+    #[test]
+    fn screenshots_a_few_pixels_apart_still_pass() {
+        let dir = std::env::temp_dir().join(format!("ankabot-golden-shot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let shot = |name: &str, flip: bool, dots: &[(u32, u32)]| {
+            let mut img = image::RgbImage::from_fn(320, 200, |x, _| {
+                let v = (x * 255 / 320) as u8;
+                let v = if flip { 255 - v } else { v };
+                image::Rgb([v, v, v])
+            });
+            for &(x, y) in dots {
+                img.put_pixel(x, y, image::Rgb([255, 0, 0]));
+            }
+            let p = dir.join(name);
+            img.save(&p).unwrap();
+            json!({ "screenshot_path": p.display().to_string() })
+        };
+        let golden = shot("golden.png", false, &[]);
+        std::fs::write(dir.join("result.json"), golden.to_string()).unwrap();
+
+        let nudged = shot("nudged.png", false, &[(10, 10), (200, 150), (201, 150)]);
+        let verdict = compare(&dir, &nudged, 0.95, 10).unwrap();
+        assert_eq!(verdict.verdict, "pass");
+
+        let redesigned = shot("redesigned.png", true, &[]);
+        let verdict = compare(&dir, &redesigned, 0.95, 10).unwrap();
+        assert_eq!(verdict.verdict, "fail");
+        assert_eq!(verdict.deltas[0].field, "screenshot_dhash");
+        assert!(verdict.deltas[0].distance.unwrap() > 10);
+
+        // The current run took the HTTP fast path.
+        let verdict = compare(&dir, &json!({}), 0.95, 10).unwrap();
+        assert_eq!(verdict.verdict, "pass");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn contacts_compare_as_sorted_value_sets() {
+        let dir = std::env::temp_dir().join(format!("ankabot-golden-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, body: serde_json::Value| {
+            let p = dir.join(name);
+            std::fs::write(&p, body.to_string()).unwrap();
+            json!({ "contacts_path": p.display().to_string() })
+        };
+        let golden = write(
+            "a.json",
+            json!({"emails": [{"value": "b@x", "text": "B"}, {"value": "a@x", "text": "A"}], "phones": []}),
+        );
+        let reordered = write(
+            "b.json",
+            json!({"emails": [{"value": "a@x", "text": "mail"}, {"value": "b@x", "text": ""}], "phones": []}),
+        );
+        let changed = write(
+            "c.json",
+            json!({"emails": [{"value": "a@x", "text": "A"}], "phones": []}),
+        );
+        let fields = |v: &serde_json::Value| extracted_fields(v, Path::new(""));
+        assert_eq!(fields(&golden)["contacts.emails"], json!(["a@x", "b@x"]));
+        assert!(extracted_deltas(&fields(&golden), &fields(&reordered)).is_empty());
+        let deltas = extracted_deltas(&fields(&golden), &fields(&changed));
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].field, "contacts.emails");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
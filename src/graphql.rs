@@ -0,0 +1,208 @@
+//! `--capture-graphql`: every GraphQL operation the page ran, with its variables and
+//! response, one JSON object per line in `graphql.jsonl`.
+//!
+//! A request counts as GraphQL by its payload rather than its URL: a POST whose JSON body
+//! (or each element of a batched array) has a `query` string or a persisted-query hash, or
+//! a GET carrying the same in its query string.
+
+use crate::network::{self, NetEntry};
+use anyhow::Result;
+use headless_chrome::protocol::cdp::Network;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write as _;
+use std::path::Path;
+
+#[derive(Serialize)]
+pub struct Operation {
+    pub url: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u32>,
+    /// `operationName`, or the name in the query text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_name: Option<String>,
+    /// `query`, `mutation` or `subscription`; unknown for persisted queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// sha256 of an automatic persisted query sent without its text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persisted_query_hash: Option<String>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub variables: Value,
+    /// Parsed JSON where possible, else the raw text.
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub response: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Write the GraphQL operations among `entries` to `path` and return how many there were.
+///
+/// Operations of a batched request each get a line, paired with their element of the
+/// response array. Response bodies come from Chrome's network buffer; an evicted body
+/// leaves `response` out and sets `error`.
+pub fn capture(tab: &headless_chrome::Tab, entries: &[NetEntry], path: &Path) -> Result<usize> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut count = 0;
+    for entry in entries {
+        let payloads = match entry.request.method.as_str() {
+            "POST" => match post_data(tab, entry) {
+                Some(body) => from_body(&body),
+                None => continue,
+            },
+            "GET" => from_query_string(&entry.request.url).into_iter().collect(),
+            _ => continue,
+        };
+        if payloads.is_empty() {
+            continue;
+        }
+        let status = entry.response.as_ref().map(|r| r.status);
+        let (response, error) = match &entry.failed {
+            Some(err) => (Value::Null, Some(err.clone())),
+            None if entry.response.is_none() => (Value::Null, Some("no response".into())),
+            None => match network::response_body(tab, &entry.request_id) {
+                Ok(data) => (parse_body(&data), None),
+                Err(e) => (Value::Null, Some(e.to_string())),
+            },
+        };
+        // A batch answers with an array in request order.
+        let batched = payloads.len() > 1;
+        let responses: Vec<Value> = match response {
+            Value::Array(items) if batched && items.len() == payloads.len() => items,
+            other => vec![other; payloads.len()],
+        };
+        for (payload, response) in payloads.into_iter().zip(responses) {
+            let op = operation(entry, status, payload, response, error.clone());
+            serde_json::to_writer(&mut out, &op)?;
+            out.write_all(b"\n")?;
+            count += 1;
+        }
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// The request body: inline in the event when small, else fetched from Chrome.
+fn post_data(tab: &headless_chrome::Tab, entry: &NetEntry) -> Option<String> {
+    if let Some(body) = &entry.request.post_data {
+        return Some(body.clone());
+    }
+    if entry.request.has_post_data != Some(true) {
+        return None;
+    }
+    tab.call_method(Network::GetRequestPostData {
+        request_id: entry.request_id.clone(),
+    })
+    .ok()
+    .map(|r| r.post_data)
+}
+
+/// GraphQL payloads in a POST body: a single object or a batched array.
+fn from_body(body: &str) -> Vec<Value> {
+    match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(items)) if !items.is_empty() && items.iter().all(is_graphql) => items,
+        Ok(v) if is_graphql(&v) => vec![v],
+        _ => Vec::new(),
+    }
+}
+
+/// A GraphQL payload from a GET's query string, its JSON-encoded fields decoded.
+fn from_query_string(url: &str) -> Option<Value> {
+    let url = url::Url::parse(url).ok()?;
+    let mut payload = serde_json::Map::new();
+    for (k, v) in url.query_pairs() {
+        let value = match k.as_ref() {
+            "query" | "operationName" => Value::String(v.into_owned()),
+            "variables" | "extensions" => serde_json::from_str(&v).ok()?,
+            _ => continue,
+        };
+        payload.insert(k.into_owned(), value);
+    }
+    let payload = Value::Object(payload);
+    is_graphql(&payload).then_some(payload)
+}
+
+fn is_graphql(v: &Value) -> bool {
+    v.get("query").is_some_and(Value::is_string) || persisted_hash(v).is_some()
+}
+
+fn persisted_hash(v: &Value) -> Option<&str> {
+    v.pointer("/extensions/persistedQuery/sha256Hash")?.as_str()
+}
+
+fn operation(
+    entry: &NetEntry,
+    status: Option<u32>,
+    payload: Value,
+    response: Value,
+    error: Option<String>,
+) -> Operation {
+    let query = payload.get("query").and_then(Value::as_str);
+    let (operation_type, parsed_name) = match query.map(header) {
+        Some((t, n)) => (Some(t), n),
+        None => (None, None),
+    };
+    Operation {
+        url: entry.request.url.clone(),
+        method: entry.request.method.clone(),
+        status,
+        operation_name: payload
+            .get("operationName")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or(parsed_name),
+        operation_type,
+        query: query.map(str::to_string),
+        persisted_query_hash: persisted_hash(&payload).map(str::to_string),
+        variables: payload.get("variables").cloned().unwrap_or(Value::Null),
+        response,
+        error,
+    }
+}
+
+/// Type and name of the first operation in a query document, skipping any fragment
+/// definitions before it. A bare `{ … }` selection set is an anonymous query.
+fn header(query: &str) -> (String, Option<String>) {
+    let mut depth = 0u32;
+    let mut word = String::new();
+    let mut in_fragment = false;
+    let mut kind: Option<String> = None;
+    let text = query.lines().map(|l| l.split('#').next().unwrap_or(""));
+    for c in text.flat_map(|l| l.chars().chain([' '])) {
+        if depth == 0 && (c.is_alphanumeric() || c == '_') {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            if let Some(kind) = kind {
+                return (kind, Some(word));
+            }
+            match word.as_str() {
+                "query" | "mutation" | "subscription" if !in_fragment => kind = Some(word.clone()),
+                "fragment" => in_fragment = true,
+                _ => {}
+            }
+            word.clear();
+        }
+        match c {
+            '{' | '(' if depth == 0 && !in_fragment => {
+                return (kind.unwrap_or_else(|| "query".to_string()), None)
+            }
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                in_fragment &= depth > 0;
+            }
+            _ => {}
+        }
+    }
+    (kind.unwrap_or_else(|| "query".to_string()), None)
+}
+
+fn parse_body(data: &[u8]) -> Value {
+    serde_json::from_slice(data)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(data).into_owned()))
+}
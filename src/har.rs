@@ -0,0 +1,144 @@
+//! HAR 1.2 export of the Chrome session's network activity (`session.har`).
+
+use crate::network::{self, NetEntry};
+use anyhow::Result;
+use headless_chrome::protocol::cdp::Network;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Write every recorded exchange (redirect hops included) as one HAR page.
+pub fn write_har(entries: &[NetEntry], page_url: &str, path: &Path) -> Result<()> {
+    let origin = entries
+        .iter()
+        .map(|e| e.start)
+        .fold(f64::INFINITY, f64::min);
+    let started = entries
+        .iter()
+        .min_by(|a, b| a.start.total_cmp(&b.start))
+        .map(|e| iso_time(e.wall_time))
+        .unwrap_or_else(|| iso_time(chrono::Utc::now().timestamp_millis() as f64 / 1000.0));
+    let har = json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "ankabot", "version": env!("CARGO_PKG_VERSION") },
+            "pages": [{
+                "startedDateTime": started,
+                "id": "page_1",
+                "title": page_url,
+                "pageTimings": {},
+            }],
+            "entries": entries.iter().map(|e| entry(e, origin)).collect::<Vec<_>>(),
+        }
+    });
+    std::fs::write(path, serde_json::to_vec_pretty(&har)?)?;
+    Ok(())
+}
+
+fn entry(e: &NetEntry, origin: f64) -> Value {
+    let phases = network::phases(e, origin);
+    let resp = e.response.as_ref();
+    let timing = resp.and_then(|r| r.timing.as_ref());
+    // HAR uses -1 for phases that don't apply (e.g. DNS on a reused connection).
+    let span = |a: f64, b: f64| if a >= 0.0 && b >= a { b - a } else { -1.0 };
+    let (dns, connect, ssl, send, wait) = match timing {
+        Some(t) => (
+            span(t.dns_start, t.dns_end),
+            span(t.connect_start, t.connect_end),
+            span(t.ssl_start, t.ssl_end),
+            span(t.send_start, t.send_end).max(0.0),
+            span(t.send_end, t.receive_headers_end).max(0.0),
+        ),
+        None => (-1.0, -1.0, -1.0, 0.0, 0.0),
+    };
+    let http_version = resp
+        .and_then(|r| r.protocol.clone())
+        .unwrap_or_else(|| "http/1.1".to_string());
+    let request_headers = resp
+        .and_then(|r| r.request_headers.as_ref())
+        .unwrap_or(&e.request.headers);
+    let mut out = json!({
+        "pageref": "page_1",
+        "startedDateTime": iso_time(e.wall_time),
+        "time": phases.total(),
+        "request": {
+            "method": e.request.method,
+            "url": e.request.url,
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": headers(request_headers),
+            "queryString": query_string(&e.request.url),
+            "headersSize": -1,
+            "bodySize": e.request.post_data.as_ref().map(|b| b.len() as i64).unwrap_or(0),
+        },
+        "response": {
+            "status": resp.map(|r| r.status).unwrap_or(0),
+            "statusText": resp.map(|r| r.status_text.as_str()).unwrap_or(""),
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": resp.map(|r| headers(&r.headers)).unwrap_or_default(),
+            "content": {
+                "size": e.decoded_bytes as i64,
+                "mimeType": resp.map(|r| r.mime_type.as_str()).unwrap_or(""),
+            },
+            "redirectURL": resp
+                .and_then(|r| network::response_header(r, "location"))
+                .unwrap_or(""),
+            "headersSize": -1,
+            "bodySize": if e.from_cache { 0 } else { e.encoded_bytes as i64 },
+            "_transferSize": e.encoded_bytes as i64,
+        },
+        "cache": {},
+        "timings": {
+            "blocked": phases.queued,
+            "dns": dns,
+            "connect": connect,
+            "ssl": ssl,
+            "send": send,
+            "wait": wait,
+            "receive": phases.download,
+        },
+        "_resourceType": e.resource_type,
+    });
+    if let Some(ip) = resp.and_then(|r| r.remote_ip_address.as_deref()) {
+        out["serverIPAddress"] = json!(ip);
+    }
+    if let Some(err) = &e.failed {
+        out["_error"] = json!(err);
+    }
+    out
+}
+
+fn headers(h: &Network::Headers) -> Vec<Value> {
+    h.0.as_ref()
+        .and_then(|v| v.as_object())
+        .map(|m| {
+            m.iter()
+                .flat_map(|(name, value)| {
+                    // CDP joins repeated headers with newlines.
+                    value
+                        .as_str()
+                        .unwrap_or_default()
+                        .split('\n')
+                        .map(|v| json!({ "name": name, "value": v }))
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn query_string(url: &str) -> Vec<Value> {
+    url::Url::parse(url)
+        .map(|u| {
+            u.query_pairs()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn iso_time(epoch_s: f64) -> String {
+    chrono::DateTime::from_timestamp_millis((epoch_s * 1000.0) as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
@@ -0,0 +1,14 @@
+//! sha256 digests of artifacts, as the lower-case hex used in result.json, watch logs,
+//! golden comparisons and signed manifests.
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+pub fn sha256_hex(bytes: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// sha256 of the file at `path`; `None` when it can't be read.
+pub fn file_sha256(path: &Path) -> Option<String> {
+    std::fs::read(path).ok().map(sha256_hex)
+}
@@ -0,0 +1,80 @@
+//! The final document's response headers: all of them in `headers.json`, and a summary in
+//! result.json of the ones that explain how the page was served.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Serialize)]
+pub struct HeaderSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+    pub set_cookie_count: usize,
+    /// Headers that only an anti-bot vendor or WAF sets (`cf-ray`, `x-datadome`, …).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub waf_headers: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct HeadersFile<'a> {
+    url: &'a str,
+    status: u16,
+    headers: Vec<Header<'a>>,
+}
+
+#[derive(Serialize)]
+struct Header<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+/// One pair per header line. Chrome joins repeated headers (`set-cookie`) with newlines;
+/// they are split back here.
+pub fn split_lines(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .flat_map(|(k, v)| v.split('\n').map(move |v| (k.clone(), v.to_string())))
+        .collect()
+}
+
+pub fn summarize(headers: &[(String, String)]) -> HeaderSummary {
+    let get = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+    };
+    HeaderSummary {
+        server: get("server"),
+        content_type: get("content-type"),
+        cache_control: get("cache-control"),
+        set_cookie_count: headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("set-cookie"))
+            .count(),
+        waf_headers: headers
+            .iter()
+            .filter(|(k, _)| crate::block::is_vendor_header(k))
+            .map(|(k, v)| (k.to_ascii_lowercase(), v.clone()))
+            .collect(),
+    }
+}
+
+/// Write `headers.json`: the response's URL, status and every header line in order.
+pub fn write(headers: &[(String, String)], url: &str, status: u16, path: &Path) -> Result<()> {
+    let file = HeadersFile {
+        url,
+        status,
+        headers: headers
+            .iter()
+            .map(|(name, value)| Header { name, value })
+            .collect(),
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&file)?)
+        .with_context(|| format!("writing {}", path.display()))
+}
@@ -0,0 +1,297 @@
+//! Index of finished runs in `<out_root>/ankabot.db` (table `runs`), so stored captures
+//! can be looked up by URL and time.
+
+use crate::frontier::db_path;
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path};
+
+pub struct RunIndex {
+    conn: Connection,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct IndexedRun {
+    pub id: i64,
+    pub url: String,
+    pub final_url: Option<String>,
+    pub created_at: String,
+    pub run_dir: String,
+    pub http_status: Option<u16>,
+    pub exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// `ok`, `timeout` or `blocked`; None for runs indexed before it was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waf_detected: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anti_bot_vendor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    /// Artifact name (`html`, `screenshot`, `pdf`, …) to path.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub artifacts: BTreeMap<String, String>,
+}
+
+const COLUMNS: &str = "id, url, final_url, created_at, run_dir, http_status, exit_code, tags,
+                       content_hash, status, wait_branch, waf_detected, anti_bot_vendor,
+                       elapsed_ms, artifacts";
+
+impl RunIndex {
+    pub fn open(out_root: &Path) -> Result<Self> {
+        std::fs::create_dir_all(out_root)?;
+        let conn = Connection::open(db_path(out_root))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id          INTEGER PRIMARY KEY,
+                url         TEXT NOT NULL,
+                final_url   TEXT,
+                created_at  TEXT NOT NULL,
+                run_dir     TEXT NOT NULL UNIQUE,
+                http_status INTEGER,
+                exit_code   INTEGER NOT NULL,
+                tags        TEXT,
+                content_hash TEXT,
+                status      TEXT,
+                wait_branch TEXT,
+                waf_detected INTEGER,
+                anti_bot_vendor TEXT,
+                elapsed_ms  INTEGER,
+                artifacts   TEXT
+            );
+            CREATE INDEX IF NOT EXISTS runs_url ON runs (url, created_at);",
+        )?;
+        // Indexes created before these columns existed.
+        for (column, kind) in [
+            ("tags", "TEXT"),
+            ("content_hash", "TEXT"),
+            ("status", "TEXT"),
+            ("wait_branch", "TEXT"),
+            ("waf_detected", "INTEGER"),
+            ("anti_bot_vendor", "TEXT"),
+            ("elapsed_ms", "INTEGER"),
+            ("artifacts", "TEXT"),
+        ] {
+            let present: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('runs') WHERE name = ?1",
+                params![column],
+                |r| r.get(0),
+            )?;
+            if !present {
+                conn.execute(
+                    &format!("ALTER TABLE runs ADD COLUMN {} {}", column, kind),
+                    [],
+                )?;
+            }
+        }
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS runs_content ON runs (content_hash);")?;
+        Ok(Self { conn })
+    }
+
+    /// Record (or re-record) the run stored in `run_dir`; final URL, HTTP status, content
+    /// hash, status, WAF flags and artifact paths are taken from its result.json (`result`),
+    /// or its timeout/blocked report.
+    pub fn record(
+        &self,
+        url: &str,
+        run_dir: &str,
+        exit_code: i32,
+        result: &serde_json::Value,
+        tags: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let str_field = |key: &str| result.get(key).and_then(|v| v.as_str());
+        let final_url = str_field("final_url").or_else(|| str_field("url"));
+        let http_status = result.get("http_status").and_then(|v| v.as_u64());
+        let tags = (!tags.is_empty())
+            .then(|| serde_json::to_string(tags))
+            .transpose()?;
+        let artifacts = artifact_paths(result);
+        let artifacts = (!artifacts.is_empty())
+            .then(|| serde_json::to_string(&artifacts))
+            .transpose()?;
+        // Blocked reports name the anti-bot vendor `vendor` and are WAF hits by definition.
+        let status = str_field("status").unwrap_or("ok");
+        let waf_detected = result
+            .get("waf_detected")
+            .and_then(|v| v.as_bool())
+            .or((status == "blocked").then_some(true));
+        self.conn.execute(
+            "INSERT INTO runs
+               (url, final_url, created_at, run_dir, http_status, exit_code, tags, content_hash,
+                status, wait_branch, waf_detected, anti_bot_vendor, elapsed_ms, artifacts)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+             ON CONFLICT (run_dir) DO UPDATE SET
+               url = excluded.url, final_url = excluded.final_url,
+               created_at = excluded.created_at, http_status = excluded.http_status,
+               exit_code = excluded.exit_code, tags = excluded.tags,
+               content_hash = excluded.content_hash, status = excluded.status,
+               wait_branch = excluded.wait_branch, waf_detected = excluded.waf_detected,
+               anti_bot_vendor = excluded.anti_bot_vendor, elapsed_ms = excluded.elapsed_ms,
+               artifacts = excluded.artifacts",
+            params![
+                url,
+                final_url,
+                now(),
+                run_dir,
+                http_status,
+                exit_code,
+                tags,
+                str_field("content_hash"),
+                status,
+                str_field("wait_branch"),
+                waf_detected,
+                str_field("anti_bot_vendor").or_else(|| str_field("vendor")),
+                result.get("elapsed_ms").and_then(|v| v.as_u64()),
+                artifacts
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The earliest stored run (other than `run_dir`) whose content hash is `hash`.
+    pub fn with_content_hash(&self, hash: &str, run_dir: &str) -> Result<Option<IndexedRun>> {
+        let sql = format!(
+            "SELECT {COLUMNS}
+                   FROM runs WHERE content_hash = ?1 AND run_dir != ?2
+                   ORDER BY id LIMIT 1"
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, params![hash, run_dir], row_to_run)
+            .optional()?)
+    }
+
+    /// Drop a run whose directory was deleted.
+    pub fn forget(&self, run_dir: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM runs WHERE run_dir = ?1", params![run_dir])?;
+        Ok(())
+    }
+
+    /// Every indexed run, newest first.
+    pub fn all(&self) -> Result<Vec<IndexedRun>> {
+        let sql = format!(
+            "SELECT {COLUMNS}
+                   FROM runs ORDER BY id DESC"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let runs = stmt.query_map([], row_to_run)?.collect::<Result<_, _>>()?;
+        Ok(runs)
+    }
+
+    /// Runs matching the filters, newest first: of `url` (input or final URL), with
+    /// `status`, created at or after `since` (RFC 3339); at most `limit`.
+    pub fn list(
+        &self,
+        url: Option<&str>,
+        status: Option<&str>,
+        since: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<IndexedRun>> {
+        let sql = format!(
+            "SELECT {COLUMNS}
+             FROM runs WHERE (?1 IS NULL OR url = ?1 OR final_url = ?1)
+                         AND (?2 IS NULL OR coalesce(status, 'ok') = ?2)
+                         AND (?3 IS NULL OR julianday(created_at) >= julianday(?3))
+             ORDER BY id DESC LIMIT ?4"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let runs = stmt
+            .query_map(params![url, status, since, limit as i64], row_to_run)?
+            .collect::<Result<_, _>>()?;
+        Ok(runs)
+    }
+
+    /// The run with index id `id`.
+    pub fn get(&self, id: i64) -> Result<Option<IndexedRun>> {
+        let sql = format!("SELECT {COLUMNS} FROM runs WHERE id = ?1");
+        Ok(self
+            .conn
+            .query_row(&sql, params![id], row_to_run)
+            .optional()?)
+    }
+
+    /// The run of `url` closest in time to `at` (RFC 3339), or the latest when `at` is None.
+    pub fn nearest(&self, url: &str, at: Option<&str>) -> Result<Option<IndexedRun>> {
+        let sql = format!(
+            "SELECT {COLUMNS}
+                   FROM runs WHERE (url = ?1 OR final_url = ?1)
+                   ORDER BY CASE WHEN ?2 IS NULL THEN -julianday(created_at)
+                                 ELSE abs(julianday(created_at) - julianday(?2)) END, id DESC
+                   LIMIT 1"
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, params![url, at], row_to_run)
+            .optional()?)
+    }
+
+    /// The latest run of `url` recorded before run `id`.
+    pub fn previous(&self, url: &str, id: i64) -> Result<Option<IndexedRun>> {
+        let sql = format!(
+            "SELECT {COLUMNS}
+                   FROM runs WHERE (url = ?1 OR final_url = ?1) AND id < ?2
+                   ORDER BY id DESC LIMIT 1"
+        );
+        Ok(self
+            .conn
+            .query_row(&sql, params![url, id], row_to_run)
+            .optional()?)
+    }
+}
+
+fn row_to_run(r: &rusqlite::Row) -> rusqlite::Result<IndexedRun> {
+    Ok(IndexedRun {
+        id: r.get(0)?,
+        url: r.get(1)?,
+        final_url: r.get(2)?,
+        created_at: r.get(3)?,
+        run_dir: r.get(4)?,
+        http_status: r.get(5)?,
+        exit_code: r.get(6)?,
+        content_hash: r.get(8)?,
+        tags: r
+            .get::<_, Option<String>>(7)?
+            .and_then(|t| serde_json::from_str(&t).ok())
+            .unwrap_or_default(),
+        status: r.get(9)?,
+        wait_branch: r.get(10)?,
+        waf_detected: r.get(11)?,
+        anti_bot_vendor: r.get(12)?,
+        elapsed_ms: r.get(13)?,
+        artifacts: r
+            .get::<_, Option<String>>(14)?
+            .and_then(|a| serde_json::from_str(&a).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// The artifact paths in a result: the `*_path` fields of result.json (`pdf_path` →
+/// `pdf`), or a report's `artifacts` object.
+fn artifact_paths(result: &serde_json::Value) -> BTreeMap<String, String> {
+    let Some(obj) = result.as_object() else {
+        return BTreeMap::new();
+    };
+    let reported = obj.get("artifacts").and_then(|a| a.as_object());
+    reported
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| (k.as_str(), v))
+        .chain(
+            obj.iter()
+                .filter_map(|(k, v)| k.strip_suffix("_path").map(|k| (k, v))),
+        )
+        .filter_map(|(k, v)| Some((k.to_string(), v.as_str()?.to_string())))
+        .collect()
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
@@ -0,0 +1,126 @@
+//! Uncaught JavaScript exceptions (`Runtime.exceptionThrown`): logged to `js_errors.log`
+//! as they happen and listed in result.json, so a page whose scripts broke can be told
+//! apart from one that is simply empty.
+
+use anyhow::{Context, Result};
+use headless_chrome::protocol::cdp::{types::Event, Runtime};
+use serde::Serialize;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// result.json keeps the first this many; the log has them all.
+const MAX_LISTED: usize = 50;
+const MAX_FRAMES: usize = 10;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct JsError {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// 1-based, like devtools.
+    pub line: u32,
+    pub column: u32,
+    /// `at fn (url:line:col)` frames, innermost first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct ErrorCapture {
+    errors: Arc<Mutex<Vec<JsError>>>,
+}
+
+impl ErrorCapture {
+    /// Start recording `tab`'s uncaught exceptions, appending each to `path` (truncated).
+    pub fn attach(tab: &headless_chrome::Tab, path: &Path) -> Result<Self> {
+        let file =
+            std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        let file = Mutex::new(file);
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let sink = errors.clone();
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            let Event::RuntimeExceptionThrown(e) = event else {
+                return;
+            };
+            let error = from_details(&e.params.exception_details);
+            if let Ok(mut f) = file.lock() {
+                let _ = f.write_all(log_line(e.params.timestamp, &error).as_bytes());
+            }
+            if let Ok(mut errors) = sink.lock() {
+                errors.push(error);
+            }
+        }))?;
+        tab.call_method(Runtime::Enable(None))?;
+        Ok(Self { errors })
+    }
+
+    /// The first exceptions so far, for result.json.
+    pub fn errors(&self) -> Vec<JsError> {
+        let errors = self.errors.lock().unwrap();
+        errors.iter().take(MAX_LISTED).cloned().collect()
+    }
+}
+
+fn from_details(d: &Runtime::ExceptionDetails) -> JsError {
+    // `description` is "TypeError: x is not a function\n    at …" for Error objects;
+    // `text` is only "Uncaught" for them, but the whole message for thrown primitives.
+    let description = d.exception.as_ref().and_then(|o| {
+        o.description
+            .clone()
+            .or_else(|| o.value.as_ref().map(|v| v.to_string()))
+    });
+    let message = match description {
+        Some(desc) => format!("{} {}", d.text, desc.lines().next().unwrap_or_default()),
+        None => d.text.clone(),
+    };
+    let frames = d
+        .stack_trace
+        .as_ref()
+        .map(|s| s.call_frames.as_slice())
+        .unwrap_or_default();
+    let source_url = d
+        .url
+        .clone()
+        .or_else(|| frames.first().map(|f| f.url.clone()))
+        .filter(|u| !u.is_empty());
+    JsError {
+        message: message.trim().to_string(),
+        source_url,
+        line: d.line_number + 1,
+        column: d.column_number + 1,
+        stack: frames
+            .iter()
+            .take(MAX_FRAMES)
+            .map(|f| {
+                let name = if f.function_name.is_empty() {
+                    "<anonymous>"
+                } else {
+                    &f.function_name
+                };
+                format!(
+                    "at {name} ({}:{}:{})",
+                    f.url,
+                    f.line_number + 1,
+                    f.column_number + 1
+                )
+            })
+            .collect(),
+    }
+}
+
+/// `<time> <message> (<url>:<line>:<col>)`, then the stack frames indented.
+fn log_line(timestamp: f64, e: &JsError) -> String {
+    let time = chrono::DateTime::from_timestamp_millis(timestamp as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let mut line = format!("{time} {}", e.message);
+    if let Some(url) = &e.source_url {
+        line.push_str(&format!(" ({url}:{}:{})", e.line, e.column));
+    }
+    line.push('\n');
+    for frame in &e.stack {
+        line.push_str(&format!("    {frame}\n"));
+    }
+    line
+}
@@ -1,19 +1,122 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
+mod alternates;
+mod anchor;
+mod annotate;
+mod api;
+mod archive;
+mod article;
+mod assets;
+mod axtree;
+mod batch;
+mod block;
+mod breakpoints;
+mod cgroup;
+mod chrome_version;
+mod console;
+mod crawl;
+mod dedup;
+mod diff;
+mod dns;
+mod domsnapshot;
+mod egress;
+mod epub;
+mod extract;
+mod faketime;
+mod filmstrip;
+#[cfg(feature = "fixtures")]
+mod fixtures;
+mod frontier;
+mod fullpage;
+mod golden;
+mod graphql;
+mod har;
+mod hash;
+mod headers;
+mod index;
+mod jserrors;
+mod maincontent;
+mod mainthread;
+mod markdown;
+mod media;
+mod mergepdf;
+mod metadata;
+mod mixed;
+mod naming;
+mod navtiming;
+mod network;
+mod notify;
+mod outline;
+mod pdf;
+mod pdfa;
+mod pdfclean;
+mod procstats;
+mod progress;
+mod prune;
+mod ready;
+mod runs;
+mod schedule;
+mod screencast;
+mod server;
+mod sign;
+mod standalone;
+mod stdout;
+mod summary;
+mod thirdparty;
+mod throttle;
+mod thumbnail;
+mod tls;
+mod trace;
+mod trackers;
+mod upload;
+mod vitals;
+mod watch;
+mod weight;
+
+#[derive(Clone)]
 struct RunPaths {
     run_dir: PathBuf,
     pdf: PathBuf,
-    png: PathBuf,
+    screenshot: PathBuf,
+    annotated_screenshot: PathBuf,
+    thumbnail: PathBuf,
+    main_content_png: PathBuf,
     dom_html: PathBuf,
+    dom_snapshot: PathBuf,
+    axtree: PathBuf,
+    trace: PathBuf,
+    mhtml: PathBuf,
+    standalone_html: PathBuf,
+    markdown: PathBuf,
+    text: PathBuf,
+    epub: PathBuf,
     http_raw: PathBuf,
     console_log: PathBuf,
+    js_errors_log: PathBuf,
     network_log: PathBuf,
+    third_parties: PathBuf,
+    headers_json: PathBuf,
+    waterfall_svg: PathBuf,
+    waterfall_json: PathBuf,
+    har: PathBuf,
+    images_dir: PathBuf,
+    assets_dir: PathBuf,
+    api_dir: PathBuf,
+    graphql_jsonl: PathBuf,
+    breakpoints_dir: PathBuf,
+    filmstrip_dir: PathBuf,
+    screencast_dir: PathBuf,
+    session_video: PathBuf,
+    contacts_json: PathBuf,
+    article_html: PathBuf,
+    article_txt: PathBuf,
     result_json: PathBuf,
 }
 
@@ -21,28 +124,86 @@ fn new_run_paths(
     out_root: Option<PathBuf>,
     run_dir_override: Option<PathBuf>,
     url: &str,
+    shot: ScreenshotFormat,
+    run_name: &naming::Template,
+    names: &[(naming::Named, naming::Template)],
 ) -> anyhow::Result<RunPaths> {
     let root = out_root.unwrap_or_else(|| PathBuf::from("out"));
-    let ts = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
-    let host = url::Url::parse(url)
-        .ok()
-        .and_then(|u| u.host_str().map(|h| h.to_string()))
-        .unwrap_or_else(|| "page".into());
-    let run = run_dir_override.unwrap_or_else(|| root.join(format!("{}-{}", host, ts)));
+    let vars = naming::Vars::new(url);
+    let run =
+        run_dir_override.unwrap_or_else(|| root.join(run_name.render(&vars, shot.extension())));
     std::fs::create_dir_all(&run)?;
     let abs = dunce::canonicalize(&run).unwrap_or(run.clone());
+    // The last --artifact-name for a kind wins.
+    let name = |kind: naming::Named, default: &str| {
+        abs.join(names.iter().rev().find(|(k, _)| *k == kind).map_or_else(
+            || default.to_string(),
+            |(_, t)| t.render(&vars, shot.extension()),
+        ))
+    };
     Ok(RunPaths {
         run_dir: abs.clone(),
-        pdf: abs.join("page.pdf"),
-        png: abs.join("snap.png"),
-        dom_html: abs.join("dom.html"),
+        pdf: name(naming::Named::Pdf, "page.pdf"),
+        screenshot: name(
+            naming::Named::Screenshot,
+            &format!("snap.{}", shot.extension()),
+        ),
+        annotated_screenshot: abs.join(format!("snap.annotated.{}", shot.extension())),
+        thumbnail: abs.join("thumb.png"),
+        main_content_png: abs.join("main_content.png"),
+        dom_html: name(naming::Named::Html, "dom.html"),
+        dom_snapshot: abs.join("dom_snapshot.json"),
+        axtree: abs.join("axtree.json"),
+        trace: abs.join("trace.json"),
+        mhtml: name(naming::Named::Mhtml, "page.mhtml"),
+        standalone_html: abs.join("page.standalone.html"),
+        markdown: name(naming::Named::Markdown, "page.md"),
+        text: name(naming::Named::Text, "page.txt"),
+        epub: name(naming::Named::Epub, "page.epub"),
         http_raw: abs.join("http_raw.html"),
         console_log: abs.join("console.log"),
+        js_errors_log: abs.join("js_errors.log"),
         network_log: abs.join("network.txt"),
+        third_parties: abs.join("third_parties.json"),
+        headers_json: abs.join("headers.json"),
+        waterfall_svg: abs.join("waterfall.svg"),
+        waterfall_json: abs.join("waterfall.json"),
+        har: abs.join("session.har"),
+        images_dir: abs.join("images"),
+        assets_dir: abs.join("assets"),
+        api_dir: abs.join("api"),
+        graphql_jsonl: abs.join("graphql.jsonl"),
+        breakpoints_dir: abs.join("breakpoints"),
+        filmstrip_dir: abs.join("filmstrip"),
+        screencast_dir: abs.join("screencast"),
+        session_video: abs.join("session.webm"),
+        contacts_json: abs.join("contacts.json"),
+        article_html: abs.join("article.html"),
+        article_txt: abs.join("article.txt"),
         result_json: abs.join("result.json"),
     })
 }
 
+/// Locate chrome-headless-shell: `$CHROME_HEADLESS_SHELL`, else the first matching binary
+/// on PATH.
+fn headless_shell_path() -> Result<PathBuf> {
+    if let Some(p) = std::env::var_os("CHROME_HEADLESS_SHELL") {
+        return Ok(PathBuf::from(p));
+    }
+    let names = ["chrome-headless-shell", "headless_shell", "headless-shell"];
+    std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .flat_map(|dir| names.iter().map(move |n| dir.join(n)))
+        .find(|p| p.is_file())
+        .ok_or_else(|| {
+            anyhow!(
+                "--engine headless-shell: chrome-headless-shell not found \
+                 (set CHROME_HEADLESS_SHELL or add it to PATH)"
+            )
+        })
+}
+
 fn profile_dir(profile: &str, override_dir: Option<PathBuf>) -> PathBuf {
     if let Some(p) = override_dir {
         return p;
@@ -55,9 +216,22 @@ fn profile_dir(profile: &str, override_dir: Option<PathBuf>) -> PathBuf {
 }
 
 #[derive(Parser, Debug, Clone)]
+#[command(subcommand_negates_reqs = true)]
+#[command(group(
+    clap::ArgGroup::new("pdf_encryption")
+        .args(["pdf_password", "pdf_owner_password"])
+        .multiple(true)
+))]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// URL to fetch
-    url: String,
+    #[cfg_attr(
+        feature = "fixtures",
+        arg(required_unless_present_any = ["target_fixture", "manifest"])
+    )]
+    #[cfg_attr(not(feature = "fixtures"), arg(required_unless_present = "manifest"))]
+    url: Option<String>,
     /// Legacy no-op alias for compatibility
     #[arg(long, hide = true)]
     pdf: Option<PathBuf>,
@@ -65,15 +239,53 @@ struct Cli {
     #[arg(long)]
     force_chrome: bool,
     /// Output root directory
-    #[arg(long, default_value = "./out")]
+    #[arg(long, default_value = "./out", global = true)]
     out_root: PathBuf,
     /// Override run directory
     #[arg(long)]
     run_dir: Option<PathBuf>,
+    /// Run directory name under --out-root, from {host}, {date}, {time}, {timestamp},
+    /// {hash} and {slug}; "/" nests directories
+    #[arg(long, default_value = "{host}-{timestamp}", value_parser = naming::parse_run_template)]
+    run_name_template: naming::Template,
+    /// File name for an artifact, e.g. "pdf={host}-{date}.pdf" (html, screenshot, pdf, mhtml,
+    /// markdown, text, epub; repeatable)
+    #[arg(long = "artifact-name", value_name = "KIND=TEMPLATE", value_parser = naming::parse_artifact_name)]
+    artifact_names: Vec<(naming::Named, naming::Template)>,
+    /// Pack each finished run directory into <run dir>.zip or .tar.gz
+    #[arg(long, value_enum, conflicts_with = "watch")]
+    archive: Option<archive::Format>,
+    /// Delete the run directory once it is archived
+    #[arg(long, requires = "archive", conflicts_with = "crawl")]
+    archive_remove: bool,
+    /// Also upload each finished run to s3://BUCKET/PREFIX or gs://BUCKET/PREFIX; result.json
+    /// lists the remote URLs and is uploaded last
+    #[arg(long, value_name = "URL", value_parser = upload::parse_target)]
+    upload: Option<upload::Target>,
+    /// Where the capture goes
+    #[arg(
+        long,
+        value_enum,
+        default_value = "dir",
+        conflicts_with_all = ["crawl", "manifest", "watch", "archive", "upload", "sign", "golden", "notify_webhook"]
+    )]
+    output: OutputTarget,
+    /// With --output stdout, leave out any artifact larger than this (its size is still given)
+    #[arg(long, value_name = "BYTES")]
+    embed_max_bytes: Option<usize>,
     /// Overall deadline for page load waits
     #[arg(long, default_value_t = 12000)]
     max_wait_ms: u64,
-    /// document.readyState to await ("complete" | "interactive" | "none")
+    /// Capture order; when the deadline runs out mid-capture, the rest are skipped
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "html,screenshot,pdf"
+    )]
+    artifact_priority: Vec<ArtifactKind>,
+    /// document.readyState to await ("complete" | "interactive" | "none"), or "dom-quiet" for
+    /// a parsed document without DOM mutations for --dom-quiet-ms
     #[arg(long, default_value = "complete")]
     wait_ready: String,
     /// How long network must stay idle (pending requests 0)
@@ -88,9 +300,34 @@ struct Cli {
     /// Minimum DOM text characters for heuristic readiness
     #[arg(long, default_value_t = 1500)]
     heuristic_min_chars: u64,
-    /// Optional CSS selector to wait for
+    /// Also require this long without DOM mutations before the page counts as ready
+    /// (default 500 with --wait-ready dom-quiet)
+    #[arg(long, value_name = "MS")]
+    dom_quiet_ms: Option<u64>,
+    /// CSS selector to wait for (repeatable; see --wait-mode)
     #[arg(long)]
-    wait_selector: Option<String>,
+    wait_selector: Vec<String>,
+    /// Whether any or all of the --wait-selector selectors must appear
+    #[arg(long, value_enum, default_value = "all")]
+    wait_mode: WaitMode,
+    /// Require --wait-selector elements to be visible, not just present
+    #[arg(long, requires = "wait_selector")]
+    wait_visible: bool,
+    /// Require --wait-selector elements' bounding boxes to stay put this long
+    #[arg(long, value_name = "MS", requires = "wait_selector")]
+    wait_stable_ms: Option<u64>,
+    /// Optional XPath expression to wait for, e.g. "//div[@data-ready='true']"
+    #[arg(long, value_name = "XPATH")]
+    wait_xpath: Option<String>,
+    /// Regex of a request URL that must get a successful response, e.g. "/api/catalog"
+    #[arg(long, value_name = "REGEX", value_parser = parse_wait_regex)]
+    wait_request: Option<regex::Regex>,
+    /// Regex of a console message the page logs once ready, e.g. "hydration:done"
+    #[arg(long, value_name = "REGEX", value_parser = parse_wait_regex)]
+    wait_console: Option<regex::Regex>,
+    /// JavaScript expression to wait on until truthy, e.g. "window.__APP_STATE__?.loaded"
+    #[arg(long, value_name = "EXPR")]
+    wait_js: Option<String>,
     /// Named Chrome profile for persistent sessions
     #[arg(long, default_value = "default")]
     profile: String,
@@ -109,6 +346,9 @@ struct Cli {
     /// Timezone override
     #[arg(long)]
     tz: Option<String>,
+    /// Run the page's clock from this instant, e.g. "2024-01-01T00:00:00Z"
+    #[arg(long, value_name = "RFC3339", value_parser = faketime::parse)]
+    fake_time: Option<chrono::DateTime<chrono::Utc>>,
     /// Geolocation "lat,lon[,accuracy]"
     #[arg(long)]
     geo: Option<String>,
@@ -124,6 +364,24 @@ struct Cli {
     /// Run Chrome in headful mode
     #[arg(long)]
     headful: bool,
+    /// Fail unless the browser's version satisfies this requirement, e.g. ">=120,<126"
+    /// (with the fetcher feature, download a compatible build instead)
+    #[arg(long, value_name = "REQ")]
+    require_chrome: Option<chrome_version::ChromeReq>,
+    /// Browser binary: full Chrome, or the lighter chrome-headless-shell for
+    /// screenshot/PDF-only runs (found via $CHROME_HEADLESS_SHELL or PATH)
+    #[arg(long, value_enum, default_value = "chrome")]
+    engine: Engine,
+    /// archive: full-fidelity DOM, screenshot and PDF; extract: only DOM, text and
+    /// metadata, as fast as possible
+    #[arg(long, value_enum, default_value = "archive")]
+    mode: Mode,
+    /// Linux: cap the memory of Chrome and all its processes (in its own cgroup)
+    #[arg(long, value_name = "MB")]
+    chrome_memory_limit_mb: Option<u64>,
+    /// Linux: cap Chrome's CPU time, in cores (e.g. 1.5), in its own cgroup
+    #[arg(long, value_name = "CORES")]
+    chrome_cpu_quota: Option<f64>,
     /// Comma-separated list of extension dirs
     #[arg(long)]
     extensions: Option<String>,
@@ -142,9 +400,336 @@ struct Cli {
     /// Action to take on render timeout
     #[arg(long, value_enum, default_value = "report")]
     on_timeout: OnTimeout,
+    /// Follow "next page" links (rel=next, common markup, or the given selector)
+    #[arg(long, value_name = "SELECTOR", num_args = 0..=1, default_missing_value = "auto")]
+    follow_pagination: Option<String>,
+    /// Maximum pages to capture when following pagination (including the first)
+    #[arg(long, default_value_t = 10)]
+    max_pages: u32,
+    /// Merge the content of all paginated pages into article.html and article.txt
+    #[arg(long, requires = "follow_pagination")]
+    merge_pages: bool,
+    /// Keep scrolling to the bottom until the page stops growing before capturing
+    #[arg(long)]
+    infinite_scroll: bool,
+    /// Maximum scroll steps for --infinite-scroll
+    #[arg(long, default_value_t = 20)]
+    max_scrolls: u32,
+    /// Save content images into images/ with an index of dimensions, alt text and source
+    #[arg(long)]
+    extract_images: bool,
+    /// Minimum natural width and height (px) for --extract-images
+    #[arg(long, default_value_t = 100)]
+    min_image_px: u64,
+    /// Extract mailto/tel links and schema.org Organization data into contacts.json
+    #[arg(long)]
+    extract_contacts: bool,
+    /// Mirror every fetched stylesheet, script, image and font into assets/ with an index.json
+    #[arg(long)]
+    save_assets: bool,
+    /// Save XHR/fetch and JSON responses whose URL matches this regex into api/ with an index.json
+    #[arg(long, value_name = "REGEX", value_parser = api::parse_filter)]
+    capture_api: Option<regex::Regex>,
+    /// Record GraphQL operations (name, variables, response) into graphql.jsonl
+    #[arg(long)]
+    capture_graphql: bool,
+    /// Also write page.standalone.html: the saved DOM with CSS and images inlined, no scripts
+    #[arg(long)]
+    standalone: bool,
+    /// Extra derived artifacts to write (comma-separated): markdown (page.md), text (page.txt)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    emit: Vec<Emit>,
+    /// Package the main content, title and images as page.epub
+    #[arg(long)]
+    epub: bool,
+    /// Crawl same-host links from the start URL, one run per page
+    #[arg(long)]
+    crawl: bool,
+    /// Maximum link depth from the start URL when crawling
+    #[arg(long, default_value_t = 1)]
+    crawl_depth: u32,
+    /// Maximum number of pages to visit when crawling
+    #[arg(long, default_value_t = 100)]
+    crawl_limit: u32,
+    /// Name of a crawl to create or resume (defaults to the start host)
+    #[arg(long)]
+    crawl_id: Option<String>,
+    /// After crawling, combine the page PDFs in crawl order into FILE, behind a contents page
+    #[arg(long, value_name = "FILE", requires = "crawl", conflicts_with_all = ["pdf_password", "pdf_owner_password"])]
+    merge_pdf: Option<PathBuf>,
+    /// JSONL job manifest: one {"url": ..., "wait_selector": ..., "proxy": ..., "profile": ...}
+    /// object per line, overriding the command-line options for that URL
+    #[arg(long, value_name = "FILE", conflicts_with = "crawl")]
+    manifest: Option<PathBuf>,
+    /// Re-render the URL every --interval and keep only runs where the page changed
+    #[arg(long, conflicts_with_all = ["crawl", "manifest"])]
+    watch: bool,
+    /// Time between --watch checks ("30s", "15m", "1h")
+    #[arg(long, default_value = "15m", value_parser = watch::parse_interval)]
+    interval: Duration,
+    /// What counts as a change in --watch mode
+    #[arg(long, value_enum, default_value = "both")]
+    watch_compare: WatchCompare,
+    /// Stop after this many --watch checks (0 = run until interrupted)
+    #[arg(long, default_value_t = 0)]
+    watch_iterations: u32,
+    /// Keep --watch runs even when nothing changed
+    #[arg(long)]
+    keep_unchanged: bool,
+    /// Renders to run in parallel in batch/crawl mode
+    #[arg(long, default_value_t = 1)]
+    workers: usize,
+    /// Maximum parallel renders per host in batch/crawl mode
+    #[arg(long, default_value_t = 1)]
+    host_concurrency: usize,
+    /// In batch mode, give each target origin its own directory inside the profile, so
+    /// cookies, storage and cache never carry over from one target to another
+    #[arg(long)]
+    partition_by_origin: bool,
+    /// Render each hreflang alternate of the page in a sub-run and link them in alternates.json
+    #[arg(long)]
+    capture_alternates: bool,
+    /// Attach a key=value tag to the run (repeatable); stored in result.json and the run index
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+    tags: Vec<(String, String)>,
+    /// Write manifest.json (artifact sha256s and capture metadata) and a detached Ed25519
+    /// signature, manifest.sig, with this PKCS#8 PEM private key
+    #[arg(long, value_name = "KEY.PEM")]
+    sign: Option<PathBuf>,
+    /// POST each finished run's result.json (or timeout report) to this URL
+    #[arg(long, value_name = "URL")]
+    notify_webhook: Option<String>,
+    /// Retries for --notify-webhook before giving up (exponential backoff)
+    #[arg(long, default_value_t = 3)]
+    notify_retries: u32,
+    /// Retries after a 429/503 response that carries Retry-After (HTTP and Chrome paths)
+    #[arg(long, default_value_t = 2)]
+    rate_limit_retries: u32,
+    /// Longest Retry-After to honor; longer requests are cut to this ("30s", "2m")
+    #[arg(long, default_value = "60s", value_parser = watch::parse_interval)]
+    max_retry_after: Duration,
+    /// Requests per minute allowed per egress IP (the --proxy host, or direct), shared by
+    /// every process using the same --egress-budget-store
+    #[arg(long, value_name = "PER_MINUTE")]
+    egress_budget: Option<u32>,
+    /// Where --egress-budget is coordinated: a directory (file locks; default
+    /// ~/.ankabot/egress) or redis://[:password@]host[:port][/db]
+    #[arg(long, value_name = "DIR|URL", value_parser = egress::parse_store)]
+    egress_budget_store: Option<egress::Store>,
+    /// Disable the live progress display for batch/crawl runs (e.g. in CI)
+    #[arg(long)]
+    no_tui: bool,
+    /// Screenshot the whole page instead of the viewport (tall pages are stitched)
+    #[arg(long)]
+    full_page: bool,
+    /// Screenshot only this rectangle of the viewport, in CSS pixels: "x,y,w,h"
+    #[arg(long, value_name = "X,Y,W,H", conflicts_with = "full_page")]
+    clip: Option<Clip>,
+    /// Screenshot image format
+    #[arg(long, value_enum, default_value = "png")]
+    screenshot_format: ScreenshotFormat,
+    /// Screenshot quality (0-100) for the lossy jpeg/webp formats
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+    screenshot_quality: Option<u32>,
+    /// Width of thumb.png, the small PNG copy of the screenshot; 0 disables it
+    #[arg(long, value_name = "PX", default_value_t = thumbnail::DEFAULT_WIDTH)]
+    thumbnail_width: u32,
+    /// Also screenshot at each of these viewport widths into breakpoints/, e.g. "375,768,1280"
+    #[arg(
+        long,
+        value_name = "WIDTHS",
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(1..=10_000)
+    )]
+    breakpoints: Vec<u32>,
+    /// Screenshot the viewport every MS milliseconds from navigation until the page is ready,
+    /// into filmstrip/
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(50..))]
+    filmstrip_ms: Option<u64>,
+    /// Record a video of the session up to the capture into session.webm (needs ffmpeg)
+    #[arg(long)]
+    screencast: bool,
+    /// Write dom_snapshot.json: every node with its layout boxes, paint order and computed
+    /// styles (CDP DOMSnapshot)
+    #[arg(long)]
+    dom_snapshot: bool,
+    /// Write axtree.json: the accessibility tree Chrome built for the page (roles, names,
+    /// states), for automated a11y checks
+    #[arg(long)]
+    axtree: bool,
+    /// Record a Chrome performance trace of the render into trace.json (chrome://tracing,
+    /// Perfetto)
+    #[arg(long)]
+    trace: bool,
+    /// Also save main_content.png: a screenshot of just the detected main content region
+    #[arg(long)]
+    crop_main_content: bool,
+    /// Also save a copy of the screenshot with consent banners, CAPTCHAs, login forms and the
+    /// main content boxed and labeled (snap.annotated.<ext>)
+    #[arg(long)]
+    annotate: bool,
+    /// Print with a cleanup stylesheet: no nav/footers/ads/overlays, accordions expanded
+    #[arg(long)]
+    pdf_clean: bool,
+    /// CSS media type the page is laid out for when printing the PDF (default: print, as
+    /// Chrome does)
+    #[arg(long, value_enum)]
+    pdf_media: Option<pdf::PdfMedia>,
+    /// PDF paper size (default: the page's CSS @page size, else Letter)
+    #[arg(long, value_enum)]
+    paper: Option<pdf::PaperSize>,
+    /// Print the PDF in landscape orientation
+    #[arg(long)]
+    landscape: bool,
+    /// PDF margins: one length or top,right,bottom,left ("0.5in", "10mm"); default 0
+    #[arg(long, value_name = "LENGTHS")]
+    pdf_margin: Option<pdf::Margins>,
+    /// Scale of the page content in the PDF (0.1-2)
+    #[arg(long, value_parser = pdf::parse_scale)]
+    pdf_scale: Option<f64>,
+    /// Only print these pages, e.g. "1-5,8"
+    #[arg(long, value_name = "RANGES", value_parser = pdf::parse_page_ranges)]
+    pdf_page_ranges: Option<String>,
+    /// HTML file printed at the top of every PDF page; elements with the classes date,
+    /// title, url, pageNumber and totalPages are filled in
+    #[arg(long, value_name = "FILE")]
+    pdf_header_template: Option<PathBuf>,
+    /// HTML file printed at the bottom of every PDF page (see --pdf-header-template)
+    #[arg(long, value_name = "FILE")]
+    pdf_footer_template: Option<PathBuf>,
+    /// PDF Title metadata (default: the page title)
+    #[arg(long, value_name = "TEXT")]
+    pdf_title: Option<String>,
+    /// PDF Author metadata
+    #[arg(long, value_name = "TEXT")]
+    pdf_author: Option<String>,
+    /// PDF Subject metadata (default: the source URL)
+    #[arg(long, value_name = "TEXT")]
+    pdf_subject: Option<String>,
+    /// PDF Keywords metadata (comma-separated)
+    #[arg(long, value_name = "WORDS", value_delimiter = ',')]
+    pdf_keywords: Vec<String>,
+    /// Don't add bookmarks for the page's h1-h3 headings to the PDF
+    #[arg(long)]
+    no_pdf_bookmarks: bool,
+    /// Convert the PDF to PDF/A-2b for archiving (sRGB output intent, XMP metadata)
+    #[arg(long, conflicts_with_all = ["stamp", "pdf_password", "pdf_owner_password"])]
+    pdfa: bool,
+    /// Encrypt the PDF; readers need this password to open it
+    #[arg(long, value_name = "PASSWORD")]
+    pdf_password: Option<String>,
+    /// Owner password for the encrypted PDF (lifts --pdf-restrict); random when omitted
+    #[arg(long, value_name = "PASSWORD")]
+    pdf_owner_password: Option<String>,
+    /// Actions to forbid in the encrypted PDF (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', requires = "pdf_encryption")]
+    pdf_restrict: Vec<pdf::PdfRestriction>,
+    /// Overlay this text on every PDF page; "{timestamp}" and "{url}" are filled in
+    #[arg(long, value_name = "TEXT")]
+    stamp: Option<String>,
+    /// Placement of the --stamp text
+    #[arg(long, value_enum, default_value = "footer")]
+    stamp_style: pdf::StampStyle,
+    /// Also stamp the screenshot
+    #[arg(long, requires = "stamp")]
+    stamp_screenshot: bool,
+    /// Compare the result against a stored golden run directory
+    #[arg(long)]
+    golden: Option<PathBuf>,
+    /// Minimum DOM text similarity (0.0-1.0) to pass the golden comparison
+    #[arg(long, default_value_t = 0.95)]
+    golden_min_similarity: f64,
+    /// Largest difference-hash distance (0-64) between the screenshots that still passes
+    /// the golden comparison
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=64))]
+    golden_max_visual_distance: u32,
+    /// Render a built-in local fixture page instead of a URL
+    #[cfg(feature = "fixtures")]
+    #[arg(long, value_name = "NAME")]
+    target_fixture: Option<String>,
+}
+
+fn parse_tag(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.trim().is_empty() => Ok((k.trim().to_string(), v.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+/// `--wait-request` / `--wait-console` patterns.
+fn parse_wait_regex(s: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(s).map_err(|e| format!("invalid wait pattern: {}", e))
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+enum Command {
+    /// Serve the HTTP API over the runs stored in --out-root
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8787")]
+        listen: std::net::SocketAddr,
+    },
+    /// Delete old runs from --out-root (and the run index)
+    Prune {
+        /// Remove runs created longer ago than this ("30d", "12h")
+        #[arg(long, value_parser = watch::parse_interval)]
+        older_than: Option<Duration>,
+        /// Keep only the newest N runs of each host
+        #[arg(long, value_name = "N")]
+        max_runs_per_host: Option<usize>,
+        /// List the runs that would be removed, without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Query the index of runs stored in --out-root
+    Runs {
+        #[command(subcommand)]
+        action: RunsAction,
+    },
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+enum RunsAction {
+    /// List indexed runs, newest first
+    List {
+        /// Only runs of this URL (as given, or as finally loaded)
+        #[arg(long)]
+        url: Option<String>,
+        /// Only runs with this status (ok, timeout, blocked)
+        #[arg(long)]
+        status: Option<String>,
+        /// Only runs created at or after this time (RFC 3339 or YYYY-MM-DD[THH:MM])
+        #[arg(long, value_parser = server::parse_time)]
+        since: Option<String>,
+        /// Maximum runs to list
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+        /// Print one JSON object per run
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show one run's index record (default: the latest run)
+    Show {
+        /// Index id, as printed by `runs list`
+        id: Option<i64>,
+        /// The latest run of this URL
+        #[arg(long, conflicts_with = "id")]
+        url: Option<String>,
+        /// With --url, the run nearest to this time instead of the latest
+        #[arg(long, requires = "url", value_parser = server::parse_time)]
+        at: Option<String>,
+    },
 }
 
 impl Cli {
+    fn url(&self) -> &str {
+        self.url.as_deref().unwrap_or_default()
+    }
+
+    fn tag_map(&self) -> BTreeMap<String, String> {
+        self.tags.iter().cloned().collect()
+    }
+
     fn locale_or_default(&self) -> String {
         self.locale
             .clone()
@@ -161,6 +746,68 @@ impl Cli {
         }
         (1366, 768)
     }
+
+    /// Whether the options ask for something only a Chrome render produces, so the plain
+    /// HTTP fast path must not serve the page. A Chrome-only option belongs here.
+    fn needs_chrome(&self) -> bool {
+        [
+            self.force_chrome,
+            self.mode != Mode::Archive,
+            // Waits that watch the live page.
+            !self.wait_selector.is_empty(),
+            self.wait_xpath.is_some(),
+            self.wait_request.is_some(),
+            self.wait_js.is_some(),
+            self.wait_console.is_some(),
+            self.dom_quiet_ms.is_some(),
+            self.wait_ready.eq_ignore_ascii_case("dom-quiet"),
+            self.fake_time.is_some(),
+            // Interaction and extraction on the rendered page.
+            self.follow_pagination.is_some(),
+            self.infinite_scroll,
+            self.extract_contacts,
+            self.extract_images,
+            self.save_assets,
+            self.capture_api.is_some(),
+            self.capture_graphql,
+            self.dom_snapshot,
+            self.axtree,
+            !self.emit.is_empty(),
+            self.epub,
+            // Recordings.
+            self.trace,
+            self.screencast,
+            self.filmstrip_ms.is_some(),
+            // Screenshots.
+            !self.breakpoints.is_empty(),
+            self.clip.is_some(),
+            self.full_page,
+            self.annotate,
+            self.crop_main_content,
+            self.screenshot_format != ScreenshotFormat::Png,
+            self.screenshot_quality.is_some(),
+            // The printed PDF.
+            self.pdfa,
+            self.stamp.is_some(),
+            self.pdf_title.is_some(),
+            self.pdf_author.is_some(),
+            self.pdf_subject.is_some(),
+            !self.pdf_keywords.is_empty(),
+            self.paper.is_some(),
+            self.landscape,
+            self.pdf_margin.is_some(),
+            self.pdf_scale.is_some(),
+            self.pdf_page_ranges.is_some(),
+            self.pdf_header_template.is_some(),
+            self.pdf_footer_template.is_some(),
+            self.pdf_clean,
+            self.pdf_media.is_some(),
+            self.pdf_password.is_some(),
+            self.pdf_owner_password.is_some(),
+        ]
+        .into_iter()
+        .any(|b| b)
+    }
 }
 
 #[derive(Clone, Debug, ValueEnum)]
@@ -170,16 +817,187 @@ enum OnTimeout {
     Fail,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputTarget {
+    /// A run directory under --out-root, recorded in the run index
+    Dir,
+    /// No files: one JSON document on stdout with the HTML inline and the screenshot and
+    /// PDF in base64
+    Stdout,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// DOM, screenshot and PDF at archival quality
+    Archive,
+    /// DOM, text and metadata only, with images, fonts and media blocked
+    Extract,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Engine {
+    Chrome,
+    HeadlessShell,
+}
+
+/// A `--clip` rectangle.
+#[derive(Clone, Copy, Debug)]
+struct Clip {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl std::str::FromStr for Clip {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let nums: Vec<f64> = s
+            .split(',')
+            .map(|n| n.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("expected x,y,w,h numbers, got '{}'", s))?;
+        let [x, y, width, height] = nums[..] else {
+            return Err(format!("expected four values x,y,w,h, got '{}'", s));
+        };
+        if x < 0.0 || y < 0.0 || width <= 0.0 || height <= 0.0 {
+            return Err("clip needs x,y >= 0 and a positive width and height".to_string());
+        }
+        Ok(Clip {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+impl Clip {
+    fn viewport(self) -> headless_chrome::protocol::cdp::Page::Viewport {
+        headless_chrome::protocol::cdp::Page::Viewport {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            scale: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ScreenshotFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ScreenshotFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Jpeg => "jpg",
+            ScreenshotFormat::Webp => "webp",
+        }
+    }
+
+    fn cdp(self) -> headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption {
+        use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption as F;
+        match self {
+            ScreenshotFormat::Png => F::Png,
+            ScreenshotFormat::Jpeg => F::Jpeg,
+            ScreenshotFormat::Webp => F::Webp,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// The main content converted to Markdown (page.md)
+    Markdown,
+    /// The visible text of the page (page.txt)
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum WaitMode {
+    /// The first selector to appear ends the wait (A/B-tested layouts)
+    Any,
+    /// Every selector must appear
+    All,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum WatchCompare {
+    /// Visible DOM text hash
+    Html,
+    /// Screenshot hash
+    Screenshot,
+    /// Either of the two
+    Both,
+}
+
 #[derive(Serialize)]
 struct TimeoutReport {
     status: &'static str,
     reason: String,
     url: String,
+    /// Status of the main document's response, when one arrived before the deadline.
+    http_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<headers::HeaderSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<tls::TlsInfo>,
     deadline_ms: u64,
     elapsed_ms: u64,
     wait_branch: String,
     diagnostics: Diagnostics,
     artifacts: Artifacts,
+    /// The PDF was encrypted with --pdf-password/--pdf-owner-password.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pdf_encrypted: bool,
+    network_summary: Option<network::NetworkSummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    trackers: Vec<trackers::Tracker>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mixed_content: Vec<mixed::MixedContent>,
+    #[serde(flatten)]
+    console: console::ConsoleCounts,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    js_errors: Vec<jserrors::JsError>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    throttled: Vec<throttle::ThrottleEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource_limits: Option<cgroup::LimitReport>,
+    /// Debug artifacts that could not be made, and why.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, String>,
+}
+
+/// result.json for a page that turned out to be a WAF block, bot challenge or geo-block.
+#[derive(Serialize)]
+struct BlockedReport {
+    status: &'static str,
+    url: String,
+    final_url: String,
+    http_status: Option<u16>,
+    #[serde(flatten)]
+    verdict: block::Verdict,
+    elapsed_ms: u64,
+    run_dir: String,
+    artifacts: Artifacts,
+    /// The PDF was encrypted with --pdf-password/--pdf-owner-password.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pdf_encrypted: bool,
+    network_summary: Option<network::NetworkSummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    throttled: Vec<throttle::ThrottleEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource_limits: Option<cgroup::LimitReport>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, String>,
 }
 
 #[derive(Serialize)]
@@ -188,134 +1006,741 @@ struct Diagnostics {
     images_total: u64,
     images_incomplete: u64,
     pending_requests: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    main_thread: Option<mainthread::MainThread>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_weight: Option<weight::PageWeight>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    web_vitals: Option<vitals::WebVitals>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    navigation: Option<navtiming::NavigationTiming>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chrome_process: Option<procstats::ProcessUsage>,
+}
+
+/// Page-health diagnostics in result.json.
+#[derive(Serialize, Default)]
+struct PageDiagnostics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    main_thread: Option<mainthread::MainThread>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_weight: Option<weight::PageWeight>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    web_vitals: Option<vitals::WebVitals>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    navigation: Option<navtiming::NavigationTiming>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chrome_process: Option<procstats::ProcessUsage>,
 }
 
 #[derive(Serialize)]
 struct Artifacts {
-    html: String,
-    screenshot: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screenshot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    annotated_screenshot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+    pdf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mhtml: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    waterfall: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    waterfall_json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    har: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    console_log: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    js_errors_log: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_log: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    third_parties: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filmstrip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screencast: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+struct ArtifactHashes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screenshot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pdf: Option<String>,
 }
 
+impl ArtifactHashes {
+    fn is_empty(&self) -> bool {
+        self.html.is_none() && self.screenshot.is_none() && self.pdf.is_none()
+    }
+}
+
 enum RenderOutcome {
-    Success(ChromeRes),
-    Timeout(TimeoutReport),
+    Success(Box<ChromeRes>),
+    Timeout(Box<TimeoutReport>),
+    Blocked(Box<BlockedReport>),
 }
 
 #[derive(Serialize)]
 struct Output {
     input_url: String,
     final_url: String,
-    http_status: u16,
+    /// `null` when no response for the document was seen.
+    http_status: Option<u16>,
     redirected: bool,
     requires_javascript: bool,
     waf_detected: bool,
     anti_bot_vendor: Option<String>,
     js_challenge_page: bool,
     screenshot_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    annotated_screenshot_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail_path: Option<String>,
     pdf_path: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pdf_encrypted: bool,
     html_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dom_snapshot_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    axtree_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mhtml_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    standalone_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    markdown_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epub_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    waterfall_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    waterfall_json_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    har_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    console_log_path: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    console: Option<console::ConsoleCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    js_errors_log_path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    js_errors: Vec<jserrors::JsError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_log_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_summary: Option<network::NetworkSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    third_parties_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    third_parties: Option<thirdparty::Summary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    trackers: Vec<trackers::Tracker>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mixed_content: Vec<mixed::MixedContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<headers::HeaderSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<tls::TlsInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<dns::Remote>,
     elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scrolls: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    images_index: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assets_index: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_index: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    graphql_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    graphql_operations: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    breakpoints_index: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filmstrip_index: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screencast_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contacts_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media: Option<media::Media>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource_limits: Option<cgroup::LimitReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<PageDiagnostics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anchor: Option<anchor::Anchor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<annotate::Region>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    main_content: Option<maincontent::MainContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    main_content_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<metadata::Metadata>,
     pages_crawled: u32,
     wait_branch: String,
+    /// Which `--wait-selector` appeared, with several in `--wait-mode any`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wait_selector_matched: Option<String>,
+    /// What `--wait-js` evaluated to when it turned truthy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wait_js_value: Option<serde_json::Value>,
     run_dir: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pages: Vec<PageCapture>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    article_path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternates: Vec<alternates::Alternate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alternates_manifest: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    throttled: Vec<throttle::ThrottleEvent>,
+    /// Artifacts skipped or cut short when the deadline ran out, and why.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+    /// sha256 of the HTML, screenshot and PDF files as written.
+    #[serde(skip_serializing_if = "ArtifactHashes::is_empty")]
+    sha256: ArtifactHashes,
+    /// Run directory of an earlier capture with the same content hash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    golden: Option<golden::GoldenVerdict>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Cli::parse();
-    let run_paths = new_run_paths(Some(args.out_root.clone()), args.run_dir.clone(), &args.url)?;
+    #[allow(unused_mut)]
+    let mut args = Cli::parse();
+    // Dropped last, after every Chrome cgroup is gone.
+    let cgroup_leaf = cgroup::LeafGuard;
+    if args.screenshot_quality.is_some() && args.screenshot_format == ScreenshotFormat::Png {
+        bail!("--screenshot-quality needs --screenshot-format jpeg or webp");
+    }
+    if args.mode == Mode::Extract && (args.annotate || args.crop_main_content) {
+        bail!("--mode extract takes no screenshots; drop --annotate and --crop-main-content");
+    }
+    if let Some(key) = &args.sign {
+        sign::load_key(key)?;
+    }
+    pdf::print_options(&args)?;
+    #[cfg(feature = "fixtures")]
+    if let Some(name) = args.target_fixture.clone() {
+        args.url = Some(fixtures::start(&name).await?);
+    }
+
+    match &args.command {
+        Some(Command::Serve { listen }) => return server::serve(args.clone(), *listen).await,
+        Some(Command::Prune {
+            older_than,
+            max_runs_per_host,
+            dry_run,
+        }) => {
+            if older_than.is_none() && max_runs_per_host.is_none() {
+                bail!("prune needs --older-than and/or --max-runs-per-host");
+            }
+            return prune::run(
+                &args.out_root,
+                &prune::Policy {
+                    older_than: *older_than,
+                    max_runs_per_host: *max_runs_per_host,
+                    dry_run: *dry_run,
+                },
+            );
+        }
+        Some(Command::Runs { action }) => {
+            return match action {
+                RunsAction::List {
+                    url,
+                    status,
+                    since,
+                    limit,
+                    json,
+                } => runs::list(
+                    &args.out_root,
+                    &runs::Filter {
+                        url: url.clone(),
+                        status: status.clone(),
+                        since: since.clone(),
+                        limit: *limit,
+                    },
+                    *json,
+                ),
+                RunsAction::Show { id, url, at } => {
+                    runs::show(&args.out_root, *id, url.as_deref(), at.as_deref())
+                }
+            };
+        }
+        None => {}
+    }
+
+    let code = if let Some(manifest) = &args.manifest {
+        batch::run(&args, manifest).await?
+    } else if args.watch {
+        watch::run(&args).await?
+    } else if args.crawl {
+        crawl::run(&args).await?
+    } else if args.output == OutputTarget::Stdout {
+        stdout::run(&args).await?
+    } else {
+        run_one(&args).await?.exit_code
+    };
+    if code != 0 {
+        drop(cgroup_leaf);
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// Where a single run left its result and the exit code it maps to.
+struct RunRecord {
+    result_json: PathBuf,
+    /// What was written to result.json.
+    result: serde_json::Value,
+    exit_code: i32,
+}
+
+/// Fetch/render one URL into its run directory and record it in the run index.
+async fn run_one(args: &Cli) -> Result<RunRecord> {
+    finish_run(args, capture_one(args).await?).await
+}
+
+/// Upload, sign, index, notify and archive a captured run.
+async fn finish_run(args: &Cli, mut rec: RunRecord) -> Result<RunRecord> {
+    let uploader = args
+        .upload
+        .as_ref()
+        .map(upload::Uploader::new)
+        .transpose()?;
+    let dir = rec.result_json.parent().map(Path::to_path_buf);
+    if let (Some(up), Some(dir)) = (&uploader, &dir) {
+        up.upload_artifacts(&args.out_root, dir, &rec.result_json, &mut rec.result)
+            .await?;
+    }
+    if let Some(key) = &args.sign {
+        let signed = sign::sign_run(
+            &sign::load_key(key)?,
+            args.url(),
+            &rec.result_json,
+            rec.exit_code,
+        )?;
+        if let (Some(up), Some(dir)) = (&uploader, &dir) {
+            for path in &signed {
+                up.upload_run_file(&args.out_root, dir, path).await?;
+            }
+        }
+    }
+    if let (Some(up), Some(dir)) = (&uploader, &dir) {
+        // Last, so its arrival in the bucket means the run is complete.
+        up.upload_run_file(&args.out_root, dir, &rec.result_json)
+            .await?;
+    }
+    let run_dir = rec
+        .result_json
+        .parent()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    index::RunIndex::open(&args.out_root)?.record(
+        args.url(),
+        &run_dir,
+        rec.exit_code,
+        &rec.result,
+        &args.tag_map(),
+    )?;
+    if let Some(hook) = &args.notify_webhook {
+        // A delivery failure is reported but doesn't fail the capture itself.
+        if let Err(e) = notify::post_result(hook, &rec.result_json, args.notify_retries).await {
+            eprintln!("warning: {:#}", e);
+        }
+    }
+    if let (Some(format), Some(dir)) = (args.archive, rec.result_json.parent()) {
+        let packed = archive::pack(dir, format)?;
+        if let Some(up) = &uploader {
+            up.upload_archive(&args.out_root, &packed).await?;
+        }
+        if args.archive_remove {
+            std::fs::remove_dir_all(dir).with_context(|| format!("removing {}", dir.display()))?;
+        }
+        println!("{}", packed.display());
+    }
+    Ok(rec)
+}
+
+/// Fetch/render one URL into its run directory and write result.json (or a timeout report).
+async fn capture_one(args: &Cli) -> Result<RunRecord> {
+    let run_paths = new_run_paths(
+        Some(args.out_root.clone()),
+        args.run_dir.clone(),
+        args.url(),
+        args.screenshot_format,
+        &args.run_name_template,
+        &args.artifact_names,
+    )?;
 
-    if !args.force_chrome {
-        if let Ok(http_res) = fetch_http(&args.url, &run_paths.http_raw).await {
+    let mut throttled = Vec::new();
+    if !args.needs_chrome() {
+        if let Ok(http_res) = fetch_http(args, &run_paths.http_raw, &mut throttled).await {
             let needs_js = http_res.looks_empty || http_res.links_found == 0;
 
             if !needs_js {
+                let mut warnings = Vec::new();
+                if args.thumbnail_width > 0 && args.thumbnail_width != thumbnail::DEFAULT_WIDTH {
+                    warnings.push(
+                        "--thumbnail-width had no effect: the page was served over plain HTTP, \
+                         which takes no screenshot"
+                            .to_string(),
+                    );
+                }
+                headers::write(
+                    &http_res.headers,
+                    &http_res.final_url,
+                    http_res.status,
+                    &run_paths.headers_json,
+                )?;
+                let remote = dns::describe(
+                    &http_res.final_url,
+                    http_res.remote_addr.map(|a| a.ip().to_string()).as_deref(),
+                    http_res.remote_addr.map(|a| a.port()),
+                );
                 let out = Output {
-                    input_url: args.url,
+                    input_url: args.url().to_string(),
                     final_url: http_res.final_url,
-                    http_status: http_res.status,
+                    http_status: Some(http_res.status),
                     redirected: http_res.redirected,
                     requires_javascript: false,
                     waf_detected: http_res.waf_detected,
                     anti_bot_vendor: http_res.anti_bot_vendor,
                     js_challenge_page: false,
                     screenshot_path: None,
+                    annotated_screenshot_path: None,
+                    thumbnail_path: None,
                     pdf_path: None,
+                    pdf_encrypted: false,
                     html_path: run_paths.http_raw.display().to_string(),
+                    dom_snapshot_path: None,
+                    axtree_path: None,
+                    trace_path: None,
+                    mhtml_path: None,
+                    standalone_path: None,
+                    markdown_path: None,
+                    text_path: None,
+                    epub_path: None,
+                    waterfall_path: None,
+                    waterfall_json_path: None,
+                    har_path: None,
+                    console_log_path: None,
+                    console: None,
+                    js_errors_log_path: None,
+                    js_errors: Vec::new(),
+                    network_log_path: None,
+                    network_summary: None,
+                    third_parties_path: None,
+                    third_parties: None,
+                    trackers: Vec::new(),
+                    mixed_content: Vec::new(),
+                    headers_path: Some(run_paths.headers_json.display().to_string()),
+                    headers: Some(headers::summarize(&http_res.headers)),
+                    tls: http_res.tls.clone(),
+                    remote,
                     elapsed_ms: http_res.elapsed_ms,
+                    scrolls: None,
+                    images_index: None,
+                    assets_index: None,
+                    api_index: None,
+                    graphql_path: None,
+                    graphql_operations: None,
+                    breakpoints_index: None,
+                    filmstrip_index: None,
+                    screencast_path: None,
+                    contacts_path: None,
+                    media: None,
+                    resource_limits: None,
+                    diagnostics: None,
+                    anchor: None,
+                    annotations: Vec::new(),
+                    main_content: None,
+                    main_content_path: None,
+                    metadata: None,
                     pages_crawled: 0,
                     wait_branch: "ready_state".to_string(),
+                    wait_selector_matched: None,
+                    wait_js_value: None,
                     run_dir: run_paths.run_dir.display().to_string(),
+                    pages: Vec::new(),
+                    article_path: None,
+                    alternates: Vec::new(),
+                    alternates_manifest: None,
+                    throttled,
+                    warnings,
+                    content_hash: None,
+                    sha256: ArtifactHashes::default(),
+                    duplicate_of: None,
+                    golden: None,
+                    tags: args.tag_map(),
                 };
-                write_json(&run_paths.result_json, &out)?;
-                return Ok(());
+                return write_output(args, &run_paths, out).await;
             }
         }
     }
 
-    let mut chrome_res = render_with_chrome(&args.url, &run_paths, &args);
-    if chrome_res.is_err() && args.headful_fallback && !args.headful {
-        let mut retry = args.clone();
-        retry.headful = true;
-        chrome_res = render_with_chrome(&args.url, &run_paths, &retry);
-    }
+    // The render blocks on CDP calls and sleeps; keep it off the runtime's workers so
+    // concurrent jobs, timers and the egress budget carry on meanwhile.
+    let (owned_args, owned_paths) = (args.clone(), run_paths.clone());
+    let chrome_res = tokio::task::spawn_blocking(move || {
+        let args = &owned_args;
+        let mut chrome_res = render_with_chrome(args.url(), &owned_paths, args);
+        if chrome_res.is_err() && args.headful_fallback && !args.headful {
+            let mut retry = args.clone();
+            retry.headful = true;
+            retry.engine = Engine::Chrome;
+            chrome_res = render_with_chrome(args.url(), &owned_paths, &retry);
+        }
+        chrome_res
+    })
+    .await
+    .map_err(|e| anyhow!("render task failed: {}", e))?;
     let outcome = chrome_res.context("headless-chrome render failed")?;
 
     match outcome {
         RenderOutcome::Success(chrome) => {
+            throttled.extend(chrome.throttled);
             let out = Output {
-                input_url: args.url,
+                input_url: args.url().to_string(),
                 final_url: chrome.final_url,
-                http_status: chrome.status.unwrap_or(200),
+                http_status: chrome.status,
                 redirected: chrome.redirected,
                 requires_javascript: true,
                 waf_detected: chrome.waf_detected,
                 anti_bot_vendor: chrome.anti_bot_vendor,
                 js_challenge_page: chrome.js_challenge,
                 screenshot_path: chrome.screenshot_path,
+                annotated_screenshot_path: chrome.annotated_screenshot_path,
+                thumbnail_path: chrome.thumbnail_path,
                 pdf_path: chrome.pdf_path,
+                pdf_encrypted: false,
                 html_path: chrome.html_path,
+                dom_snapshot_path: chrome.dom_snapshot_path,
+                axtree_path: chrome.axtree_path,
+                trace_path: chrome.trace_path,
+                mhtml_path: chrome.mhtml_path,
+                standalone_path: None,
+                markdown_path: chrome.markdown_path,
+                text_path: chrome.text_path,
+                epub_path: chrome.epub_path,
+                waterfall_path: chrome.waterfall_path,
+                waterfall_json_path: chrome.waterfall_json_path,
+                har_path: chrome.har_path,
+                console_log_path: chrome.console_log_path,
+                console: chrome.console,
+                js_errors_log_path: chrome.js_errors_log_path,
+                js_errors: chrome.js_errors,
+                network_log_path: chrome.network_log_path,
+                network_summary: chrome.network_summary,
+                third_parties_path: chrome.third_parties_path,
+                third_parties: chrome.third_parties,
+                trackers: chrome.trackers,
+                mixed_content: chrome.mixed_content,
+                headers_path: chrome.headers_path,
+                headers: chrome.headers,
+                tls: chrome.tls,
+                remote: chrome.remote,
                 elapsed_ms: chrome.elapsed_ms,
-                pages_crawled: 1,
+                scrolls: chrome.scrolls,
+                images_index: chrome.images_index,
+                assets_index: chrome.assets_index,
+                api_index: chrome.api_index,
+                graphql_path: chrome.graphql_path,
+                graphql_operations: chrome.graphql_operations,
+                breakpoints_index: chrome.breakpoints_index,
+                filmstrip_index: chrome.filmstrip_index,
+                screencast_path: chrome.screencast_path,
+                contacts_path: chrome.contacts_path,
+                media: chrome.media,
+                resource_limits: chrome.resource_limits,
+                diagnostics: chrome.diagnostics,
+                anchor: chrome.anchor,
+                annotations: chrome.annotations,
+                main_content: chrome.main_content,
+                main_content_path: chrome.main_content_path,
+                metadata: chrome.metadata,
+                pages_crawled: 1 + chrome.pages.len() as u32,
                 wait_branch: chrome.wait_branch,
+                wait_selector_matched: chrome.wait_selector_matched,
+                wait_js_value: chrome.wait_js_value,
                 run_dir: run_paths.run_dir.display().to_string(),
+                pages: chrome.pages,
+                article_path: chrome.article_path,
+                alternates: Vec::new(),
+                alternates_manifest: None,
+                throttled,
+                warnings: chrome.warnings,
+                content_hash: None,
+                sha256: ArtifactHashes::default(),
+                duplicate_of: None,
+                golden: None,
+                tags: args.tag_map(),
             };
-            write_json(&run_paths.result_json, &out)?;
-            Ok(())
+            write_output(args, &run_paths, out).await
+        }
+        RenderOutcome::Blocked(mut report) => {
+            throttled.append(&mut report.throttled);
+            report.throttled = throttled;
+            if let Some(p) = &report.artifacts.pdf {
+                report.pdf_encrypted = pdf::postprocess(args, Path::new(p), &report.url)?;
+            }
+            write_json(&run_paths.result_json, &report)?;
+            Ok(RunRecord {
+                result_json: run_paths.result_json.clone(),
+                result: serde_json::to_value(&report)?,
+                exit_code: 4,
+            })
         }
         RenderOutcome::Timeout(report) => match args.on_timeout {
             OnTimeout::Report => {
+                let mut report = report;
+                throttled.append(&mut report.throttled);
+                report.throttled = throttled;
+                if let Some(p) = &report.artifacts.pdf {
+                    report.pdf_encrypted = pdf::postprocess(args, Path::new(p), &report.url)?;
+                }
                 write_json(&run_paths.result_json, &report)?;
-                std::process::exit(2);
+                Ok(RunRecord {
+                    result_json: run_paths.result_json.clone(),
+                    result: serde_json::to_value(&report)?,
+                    exit_code: 2,
+                })
             }
             OnTimeout::Continue => {
                 let TimeoutReport {
                     url,
+                    http_status,
+                    headers: header_summary,
+                    tls,
                     elapsed_ms,
                     wait_branch,
                     artifacts,
+                    network_summary,
+                    trackers: timeout_trackers,
+                    mixed_content: timeout_mixed_content,
+                    console: timeout_console,
+                    js_errors: timeout_js_errors,
+                    throttled: chrome_throttled,
+                    resource_limits,
+                    diagnostics,
+                    warnings,
                     ..
-                } = report;
+                } = *report;
+                throttled.extend(chrome_throttled);
                 let out = Output {
-                    input_url: args.url,
+                    input_url: args.url().to_string(),
                     final_url: url,
-                    http_status: 0,
+                    http_status,
                     redirected: false,
                     requires_javascript: true,
                     waf_detected: false,
                     anti_bot_vendor: None,
                     js_challenge_page: false,
-                    screenshot_path: Some(artifacts.screenshot),
+                    screenshot_path: artifacts.screenshot,
+                    annotated_screenshot_path: artifacts.annotated_screenshot,
+                    thumbnail_path: artifacts.thumbnail,
                     pdf_path: artifacts.pdf,
-                    html_path: artifacts.html,
+                    pdf_encrypted: false,
+                    html_path: artifacts.html.unwrap_or_default(),
+                    dom_snapshot_path: None,
+                    axtree_path: None,
+                    trace_path: artifacts.trace,
+                    mhtml_path: artifacts.mhtml,
+                    standalone_path: None,
+                    markdown_path: None,
+                    text_path: None,
+                    epub_path: None,
+                    waterfall_path: artifacts.waterfall,
+                    waterfall_json_path: artifacts.waterfall_json,
+                    har_path: artifacts.har,
+                    console_log_path: artifacts.console_log,
+                    console: Some(timeout_console),
+                    js_errors_log_path: artifacts.js_errors_log,
+                    js_errors: timeout_js_errors,
+                    network_log_path: artifacts.network_log,
+                    network_summary,
+                    third_parties_path: artifacts.third_parties,
+                    third_parties: None,
+                    trackers: timeout_trackers,
+                    mixed_content: timeout_mixed_content,
+                    headers_path: artifacts.headers,
+                    headers: header_summary,
+                    tls,
+                    remote: None,
                     elapsed_ms,
+                    scrolls: None,
+                    images_index: None,
+                    assets_index: None,
+                    api_index: None,
+                    graphql_path: None,
+                    graphql_operations: None,
+                    breakpoints_index: None,
+                    filmstrip_index: None,
+                    screencast_path: None,
+                    contacts_path: None,
+                    media: None,
+                    resource_limits,
+                    diagnostics: Some(PageDiagnostics {
+                        main_thread: diagnostics.main_thread,
+                        page_weight: diagnostics.page_weight,
+                        web_vitals: diagnostics.web_vitals,
+                        navigation: diagnostics.navigation,
+                        chrome_process: diagnostics.chrome_process,
+                    }),
+                    anchor: None,
+                    annotations: Vec::new(),
+                    main_content: None,
+                    main_content_path: None,
+                    metadata: None,
                     pages_crawled: 1,
                     wait_branch,
+                    wait_selector_matched: None,
+                    wait_js_value: None,
                     run_dir: run_paths.run_dir.display().to_string(),
+                    pages: Vec::new(),
+                    article_path: None,
+                    alternates: Vec::new(),
+                    alternates_manifest: None,
+                    throttled,
+                    warnings,
+                    content_hash: None,
+                    sha256: ArtifactHashes::default(),
+                    duplicate_of: None,
+                    golden: None,
+                    tags: args.tag_map(),
                 };
-                write_json(&run_paths.result_json, &out)?;
-                Ok(())
+                write_output(args, &run_paths, out).await
             }
             OnTimeout::Fail => Err(anyhow!(report.reason)),
         },
@@ -331,9 +1756,17 @@ struct HttpRes {
     elapsed_ms: u64,
     waf_detected: bool,
     anti_bot_vendor: Option<String>,
+    headers: Vec<(String, String)>,
+    remote_addr: Option<std::net::SocketAddr>,
+    tls: Option<tls::TlsInfo>,
 }
 
-async fn fetch_http(url: &str, html_path: &Path) -> Result<HttpRes> {
+async fn fetch_http(
+    args: &Cli,
+    html_path: &Path,
+    throttled: &mut Vec<throttle::ThrottleEvent>,
+) -> Result<HttpRes> {
+    let url = args.url();
     let client = reqwest::Client::builder()
         .user_agent(ua_generator::ua::spoof_ua())
         .redirect(reqwest::redirect::Policy::limited(8))
@@ -341,13 +1774,58 @@ async fn fetch_http(url: &str, html_path: &Path) -> Result<HttpRes> {
         .brotli(true)
         .deflate(true)
         .timeout(Duration::from_secs(10))
+        .tls_info(true)
         .build()?;
 
+    let budget = egress::Budget::http(args);
     let start = std::time::Instant::now();
-    let resp = client.get(url).send().await?;
+    let mut attempt = 0;
+    let resp = loop {
+        if let Some(budget) = &budget {
+            budget.wait().await?;
+        }
+        let resp = client.get(url).send().await?;
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok());
+        let status = resp.status().as_u16();
+        let Some(delay) = throttle::retry_delay(status, retry_after, args.max_retry_after) else {
+            break resp;
+        };
+        let retry = attempt < args.rate_limit_retries;
+        throttled.push(throttle::ThrottleEvent {
+            path: "http",
+            url: resp.url().to_string(),
+            status,
+            retry_after: retry_after.unwrap_or_default().to_string(),
+            waited_ms: if retry { delay.as_millis() as u64 } else { 0 },
+        });
+        if !retry {
+            break resp;
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+    };
     let status = resp.status().as_u16();
     let final_url = resp.url().to_string();
     let redirected = final_url != url;
+    let remote_addr = resp.remote_addr();
+    let tls = resp
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|t| t.peer_certificate())
+        .and_then(tls::from_certificate);
+    let headers: Vec<(String, String)> = resp
+        .headers()
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
 
     let is_html = resp
         .headers()
@@ -367,6 +1845,13 @@ async fn fetch_http(url: &str, html_path: &Path) -> Result<HttpRes> {
     let looks_empty =
         html.trim().is_empty() || html.len() < 512 || !html.to_lowercase().contains("<body");
     let elapsed_ms = start.elapsed().as_millis() as u64;
+    let vendor = block::vendor(&block::Page {
+        status: Some(status),
+        headers: &headers,
+        html: &html,
+        text: "",
+    })
+    .map(|(v, _)| v.to_string());
 
     Ok(HttpRes {
         final_url,
@@ -375,8 +1860,11 @@ async fn fetch_http(url: &str, html_path: &Path) -> Result<HttpRes> {
         links_found,
         looks_empty,
         elapsed_ms,
-        waf_detected: false,
-        anti_bot_vendor: None,
+        waf_detected: vendor.is_some(),
+        anti_bot_vendor: vendor,
+        headers,
+        remote_addr,
+        tls,
     })
 }
 
@@ -385,13 +1873,77 @@ struct ChromeRes {
     status: Option<u16>,
     redirected: bool,
     html_path: String,
+    dom_snapshot_path: Option<String>,
+    axtree_path: Option<String>,
+    trace_path: Option<String>,
+    mhtml_path: Option<String>,
+    markdown_path: Option<String>,
+    text_path: Option<String>,
+    epub_path: Option<String>,
     elapsed_ms: u64,
     screenshot_path: Option<String>,
+    annotated_screenshot_path: Option<String>,
+    thumbnail_path: Option<String>,
     pdf_path: Option<String>,
     waf_detected: bool,
     anti_bot_vendor: Option<String>,
     js_challenge: bool,
     wait_branch: String,
+    wait_selector_matched: Option<String>,
+    wait_js_value: Option<serde_json::Value>,
+    pages: Vec<PageCapture>,
+    article_path: Option<String>,
+    waterfall_path: Option<String>,
+    waterfall_json_path: Option<String>,
+    har_path: Option<String>,
+    console_log_path: Option<String>,
+    console: Option<console::ConsoleCounts>,
+    js_errors_log_path: Option<String>,
+    js_errors: Vec<jserrors::JsError>,
+    network_log_path: Option<String>,
+    network_summary: Option<network::NetworkSummary>,
+    third_parties_path: Option<String>,
+    third_parties: Option<thirdparty::Summary>,
+    trackers: Vec<trackers::Tracker>,
+    mixed_content: Vec<mixed::MixedContent>,
+    headers_path: Option<String>,
+    headers: Option<headers::HeaderSummary>,
+    tls: Option<tls::TlsInfo>,
+    remote: Option<dns::Remote>,
+    scrolls: Option<u32>,
+    images_index: Option<String>,
+    assets_index: Option<String>,
+    api_index: Option<String>,
+    graphql_path: Option<String>,
+    graphql_operations: Option<usize>,
+    breakpoints_index: Option<String>,
+    filmstrip_index: Option<String>,
+    screencast_path: Option<String>,
+    contacts_path: Option<String>,
+    media: Option<media::Media>,
+    resource_limits: Option<cgroup::LimitReport>,
+    diagnostics: Option<PageDiagnostics>,
+    anchor: Option<anchor::Anchor>,
+    annotations: Vec<annotate::Region>,
+    main_content: Option<maincontent::MainContent>,
+    main_content_path: Option<String>,
+    metadata: Option<metadata::Metadata>,
+    throttled: Vec<throttle::ThrottleEvent>,
+    warnings: Vec<String>,
+}
+
+/// Artifacts of one additional page captured while following pagination.
+#[derive(Serialize)]
+struct PageCapture {
+    url: String,
+    wait_branch: String,
+    html_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screenshot_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pdf_path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -465,15 +2017,96 @@ fn build_instrument_js(ignore: &str) -> String {
     format!(
         r#"(() => {{
   if (window.__ankabot) return;
-  window.__ankabot = {{ pending: 0 }};
+  window.__ankabot = {{ pending: 0, lastMutation: performance.now(),
+    netChanged: performance.now(), netListeners: new Set() }};
+  // Structure and text only: attribute churn (carousels, animations) never settles.
+  new MutationObserver(() => {{ window.__ankabot.lastMutation = performance.now(); }})
+    .observe(document, {{ subtree: true, childList: true, characterData: true }});
+  // Tracked requests starting or ending and resources finishing: the in-page waits
+  // (src/ready.rs) listen for these to time network idleness.
+  const netActivity = () => {{
+    window.__ankabot.netChanged = performance.now();
+    for (const f of window.__ankabot.netListeners) f();
+  }};
+  try {{
+    new PerformanceObserver(netActivity).observe({{ type: 'resource' }});
+  }} catch (e) {{}}
+  window.__ankabot.longTasks = [];
+  window.__ankabot.longScripts = [];
+  try {{
+    new PerformanceObserver((list) => {{
+      for (const e of list.getEntries()) {{
+        if (window.__ankabot.longTasks.length >= 1000) return;
+        const a = (e.attribution || [])[0];
+        window.__ankabot.longTasks.push({{ start: e.startTime, duration: e.duration,
+          frame: a ? (a.containerSrc || a.containerName || a.containerId || '') : '' }});
+      }}
+    }}).observe({{ type: 'longtask', buffered: true }});
+  }} catch (e) {{}}
+  try {{
+    new PerformanceObserver((list) => {{
+      for (const f of list.getEntries()) {{
+        for (const sc of f.scripts || []) {{
+          if (window.__ankabot.longScripts.length >= 1000) return;
+          window.__ankabot.longScripts.push({{ start: sc.startTime, duration: sc.duration,
+            source: sc.sourceURL || '', invoker: sc.invoker || '' }});
+        }}
+      }}
+    }}).observe({{ type: 'long-animation-frame', buffered: true }});
+  }} catch (e) {{}}
+  // Web vitals, following the web-vitals library's definitions.
+  const vitals = window.__ankabot.vitals = {{ lcp: null, lcpElement: '', fcp: null, cls: 0,
+    interactions: {{}} }};
+  try {{
+    new PerformanceObserver((list) => {{
+      for (const e of list.getEntries()) {{
+        vitals.lcp = e.startTime;
+        vitals.lcpElement = e.element ? e.element.tagName.toLowerCase() +
+          (e.element.id ? '#' + e.element.id : '') : (e.url || '');
+      }}
+    }}).observe({{ type: 'largest-contentful-paint', buffered: true }});
+  }} catch (e) {{}}
+  try {{
+    new PerformanceObserver((list) => {{
+      for (const e of list.getEntries()) {{
+        if (e.name === 'first-contentful-paint') vitals.fcp = e.startTime;
+      }}
+    }}).observe({{ type: 'paint', buffered: true }});
+  }} catch (e) {{}}
+  try {{
+    // CLS: the worst session window (shifts under 1 s apart, at most 5 s long).
+    let session = 0, first = 0, last = 0;
+    new PerformanceObserver((list) => {{
+      for (const e of list.getEntries()) {{
+        if (e.hadRecentInput) continue;
+        if (session && e.startTime - last < 1000 && e.startTime - first < 5000) {{
+          session += e.value;
+        }} else {{
+          session = e.value;
+          first = e.startTime;
+        }}
+        last = e.startTime;
+        vitals.cls = Math.max(vitals.cls, session);
+      }}
+    }}).observe({{ type: 'layout-shift', buffered: true }});
+  }} catch (e) {{}}
+  try {{
+    new PerformanceObserver((list) => {{
+      for (const e of list.getEntries()) {{
+        if (!e.interactionId) continue;
+        const prev = vitals.interactions[e.interactionId] || 0;
+        vitals.interactions[e.interactionId] = Math.max(prev, e.duration);
+      }}
+    }}).observe({{ type: 'event', buffered: true, durationThreshold: 16 }});
+  }} catch (e) {{}}
   const IGNORE = new RegExp({:?});
   const ofetch = window.fetch;
   if (ofetch) {{
     window.fetch = function(res, init) {{
       const url = (typeof res === 'string') ? res : (res && res.url) || '';
-      if (!IGNORE.test(url)) window.__ankabot.pending++;
+      if (!IGNORE.test(url)) {{ window.__ankabot.pending++; netActivity(); }}
       return ofetch.apply(this, arguments)
-        .finally(()=>{{ if (!IGNORE.test(url)) window.__ankabot.pending--; }});
+        .finally(()=>{{ if (!IGNORE.test(url)) {{ window.__ankabot.pending--; netActivity(); }} }});
     }}
   }}
   const oopen = XMLHttpRequest.prototype.open;
@@ -483,9 +2116,9 @@ fn build_instrument_js(ignore: &str) -> String {
   }};
   const osend = XMLHttpRequest.prototype.send;
   XMLHttpRequest.prototype.send = function(){{
-    if (!IGNORE.test(this.__ankabotURL||'')) window.__ankabot.pending++;
+    if (!IGNORE.test(this.__ankabotURL||'')) {{ window.__ankabot.pending++; netActivity(); }}
     this.addEventListener('loadend', ()=>{{
-      if (!IGNORE.test(this.__ankabotURL||'')) window.__ankabot.pending--;
+      if (!IGNORE.test(this.__ankabotURL||'')) {{ window.__ankabot.pending--; netActivity(); }}
     }}, {{ once:true }});
     return osend.apply(this, arguments);
   }};
@@ -494,162 +2127,875 @@ fn build_instrument_js(ignore: &str) -> String {
         ignore
     )
 }
+/// `--dom-quiet-ms` when `--wait-ready dom-quiet` is given without it.
+const DEFAULT_DOM_QUIET_MS: u64 = 500;
+/// How long the timeout report's debug capture may wait for images and fonts; the page's
+/// own deadline has already passed by then.
+const TIMEOUT_CAPTURE_GRACE: Duration = Duration::from_secs(2);
+
 fn wait_until_ready(
     tab: &headless_chrome::Tab,
     wait_ready: &str,
     network_idle_ms: u64,
     idle_threshold: u64,
     heuristic_min_chars: u64,
+    dom_quiet_ms: Option<u64>,
     deadline: Instant,
 ) -> Result<String> {
-    let idle_dur = Duration::from_millis(network_idle_ms);
-    let mut last_cnt: i64 = -1;
-    let mut idle_since: Option<Instant> = None;
-    let mut heur_since: Option<Instant> = None;
-
     if wait_ready.eq_ignore_ascii_case("none") {
         return Ok("ready_state".to_string());
     }
-
-    loop {
-        if Instant::now() >= deadline {
-            return Err(anyhow!("wait_until_ready timeout"));
+    // Hydrating SPAs often settle structurally while analytics and polling keep the
+    // network busy: in this mode a parsed, quiet DOM is ready on its own.
+    let dom_mode = wait_ready.eq_ignore_ascii_case("dom-quiet");
+    let dom_quiet_ms = match dom_quiet_ms {
+        None if dom_mode => Some(DEFAULT_DOM_QUIET_MS),
+        ms => ms,
+    };
+    // Some SPAs go network-idle (or even readyState complete) while still building the
+    // DOM from cached data; with --dom-quiet-ms those branches also wait for the DOM.
+    let spec = serde_json::json!({
+        "kind": "ready",
+        "readyState": if dom_mode || wait_ready == "interactive" { "interactive" } else { "complete" },
+        "idleMs": network_idle_ms,
+        "threshold": idle_threshold,
+        "minChars": heuristic_min_chars,
+        "domQuietMs": dom_quiet_ms,
+    });
+    match ready::until(tab, &spec, deadline)? {
+        ready::Outcome::Done(branch) => {
+            let branch = branch.as_str().unwrap_or("ready_state").to_string();
+            if branch == "heuristic" {
+                let settle_deadline =
+                    std::cmp::min(deadline, Instant::now() + Duration::from_millis(800));
+                let _ = wait_images_and_fonts(tab, settle_deadline);
+            }
+            Ok(branch)
         }
+        ready::Outcome::TimedOut(_) => Err(anyhow!("wait_until_ready timeout")),
+    }
+}
 
-        let ready_state = tab
-            .evaluate("document.readyState", false)?
-            .value
-            .and_then(|v| v.as_str().map(|s| s.to_string()))
-            .unwrap_or_default();
-        let ready_ok = match wait_ready {
-            "interactive" => ready_state == "interactive" || ready_state == "complete",
-            _ => ready_state == "complete",
-        };
-        if ready_ok {
-            return Ok("ready_state".to_string());
-        }
+/// Wait until no tracked requests are pending for `idle_ms`.
+fn wait_network_idle(
+    tab: &headless_chrome::Tab,
+    idle_ms: u64,
+    idle_threshold: u64,
+    deadline: Instant,
+) -> Result<()> {
+    let spec =
+        serde_json::json!({ "kind": "idle", "idleMs": idle_ms, "threshold": idle_threshold });
+    match ready::until(tab, &spec, deadline)? {
+        ready::Outcome::Done(_) => Ok(()),
+        ready::Outcome::TimedOut(_) => Err(anyhow!("network idle timeout")),
+    }
+}
 
-        let pending = tab
-            .evaluate("window.__ankabot ? window.__ankabot.pending : 0", false)?
-            .value
-            .and_then(|v| v.as_i64())
-            .unwrap_or(0);
-        let cnt = tab
-            .evaluate("performance.getEntriesByType('resource').length", false)?
+/// Scroll to the bottom repeatedly until the document stops growing or `max_scrolls` is hit,
+/// then return to the top. Returns the number of scroll steps that grew the page.
+fn expand_infinite_scroll(tab: &headless_chrome::Tab, args: &Cli) -> Result<u32> {
+    let height = |tab: &headless_chrome::Tab| -> Result<i64> {
+        Ok(tab
+            .evaluate("document.documentElement.scrollHeight", false)?
             .value
             .and_then(|v| v.as_i64())
-            .unwrap_or(0);
-        let stable = cnt == last_cnt;
-        if pending <= idle_threshold as i64 && stable {
-            if idle_since.is_none() {
-                idle_since = Some(Instant::now());
-            }
-            if Instant::now().duration_since(idle_since.unwrap()) >= idle_dur {
-                return Ok("network_idle".to_string());
-            }
-        } else {
-            idle_since = None;
+            .unwrap_or(0))
+    };
+    let step_budget = Duration::from_millis(args.network_idle_ms.max(1000) * 4);
+    let mut grown = 0;
+    let mut last = height(tab)?;
+    for _ in 0..args.max_scrolls {
+        tab.evaluate(
+            "window.scrollTo(0, document.documentElement.scrollHeight)",
+            false,
+        )?;
+        // Give lazy loaders a moment to fire before checking for idleness.
+        std::thread::sleep(Duration::from_millis(300));
+        let _ = wait_network_idle(
+            tab,
+            args.network_idle_ms,
+            args.idle_threshold,
+            Instant::now() + step_budget,
+        );
+        let now = height(tab)?;
+        if now <= last {
+            break;
         }
-        last_cnt = cnt;
+        grown += 1;
+        last = now;
+    }
+    tab.evaluate("window.scrollTo(0, 0)", false)?;
+    Ok(grown)
+}
 
-        let val = tab
-            .evaluate(
-                "(() => { const t = document.body ? document.body.innerText.length : 0; const h = !!document.querySelector('main,article,#app,#root'); return {t:t, h:h}; })()",
-                false,
-            )?
-            .value
-            .unwrap_or_else(|| serde_json::json!({}));
-        let text_len = val.get("t").and_then(|v| v.as_u64()).unwrap_or(0);
-        let has_main = val.get("h").and_then(|v| v.as_bool()).unwrap_or(false);
-        if text_len >= heuristic_min_chars && has_main {
-            if heur_since.is_none() {
-                heur_since = Some(Instant::now());
-            }
-            if Instant::now().duration_since(heur_since.unwrap()) >= Duration::from_millis(600) {
-                let settle_deadline =
-                    std::cmp::min(deadline, Instant::now() + Duration::from_millis(800));
-                let _ = wait_images_and_fonts(tab, settle_deadline);
-                return Ok("heuristic".to_string());
+/// Wait until any or all of `--wait-selector` match, and with `--wait-visible` /
+/// `--wait-stable-ms` are visible and have held still. In `any` mode, returns the selector
+/// that made it first. A selector that doesn't parse fails at once.
+fn wait_for_selectors(
+    tab: &headless_chrome::Tab,
+    args: &Cli,
+    deadline: Instant,
+) -> Result<Option<String>> {
+    let sels = &args.wait_selector;
+    let spec = serde_json::json!({
+        "kind": "selectors",
+        "sels": sels,
+        "mode": if args.wait_mode == WaitMode::Any { "any" } else { "all" },
+        "visible": args.wait_visible,
+        "stableMs": args.wait_stable_ms,
+    });
+    let states = match ready::until(tab, &spec, deadline)? {
+        ready::Outcome::Done(v) => {
+            if let Some(sel) = v.get("invalid").and_then(|s| s.as_str()) {
+                bail!("--wait-selector '{}' is not a valid CSS selector", sel);
             }
-        } else {
-            heur_since = None;
+            return Ok(v.as_str().map(str::to_string));
         }
+        ready::Outcome::TimedOut(states) => states,
+    };
+    let pending: Vec<String> = sels
+        .iter()
+        .enumerate()
+        .filter_map(|(i, sel)| match states.get(i).and_then(|s| s.as_str()) {
+            Some("ready") => None,
+            Some("hidden") => Some(format!("'{sel}' (hidden)")),
+            Some("moving") => Some(format!("'{sel}' (still moving)")),
+            _ => Some(format!("'{sel}'")),
+        })
+        .collect();
+    match (args.wait_mode, pending.len()) {
+        (WaitMode::Any, n) if n > 1 => Err(anyhow!(
+            "none of selectors {} ready before timeout",
+            pending.join(", ")
+        )),
+        _ => Err(anyhow!(
+            "selector {} not ready before timeout",
+            pending.join(", ")
+        )),
+    }
+}
 
-        std::thread::sleep(Duration::from_millis(200));
+/// Like [`wait_for_selectors`], for conditions CSS can't express (text, ancestors, …).
+/// An invalid expression fails at once instead of waiting out the deadline.
+fn wait_for_xpath(tab: &headless_chrome::Tab, xpath: &str, deadline: Instant) -> Result<()> {
+    let spec = serde_json::json!({ "kind": "xpath", "xpath": xpath });
+    match ready::until(tab, &spec, deadline)? {
+        ready::Outcome::Done(v) => match v.get("invalid").and_then(|e| e.as_str()) {
+            Some(err) => bail!("--wait-xpath '{}': {}", xpath, err),
+            None => Ok(()),
+        },
+        ready::Outcome::TimedOut(_) => Err(anyhow!("xpath '{}' not found before timeout", xpath)),
     }
 }
 
-fn wait_for_selector(tab: &headless_chrome::Tab, sel: &str, deadline: Instant) -> Result<()> {
-    while Instant::now() < deadline {
-        if tab.find_element(sel).is_ok() {
-            return Ok(());
+/// Wait until a request whose URL matches `re` has finished loading with a 2xx (or 304)
+/// response. A matching request that failed doesn't end the wait: apps retry, and
+/// polling endpoints are requested again. Checked as network events arrive.
+fn wait_for_request(
+    net: &network::NetworkCapture,
+    from: usize,
+    re: &regex::Regex,
+    deadline: Instant,
+) -> Result<()> {
+    let mut last = None;
+    let done = net.wait_until(from, deadline, |entries| {
+        for e in entries {
+            if !re.is_match(&e.request.url) {
+                continue;
+            }
+            let status = e.response.as_ref().map(|r| r.status);
+            let ok = matches!(status, Some(200..=299 | 304));
+            if ok && e.end.is_some() && e.failed.is_none() {
+                return true;
+            }
+            if let Some(err) = &e.failed {
+                last = Some(err.clone());
+            } else if let Some(status) = status.filter(|_| !ok) {
+                last = Some(format!("HTTP {status}"));
+            }
         }
-        std::thread::sleep(Duration::from_millis(150));
+        false
+    });
+    if done {
+        return Ok(());
     }
-    Err(anyhow!("selector '{}' not found before timeout", sel))
+    let last = last.map(|l| format!(" (last: {l})")).unwrap_or_default();
+    Err(anyhow!(
+        "--wait-request '{}' not completed before timeout{}",
+        re,
+        last
+    ))
 }
 
-fn wait_images_and_fonts(tab: &headless_chrome::Tab, deadline: Instant) -> Result<()> {
-    loop {
-        if Instant::now() >= deadline {
-            return Err(anyhow!("images/fonts timeout"));
-        }
-        let imgs_unloaded = tab
-            .evaluate(
-                "Array.from(document.images).filter(i=>!i.complete).length",
-                false,
-            )?
-            .value
-            .and_then(|v| v.as_i64())
-            .unwrap_or(0);
-        let fonts_ready = tab
-            .evaluate(
-                "document.fonts ? document.fonts.status === 'loaded' : true",
-                false,
-            )?
-            .value
-            .and_then(|v| v.as_bool())
-            .unwrap_or(true);
-        if imgs_unloaded == 0 && fonts_ready {
-            return Ok(());
-        }
-        std::thread::sleep(Duration::from_millis(200));
+fn wait_for_console(
+    watch: &console::ConsoleWatch,
+    re: &regex::Regex,
+    deadline: Instant,
+) -> Result<()> {
+    if watch.wait(deadline).is_some() {
+        return Ok(());
     }
+    Err(anyhow!("--wait-console '{}' not logged before timeout", re))
 }
 
-fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderOutcome> {
-    use headless_chrome::{
-        protocol::cdp::Emulation::{
-            SetDeviceMetricsOverride, SetFocusEmulationEnabled, SetGeolocationOverride,
-            SetLocaleOverride, SetTimezoneOverride,
-        },
-        protocol::cdp::Page::{
-            AddScriptToEvaluateOnNewDocument, BringToFront, CaptureScreenshotFormatOption,
-            SetLifecycleEventsEnabled,
-        },
-        types::PrintToPdfOptions,
-        Browser, LaunchOptionsBuilder,
+/// Wait until the `--wait-js` expression is truthy and return its value. Promises are
+/// awaited. An expression that throws (usually app state not defined yet) counts as
+/// falsy. On timeout the last value or exception is part of the error.
+fn wait_for_js(
+    tab: &headless_chrome::Tab,
+    expr: &str,
+    deadline: Instant,
+) -> Result<serde_json::Value> {
+    let last = match ready::until_js(tab, expr, deadline)? {
+        ready::Outcome::Done(v) => return Ok(v["value"].clone()),
+        ready::Outcome::TimedOut(last) => last,
     };
-    use std::ffi::{OsStr, OsString};
+    let last = match last.get("error").and_then(|e| e.as_str()) {
+        Some(err) => format!("threw {err}"),
+        None => format!("last value {}", last["value"]),
+    };
+    Err(anyhow!("--wait-js not truthy before timeout ({})", last))
+}
 
-    let user_dir = profile_dir(&args.profile, args.user_data_dir.clone());
-    std::fs::create_dir_all(&user_dir)?;
+fn wait_images_and_fonts(tab: &headless_chrome::Tab, deadline: Instant) -> Result<()> {
+    match ready::until(tab, &serde_json::json!({ "kind": "assets" }), deadline)? {
+        ready::Outcome::Done(_) => Ok(()),
+        ready::Outcome::TimedOut(_) => Err(anyhow!("images/fonts timeout")),
+    }
+}
 
-    let (win_w, win_h) = args.window_size();
+/// How a page became ready.
+struct Readiness {
+    wait_branch: String,
+    wait_selector_matched: Option<String>,
+    wait_js_value: Option<serde_json::Value>,
+}
 
-    let mut arg_vec: Vec<OsString> = vec![
-        OsString::from("--disable-gpu"),
-        OsString::from("--disable-dev-shm-usage"),
-        OsString::from("--no-first-run"),
-        OsString::from("--no-default-browser-check"),
-        OsString::from("--hide-scrollbars"),
-        OsString::from("--disable-blink-features=AutomationControlled"),
-        OsString::from("--disable-background-timer-throttling"),
+/// What the optional waits observe besides the page itself: the requests `net` recorded
+/// from entry `from` on, and the console.
+struct Watchers<'a> {
+    net: &'a network::NetworkCapture,
+    from: usize,
+    console: Option<&'a console::ConsoleWatch>,
+}
+
+/// Wait for readiness and the optional selector, XPath, request, console message and JS
+/// predicate on the current document.
+fn wait_for_page(
+    tab: &headless_chrome::Tab,
+    watchers: &Watchers,
+    args: &Cli,
+    deadline: Instant,
+) -> Result<Readiness> {
+    let wait_branch = wait_until_ready(
+        tab,
+        &args.wait_ready,
+        args.network_idle_ms,
+        args.idle_threshold,
+        args.heuristic_min_chars,
+        args.dom_quiet_ms,
+        deadline,
+    )?;
+    let wait_selector_matched = if args.wait_selector.is_empty() {
+        None
+    } else {
+        wait_for_selectors(tab, args, deadline)?.filter(|_| args.wait_selector.len() > 1)
+    };
+    if let Some(xpath) = &args.wait_xpath {
+        wait_for_xpath(tab, xpath, deadline)?;
+    }
+    if let Some(re) = &args.wait_request {
+        wait_for_request(watchers.net, watchers.from, re, deadline)?;
+    }
+    if let (Some(re), Some(watch)) = (&args.wait_console, watchers.console) {
+        wait_for_console(watch, re, deadline)?;
+    }
+    let wait_js_value = match &args.wait_js {
+        Some(expr) => Some(wait_for_js(tab, expr, deadline)?),
+        None => None,
+    };
+    Ok(Readiness {
+        wait_branch,
+        wait_selector_matched,
+        wait_js_value,
+    })
+}
+
+/// Navigate to `url`, reloading while the document comes back 429/503 with Retry-After.
+/// Returns the total time spent waiting so callers can extend their deadlines.
+fn navigate_with_backoff(
+    tab: &headless_chrome::Tab,
+    net: &network::NetworkCapture,
+    url: &str,
+    args: &Cli,
+    throttled: &mut Vec<throttle::ThrottleEvent>,
+) -> Result<Duration> {
+    let budget = egress::Budget::chrome(args);
+    let mut waited = Duration::ZERO;
+    for attempt in 0.. {
+        if let Some(budget) = &budget {
+            waited += budget.wait_blocking()?;
+        }
+        let from = net.recorded();
+        tab.navigate_to(url)?;
+        tab.wait_until_navigated()?;
+        let entries = net.entries();
+        let Some(doc) = network::document_response(&entries, from) else {
+            break;
+        };
+        let retry_after = network::response_header(doc, "retry-after");
+        let status = doc.status as u16;
+        let Some(delay) = throttle::retry_delay(status, retry_after, args.max_retry_after) else {
+            break;
+        };
+        let retry = attempt < args.rate_limit_retries;
+        throttled.push(throttle::ThrottleEvent {
+            path: "chrome",
+            url: doc.url.clone(),
+            status,
+            retry_after: retry_after.unwrap_or_default().to_string(),
+            waited_ms: if retry { delay.as_millis() as u64 } else { 0 },
+        });
+        if !retry {
+            break;
+        }
+        std::thread::sleep(delay);
+        waited += delay;
+    }
+    Ok(waited)
+}
+
+/// One artifact produced by [`capture_artifacts`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Artifact {
+    Html,
+    Screenshot(ScreenshotFormat),
+    Pdf,
+    Mhtml,
+}
+
+impl Artifact {
+    /// The artifact's file name in a run directory.
+    fn file_name(self) -> &'static str {
+        match self {
+            Artifact::Html => "dom.html",
+            Artifact::Screenshot(ScreenshotFormat::Png) => "snap.png",
+            Artifact::Screenshot(ScreenshotFormat::Jpeg) => "snap.jpg",
+            Artifact::Screenshot(ScreenshotFormat::Webp) => "snap.webp",
+            Artifact::Pdf => "page.pdf",
+            Artifact::Mhtml => "page.mhtml",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Artifact::Html => "text/html; charset=utf-8",
+            Artifact::Screenshot(ScreenshotFormat::Png) => "image/png",
+            Artifact::Screenshot(ScreenshotFormat::Jpeg) => "image/jpeg",
+            Artifact::Screenshot(ScreenshotFormat::Webp) => "image/webp",
+            Artifact::Pdf => "application/pdf",
+            Artifact::Mhtml => "multipart/related",
+        }
+    }
+}
+
+/// The artifacts `--artifact-priority` orders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ArtifactKind {
+    Html,
+    Screenshot,
+    Pdf,
+}
+
+impl ArtifactKind {
+    /// The order to capture in: `--artifact-priority`, then any kinds it leaves out.
+    fn order(args: &Cli) -> Vec<ArtifactKind> {
+        let mut order: Vec<ArtifactKind> = Vec::new();
+        for &k in args.artifact_priority.iter().chain(&[
+            ArtifactKind::Html,
+            ArtifactKind::Screenshot,
+            ArtifactKind::Pdf,
+        ]) {
+            if !order.contains(&k) {
+                order.push(k);
+            }
+        }
+        if args.mode == Mode::Extract {
+            order.retain(|&k| k == ArtifactKind::Html);
+        }
+        order
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ArtifactKind::Html => "html",
+            ArtifactKind::Screenshot => "screenshot",
+            ArtifactKind::Pdf => "pdf",
+        }
+    }
+}
+
+/// Capture the DOM, a screenshot, a PDF and optionally an MHTML archive of the current
+/// document in `--artifact-priority` order, handing each to `sink` as soon as Chrome
+/// returns it.
+///
+/// Once `deadline` has passed, the remaining artifacts are skipped rather than failing the
+/// capture; the first one is always taken. The HTML is cheap and read by everything after
+/// the capture, so it is never skipped. Returns a warning for each artifact skipped or
+/// captured short of its wait.
+///
+/// The MHTML snapshot is taken while Chrome is printing the PDF; a failed snapshot is
+/// skipped rather than failing the capture.
+fn capture_artifacts(
+    tab: &headless_chrome::Tab,
+    args: &Cli,
+    mhtml: bool,
+    deadline: Instant,
+    mut sink: impl FnMut(Artifact, Vec<u8>) -> Result<()>,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    for (i, kind) in ArtifactKind::order(args).into_iter().enumerate() {
+        if i > 0 && kind != ArtifactKind::Html && Instant::now() >= deadline {
+            warnings.push(format!(
+                "{} skipped: the deadline passed before it could be captured",
+                kind.name()
+            ));
+            if kind == ArtifactKind::Pdf && mhtml {
+                warnings.push("mhtml skipped: it is taken together with the pdf".to_string());
+            }
+            continue;
+        }
+        match kind {
+            ArtifactKind::Html => {
+                let html = tab.get_content()?;
+                sink(Artifact::Html, html.into_bytes())?;
+            }
+            ArtifactKind::Screenshot => {
+                capture_screenshot(tab, args, deadline, &mut sink, &mut warnings)?
+            }
+            ArtifactKind::Pdf => capture_pdf(tab, args, mhtml, deadline, &mut sink, &mut warnings)?,
+        }
+    }
+    Ok(warnings)
+}
+
+/// [`wait_images_and_fonts`] before capturing `what`; running out of time only adds a
+/// warning, and what has loaded is captured.
+fn settle(
+    tab: &headless_chrome::Tab,
+    deadline: Instant,
+    what: &str,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    match wait_images_and_fonts(tab, deadline) {
+        Err(_) if Instant::now() >= deadline => {
+            warnings.push(format!(
+                "{what} captured before all images and fonts had loaded: the deadline passed"
+            ));
+            Ok(())
+        }
+        r => r,
+    }
+}
+
+fn capture_screenshot(
+    tab: &headless_chrome::Tab,
+    args: &Cli,
+    deadline: Instant,
+    sink: &mut impl FnMut(Artifact, Vec<u8>) -> Result<()>,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    if args.full_page {
+        fullpage::scroll_through(tab)?;
+        settle(tab, deadline, "screenshot", warnings)?;
+    }
+
+    // The screenshot stamp is a temporary overlay; the PDF is stamped in post-processing.
+    let stamp = args
+        .stamp
+        .as_deref()
+        .filter(|_| args.stamp_screenshot)
+        .map(|t| pdf::stamp_text(t, &tab.get_url()));
+    if let Some(text) = &stamp {
+        tab.evaluate(
+            &format!(
+                "{}({}, {})",
+                STAMP_OVERLAY_JS,
+                serde_json::to_string(text)?,
+                args.stamp_style == pdf::StampStyle::Diagonal
+            ),
+            false,
+        )?;
+    }
+    let shot = screenshot(tab, args);
+    if stamp.is_some() {
+        tab.evaluate(
+            "document.getElementById('__ankabot_stamp')?.remove()",
+            false,
+        )?;
+    }
+    sink(Artifact::Screenshot(args.screenshot_format), shot?)
+}
+
+fn capture_pdf(
+    tab: &headless_chrome::Tab,
+    args: &Cli,
+    mhtml: bool,
+    deadline: Instant,
+    sink: &mut impl FnMut(Artifact, Vec<u8>) -> Result<()>,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    if args.pdf_clean {
+        pdfclean::apply(tab)?;
+    }
+    if args.pdf_media.is_some() {
+        pdf::emulate_media(tab, args.pdf_media)?;
+    }
+    settle(tab, deadline, "pdf", warnings)?;
+    let headings = (!args.no_pdf_bookmarks)
+        .then(|| outline::prepare(tab))
+        .transpose()?;
+    let printed = tab.print_to_pdf(Some(pdf::print_options(args)?));
+    if headings.is_some() {
+        outline::cleanup(tab)?;
+    }
+    if args.pdf_clean {
+        pdfclean::cleanup(tab)?;
+    }
+    if args.pdf_media.is_some() {
+        pdf::emulate_media(tab, None)?;
+    }
+    std::thread::scope(|s| {
+        // Taken once the print changes are undone, so the archive shows the page as
+        // rendered; it runs while the PDF is post-processed.
+        let snapshot = mhtml.then(|| s.spawn(|| mhtml_snapshot(tab)));
+        let bytes = outline::add(printed?, headings.as_deref().unwrap_or_default())?;
+        sink(Artifact::Pdf, bytes)?;
+        match snapshot.map(|h| h.join()) {
+            Some(Ok(Ok(data))) => sink(Artifact::Mhtml, data.into_bytes())?,
+            Some(Ok(Err(e))) => warnings.push(format!("mhtml skipped: {e:#}")),
+            Some(Err(_)) => warnings.push("mhtml skipped: the snapshot panicked".to_string()),
+            None => {}
+        }
+        Ok(())
+    })
+}
+
+/// Screenshot the page as configured: the whole document with `--full-page`, else the
+/// viewport (or `--clip`).
+fn screenshot(tab: &headless_chrome::Tab, args: &Cli) -> Result<Vec<u8>> {
+    if args.full_page {
+        fullpage::capture(tab, args.screenshot_format, args.screenshot_quality)
+    } else {
+        tab.capture_screenshot(
+            args.screenshot_format.cdp(),
+            args.screenshot_quality,
+            args.clip.map(Clip::viewport),
+            true,
+        )
+    }
+}
+
+/// What [`capture_page`] saved; artifacts can be skipped when the deadline runs out.
+#[derive(Default)]
+struct Saved {
+    screenshot: bool,
+    pdf: bool,
+    mhtml: bool,
+    warnings: Vec<String>,
+}
+
+/// Save the DOM, a screenshot, a PDF and optionally an MHTML archive of the current
+/// document.
+///
+/// File writes run on a writer thread so the next CDP call is already in flight while the
+/// previous artifact is still being written.
+fn capture_page(
+    tab: &headless_chrome::Tab,
+    args: &Cli,
+    html_path: &Path,
+    shot_path: &Path,
+    pdf_path: &Path,
+    mhtml_path: Option<&Path>,
+    deadline: Instant,
+) -> Result<Saved> {
+    std::thread::scope(|s| {
+        let (tx, rx) = std::sync::mpsc::channel::<(&Path, Vec<u8>)>();
+        let writer = s.spawn(move || -> Result<()> {
+            for (path, bytes) in rx {
+                std::fs::write(path, bytes)
+                    .with_context(|| format!("writing {}", path.display()))?;
+            }
+            Ok(())
+        });
+
+        let mut saved = Saved::default();
+        let captured = capture_artifacts(tab, args, mhtml_path.is_some(), deadline, |a, bytes| {
+            let path = match a {
+                Artifact::Html => html_path,
+                Artifact::Screenshot(_) => {
+                    saved.screenshot = true;
+                    shot_path
+                }
+                Artifact::Pdf => {
+                    saved.pdf = true;
+                    pdf_path
+                }
+                // capture_artifacts only archives when asked to, i.e. with a path.
+                Artifact::Mhtml => {
+                    let path = mhtml_path
+                        .ok_or_else(|| anyhow!("mhtml captured without an mhtml path"))?;
+                    saved.mhtml = true;
+                    path
+                }
+            };
+            // The writer only stops early when a write failed; that error wins below.
+            tx.send((path, bytes))
+                .map_err(|_| anyhow!("artifact writer stopped"))
+        });
+        drop(tx);
+        writer
+            .join()
+            .map_err(|_| anyhow!("artifact writer panicked"))??;
+        saved.warnings = captured?;
+        Ok(saved)
+    })
+}
+
+const STAMP_OVERLAY_JS: &str = r#"((text, diagonal) => {
+  const d = document.createElement('div');
+  d.id = '__ankabot_stamp';
+  d.textContent = text;
+  d.style.cssText = diagonal
+    ? 'position:fixed;top:50%;left:50%;transform:translate(-50%,-50%) rotate(-30deg);' +
+      'font:bold 48px sans-serif;color:rgba(0,0,0,.2);white-space:nowrap;'
+    : 'position:fixed;left:0;right:0;bottom:0;padding:4px 8px;font:12px sans-serif;' +
+      'color:#333;background:rgba(255,255,255,.8);';
+  d.style.zIndex = '2147483647';
+  d.style.pointerEvents = 'none';
+  document.documentElement.appendChild(d);
+  return true;
+})"#;
+
+/// Archive the current document with its subresources (images, CSS, frames) as MHTML.
+fn mhtml_snapshot(tab: &headless_chrome::Tab) -> Result<String> {
+    use headless_chrome::protocol::cdp::Page::{CaptureSnapshot, CaptureSnapshotFormatOption};
+
+    let snap = tab.call_method(CaptureSnapshot {
+        format: Some(CaptureSnapshotFormatOption::Mhtml),
+    })?;
+    Ok(snap.data)
+}
+
+/// Write the rendered page's visible text (`innerText`, so block elements keep their line
+/// breaks and hidden elements are left out).
+fn save_text(tab: &headless_chrome::Tab, path: &Path) -> Result<()> {
+    let text = tab
+        .evaluate("document.body ? document.body.innerText : ''", false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+const NEXT_PAGE_JS: &str = r#"((sel) => {
+  const pick = (el) => el && (el.href || el.getAttribute('href')) ? el.href : null;
+  if (sel) return pick(document.querySelector(sel));
+  let href = pick(document.querySelector('link[rel~="next"], a[rel~="next"]'));
+  if (href) return href;
+  const common = ['.pagination .next a', 'a.next', 'a.pagination-next', '.next-page a',
+    'a[aria-label="Next"]', 'a[aria-label="Next page"]', 'li.next > a'];
+  for (const s of common) { href = pick(document.querySelector(s)); if (href) return href; }
+  const re = /^\s*(next|next page|older posts?|»|›|→)\s*$/i;
+  for (const a of document.querySelectorAll('a[href]')) {
+    if (re.test(a.textContent || '')) return a.href;
+  }
+  return null;
+})"#;
+
+/// Resolve the next page URL via a user selector, `rel=next` or common pagination markup.
+fn find_next_page(tab: &headless_chrome::Tab, selector: &str) -> Result<Option<String>> {
+    let sel = if selector == "auto" { "" } else { selector };
+    let expr = format!("{}({:?})", NEXT_PAGE_JS, sel);
+    Ok(tab
+        .evaluate(&expr, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .filter(|s| s.starts_with("http")))
+}
+
+/// Follow "next" links from the current page, capturing numbered artifacts for each page.
+/// Stops at `--max-pages`, on a repeated URL, or on the first page that fails to render;
+/// a failure is explained in `warnings`.
+fn follow_pagination(
+    tab: &headless_chrome::Tab,
+    net: &network::NetworkCapture,
+    console: Option<&console::ConsoleWatch>,
+    args: &Cli,
+    paths: &RunPaths,
+    selector: &str,
+    warnings: &mut Vec<String>,
+) -> Vec<PageCapture> {
+    let mut pages = Vec::new();
+    let mut seen = std::collections::HashSet::from([tab.get_url()]);
+    while pages.len() + 1 < args.max_pages as usize {
+        let n = pages.len() + 2;
+        let next = match find_next_page(tab, selector) {
+            Ok(Some(u)) if seen.insert(u.clone()) => u,
+            Ok(_) => break,
+            Err(e) => {
+                warnings.push(format!("pagination page {n}: finding the next link: {e:#}"));
+                break;
+            }
+        };
+        if let Some(budget) = egress::Budget::chrome(args) {
+            if let Err(e) = budget.wait_blocking() {
+                warnings.push(format!("pagination page {n}: {e:#}"));
+                break;
+            }
+        }
+        let deadline = Instant::now() + Duration::from_millis(args.max_wait_ms);
+        let res = (|| -> Result<PageCapture> {
+            let watchers = Watchers {
+                net,
+                from: net.recorded(),
+                console,
+            };
+            if let Some(watch) = console {
+                watch.reset();
+            }
+            tab.navigate_to(&next)?;
+            tab.wait_until_navigated()?;
+            let wait_branch = wait_for_page(tab, &watchers, args, deadline)?.wait_branch;
+            if args.infinite_scroll {
+                expand_infinite_scroll(tab, args)?;
+            }
+            let html = numbered(&paths.dom_html, n);
+            let shot = numbered(&paths.screenshot, n);
+            let pdf = numbered(&paths.pdf, n);
+            let saved = capture_page(tab, args, &html, &shot, &pdf, None, deadline)?;
+            Ok(PageCapture {
+                url: tab.get_url(),
+                wait_branch,
+                html_path: html.display().to_string(),
+                screenshot_path: saved.screenshot.then(|| shot.display().to_string()),
+                pdf_path: saved.pdf.then(|| pdf.display().to_string()),
+                warnings: saved.warnings,
+            })
+        })();
+        match res {
+            Ok(p) => pages.push(p),
+            Err(e) => {
+                warnings.push(format!("pagination page {n}: {e:#}"));
+                break;
+            }
+        }
+    }
+    pages
+}
+
+/// `dir/page.pdf` -> `dir/page-N.pdf`
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, n, ext),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+/// Emulate a `width`x`height` viewport with the `--dpr` and `--mobile` settings.
+fn apply_device_metrics(
+    tab: &headless_chrome::Tab,
+    args: &Cli,
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    use headless_chrome::protocol::cdp::Emulation::SetDeviceMetricsOverride;
+
+    tab.call_method(SetDeviceMetricsOverride {
+        width,
+        height,
+        device_scale_factor: args.dpr,
+        mobile: args.mobile,
+        scale: None,
+        screen_width: None,
+        screen_height: None,
+        position_x: None,
+        position_y: None,
+        dont_set_visible_size: None,
+        screen_orientation: None,
+        viewport: None,
+        display_feature: None,
+        device_posture: None,
+    })?;
+    Ok(())
+}
+
+/// Launch Chrome with the emulation, instrumentation and cookies from `args` and open the
+/// tab to capture in. `user_dir` is the profile to use; `None` gives a throwaway profile
+/// that is deleted with the browser.
+///
+/// With resource limits, Chrome runs in its own cgroup, returned first so that it outlives
+/// (and cleans up after) the browser when the tuple is dropped.
+fn launch_chrome(
+    args: &Cli,
+    user_dir: Option<PathBuf>,
+) -> Result<(
+    Option<cgroup::ChromeCgroup>,
+    headless_chrome::Browser,
+    std::sync::Arc<headless_chrome::Tab>,
+)> {
+    use headless_chrome::{
+        protocol::cdp::Emulation::{
+            SetGeolocationOverride, SetLocaleOverride, SetTimezoneOverride,
+        },
+        protocol::cdp::Page::{AddScriptToEvaluateOnNewDocument, SetLifecycleEventsEnabled},
+        Browser, LaunchOptionsBuilder,
+    };
+    use std::ffi::{OsStr, OsString};
+
+    let shell = match args.engine {
+        Engine::Chrome => None,
+        Engine::HeadlessShell => {
+            if args.headful || args.extensions.is_some() {
+                bail!("--engine headless-shell can't run headful or load extensions");
+            }
+            Some(headless_shell_path()?)
+        }
+    };
+    if let Some(dir) = &user_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    if args.chrome_cpu_quota.is_some_and(|q| q <= 0.0) {
+        bail!("--chrome-cpu-quota must be positive");
+    }
+    let cgroup = if args.chrome_memory_limit_mb.is_some() || args.chrome_cpu_quota.is_some() {
+        Some(
+            cgroup::ChromeCgroup::create(args.chrome_memory_limit_mb, args.chrome_cpu_quota)
+                .context("setting up Chrome resource limits")?,
+        )
+    } else {
+        None
+    };
+
+    let (win_w, win_h) = args.window_size();
+
+    let mut arg_vec: Vec<OsString> = vec![
+        OsString::from("--disable-gpu"),
+        OsString::from("--disable-dev-shm-usage"),
+        OsString::from("--no-first-run"),
+        OsString::from("--no-default-browser-check"),
+        OsString::from("--hide-scrollbars"),
+        OsString::from("--disable-blink-features=AutomationControlled"),
+        OsString::from("--disable-background-timer-throttling"),
         OsString::from("--disable-renderer-backgrounding"),
         OsString::from("--disable-backgrounding-occluded-windows"),
         OsString::from(format!("--window-size={},{}", win_w, win_h)),
     ];
-    if !args.headful {
+    // The headless shell is always headless and doesn't know the new-headless flag.
+    if !args.headful && shell.is_none() {
         arg_vec.push(OsString::from("--headless=new"));
     }
     if let Some(p) = &args.proxy {
@@ -669,9 +3015,17 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
         )));
     }
 
+    // With the fetcher feature headless_chrome downloads its own Chromium whenever no
+    // binary is given; keep using the installed Chrome unless --require-chrome needs one.
+    #[cfg(feature = "fetcher")]
+    let shell = match shell {
+        Some(p) => Some(p),
+        None => Some(headless_chrome::browser::default_executable().map_err(|e| anyhow!(e))?),
+    };
     let launch_opts = LaunchOptionsBuilder::default()
         .headless(!args.headful)
-        .user_data_dir(Some(user_dir))
+        .path(shell)
+        .user_data_dir(user_dir)
         .args(
             arg_vec
                 .iter()
@@ -681,27 +3035,23 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
         .build()
         .unwrap();
 
-    let browser = Browser::new(launch_opts)?;
+    let browser = match &args.require_chrome {
+        Some(req) => {
+            chrome_version::launch_checked(launch_opts, req, args.engine == Engine::Chrome)?
+        }
+        None => Browser::new(launch_opts)?,
+    };
+    if let Some(cg) = &cgroup {
+        let pid = browser
+            .get_process_id()
+            .ok_or_else(|| anyhow!("no Chrome process to limit"))?;
+        cg.adopt(pid)?;
+    }
     let tab = browser.new_tab()?;
 
     tab.call_method(SetLifecycleEventsEnabled { enabled: true })?;
 
-    tab.call_method(SetDeviceMetricsOverride {
-        width: win_w,
-        height: win_h,
-        device_scale_factor: args.dpr,
-        mobile: args.mobile,
-        scale: None,
-        screen_width: None,
-        screen_height: None,
-        position_x: None,
-        position_y: None,
-        dont_set_visible_size: None,
-        screen_orientation: None,
-        viewport: None,
-        display_feature: None,
-        device_posture: None,
-    })?;
+    apply_device_metrics(&tab, args, win_w, win_h)?;
 
     let inject_js = build_instrument_js(args.idle_ignore.as_deref().unwrap_or(""));
     tab.call_method(AddScriptToEvaluateOnNewDocument {
@@ -742,8 +3092,17 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
         run_immediately: Some(true),
     })?;
 
+    if let Some(t) = args.fake_time {
+        tab.call_method(AddScriptToEvaluateOnNewDocument {
+            source: faketime::script(t),
+            world_name: None,
+            include_command_line_api: None,
+            run_immediately: Some(true),
+        })?;
+    }
+
     tab.set_user_agent(
-        &ua_generator::ua::spoof_ua(),
+        ua_generator::ua::spoof_ua(),
         Some(&args.locale_or_default()),
         Some("Windows"),
     )?;
@@ -781,29 +3140,160 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
         import_cookies_to_chrome(&tab, &list)?;
     }
 
+    if args.mode == Mode::Extract {
+        use headless_chrome::protocol::cdp::Network::ResourceType;
+        network::block_resources(
+            &tab,
+            &[ResourceType::Image, ResourceType::Font, ResourceType::Media],
+        )?;
+    }
+
+    Ok((cgroup, browser, tab))
+}
+
+/// A capture whose artifacts were kept in memory (`GET /capture` in server mode,
+/// `--output stdout`).
+struct MemoryCapture {
+    final_url: String,
+    wait_branch: String,
+    elapsed_ms: u64,
+    pdf_encrypted: bool,
+    artifacts: Vec<(Artifact, Vec<u8>)>,
+    warnings: Vec<String>,
+}
+
+/// Render `url` and return its DOM, screenshot, PDF and (with `mhtml`) MHTML archive as
+/// bytes. Nothing is written to disk: Chrome runs on a throwaway profile and the PDF
+/// options are applied in memory.
+fn capture_in_memory(url: &str, args: &Cli, mhtml: bool) -> Result<MemoryCapture> {
+    use headless_chrome::protocol::cdp::{Emulation::SetFocusEmulationEnabled, Page::BringToFront};
+
+    let (_cgroup, _browser, tab) = launch_chrome(args, None)?;
+    let net = network::NetworkCapture::attach(&tab)?;
+    let console_watch = args
+        .wait_console
+        .clone()
+        .map(|re| console::ConsoleWatch::attach(&tab, re))
+        .transpose()?;
+    let start = Instant::now();
+    let waited = navigate_with_backoff(&tab, &net, url, args, &mut Vec::new())?;
+    let deadline = start + Duration::from_millis(args.max_wait_ms) + waited;
+    tab.call_method(BringToFront(None))?;
+    tab.call_method(SetFocusEmulationEnabled { enabled: true })?;
+    let watchers = Watchers {
+        net: &net,
+        from: 0,
+        console: console_watch.as_ref(),
+    };
+    let wait_branch = wait_for_page(&tab, &watchers, args, deadline)?.wait_branch;
+
+    let mut artifacts = Vec::new();
+    let warnings = capture_artifacts(&tab, args, mhtml, deadline, |a, bytes| {
+        artifacts.push((a, bytes));
+        Ok(())
+    })?;
+    let final_url = tab.get_url();
+    let mut pdf_encrypted = false;
+    for (a, bytes) in &mut artifacts {
+        if *a == Artifact::Pdf {
+            let (out, encrypted) = pdf::postprocess_bytes(args, std::mem::take(bytes), &final_url)?;
+            *bytes = out;
+            pdf_encrypted = encrypted;
+        }
+    }
+    Ok(MemoryCapture {
+        final_url,
+        wait_branch,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        pdf_encrypted,
+        artifacts,
+        warnings,
+    })
+}
+
+fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderOutcome> {
+    use headless_chrome::{
+        protocol::cdp::Emulation::SetFocusEmulationEnabled, protocol::cdp::Page::BringToFront,
+    };
+
+    let (cgroup, browser, tab) = launch_chrome(
+        args,
+        Some(profile_dir(&args.profile, args.user_data_dir.clone())),
+    )?;
+    let usage = browser.get_process_id().map(procstats::Sampler::start);
+
+    let net = network::NetworkCapture::attach(&tab)?;
+    let console = console::attach(&tab, &paths.console_log)?;
+    let console_watch = args
+        .wait_console
+        .clone()
+        .map(|re| console::ConsoleWatch::attach(&tab, re))
+        .transpose()?;
+    let console_log = Some(paths.console_log.display().to_string());
+    let js_errors = jserrors::ErrorCapture::attach(&tab, &paths.js_errors_log)?;
+    let js_errors_log = Some(paths.js_errors_log.display().to_string());
+    let trace = args.trace.then(|| trace::Trace::start(&tab)).transpose()?;
+
     let start = Instant::now();
     let deadline = start + Duration::from_millis(args.max_wait_ms);
 
+    let mut throttled = Vec::new();
+    let mut filmstrip = args
+        .filmstrip_ms
+        .map(|ms| {
+            filmstrip::Filmstrip::start(
+                tab.clone(),
+                &paths.filmstrip_dir,
+                Duration::from_millis(ms),
+            )
+        })
+        .transpose()?;
+    let mut filmstrip_index = None;
+    let (win_w, win_h) = args.window_size();
+    let mut screencast = if args.screencast {
+        Some(screencast::Screencast::start(
+            tab.clone(),
+            &paths.screencast_dir,
+            win_w,
+            win_h,
+        )?)
+    } else {
+        None
+    };
+    let mut screencast_path = None;
     let res: Result<ChromeRes> = (|| {
-        tab.navigate_to(url)?;
-        tab.wait_until_navigated()?;
+        let waited = navigate_with_backoff(&tab, &net, url, args, &mut throttled)?;
+        let deadline = deadline + waited;
         tab.call_method(BringToFront(None))?;
         tab.call_method(SetFocusEmulationEnabled { enabled: true })?;
-        let wait_branch = wait_until_ready(
-            &tab,
-            &args.wait_ready,
-            args.network_idle_ms,
-            args.idle_threshold,
-            args.heuristic_min_chars,
-            deadline,
-        )?;
-        if let Some(sel) = &args.wait_selector {
-            wait_for_selector(&tab, sel, deadline)?;
+        let watchers = Watchers {
+            net: &net,
+            from: 0,
+            console: console_watch.as_ref(),
+        };
+        let ready = wait_for_page(&tab, &watchers, args, deadline)?;
+        if let Some(f) = filmstrip.take() {
+            filmstrip_index = Some(f.finish()?.display().to_string());
         }
-
-        if let Some(p) = &args.export_cookies {
-            let list = export_cookies_from_chrome(&tab)?;
-            std::fs::write(p, serde_json::to_vec_pretty(&list)?)?;
+        // Long tasks up to readiness; later scrolling and capturing would add their own.
+        let diagnostics = Some(PageDiagnostics {
+            main_thread: mainthread::collect(&tab).ok(),
+            web_vitals: vitals::collect(&tab).ok(),
+            navigation: navtiming::collect(&tab).ok(),
+            ..Default::default()
+        });
+        let scrolls = if args.infinite_scroll {
+            Some(expand_infinite_scroll(&tab, args)?)
+        } else {
+            None
+        };
+        // Chrome scrolls to the fragment on load, but the waits and scrolling above move
+        // the page; put the anchor back at the top just before capturing.
+        let anchor = anchor::target(&tab, url)?;
+        if let Some(rec) = screencast.take() {
+            screencast_path = rec
+                .finish(&paths.session_video)?
+                .map(|p| p.display().to_string());
         }
 
         let body_text = tab
@@ -812,7 +3302,7 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
                 false,
             )?
             .value
-            .map(|v| v.to_string());
+            .and_then(|v| v.as_str().map(|s| s.to_string()));
         let challenge = body_text
             .as_deref()
             .map(|t| {
@@ -823,50 +3313,426 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
             })
             .unwrap_or(false);
 
-        let html = tab.get_content()?;
-        std::fs::write(&paths.dom_html, &html)?;
         let final_url = tab.get_url();
         let redirected = final_url != url;
+        let mut saved = std::thread::scope(|s| -> Result<Saved> {
+            // Cookies live in the browser process, so exporting them doesn't have to wait
+            // for the page capture.
+            let cookies = args.export_cookies.as_ref().map(|p| {
+                let tab = &tab;
+                s.spawn(move || -> Result<()> {
+                    let list = export_cookies_from_chrome(tab)?;
+                    std::fs::write(p, serde_json::to_vec_pretty(&list)?)?;
+                    Ok(())
+                })
+            });
+            let saved = capture_page(
+                &tab,
+                args,
+                &paths.dom_html,
+                &paths.screenshot,
+                &paths.pdf,
+                Some(&paths.mhtml),
+                deadline,
+            )?;
+            if let Some(h) = cookies {
+                h.join()
+                    .map_err(|_| anyhow::anyhow!("cookie export panicked"))??;
+            }
+            Ok(saved)
+        })?;
+        let screenshot_path = saved
+            .screenshot
+            .then(|| paths.screenshot.display().to_string());
+        let thumbnail_path = if saved.screenshot && args.thumbnail_width > 0 {
+            match thumbnail::write(&paths.screenshot, args.thumbnail_width, &paths.thumbnail) {
+                Ok(()) => Some(paths.thumbnail.display().to_string()),
+                Err(e) => {
+                    saved.warnings.push(format!("thumbnail skipped: {e:#}"));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let dom_snapshot_path = if args.dom_snapshot {
+            domsnapshot::capture(&tab, &paths.dom_snapshot)?;
+            Some(paths.dom_snapshot.display().to_string())
+        } else {
+            None
+        };
+        let axtree_path = if args.axtree {
+            axtree::capture(&tab, &paths.axtree)?;
+            Some(paths.axtree.display().to_string())
+        } else {
+            None
+        };
+        // Best effort: a page script can break the heuristics without failing the capture.
+        let main_content = maincontent::detect(&tab).ok().flatten();
+        let main_content_path = match &main_content {
+            Some(region) if args.crop_main_content => {
+                maincontent::crop(&tab, region, &paths.main_content_png)?;
+                Some(paths.main_content_png.display().to_string())
+            }
+            _ => None,
+        };
+        let annotations = if args.annotate {
+            annotate::capture(&tab, args, &paths.annotated_screenshot)?
+        } else {
+            Vec::new()
+        };
+        let annotated_screenshot_path = args
+            .annotate
+            .then(|| paths.annotated_screenshot.display().to_string());
+        let pdf_saved = saved.pdf.then(|| paths.pdf.display().to_string());
+        let mhtml_path = saved.mhtml.then(|| paths.mhtml.display().to_string());
+
+        // The artifacts above are the evidence; a blocked page gets nothing else.
+        let entries = net.entries();
+        let doc = network::final_document_response(&entries, &final_url);
+        let status = doc.map(|r| r.status as u16);
+        let doc_headers =
+            headers::split_lines(&doc.map(network::response_headers).unwrap_or_default());
+        // Written before the block check: a block page's headers are part of the evidence.
+        let headers_path = match (doc, status) {
+            (Some(doc), Some(status)) => {
+                headers::write(&doc_headers, &doc.url, status, &paths.headers_json)?;
+                Some(paths.headers_json.display().to_string())
+            }
+            _ => None,
+        };
+        let header_summary = doc.map(|_| headers::summarize(&doc_headers));
+        let tls = doc.and_then(tls::from_response);
+        let remote = doc.and_then(|d| {
+            dns::describe(
+                &d.url,
+                d.remote_ip_address.as_deref(),
+                d.remote_port.map(|p| p as u16),
+            )
+        });
+        let html = std::fs::read_to_string(&paths.dom_html).unwrap_or_default();
+        let page = block::Page {
+            status,
+            headers: &doc_headers,
+            html: &html,
+            text: body_text.as_deref().unwrap_or_default(),
+        };
+        if let Some(verdict) = block::detect(&page) {
+            return Err(verdict.into());
+        }
+        let anti_bot_vendor = block::vendor(&page).map(|(v, _)| v.to_string());
 
-        let png = tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true)?;
-        std::fs::write(&paths.png, png)?;
-        let screenshot_path = Some(paths.png.display().to_string());
-
-        wait_images_and_fonts(&tab, deadline)?;
-        let bytes = tab.print_to_pdf(Some(PrintToPdfOptions {
-            print_background: Some(true),
-            prefer_css_page_size: Some(true),
-            margin_top: Some(0.0),
-            margin_bottom: Some(0.0),
-            margin_left: Some(0.0),
-            margin_right: Some(0.0),
-            ..Default::default()
-        }))?;
-        std::fs::write(&paths.pdf, &bytes)?;
-        let pdf_saved = Some(paths.pdf.display().to_string());
+        let markdown_path = if args.emit.contains(&Emit::Markdown) {
+            markdown::write_markdown(&tab, &paths.markdown)?;
+            Some(paths.markdown.display().to_string())
+        } else {
+            None
+        };
+
+        let text_path = if args.emit.contains(&Emit::Text) || args.mode == Mode::Extract {
+            save_text(&tab, &paths.text)?;
+            Some(paths.text.display().to_string())
+        } else {
+            None
+        };
+
+        let metadata = (args.mode == Mode::Extract)
+            .then(|| metadata::collect(&tab))
+            .transpose()?;
+
+        let epub_path = if args.epub {
+            epub::write_epub(&tab, &net.entries(), &final_url, &paths.epub)?;
+            Some(paths.epub.display().to_string())
+        } else {
+            None
+        };
+
+        let images_index = if args.extract_images {
+            extract::extract_images(&tab, &net.entries(), &paths.images_dir, args.min_image_px)?;
+            Some(paths.images_dir.join("index.json").display().to_string())
+        } else {
+            None
+        };
+
+        let assets_index = if args.save_assets {
+            assets::save_assets(&tab, &net.entries(), &paths.assets_dir)?;
+            Some(paths.assets_dir.join("index.json").display().to_string())
+        } else {
+            None
+        };
+
+        let api_index = match &args.capture_api {
+            Some(filter) => {
+                api::capture(&tab, &net.entries(), filter, &paths.api_dir)?;
+                Some(paths.api_dir.join("index.json").display().to_string())
+            }
+            None => None,
+        };
+
+        let (graphql_path, graphql_operations) = if args.capture_graphql {
+            let n = graphql::capture(&tab, &net.entries(), &paths.graphql_jsonl)?;
+            (Some(paths.graphql_jsonl.display().to_string()), Some(n))
+        } else {
+            (None, None)
+        };
+
+        let contacts_path = if args.extract_contacts {
+            extract::extract_contacts(&tab, &paths.contacts_json)?;
+            Some(paths.contacts_json.display().to_string())
+        } else {
+            None
+        };
+
+        let breakpoints_index = if args.breakpoints.is_empty() {
+            None
+        } else {
+            breakpoints::capture(&tab, args, &args.breakpoints, &paths.breakpoints_dir)?;
+            Some(
+                paths
+                    .breakpoints_dir
+                    .join("index.json")
+                    .display()
+                    .to_string(),
+            )
+        };
+
+        let media = media::detect(&tab, &net.entries())
+            .ok()
+            .filter(|m| !m.is_empty());
+
+        let pages = if let Some(sel) = &args.follow_pagination {
+            follow_pagination(
+                &tab,
+                &net,
+                console_watch.as_ref(),
+                args,
+                paths,
+                sel,
+                &mut saved.warnings,
+            )
+        } else {
+            Vec::new()
+        };
+        let article_path = if args.merge_pages {
+            let mut html_files = vec![paths.dom_html.clone()];
+            html_files.extend(pages.iter().map(|p| PathBuf::from(&p.html_path)));
+            article::merge_pages(&html_files, &paths.article_html, &paths.article_txt)?;
+            Some(paths.article_html.display().to_string())
+        } else {
+            None
+        };
 
         Ok(ChromeRes {
             final_url,
-            status: None,
+            status,
             redirected,
             html_path: paths.dom_html.display().to_string(),
+            dom_snapshot_path,
+            axtree_path,
+            trace_path: None,
+            mhtml_path,
+            markdown_path,
+            text_path,
+            epub_path,
             elapsed_ms: start.elapsed().as_millis() as u64,
             screenshot_path,
+            annotated_screenshot_path,
+            thumbnail_path,
             pdf_path: pdf_saved,
-            waf_detected: challenge,
-            anti_bot_vendor: None,
+            waf_detected: challenge || anti_bot_vendor.is_some(),
+            anti_bot_vendor,
             js_challenge: challenge,
-            wait_branch,
+            wait_branch: ready.wait_branch,
+            wait_selector_matched: ready.wait_selector_matched,
+            wait_js_value: ready.wait_js_value,
+            pages,
+            article_path,
+            waterfall_path: None,
+            waterfall_json_path: None,
+            har_path: None,
+            console_log_path: None,
+            console: None,
+            js_errors_log_path: None,
+            js_errors: Vec::new(),
+            network_log_path: None,
+            network_summary: None,
+            third_parties_path: None,
+            third_parties: None,
+            trackers: Vec::new(),
+            mixed_content: Vec::new(),
+            headers_path,
+            headers: header_summary,
+            tls,
+            remote,
+            scrolls,
+            images_index,
+            assets_index,
+            api_index,
+            graphql_path,
+            graphql_operations,
+            breakpoints_index,
+            filmstrip_index: None,
+            screencast_path: None,
+            contacts_path,
+            media,
+            resource_limits: None,
+            diagnostics,
+            anchor,
+            annotations,
+            main_content,
+            main_content_path,
+            metadata,
+            throttled: std::mem::take(&mut throttled),
+            warnings: saved.warnings,
         })
     })();
 
+    let trace_path = trace.and_then(|t| match t.finish(&tab, &paths.trace) {
+        Ok(()) => Some(paths.trace.display().to_string()),
+        Err(e) => {
+            eprintln!("warning: trace: {:#}", e);
+            None
+        }
+    });
+    let chrome_process = usage.and_then(procstats::Sampler::finish);
+    let entries = net.entries();
+    let network_summary = network::summarize(&entries);
+    let page_weight = weight::measure(&entries, &network_summary);
+    let waterfall = network::write_waterfall_svg(&entries, &paths.waterfall_svg)
+        .ok()
+        .map(|_| paths.waterfall_svg.display().to_string());
+    let waterfall_json = network::write_waterfall_json(&entries, &paths.waterfall_json)
+        .ok()
+        .map(|_| paths.waterfall_json.display().to_string());
+    let har = har::write_har(&entries, &tab.get_url(), &paths.har)
+        .ok()
+        .map(|_| paths.har.display().to_string());
+    let network_log = network::write_log(&entries, &paths.network_log)
+        .ok()
+        .map(|_| paths.network_log.display().to_string());
+    let third_parties = thirdparty::write(&entries, &tab.get_url(), &paths.third_parties).ok();
+    let third_parties_path = third_parties
+        .is_some()
+        .then(|| paths.third_parties.display().to_string());
+    let detected_trackers = trackers::detect(&entries);
+    let mixed_content = mixed::detect(&entries, &tab.get_url());
+    let uncaught = js_errors.errors();
+    let console_counts = console.counts();
+    // Navigation or the waits failed: the frames up to here are what explains it.
+    if let Some(f) = filmstrip.take() {
+        filmstrip_index = f.finish().ok().map(|p| p.display().to_string());
+    }
+    if let Some(rec) = screencast.take() {
+        screencast_path = rec
+            .finish(&paths.session_video)
+            .ok()
+            .flatten()
+            .map(|p| p.display().to_string());
+    }
+    let resource_limits = cgroup.as_ref().map(|c| c.report());
+    if let Some(r) = resource_limits.as_ref().filter(|r| r.limit_hit()) {
+        eprintln!(
+            "warning: Chrome hit its memory limit ({} OOM kill(s))",
+            r.oom_kills
+        );
+    }
+
     match res {
-        Ok(r) => Ok(RenderOutcome::Success(r)),
+        Ok(mut r) => {
+            r.waterfall_path = waterfall;
+            r.trace_path = trace_path;
+            r.waterfall_json_path = waterfall_json;
+            r.har_path = har;
+            r.console_log_path = console_log;
+            r.console = Some(console_counts);
+            r.js_errors_log_path = js_errors_log;
+            r.js_errors = uncaught;
+            r.network_log_path = network_log;
+            r.third_parties_path = third_parties_path;
+            r.third_parties = third_parties;
+            r.trackers = detected_trackers;
+            r.mixed_content = mixed_content;
+            r.network_summary = Some(network_summary);
+            let diagnostics = r.diagnostics.get_or_insert_with(Default::default);
+            diagnostics.page_weight = Some(page_weight);
+            diagnostics.chrome_process = chrome_process;
+            r.resource_limits = resource_limits;
+            r.filmstrip_index = filmstrip_index;
+            r.screencast_path = screencast_path;
+            Ok(RenderOutcome::Success(Box::new(r)))
+        }
         Err(e) => {
+            let e = match e.downcast::<block::Verdict>() {
+                Ok(verdict) => {
+                    return Ok(RenderOutcome::Blocked(Box::new(BlockedReport {
+                        status: "blocked",
+                        url: url.to_string(),
+                        final_url: tab.get_url(),
+                        http_status: network::final_document_response(&entries, &tab.get_url())
+                            .map(|r| r.status as u16),
+                        verdict,
+                        elapsed_ms: start.elapsed().as_millis() as u64,
+                        run_dir: paths.run_dir.display().to_string(),
+                        artifacts: Artifacts {
+                            html: paths
+                                .dom_html
+                                .exists()
+                                .then(|| paths.dom_html.display().to_string()),
+                            screenshot: paths
+                                .screenshot
+                                .exists()
+                                .then(|| paths.screenshot.display().to_string()),
+                            annotated_screenshot: paths
+                                .annotated_screenshot
+                                .exists()
+                                .then(|| paths.annotated_screenshot.display().to_string()),
+                            thumbnail: paths
+                                .thumbnail
+                                .exists()
+                                .then(|| paths.thumbnail.display().to_string()),
+                            pdf: paths.pdf.exists().then(|| paths.pdf.display().to_string()),
+                            mhtml: paths
+                                .mhtml
+                                .exists()
+                                .then(|| paths.mhtml.display().to_string()),
+                            trace: trace_path,
+                            waterfall,
+                            waterfall_json,
+                            har,
+                            console_log,
+                            js_errors_log,
+                            network_log,
+                            third_parties: third_parties_path,
+                            headers: paths
+                                .headers_json
+                                .exists()
+                                .then(|| paths.headers_json.display().to_string()),
+                            filmstrip: filmstrip_index,
+                            screencast: screencast_path,
+                        },
+                        pdf_encrypted: false,
+                        network_summary: Some(network_summary),
+                        throttled,
+                        resource_limits,
+                        tags: args.tag_map(),
+                    })));
+                }
+                Err(e) => e,
+            };
             let msg = e.to_string();
             if msg.contains("timeout") || msg.contains("EventNeverCame") {
                 let wait_branch = if msg.contains("network idle") {
                     "network_idle"
+                } else if msg.contains("--wait-js") {
+                    "wait_js"
+                } else if msg.contains("--wait-request") {
+                    "wait_request"
+                } else if msg.contains("--wait-console") {
+                    "wait_console"
+                } else if msg.starts_with("xpath ") {
+                    "wait_xpath"
+                } else if msg.contains("selector") {
+                    "wait_selector"
                 } else if msg.contains("readyState") {
                     "ready_state"
                 } else {
@@ -900,40 +3766,67 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
                 let ts = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
                 let dbg_dir = args.debug_dir.join(&ts);
                 std::fs::create_dir_all(&dbg_dir)?;
-                let html_content = tab.get_content().unwrap_or_default();
                 let html_path = dbg_dir.join("dom.html");
-                let _ = std::fs::write(&html_path, html_content);
-                let shot_path = dbg_dir.join("snap.png");
-                if let Ok(png) =
-                    tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true)
-                {
-                    let _ = std::fs::write(&shot_path, png);
-                }
-
+                let shot_path =
+                    dbg_dir.join(Artifact::Screenshot(args.screenshot_format).file_name());
                 let host = url::Url::parse(url)
                     .ok()
                     .and_then(|u| u.host_str().map(|h| h.to_string()))
                     .unwrap_or_else(|| "page".to_string());
                 let pdf_file = dbg_dir.join(format!("{host}.pdf"));
-                let mut pdf_saved = None;
-                if let Ok(bytes) = tab.print_to_pdf(Some(PrintToPdfOptions {
-                    print_background: Some(true),
-                    prefer_css_page_size: Some(true),
-                    margin_top: Some(0.0),
-                    margin_bottom: Some(0.0),
-                    margin_left: Some(0.0),
-                    margin_right: Some(0.0),
-                    ..Default::default()
-                })) {
-                    if std::fs::write(&pdf_file, bytes).is_ok() {
-                        pdf_saved = Some(pdf_file.display().to_string());
+                let mhtml_file = dbg_dir.join("page.mhtml");
+                // The same capture as a finished render, so the debug artifacts follow
+                // --full-page, --clip, --pdf-clean and the rest. The page's deadline has
+                // passed; the capture gets a short one of its own.
+                let mut warnings = match capture_page(
+                    &tab,
+                    args,
+                    &html_path,
+                    &shot_path,
+                    &pdf_file,
+                    Some(&mhtml_file),
+                    Instant::now() + TIMEOUT_CAPTURE_GRACE,
+                ) {
+                    Ok(saved) => saved.warnings,
+                    Err(e) => vec![format!("debug capture stopped early: {e:#}")],
+                };
+                let annotated = dbg_dir.join(format!(
+                    "snap.annotated.{}",
+                    args.screenshot_format.extension()
+                ));
+                let annotated = (args.annotate
+                    && annotate::capture(&tab, args, &annotated).is_ok())
+                .then(|| annotated.display().to_string());
+                let thumb = dbg_dir.join("thumb.png");
+                let thumb = if shot_path.exists() && args.thumbnail_width > 0 {
+                    match thumbnail::write(&shot_path, args.thumbnail_width, &thumb) {
+                        Ok(()) => Some(thumb.display().to_string()),
+                        Err(e) => {
+                            warnings.push(format!("thumbnail skipped: {e:#}"));
+                            None
+                        }
                     }
-                }
+                } else {
+                    None
+                };
+
+                let doc = network::final_document_response(&entries, &tab.get_url());
+                let doc_headers =
+                    headers::split_lines(&doc.map(network::response_headers).unwrap_or_default());
+                let headers_file = dbg_dir.join("headers.json");
+                let headers_saved = doc
+                    .filter(|d| {
+                        headers::write(&doc_headers, &d.url, d.status as u16, &headers_file).is_ok()
+                    })
+                    .map(|_| headers_file.display().to_string());
 
                 let report = TimeoutReport {
                     status: "timeout",
                     reason: msg,
                     url: url.to_string(),
+                    http_status: doc.map(|r| r.status as u16),
+                    headers: doc.map(|_| headers::summarize(&doc_headers)),
+                    tls: doc.and_then(tls::from_response),
                     deadline_ms: args.max_wait_ms,
                     elapsed_ms: start.elapsed().as_millis() as u64,
                     wait_branch: wait_branch.to_string(),
@@ -942,14 +3835,48 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
                         images_total,
                         images_incomplete,
                         pending_requests,
+                        main_thread: mainthread::collect(&tab).ok(),
+                        page_weight: Some(page_weight),
+                        web_vitals: vitals::collect(&tab).ok(),
+                        navigation: navtiming::collect(&tab).ok(),
+                        chrome_process,
                     },
                     artifacts: Artifacts {
-                        html: html_path.display().to_string(),
-                        screenshot: shot_path.display().to_string(),
-                        pdf: pdf_saved,
+                        html: html_path.exists().then(|| html_path.display().to_string()),
+                        screenshot: shot_path.exists().then(|| shot_path.display().to_string()),
+                        annotated_screenshot: annotated,
+                        thumbnail: thumb,
+                        pdf: pdf_file.exists().then(|| pdf_file.display().to_string()),
+                        mhtml: mhtml_file
+                            .exists()
+                            .then(|| mhtml_file.display().to_string()),
+                        trace: trace_path,
+                        waterfall,
+                        waterfall_json,
+                        har,
+                        console_log,
+                        js_errors_log,
+                        network_log,
+                        third_parties: third_parties_path,
+                        headers: headers_saved,
+                        filmstrip: filmstrip_index,
+                        screencast: screencast_path,
                     },
+                    pdf_encrypted: false,
+                    network_summary: Some(network_summary),
+                    trackers: detected_trackers,
+                    mixed_content,
+                    console: console_counts,
+                    js_errors: uncaught,
+                    throttled,
+                    resource_limits,
+                    warnings,
+                    tags: args.tag_map(),
                 };
-                Ok(RenderOutcome::Timeout(report))
+                Ok(RenderOutcome::Timeout(Box::new(report)))
+            } else if resource_limits.is_some_and(|r| r.oom_kills > 0) {
+                // A renderer killed by the OOM killer surfaces as a vague CDP error.
+                Err(e.context("Chrome was OOM-killed; consider raising --chrome-memory-limit-mb"))
             } else {
                 Err(e)
             }
@@ -957,8 +3884,130 @@ fn render_with_chrome(url: &str, paths: &RunPaths, args: &Cli) -> Result<RenderO
     }
 }
 
+/// Write result.json, capturing hreflang alternates and applying the golden comparison
+/// first when requested.
+async fn write_output(args: &Cli, paths: &RunPaths, mut out: Output) -> Result<RunRecord> {
+    out.alternates = alternates::from_html_file(Path::new(&out.html_path), &out.final_url);
+    if args.capture_alternates && !out.alternates.is_empty() {
+        out.alternates_manifest = Some(
+            alternates::capture(args, &paths.run_dir, &out.final_url, &mut out.alternates).await?,
+        );
+    }
+    if args.standalone {
+        standalone::write_standalone(
+            Path::new(&out.html_path),
+            &out.final_url,
+            &paths.standalone_html,
+        )
+        .await?;
+        out.standalone_path = Some(paths.standalone_html.display().to_string());
+    }
+    if let Ok(html) = std::fs::read_to_string(&out.html_path) {
+        let hash = dedup::content_hash(&html);
+        out.duplicate_of = index::RunIndex::open(&args.out_root)?
+            .with_content_hash(&hash, &out.run_dir)?
+            .map(|r| r.run_dir);
+        out.content_hash = Some(hash);
+    }
+    if let Some(p) = &out.pdf_path {
+        out.pdf_encrypted = pdf::postprocess(args, Path::new(p), &out.final_url)?;
+    }
+    for page in &out.pages {
+        if let Some(p) = &page.pdf_path {
+            pdf::postprocess(args, Path::new(p), &page.url)?;
+        }
+    }
+    let hash = |p: &Option<String>| p.as_deref().and_then(|p| hash::file_sha256(Path::new(p)));
+    out.sha256 = ArtifactHashes {
+        html: hash::file_sha256(Path::new(&out.html_path)),
+        screenshot: hash(&out.screenshot_path),
+        pdf: hash(&out.pdf_path),
+    };
+    let mut golden_failed = false;
+    if let Some(dir) = &args.golden {
+        let verdict = golden::compare(
+            dir,
+            &serde_json::to_value(&out)?,
+            args.golden_min_similarity,
+            args.golden_max_visual_distance,
+        )?;
+        golden_failed = !verdict.passed();
+        out.golden = Some(verdict);
+    }
+    write_json(&paths.result_json, &out)?;
+    Ok(RunRecord {
+        result_json: paths.result_json.clone(),
+        result: serde_json::to_value(&out)?,
+        exit_code: if golden_failed { 3 } else { 0 },
+    })
+}
+
 fn write_json<T: Serialize>(path: &Path, v: &T) -> Result<()> {
     std::fs::write(path, serde_json::to_string_pretty(v)?)?;
     println!("{}", path.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chrome_only_options_skip_the_fast_path() {
+        let cli = |extra: &[&str]| {
+            Cli::parse_from(
+                ["ankabot", "https://example.com"]
+                    .iter()
+                    .chain(extra)
+                    .copied(),
+            )
+        };
+        assert!(!cli(&[]).needs_chrome());
+        assert!(!cli(&["--thumbnail-width", "100", "--tag", "a=b"]).needs_chrome());
+        for flags in [
+            &["--force-chrome"][..],
+            &["--mode", "extract"],
+            &["--wait-selector", "#app"],
+            &["--wait-ready", "dom-quiet"],
+            &["--full-page"],
+            &["--screenshot-format", "jpeg"],
+            &["--pdfa"],
+            &["--stamp", "evidence"],
+            &["--pdf-title", "Report"],
+            &["--landscape"],
+            &["--pdf-password", "secret"],
+        ] {
+            assert!(cli(flags).needs_chrome(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn clip_parses_four_numbers() {
+        let c: Clip = "0, 120.5,800,600".parse().unwrap();
+        assert_eq!((c.x, c.y, c.width, c.height), (0.0, 120.5, 800.0, 600.0));
+    }
+
+    #[test]
+    fn clip_rejects_negative_and_empty_areas() {
+        for bad in ["-1,0,10,10", "0,-5,10,10", "0,0,0,10", "0,0,10,-3"] {
+            let err = bad.parse::<Clip>().unwrap_err();
+            assert!(err.contains("positive width and height"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn clip_rejects_missing_or_extra_fields() {
+        for bad in ["0,0,10", "0,0,10,10,5", "10"] {
+            let err = bad.parse::<Clip>().unwrap_err();
+            assert!(err.starts_with("expected four values"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn clip_rejects_non_numbers() {
+        for bad in ["a,0,10,10", "0,0,10px,10", "0,,10,10", ""] {
+            let err = bad.parse::<Clip>().unwrap_err();
+            assert!(err.starts_with("expected x,y,w,h numbers"), "{bad}: {err}");
+        }
+    }
+}
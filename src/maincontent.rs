@@ -0,0 +1,131 @@
+//! Main content detection: the element holding the page's primary text, without
+//! navigation, sidebars and footers.
+//!
+//! Scoring follows Readability: each paragraph adds points to its parent and, less, to
+//! its grandparents; class/id names and semantic tags adjust the score, and text that is
+//! mostly links (menus, link lists) or a narrow column (sidebars) counts for less. The
+//! winner is marked with `data-ankabot-main` so `--annotate` boxes the same element.
+
+use anyhow::{Context, Result};
+use headless_chrome::protocol::cdp::Page::{CaptureScreenshotFormatOption, Viewport};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Tallest crop, in CSS px; one capture can't exceed Chrome's texture limit.
+const MAX_CROP_HEIGHT: f64 = 8000.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MainContent {
+    /// CSS selector that matches the element, e.g. `#content > div:nth-of-type(2)`.
+    pub selector: String,
+    /// Bounding box in CSS px, relative to the document.
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Length of its visible text.
+    pub text_chars: u64,
+}
+
+const DETECT_JS: &str = r#"(() => {
+  document.querySelectorAll('[data-ankabot-main]').forEach((e) => e.removeAttribute('data-ankabot-main'));
+  const POSITIVE = /article|body|content|entry|main|page|post|story|text|blog/i;
+  const NEGATIVE = /comment|footer|header|masthead|menu|nav|sidebar|sponsor|\bads?\b|banner|related|share|social|promo|widget|cookie|consent|breadcrumb|pagination/i;
+  const CHROME = 'nav, header, footer, aside, [role="navigation"], [role="banner"], [role="contentinfo"], [aria-hidden="true"]';
+  const BLOCKS = ':scope > :is(p, div, section, article, table, ul, ol, pre, blockquote)';
+
+  const visible = (el) => {
+    const r = el.getBoundingClientRect();
+    if (r.width < 50 || r.height < 20) return false;
+    const s = getComputedStyle(el);
+    return s.display !== 'none' && s.visibility !== 'hidden';
+  };
+  const classWeight = (el) => {
+    const names = (el.id || '') + ' ' + (typeof el.className === 'string' ? el.className : '');
+    return (POSITIVE.test(names) ? 25 : 0) - (NEGATIVE.test(names) ? 25 : 0);
+  };
+  const scores = new Map();
+  const add = (el, v) => {
+    if (el && el !== document.body && el !== document.documentElement)
+      scores.set(el, (scores.get(el) || 0) + v);
+  };
+
+  // Paragraphs, and divs used as paragraphs (no block children).
+  const paragraphs = [...document.querySelectorAll('p, pre, td, blockquote')]
+    .concat([...document.querySelectorAll('div')].filter((d) => !d.querySelector(BLOCKS)));
+  for (const p of paragraphs) {
+    if (p.closest(CHROME)) continue;
+    const text = (p.innerText || '').trim();
+    if (text.length < 25) continue;
+    const v = 1 + text.split(',').length + Math.min(Math.floor(text.length / 100), 3);
+    add(p.parentElement, v);
+    add(p.parentElement?.parentElement, v / 2);
+    add(p.parentElement?.parentElement?.parentElement, v / 4);
+  }
+
+  let best = null;
+  let bestScore = 0;
+  for (const [el, base] of scores) {
+    if (!visible(el)) continue;
+    const text = el.innerText || '';
+    if (!text.length) continue;
+    let linked = 0;
+    el.querySelectorAll('a').forEach((a) => { linked += (a.innerText || '').length; });
+    let score = (base + classWeight(el)) * (1 - Math.min(linked / text.length, 1));
+    if (el.matches('article, main, [role="main"]')) score *= 1.25;
+    if (el.getBoundingClientRect().width < innerWidth * 0.3) score *= 0.5;
+    if (score > bestScore) {
+      best = el;
+      bestScore = score;
+    }
+  }
+  if (!best) best = [...document.querySelectorAll('main, [role="main"], article')].find(visible);
+  if (!best) return null;
+  best.setAttribute('data-ankabot-main', '');
+
+  const parts = [];
+  for (let n = best; n && n.nodeType === 1; n = n.parentElement) {
+    if (n.id && document.querySelectorAll('#' + CSS.escape(n.id)).length === 1) {
+      parts.unshift('#' + CSS.escape(n.id));
+      break;
+    }
+    let part = n.tagName.toLowerCase();
+    if (n.parentElement && n !== document.body) {
+      const same = [...n.parentElement.children].filter((c) => c.tagName === n.tagName);
+      if (same.length > 1) part += `:nth-of-type(${same.indexOf(n) + 1})`;
+    }
+    parts.unshift(part);
+  }
+  const r = best.getBoundingClientRect();
+  return JSON.stringify({
+    selector: parts.join(' > '),
+    x: r.left + scrollX, y: r.top + scrollY, width: r.width, height: r.height,
+    text_chars: (best.innerText || '').length,
+  });
+})()"#;
+
+/// Find and mark the main content region; `None` when the page has no text to speak of.
+pub fn detect(tab: &headless_chrome::Tab) -> Result<Option<MainContent>> {
+    let found = tab.evaluate(DETECT_JS, false)?.value;
+    match found.as_ref().and_then(|v| v.as_str()) {
+        Some(json) => Ok(Some(serde_json::from_str(json)?)),
+        None => Ok(None),
+    }
+}
+
+/// Screenshot just the main content region as PNG into `path`.
+pub fn crop(tab: &headless_chrome::Tab, region: &MainContent, path: &Path) -> Result<()> {
+    let png = tab.capture_screenshot(
+        CaptureScreenshotFormatOption::Png,
+        None,
+        Some(Viewport {
+            x: region.x.max(0.0),
+            y: region.y.max(0.0),
+            width: region.width.max(1.0),
+            height: region.height.clamp(1.0, MAX_CROP_HEIGHT),
+            scale: 1.0,
+        }),
+        true,
+    )?;
+    std::fs::write(path, png).with_context(|| format!("writing {}", path.display()))
+}
@@ -0,0 +1,133 @@
+//! Main-thread diagnostics: Long Tasks recorded while the page loaded and settled, for
+//! pages whose readyState completes but which stay visually frozen.
+//!
+//! The observers live in the instrumentation script (`window.__ankabot.longTasks` and
+//! `.longScripts`, the latter from the Long Animation Frames API where Chrome has it).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Work beyond this on one task blocks input (the Total Blocking Time definition).
+const BLOCKING_THRESHOLD_MS: f64 = 50.0;
+const WORST: usize = 5;
+const SCRIPTS_PER_TASK: usize = 3;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MainThread {
+    pub long_tasks: usize,
+    /// Sum over long tasks of the time each ran past 50 ms.
+    pub total_blocking_time_ms: u64,
+    pub longest_task_ms: u64,
+    /// The longest tasks, with the scripts that ran in them when Chrome attributes them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub worst: Vec<LongTask>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LongTask {
+    /// Milliseconds since navigation start.
+    pub start_ms: u64,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub scripts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Raw {
+    #[serde(default)]
+    tasks: Vec<RawTask>,
+    #[serde(default)]
+    scripts: Vec<RawScript>,
+}
+
+#[derive(Deserialize)]
+struct RawTask {
+    start: f64,
+    duration: f64,
+    #[serde(default)]
+    frame: String,
+}
+
+#[derive(Deserialize)]
+struct RawScript {
+    start: f64,
+    duration: f64,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    invoker: String,
+}
+
+const COLLECT_JS: &str = r#"JSON.stringify({
+  tasks: (window.__ankabot && window.__ankabot.longTasks) || [],
+  scripts: (window.__ankabot && window.__ankabot.longScripts) || [],
+})"#;
+
+/// Summarize the long tasks recorded so far on the current document.
+pub fn collect(tab: &headless_chrome::Tab) -> Result<MainThread> {
+    let raw = tab
+        .evaluate(COLLECT_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+    let raw: Raw = serde_json::from_str(&raw).unwrap_or(Raw {
+        tasks: Vec::new(),
+        scripts: Vec::new(),
+    });
+
+    let total_blocking: f64 = raw
+        .tasks
+        .iter()
+        .map(|t| (t.duration - BLOCKING_THRESHOLD_MS).max(0.0))
+        .sum();
+    let longest = raw.tasks.iter().map(|t| t.duration).fold(0.0, f64::max);
+
+    let mut tasks: Vec<&RawTask> = raw.tasks.iter().collect();
+    tasks.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+    let worst = tasks
+        .into_iter()
+        .take(WORST)
+        .map(|t| {
+            let end = t.start + t.duration;
+            let mut scripts: Vec<&RawScript> = raw
+                .scripts
+                .iter()
+                .filter(|s| s.start < end && s.start + s.duration > t.start)
+                .collect();
+            scripts.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+            LongTask {
+                start_ms: t.start as u64,
+                duration_ms: t.duration as u64,
+                frame: (!t.frame.is_empty()).then(|| t.frame.clone()),
+                scripts: scripts
+                    .into_iter()
+                    .take(SCRIPTS_PER_TASK)
+                    .map(describe)
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(MainThread {
+        long_tasks: raw.tasks.len(),
+        total_blocking_time_ms: total_blocking as u64,
+        longest_task_ms: longest as u64,
+        worst,
+    })
+}
+
+/// `https://cdn.example.com/app.js (TimerHandler:setTimeout, 180 ms)`
+fn describe(s: &RawScript) -> String {
+    let source = if s.source.is_empty() {
+        "inline script"
+    } else {
+        &s.source
+    };
+    if s.invoker.is_empty() {
+        format!("{} ({} ms)", source, s.duration as u64)
+    } else {
+        format!("{} ({}, {} ms)", source, s.invoker, s.duration as u64)
+    }
+}
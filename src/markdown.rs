@@ -0,0 +1,151 @@
+//! `--emit markdown`: the page's main content as Markdown (`page.md`).
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Pick the main content element: the largest visible `<article>`/`<main>`-like element
+/// with real text, else the ancestor that collects the most paragraph text (weighted
+/// against link-heavy blocks, as Readability does).
+pub const MAIN_CONTENT_JS: &str = r#"(() => {
+  const visible = (el) => {
+    const s = getComputedStyle(el);
+    return s.display !== 'none' && s.visibility !== 'hidden';
+  };
+  const textLen = (el) => (el.innerText || '').trim().length;
+  const linkDensity = (el) => {
+    let links = 0;
+    for (const a of el.querySelectorAll('a')) links += (a.innerText || '').trim().length;
+    return links / (textLen(el) || 1);
+  };
+  for (const sel of ['article', 'main', '[role="main"]', '[itemprop="articleBody"]']) {
+    const best = Array.from(document.querySelectorAll(sel))
+      .filter(visible)
+      .sort((a, b) => textLen(b) - textLen(a))[0];
+    if (best && textLen(best) > 200) return best;
+  }
+  const scores = new Map();
+  for (const p of document.querySelectorAll('p, pre, td, blockquote')) {
+    const len = textLen(p);
+    if (len < 25) continue;
+    const score = 1 + p.innerText.split(',').length + Math.min(Math.floor(len / 100), 3);
+    let node = p.parentElement;
+    for (let depth = 0; node && depth < 3; depth++, node = node.parentElement) {
+      scores.set(node, (scores.get(node) || 0) + score / (depth + 1));
+    }
+  }
+  let best = null;
+  let bestScore = 0;
+  for (const [el, score] of scores) {
+    const adjusted = score * (1 - linkDensity(el));
+    if (adjusted > bestScore) {
+      best = el;
+      bestScore = adjusted;
+    }
+  }
+  return best || document.body;
+})()"#;
+
+const MARKDOWN_JS: &str = r#"((root) => {
+  const SKIP = new Set(['SCRIPT', 'STYLE', 'NOSCRIPT', 'TEMPLATE', 'IFRAME', 'SVG', 'CANVAS',
+    'FORM', 'BUTTON', 'INPUT', 'SELECT', 'TEXTAREA', 'NAV', 'ASIDE', 'FOOTER', 'DIALOG']);
+  const NOISE = /comment|share|social|related|promo|sidebar|newsletter|subscribe|advert|cookie|banner|breadcrumb/i;
+  const abs = (u) => { try { return new URL(u, document.baseURI).href; } catch (e) { return u; } };
+  const cell = (c) => md(c).replace(/\s*\n\s*/g, ' ').trim().replace(/\|/g, '\\|');
+  const md = (node, depth = -1) => {
+    if (node.nodeType === Node.TEXT_NODE) return node.textContent.replace(/\s+/g, ' ');
+    if (node.nodeType !== Node.ELEMENT_NODE) return '';
+    const el = node;
+    const tag = el.tagName;
+    if (el !== root) {
+      if (SKIP.has(tag)) return '';
+      if (NOISE.test(`${el.id} ${typeof el.className === 'string' ? el.className : ''}`)) return '';
+      const s = getComputedStyle(el);
+      if (s.display === 'none' || s.visibility === 'hidden') return '';
+    }
+    const kids = (d = depth) => Array.from(el.childNodes).map((c) => md(c, d)).join('');
+    switch (tag) {
+      case 'H1': case 'H2': case 'H3': case 'H4': case 'H5': case 'H6': {
+        const t = kids().replace(/\s+/g, ' ').trim();
+        return t ? `\n\n${'#'.repeat(+tag[1])} ${t}\n\n` : '';
+      }
+      case 'BR': return '\n';
+      case 'HR': return '\n\n---\n\n';
+      case 'STRONG': case 'B': { const t = kids().trim(); return t ? `**${t}**` : ''; }
+      case 'EM': case 'I': { const t = kids().trim(); return t ? `*${t}*` : ''; }
+      case 'CODE': return el.closest('pre') ? el.textContent : '`' + el.textContent + '`';
+      case 'PRE': return '\n\n```\n' + el.textContent.replace(/\n+$/, '') + '\n```\n\n';
+      case 'A': {
+        const t = kids().trim();
+        const href = el.getAttribute('href');
+        if (!t || !href || href.startsWith('#') || href.startsWith('javascript:')) return t;
+        return `[${t}](${abs(href)})`;
+      }
+      case 'IMG': {
+        const src = el.currentSrc || el.src;
+        if (!src || src.startsWith('data:')) return '';
+        return `![${(el.alt || '').replace(/[\[\]]/g, '')}](${src})`;
+      }
+      case 'FIGCAPTION': { const t = kids().trim(); return t ? `\n\n*${t}*\n\n` : ''; }
+      case 'BLOCKQUOTE':
+        return '\n\n' + kids().trim().replace(/\n{3,}/g, '\n\n').split('\n').map((l) => '> ' + l).join('\n') + '\n\n';
+      case 'UL': case 'OL': {
+        const d = depth + 1;
+        let n = 0;
+        const items = Array.from(el.children).filter((c) => c.tagName === 'LI').map((li) => {
+          const bullet = tag === 'OL' ? `${++n}.` : '-';
+          const body = Array.from(li.childNodes).map((c) => md(c, d)).join('')
+            .replace(/\n{2,}/g, '\n').trim();
+          return `${'  '.repeat(d)}${bullet} ${body}`;
+        });
+        return d > 0 ? `\n${items.join('\n')}\n` : `\n\n${items.join('\n')}\n\n`;
+      }
+      case 'TABLE': {
+        const rows = Array.from(el.rows).map((r) => Array.from(r.cells).map(cell));
+        const width = Math.max(0, ...rows.map((r) => r.length));
+        // One-column or nested tables are layout, not data.
+        if (width < 2 || el.querySelector('table')) return `\n\n${kids()}\n\n`;
+        const line = (r) => `| ${[...r, ...Array(width - r.length).fill('')].join(' | ')} |`;
+        return `\n\n${line(rows[0])}\n|${' --- |'.repeat(width)}\n${rows.slice(1).map(line).join('\n')}\n\n`;
+      }
+      default: {
+        const display = getComputedStyle(el).display;
+        const t = kids();
+        return display === 'inline' || display === 'inline-block' ? t : `\n\n${t}\n\n`;
+      }
+    }
+  };
+
+  let fenced = false;
+  let body = md(root)
+    .split('\n')
+    .map((l) => {
+      if (l.startsWith('```')) fenced = !fenced;
+      if (fenced || l.startsWith('```')) return l;
+      // Keep list indentation; drop stray leading spaces that would start a code block.
+      return /^ *(-|\d+\.) /.test(l) ? l.trimEnd() : l.trim();
+    })
+    .join('\n')
+    .replace(/\n{3,}/g, '\n\n')
+    .trim();
+  const title = (document.title || '').trim();
+  if (title && !body.startsWith('# ')) body = `# ${title}\n\n${body}`;
+  return JSON.stringify({ title, markdown: body + '\n' });
+})"#;
+
+#[derive(Deserialize)]
+struct Extracted {
+    markdown: String,
+}
+
+/// Convert the main content of the current document to Markdown and write it to `path`.
+pub fn write_markdown(tab: &headless_chrome::Tab, path: &Path) -> Result<()> {
+    let raw = tab
+        .evaluate(&format!("{}({})", MARKDOWN_JS, MAIN_CONTENT_JS), false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .ok_or_else(|| anyhow::anyhow!("markdown conversion returned nothing"))?;
+    let extracted: Extracted = serde_json::from_str(&raw)?;
+    std::fs::write(path, extracted.markdown)?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Video and streaming media found on the rendered page (metadata only, nothing is downloaded).
+
+use crate::network::NetEntry;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Default)]
+pub struct Media {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub videos: Vec<Video>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<Stream>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<Embed>,
+}
+
+impl Media {
+    pub fn is_empty(&self) -> bool {
+        self.videos.is_empty() && self.streams.is_empty() && self.embeds.is_empty()
+    }
+}
+
+/// A `<video>` element. `duration_s` is absent until the browser has loaded metadata
+/// (and for live streams).
+#[derive(Serialize, Deserialize)]
+pub struct Video {
+    pub src: Option<String>,
+    pub sources: Vec<String>,
+    pub poster: Option<String>,
+    pub duration_s: Option<f64>,
+    pub width: u64,
+    pub height: u64,
+}
+
+/// An adaptive-streaming manifest requested by the page.
+#[derive(Serialize)]
+pub struct Stream {
+    pub url: String,
+    /// `hls` or `dash`.
+    pub kind: &'static str,
+    pub status: Option<u16>,
+}
+
+/// A third-party player iframe.
+#[derive(Serialize, Deserialize)]
+pub struct Embed {
+    pub provider: String,
+    pub video_id: Option<String>,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct DomMedia {
+    videos: Vec<Video>,
+    embeds: Vec<Embed>,
+}
+
+const MEDIA_JS: &str = r#"(() => {
+  const abs = (u) => { try { return u ? new URL(u, document.baseURI).href : null; } catch (e) { return null; } };
+  const videos = Array.from(document.querySelectorAll('video')).map(v => ({
+    src: abs(v.currentSrc || v.getAttribute('src')),
+    sources: Array.from(v.querySelectorAll('source')).map(s => abs(s.getAttribute('src'))).filter(Boolean),
+    poster: abs(v.getAttribute('poster')),
+    duration_s: Number.isFinite(v.duration) ? v.duration : null,
+    width: v.videoWidth || v.clientWidth || 0,
+    height: v.videoHeight || v.clientHeight || 0,
+  }));
+  const embeds = [];
+  for (const f of document.querySelectorAll('iframe[src]')) {
+    let u;
+    try { u = new URL(f.src, document.baseURI); } catch (e) { continue; }
+    const host = u.hostname.replace(/^www\./, '');
+    let provider = null, id = null;
+    if (/(^|\.)youtube(-nocookie)?\.com$/.test(host)) {
+      provider = 'youtube';
+      const m = u.pathname.match(/\/embed\/([^/?#]+)/);
+      id = m ? m[1] : u.searchParams.get('v');
+    } else if (host === 'youtu.be') {
+      provider = 'youtube';
+      id = u.pathname.slice(1) || null;
+    } else if (/(^|\.)vimeo\.com$/.test(host)) {
+      provider = 'vimeo';
+      const m = u.pathname.match(/\/video\/(\d+)/) || u.pathname.match(/^\/(\d+)/);
+      id = m ? m[1] : null;
+    }
+    if (provider) embeds.push({ provider, video_id: id, url: u.href });
+  }
+  return JSON.stringify({ videos, embeds });
+})()"#;
+
+/// Collect `<video>` elements and YouTube/Vimeo embeds from the DOM, plus HLS/DASH
+/// manifests seen on the network.
+pub fn detect(tab: &headless_chrome::Tab, entries: &[NetEntry]) -> Result<Media> {
+    let raw = tab
+        .evaluate(MEDIA_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| r#"{"videos":[],"embeds":[]}"#.to_string());
+    let dom: DomMedia = serde_json::from_str(&raw)?;
+
+    let mut streams: Vec<Stream> = Vec::new();
+    for e in entries {
+        let mime = e.response.as_ref().map(|r| r.mime_type.as_str());
+        let Some(kind) = stream_kind(&e.request.url, mime) else {
+            continue;
+        };
+        if streams.iter().any(|s| s.url == e.request.url) {
+            continue;
+        }
+        streams.push(Stream {
+            url: e.request.url.clone(),
+            kind,
+            status: e.response.as_ref().map(|r| r.status as u16),
+        });
+    }
+
+    Ok(Media {
+        videos: dom.videos,
+        streams,
+        embeds: dom.embeds,
+    })
+}
+
+fn stream_kind(url: &str, mime: Option<&str>) -> Option<&'static str> {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let mime = mime.unwrap_or("").to_ascii_lowercase();
+    if path.ends_with(".m3u8") || mime.contains("mpegurl") {
+        Some("hls")
+    } else if path.ends_with(".mpd") || mime.contains("dash+xml") {
+        Some("dash")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,361 @@
+//! `--merge-pdf`: one PDF for a whole crawl, for handing over as a single report.
+//!
+//! The page PDFs are concatenated in crawl order behind generated cover/contents pages.
+//! Each contents entry links to its page's first sheet, and every page also gets a
+//! bookmark.
+
+use anyhow::{bail, Context, Result};
+use lopdf::{
+    content::{Content, Operation},
+    dictionary, Dictionary, Document, Object, ObjectId, Stream,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// One captured page to include.
+pub struct Part {
+    pub url: String,
+    pub pdf: PathBuf,
+}
+
+/// A loaded page PDF, renumbered into the combined document's id space.
+struct Loaded {
+    url: String,
+    title: String,
+    pages: Vec<ObjectId>,
+}
+
+const PAGE_W: f32 = 612.0;
+const PAGE_H: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+/// Vertical space of one contents entry (title line plus URL line).
+const ENTRY_H: f32 = 30.0;
+/// Space the heading takes on the first contents page.
+const HEADING_H: f32 = 110.0;
+
+/// Write `parts` into `out` behind a cover titled `heading`. PDFs that can't be read are
+/// skipped with a warning. Returns how many pages were merged.
+pub fn merge(parts: &[Part], heading: &str, out: &Path) -> Result<usize> {
+    let mut merged = Document::with_version("1.7");
+    let mut next_id = 1;
+    let mut loaded = Vec::new();
+    for part in parts {
+        let mut doc = match Document::load(&part.pdf) {
+            Ok(doc) if !doc.is_encrypted() => doc,
+            Ok(_) => {
+                eprintln!("warning: not merging encrypted {}", part.pdf.display());
+                continue;
+            }
+            Err(e) => {
+                eprintln!("warning: not merging {}: {}", part.pdf.display(), e);
+                continue;
+            }
+        };
+        doc.renumber_objects_with(next_id);
+        next_id = doc.max_id + 1;
+        let title = info_title(&doc).unwrap_or_else(|| part.url.clone());
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        for &page in &pages {
+            inline_inherited(&mut doc, page);
+        }
+        // Keep everything except the old document structure; the pages are re-parented.
+        for (id, obj) in doc.objects {
+            let kind = obj
+                .as_dict()
+                .ok()
+                .and_then(|d| d.get(b"Type").ok())
+                .and_then(|t| t.as_name().ok());
+            if !matches!(kind, Some(b"Catalog" | b"Pages" | b"Outlines")) {
+                merged.objects.insert(id, obj);
+            }
+        }
+        loaded.push(Loaded {
+            url: part.url.clone(),
+            title,
+            pages,
+        });
+    }
+    if loaded.is_empty() {
+        bail!("no page PDFs to merge");
+    }
+    merged.max_id = next_id - 1;
+
+    let pages_id = merged.new_object_id();
+    let font = merged.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let bold = merged.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica-Bold",
+        "Encoding" => "WinAnsiEncoding",
+    });
+
+    // Contents pages come first, so page numbers start after them.
+    let first_page_entries = ((PAGE_H - 2.0 * MARGIN - HEADING_H) / ENTRY_H) as usize;
+    let later_page_entries = ((PAGE_H - 2.0 * MARGIN) / ENTRY_H) as usize;
+    let toc_pages = 1 + loaded
+        .len()
+        .saturating_sub(first_page_entries)
+        .div_ceil(later_page_entries);
+    let mut page_no = toc_pages + 1;
+    let mut starts = Vec::new();
+    for l in &loaded {
+        starts.push(page_no);
+        page_no += l.pages.len();
+    }
+
+    let mut kids = Vec::new();
+    let mut entries = loaded.iter().zip(&starts).enumerate();
+    for sheet in 0..toc_pages {
+        let mut ops = Vec::new();
+        let mut annots = Vec::new();
+        let mut y = PAGE_H - MARGIN;
+        if sheet == 0 {
+            text(
+                &mut ops,
+                "F2",
+                20.0,
+                MARGIN,
+                y - 20.0,
+                0.0,
+                &fit(heading, 20.0),
+            );
+            let generated = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+            let sub = format!("{} captured pages, generated {}", loaded.len(), generated);
+            text(&mut ops, "F1", 10.0, MARGIN, y - 40.0, 0.4, &sub);
+            text(&mut ops, "F2", 13.0, MARGIN, y - 80.0, 0.0, "Contents");
+            y -= HEADING_H;
+        }
+        while y - ENTRY_H >= MARGIN - 1.0 {
+            let Some((i, (l, &start))) = entries.next() else {
+                break;
+            };
+            let num = start.to_string();
+            let label = fit(&format!("{}. {}", i + 1, l.title), 11.0 * 0.9);
+            text(&mut ops, "F1", 11.0, MARGIN, y - 12.0, 0.0, &label);
+            let num_x = PAGE_W - MARGIN - 0.55 * 11.0 * num.len() as f32;
+            text(&mut ops, "F1", 11.0, num_x, y - 12.0, 0.0, &num);
+            text(
+                &mut ops,
+                "F1",
+                8.0,
+                MARGIN + 14.0,
+                y - 24.0,
+                0.45,
+                &fit(&l.url, 8.0),
+            );
+            annots.push(Object::Reference(merged.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                "Rect" => vec![MARGIN.into(), (y - ENTRY_H).into(), (PAGE_W - MARGIN).into(), y.into()],
+                "Border" => vec![0.into(), 0.into(), 0.into()],
+                "Dest" => vec![Object::Reference(l.pages[0]), "Fit".into()],
+            })));
+            y -= ENTRY_H;
+        }
+        let content = merged.add_object(Stream::new(
+            dictionary! {},
+            Content { operations: ops }.encode()?,
+        ));
+        kids.push(Object::Reference(merged.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), PAGE_W.into(), PAGE_H.into()],
+            "Resources" => dictionary! {
+                "Font" => dictionary! { "F1" => font, "F2" => bold },
+            },
+            "Contents" => content,
+            "Annots" => annots,
+        })));
+    }
+    for l in &loaded {
+        for &page in &l.pages {
+            if let Ok(dict) = merged.get_object_mut(page).and_then(Object::as_dict_mut) {
+                dict.set("Parent", pages_id);
+                kids.push(Object::Reference(page));
+            }
+        }
+    }
+    let count = kids.len();
+    merged.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count as i64,
+        }),
+    );
+
+    let outlines = outlines(&mut merged, &loaded);
+    let catalog = merged.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Outlines" => outlines,
+        "PageMode" => "UseOutlines",
+    });
+    merged.trailer.set("Root", catalog);
+    let now = chrono::Utc::now()
+        .format("D:%Y%m%d%H%M%S+00'00'")
+        .to_string();
+    let info = merged.add_object(dictionary! {
+        "Title" => crate::pdf::text_string(heading),
+        "Creator" => Object::string_literal("ankabot"),
+        "CreationDate" => Object::string_literal(now.clone()),
+        "ModDate" => Object::string_literal(now),
+    });
+    merged.trailer.set("Info", info);
+
+    if let Some(dir) = out.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    // The old catalogs, page trees and info dictionaries are no longer referenced.
+    merged.prune_objects();
+    merged.compress();
+    merged
+        .save(out)
+        .with_context(|| format!("writing {}", out.display()))?;
+    Ok(loaded.len())
+}
+
+/// One top-level bookmark per merged page, pointing at its first sheet.
+fn outlines(doc: &mut Document, loaded: &[Loaded]) -> ObjectId {
+    let root = doc.new_object_id();
+    let ids: Vec<ObjectId> = loaded.iter().map(|_| doc.new_object_id()).collect();
+    for (i, (l, &id)) in loaded.iter().zip(&ids).enumerate() {
+        let mut item = dictionary! {
+            "Title" => crate::pdf::text_string(&l.title),
+            "Parent" => root,
+            "Dest" => vec![Object::Reference(l.pages[0]), "Fit".into()],
+        };
+        if i > 0 {
+            item.set("Prev", ids[i - 1]);
+        }
+        if let Some(&next) = ids.get(i + 1) {
+            item.set("Next", next);
+        }
+        doc.objects.insert(id, Object::Dictionary(item));
+    }
+    doc.objects.insert(
+        root,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => ids[0],
+            "Last" => ids[ids.len() - 1],
+            "Count" => ids.len() as i64,
+        }),
+    );
+    root
+}
+
+/// The document title Chrome (or `--pdf-title`) put in the info dictionary.
+fn info_title(doc: &Document) -> Option<String> {
+    let info = doc
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .ok()?;
+    match info.get(b"Title") {
+        Ok(Object::String(bytes, _)) => {
+            Some(crate::pdf::decode_text(bytes)).filter(|t| !t.trim().is_empty())
+        }
+        _ => None,
+    }
+}
+
+/// Copy the attributes a page inherits from its page tree onto the page itself, since the
+/// tree it inherits them from is dropped.
+fn inline_inherited(doc: &mut Document, page: ObjectId) {
+    const INHERITED: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+    let mut found: Dictionary = Dictionary::new();
+    let mut node = doc
+        .get_dictionary(page)
+        .ok()
+        .and_then(|d| d.get(b"Parent").and_then(Object::as_reference).ok());
+    while let Some(id) = node {
+        let Ok(dict) = doc.get_dictionary(id) else {
+            break;
+        };
+        for key in INHERITED {
+            if let (false, Ok(v)) = (found.has(key), dict.get(key)) {
+                found.set(key, v.clone());
+            }
+        }
+        node = dict.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    if let Ok(dict) = doc.get_object_mut(page).and_then(Object::as_dict_mut) {
+        for (key, value) in found {
+            if !dict.has(&key) {
+                dict.set(key, value);
+            }
+        }
+    }
+}
+
+/// Append a line of Helvetica text at (`x`, `y`) in `gray` (0 black).
+fn text(ops: &mut Vec<Operation>, font: &str, size: f32, x: f32, y: f32, gray: f32, s: &str) {
+    // The standard fonts only cover WinAnsi (roughly Latin-1).
+    let bytes: Vec<u8> = s
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect();
+    ops.extend([
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec![font.into(), size.into()]),
+        Operation::new("g", vec![gray.into()]),
+        Operation::new("Td", vec![x.into(), y.into()]),
+        Operation::new("Tj", vec![Object::string_literal(bytes)]),
+        Operation::new("ET", vec![]),
+    ]);
+}
+
+/// Shorten `s` to fit the line, assuming Helvetica's average advance of about half the
+/// font size.
+fn fit(s: &str, size: f32) -> String {
+    let max = ((PAGE_W - 2.0 * MARGIN - 40.0) / (0.5 * size)) as usize;
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if s.chars().count() <= max {
+        return s;
+    }
+    let cut: String = s.chars().take(max.saturating_sub(3)).collect();
+    format!("{}...", cut.trim_end())
+}
+
+/// Pages a crawl rendered with a PDF, in crawl order: the run directories are numbered as
+/// pages are claimed (`0001-…`).
+pub fn crawl_parts(visited: &[(String, String)]) -> Vec<Part> {
+    let mut parts: BTreeMap<u64, Part> = BTreeMap::new();
+    for (url, run_dir) in visited {
+        let dir = Path::new(run_dir);
+        let Some(seq) = dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.split('-').next())
+            .and_then(|n| n.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        let Ok(result) = std::fs::read(dir.join("result.json")) else {
+            continue;
+        };
+        let result: serde_json::Value = serde_json::from_slice(&result).unwrap_or_default();
+        // Only captures (not timeout or block reports) carry a top-level pdf_path.
+        let Some(pdf) = result.get("pdf_path").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        if Path::new(pdf).is_file() {
+            parts.insert(
+                seq,
+                Part {
+                    url: url.clone(),
+                    pdf: PathBuf::from(pdf),
+                },
+            );
+        }
+    }
+    parts.into_values().collect()
+}
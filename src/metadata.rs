@@ -0,0 +1,61 @@
+//! Page metadata for `--mode extract`: title, description, canonical URL, language and the
+//! Open Graph / Twitter card properties, read from the rendered document (so tags added by
+//! scripts count).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    /// The `<html lang>` attribute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// `article:published_time`, else the first `<time datetime>` in an article.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    /// `og:*` and `twitter:*` properties by name; the first wins when one repeats.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub social: BTreeMap<String, String>,
+}
+
+const METADATA_JS: &str = r#"(() => {
+  const meta = (sel) => {
+    const v = document.querySelector(sel)?.getAttribute('content');
+    return v && v.trim() ? v.trim() : null;
+  };
+  const social = {};
+  document.querySelectorAll('meta[property], meta[name]').forEach((m) => {
+    const key = (m.getAttribute('property') || m.getAttribute('name') || '').trim();
+    const value = (m.getAttribute('content') || '').trim();
+    if (/^(og|twitter):/i.test(key) && value && !(key in social)) social[key] = value;
+  });
+  const canonical = document.querySelector('link[rel~="canonical"]');
+  return JSON.stringify({
+    title: document.title.trim() || null,
+    description: meta('meta[name="description" i]') || meta('meta[property="og:description"]'),
+    canonical: canonical ? canonical.href : null,
+    lang: document.documentElement.lang || null,
+    author: meta('meta[name="author" i]') || meta('meta[property="article:author"]'),
+    published: meta('meta[property="article:published_time"]')
+      || document.querySelector('article time[datetime]')?.getAttribute('datetime') || null,
+    social,
+  });
+})()"#;
+
+pub fn collect(tab: &headless_chrome::Tab) -> Result<Metadata> {
+    let json = tab
+        .evaluate(METADATA_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "{}".to_string());
+    Ok(serde_json::from_str(&json)?)
+}
@@ -0,0 +1,187 @@
+//! Mixed content: plain `http://` (and `ws://`) subresources requested by an `https://`
+//! page, listed as `mixed_content` in result.json.
+//!
+//! Chrome blocks the active kinds (scripts, frames, XHR) and upgrades most passive ones
+//! (images, media) to HTTPS before requesting them; whatever still went out as plain
+//! HTTP, or was blocked trying, is listed.
+
+use crate::network::NetEntry;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MixedContent {
+    pub url: String,
+    pub resource_type: String,
+    /// Chrome's classification: `blockable` (active) or `optionally-blockable` (passive).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// The request failed, usually because Chrome blocked it.
+    pub blocked: bool,
+}
+
+/// The insecure requests among `entries` when `page_url` is HTTPS.
+pub fn detect(entries: &[NetEntry], page_url: &str) -> Vec<MixedContent> {
+    if !page_url.starts_with("https://") {
+        return Vec::new();
+    }
+    // The navigation itself may start at http:// and redirect; that isn't mixed content.
+    let main_frame = entries
+        .iter()
+        .find(|e| e.resource_type == "Document")
+        .and_then(|e| e.frame_id.clone());
+    entries
+        .iter()
+        .filter(|e| !(e.resource_type == "Document" && e.frame_id == main_frame))
+        .filter(|e| {
+            let url = e.request.url.to_ascii_lowercase();
+            url.starts_with("http://") || url.starts_with("ws://")
+        })
+        .map(|e| MixedContent {
+            url: e.request.url.clone(),
+            resource_type: e.resource_type.clone(),
+            kind: e
+                .request
+                .mixed_content_Type
+                .as_ref()
+                .and_then(|t| serde_json::to_value(t).ok())
+                .and_then(|v| v.as_str().map(str::to_string))
+                .filter(|k| k != "none"),
+            blocked: e.failed.is_some(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(kind: &str, url: &str, frame: &str, mixed: Option<&str>) -> NetEntry {
+        let mut request = json!({
+            "url": url,
+            "method": "GET",
+            "headers": {},
+            "initialPriority": "Low",
+            "referrerPolicy": "strict-origin-when-cross-origin",
+        });
+        if let Some(m) = mixed {
+            request["mixedContentType"] = json!(m);
+        }
+        NetEntry {
+            request_id: url.into(),
+            resource_type: kind.into(),
+            frame_id: Some(frame.into()),
+            request: serde_json::from_value(request).unwrap(),
+            response: None,
+            start: 0.0,
+            wall_time: 0.0,
+            end: Some(1.0),
+            encoded_bytes: 0.0,
+            decoded_bytes: 0.0,
+            from_cache: false,
+            failed: None,
+        }
+    }
+
+    fn blocked(mut e: NetEntry) -> NetEntry {
+        e.failed = Some("mixed-content".into());
+        e
+    }
+
+    #[test]
+    fn insecure_subresources_are_classified() {
+        let page = "https://example.com/";
+        for (e, want) in [
+            // Active content: Chrome blocks it.
+            (
+                blocked(entry(
+                    "Script",
+                    "http://cdn.example.com/a.js",
+                    "F1",
+                    Some("blockable"),
+                )),
+                Some((Some("blockable"), true)),
+            ),
+            (
+                blocked(entry(
+                    "XHR",
+                    "http://api.example.com/data",
+                    "F1",
+                    Some("blockable"),
+                )),
+                Some((Some("blockable"), true)),
+            ),
+            (
+                blocked(entry(
+                    "WebSocket",
+                    "ws://live.example.com/",
+                    "F1",
+                    Some("blockable"),
+                )),
+                Some((Some("blockable"), true)),
+            ),
+            // Passive content that still went out over plain HTTP.
+            (
+                entry(
+                    "Image",
+                    "HTTP://img.example.com/a.png",
+                    "F1",
+                    Some("optionally-blockable"),
+                ),
+                Some((Some("optionally-blockable"), false)),
+            ),
+            (
+                entry("Media", "http://media.example.com/a.mp4", "F1", None),
+                Some((None, false)),
+            ),
+            (
+                entry("Image", "http://img.example.com/b.png", "F1", Some("none")),
+                Some((None, false)),
+            ),
+            // Upgraded to HTTPS before it was requested: not mixed.
+            (
+                entry(
+                    "Image",
+                    "https://img.example.com/a.png",
+                    "F1",
+                    Some("optionally-blockable"),
+                ),
+                None,
+            ),
+            (
+                entry("Stylesheet", "https://example.com/a.css", "F1", None),
+                None,
+            ),
+            (
+                entry("Image", "data:image/png;base64,AAAA", "F1", None),
+                None,
+            ),
+            // An insecure iframe document is mixed; only the main frame's navigation isn't.
+            (
+                blocked(entry(
+                    "Document",
+                    "http://ads.example.net/",
+                    "F2",
+                    Some("blockable"),
+                )),
+                Some((Some("blockable"), true)),
+            ),
+        ] {
+            let url = e.request.url.clone();
+            let main = entry("Document", "https://example.com/", "F1", None);
+            let got = detect(&[main, e], page);
+            let got = got.first().map(|m| (m.kind.as_deref(), m.blocked));
+            assert_eq!(got, want, "{url}");
+        }
+    }
+
+    #[test]
+    fn http_pages_and_redirected_navigations_are_not_mixed() {
+        let redirect = entry("Document", "http://example.com/", "F1", None);
+        let image = entry("Image", "http://img.example.com/a.png", "F1", None);
+        assert!(detect(&[redirect.clone(), image.clone()], "http://example.com/").is_empty());
+        let found = detect(&[redirect, image], "https://example.com/");
+        let urls: Vec<&str> = found.iter().map(|m| m.url.as_str()).collect();
+        assert_eq!(urls, ["http://img.example.com/a.png"]);
+    }
+}
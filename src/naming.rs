@@ -0,0 +1,221 @@
+//! `--run-name-template` and `--artifact-name`: run directory and artifact file names built
+//! from placeholders, so output can follow an existing storage layout.
+//!
+//! Placeholders: `{host}`, `{date}` (2024-05-31), `{time}` (142501), `{timestamp}`
+//! (20240531-142501, the default run name's), `{hash}` (first 12 hex digits of the URL's
+//! sha256), `{slug}` (the URL reduced to `[A-Za-z0-9-]`) and, in screenshot names, `{ext}`.
+
+use sha2::{Digest, Sha256};
+
+const PLACEHOLDERS: &[&str] = &["host", "date", "time", "timestamp", "hash", "slug", "ext"];
+
+#[derive(Clone, Debug)]
+pub struct Template(String);
+
+/// Values for one run.
+pub struct Vars {
+    host: String,
+    date: String,
+    time: String,
+    timestamp: String,
+    hash: String,
+    slug: String,
+}
+
+impl Vars {
+    pub fn new(url: &str) -> Self {
+        let now = chrono::Local::now();
+        let digest = Sha256::digest(url.as_bytes());
+        Self {
+            host: url::Url::parse(url)
+                .ok()
+                .and_then(|u| u.host_str().map(|h| h.to_string()))
+                .unwrap_or_else(|| "page".into()),
+            date: now.format("%Y-%m-%d").to_string(),
+            time: now.format("%H%M%S").to_string(),
+            timestamp: now.format("%Y%m%d-%H%M%S").to_string(),
+            hash: digest[..6].iter().map(|b| format!("{b:02x}")).collect(),
+            slug: crate::crawl::slug(url),
+        }
+    }
+}
+
+impl Template {
+    /// The template with its placeholders filled in; `ext` is the screenshot extension.
+    pub fn render(&self, vars: &Vars, ext: &str) -> String {
+        let mut out = String::new();
+        let mut rest = self.0.as_str();
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let end = rest[start..].find('}').map_or(rest.len(), |e| start + e);
+            out.push_str(match &rest[start + 1..end] {
+                "host" => &vars.host,
+                "date" => &vars.date,
+                "time" => &vars.time,
+                "timestamp" => &vars.timestamp,
+                "hash" => &vars.hash,
+                "slug" => &vars.slug,
+                "ext" => ext,
+                _ => "",
+            });
+            rest = &rest[(end + 1).min(rest.len())..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// A run directory template, relative to `--out-root`; `/` makes nested directories.
+pub fn parse_run_template(s: &str) -> Result<Template, String> {
+    check(s)?;
+    if s.starts_with('/') || s.split('/').any(|c| c.is_empty() || c == "." || c == "..") {
+        return Err("the run name must be a relative path without empty, . or .. parts".into());
+    }
+    Ok(Template(s.to_string()))
+}
+
+/// The artifacts `--artifact-name` can rename.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Named {
+    Html,
+    Screenshot,
+    Pdf,
+    Mhtml,
+    Markdown,
+    Text,
+    Epub,
+}
+
+/// `KIND=TEMPLATE`, e.g. `pdf={host}-{date}.pdf`.
+pub fn parse_artifact_name(s: &str) -> Result<(Named, Template), String> {
+    let (kind, template) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KIND=TEMPLATE, got '{s}'"))?;
+    let kind = match kind.trim() {
+        "html" => Named::Html,
+        "screenshot" => Named::Screenshot,
+        "pdf" => Named::Pdf,
+        "mhtml" => Named::Mhtml,
+        "markdown" => Named::Markdown,
+        "text" => Named::Text,
+        "epub" => Named::Epub,
+        other => {
+            return Err(format!(
+                "unknown artifact '{other}' (html, screenshot, pdf, mhtml, markdown, text, epub)"
+            ))
+        }
+    };
+    check(template)?;
+    if template.is_empty() || template.contains(['/', '\\']) || template.starts_with('.') {
+        return Err("an artifact name must be a plain file name".into());
+    }
+    Ok((kind, Template(template.to_string())))
+}
+
+/// Reject unknown or unclosed placeholders.
+fn check(s: &str) -> Result<(), String> {
+    let mut rest = s;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed placeholder in '{s}'"))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "unknown placeholder {{{name}}} (use {})",
+                PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{p}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vars {
+        Vars {
+            host: "example.com".into(),
+            date: "2024-05-31".into(),
+            time: "142501".into(),
+            timestamp: "20240531-142501".into(),
+            hash: "0123456789ab".into(),
+            slug: "https-example-com-a".into(),
+        }
+    }
+
+    #[test]
+    fn render_fills_every_placeholder() {
+        let t = parse_run_template("{host}/{date}/{time}-{hash}").unwrap();
+        assert_eq!(
+            t.render(&vars(), "png"),
+            "example.com/2024-05-31/142501-0123456789ab"
+        );
+        let (_, t) = parse_artifact_name("screenshot={slug}-{timestamp}.{ext}").unwrap();
+        assert_eq!(
+            t.render(&vars(), "webp"),
+            "https-example-com-a-20240531-142501.webp"
+        );
+    }
+
+    #[test]
+    fn render_keeps_text_without_placeholders() {
+        let t = parse_run_template("static/name").unwrap();
+        assert_eq!(t.render(&vars(), "png"), "static/name");
+    }
+
+    #[test]
+    fn unclosed_placeholder_is_rejected() {
+        let err = parse_run_template("{host}/{date").unwrap_err();
+        assert!(err.contains("unclosed placeholder"), "{err}");
+        assert!(parse_artifact_name("pdf={host.pdf").is_err());
+    }
+
+    #[test]
+    fn unknown_placeholder_is_rejected_with_the_known_ones() {
+        let err = parse_run_template("{host}/{user}").unwrap_err();
+        assert!(err.starts_with("unknown placeholder {user}"), "{err}");
+        assert!(err.contains("{timestamp}"), "{err}");
+    }
+
+    #[test]
+    fn run_template_must_stay_relative() {
+        for bad in ["/abs/{host}", "a/../b", "./{host}", "a//b", "{host}/", ".."] {
+            assert!(parse_run_template(bad).is_err(), "{bad} accepted");
+        }
+        assert!(parse_run_template("sites/{host}/{timestamp}").is_ok());
+    }
+
+    #[test]
+    fn artifact_name_parses_kind_and_plain_file_name() {
+        let (kind, t) = parse_artifact_name("pdf={host}-{date}.pdf").unwrap();
+        assert_eq!(kind, Named::Pdf);
+        assert_eq!(t.render(&vars(), "png"), "example.com-2024-05-31.pdf");
+        assert_eq!(
+            parse_artifact_name(" html =page.html").unwrap().0,
+            Named::Html
+        );
+
+        assert!(parse_artifact_name("pdf")
+            .unwrap_err()
+            .contains("KIND=TEMPLATE"));
+        assert!(parse_artifact_name("video=x.mp4")
+            .unwrap_err()
+            .starts_with("unknown artifact 'video'"));
+        for bad in [
+            "pdf=",
+            "pdf=dir/x.pdf",
+            "pdf=..\\x.pdf",
+            "pdf=.hidden",
+            "pdf=../x",
+        ] {
+            assert!(parse_artifact_name(bad).is_err(), "{bad} accepted");
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Navigation timing: the main document's `PerformanceNavigationTiming` entry, read after
+//! readiness, as `diagnostics.navigation` in result.json.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Milliseconds since navigation start. An event that hasn't happened yet (a load event
+/// still pending when readiness came from the heuristics) is left out.
+#[derive(Serialize, Debug, Clone)]
+pub struct NavigationTiming {
+    /// `navigate`, `reload`, `back_forward` or `prerender`.
+    #[serde(rename = "type")]
+    pub nav_type: String,
+    pub redirect_count: u32,
+    pub ttfb_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dom_content_loaded_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_event_end_ms: Option<u64>,
+    /// Document bytes over the network, headers included; 0 when served from cache.
+    pub transfer_size: u64,
+    pub encoded_body_size: u64,
+    pub decoded_body_size: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Raw {
+    #[serde(rename = "type", default)]
+    nav_type: String,
+    #[serde(default)]
+    redirect_count: u32,
+    #[serde(default)]
+    response_start: f64,
+    #[serde(default)]
+    dom_content_loaded_event_end: f64,
+    #[serde(default)]
+    load_event_end: f64,
+    #[serde(default)]
+    transfer_size: f64,
+    #[serde(default)]
+    encoded_body_size: f64,
+    #[serde(default)]
+    decoded_body_size: f64,
+}
+
+const COLLECT_JS: &str = "JSON.stringify(performance.getEntriesByType('navigation')[0] || null)";
+
+pub fn collect(tab: &headless_chrome::Tab) -> Result<NavigationTiming> {
+    let raw = tab
+        .evaluate(COLLECT_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+    let raw: Option<Raw> = serde_json::from_str(&raw)?;
+    let raw = raw.context("no navigation timing entry")?;
+    let at = |v: f64| (v > 0.0).then_some(v as u64);
+    Ok(NavigationTiming {
+        nav_type: raw.nav_type.replace('-', "_"),
+        redirect_count: raw.redirect_count,
+        ttfb_ms: raw.response_start as u64,
+        dom_content_loaded_ms: at(raw.dom_content_loaded_event_end),
+        load_event_end_ms: at(raw.load_event_end),
+        transfer_size: raw.transfer_size as u64,
+        encoded_body_size: raw.encoded_body_size as u64,
+        decoded_body_size: raw.decoded_body_size as u64,
+    })
+}
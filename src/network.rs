@@ -0,0 +1,941 @@
+//! CDP Network event capture for the Chrome path and the artifacts derived from it.
+
+use anyhow::Result;
+use headless_chrome::protocol::cdp::{types::Event, Fetch, Network};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    path::Path,
+    sync::{Arc, Condvar, Mutex},
+    time::Instant,
+};
+
+/// One request/response exchange; a redirect hop is recorded as its own entry.
+#[derive(Clone)]
+pub struct NetEntry {
+    pub request_id: String,
+    pub resource_type: String,
+    /// Frame that made the request.
+    pub frame_id: Option<String>,
+    pub request: Network::Request,
+    pub response: Option<Network::Response>,
+    /// Monotonic timestamp (seconds) of requestWillBeSent.
+    pub start: f64,
+    /// Wall-clock time (seconds since the epoch) of requestWillBeSent.
+    pub wall_time: f64,
+    /// Monotonic timestamp (seconds) of loadingFinished/loadingFailed.
+    pub end: Option<f64>,
+    pub encoded_bytes: f64,
+    /// Decoded body bytes, summed from dataReceived.
+    pub decoded_bytes: f64,
+    pub from_cache: bool,
+    pub failed: Option<String>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: Vec<NetEntry>,
+    by_id: HashMap<String, usize>,
+}
+
+#[derive(Clone)]
+pub struct NetworkCapture {
+    state: Arc<Mutex<State>>,
+    /// Signalled after every recorded event.
+    changed: Arc<Condvar>,
+}
+
+impl NetworkCapture {
+    /// Enable the Network domain on `tab` and start recording its events.
+    pub fn attach(tab: &headless_chrome::Tab) -> Result<Self> {
+        tab.call_method(Network::Enable {
+            max_total_buffer_size: None,
+            max_resource_buffer_size: None,
+            max_post_data_size: None,
+        })?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let changed = Arc::new(Condvar::new());
+        let (sink, notify) = (state.clone(), changed.clone());
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            sink.lock().unwrap().record(event);
+            notify.notify_all();
+        }))?;
+        Ok(Self { state, changed })
+    }
+
+    /// Snapshot of all entries in request order.
+    pub fn entries(&self) -> Vec<NetEntry> {
+        self.state.lock().unwrap().entries.clone()
+    }
+
+    /// Number of entries recorded so far.
+    pub fn recorded(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Block until `done` holds for the entries recorded from index `from` on, or until
+    /// `deadline`. `done` is re-run as network events arrive, not on a timer. Returns
+    /// whether it held.
+    pub fn wait_until(
+        &self,
+        from: usize,
+        deadline: Instant,
+        mut done: impl FnMut(&[NetEntry]) -> bool,
+    ) -> bool {
+        let mut st = self.state.lock().unwrap();
+        loop {
+            if done(st.entries.get(from..).unwrap_or_default()) {
+                return true;
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            st = self.changed.wait_timeout(st, left).unwrap().0;
+        }
+    }
+}
+
+impl State {
+    fn record(&mut self, event: &Event) {
+        match event {
+            Event::NetworkRequestWillBeSent(e) => {
+                let p = &e.params;
+                if let (Some(redirect), Some(&idx)) =
+                    (&p.redirect_response, self.by_id.get(&p.request_id))
+                {
+                    let prev = &mut self.entries[idx];
+                    prev.response = Some(redirect.clone());
+                    prev.end = Some(p.timestamp);
+                }
+                let resource_type = p
+                    .Type
+                    .as_ref()
+                    .map(resource_type_name)
+                    .unwrap_or_else(|| "Other".to_string());
+                self.by_id.insert(p.request_id.clone(), self.entries.len());
+                self.entries.push(NetEntry {
+                    request_id: p.request_id.clone(),
+                    resource_type,
+                    frame_id: p.frame_id.clone(),
+                    request: p.request.clone(),
+                    response: None,
+                    start: p.timestamp,
+                    wall_time: p.wall_time,
+                    end: None,
+                    encoded_bytes: 0.0,
+                    decoded_bytes: 0.0,
+                    from_cache: false,
+                    failed: None,
+                });
+            }
+            Event::NetworkResponseReceived(e) => {
+                let p = &e.params;
+                if let Some(&idx) = self.by_id.get(&p.request_id) {
+                    let entry = &mut self.entries[idx];
+                    let r = &p.response;
+                    entry.from_cache |= r.from_disk_cache == Some(true)
+                        || r.from_prefetch_cache == Some(true)
+                        || r.from_service_worker == Some(true)
+                        || r.status == 304;
+                    entry.response = Some(r.clone());
+                    entry.resource_type = resource_type_name(&p.Type);
+                }
+            }
+            Event::NetworkRequestServedFromCache(e) => {
+                if let Some(&idx) = self.by_id.get(&e.params.request_id) {
+                    self.entries[idx].from_cache = true;
+                }
+            }
+            Event::NetworkDataReceived(e) => {
+                if let Some(&idx) = self.by_id.get(&e.params.request_id) {
+                    self.entries[idx].decoded_bytes += e.params.data_length as f64;
+                }
+            }
+            Event::NetworkLoadingFinished(e) => {
+                let p = &e.params;
+                if let Some(&idx) = self.by_id.get(&p.request_id) {
+                    let entry = &mut self.entries[idx];
+                    entry.end = Some(p.timestamp);
+                    entry.encoded_bytes = p.encoded_data_length;
+                }
+            }
+            Event::NetworkLoadingFailed(e) => {
+                let p = &e.params;
+                if let Some(&idx) = self.by_id.get(&p.request_id) {
+                    let entry = &mut self.entries[idx];
+                    entry.end = Some(p.timestamp);
+                    entry.failed = Some(p.error_text.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fail every request for one of `types` before it is sent (`--mode extract` drops images,
+/// fonts and media). The requests still show up in the capture, as failed.
+pub fn block_resources(tab: &headless_chrome::Tab, types: &[Network::ResourceType]) -> Result<()> {
+    use headless_chrome::browser::tab::RequestPausedDecision;
+    use headless_chrome::protocol::cdp::Fetch::{FailRequest, RequestPattern, RequestStage};
+
+    let patterns: Vec<RequestPattern> = types
+        .iter()
+        .map(|t| RequestPattern {
+            url_pattern: None,
+            resource_Type: Some(t.clone()),
+            request_stage: Some(RequestStage::Request),
+        })
+        .collect();
+    tab.enable_fetch(Some(&patterns), None)?;
+    tab.enable_request_interception(Arc::new(
+        |_, _, event: Fetch::events::RequestPausedEvent| {
+            RequestPausedDecision::Fail(FailRequest {
+                request_id: event.params.request_id,
+                error_reason: Network::ErrorReason::BlockedByClient,
+            })
+        },
+    ))?;
+    Ok(())
+}
+
+/// Fetch a response body that Chrome still holds in its network buffer.
+pub fn response_body(tab: &headless_chrome::Tab, request_id: &str) -> Result<Vec<u8>> {
+    use base64::Engine as _;
+    let body = tab.call_method(Network::GetResponseBody {
+        request_id: request_id.to_string(),
+    })?;
+    if body.base_64_encoded {
+        Ok(base64::engine::general_purpose::STANDARD.decode(body.body)?)
+    } else {
+        Ok(body.body.into_bytes())
+    }
+}
+
+/// Final response (after redirects) of the first top-level document requested at or after
+/// entry index `from`.
+pub fn document_response(entries: &[NetEntry], from: usize) -> Option<&Network::Response> {
+    let first = entries
+        .iter()
+        .skip(from)
+        .find(|e| e.resource_type == "Document")?;
+    entries
+        .iter()
+        .rev()
+        .find(|e| e.request_id == first.request_id)?
+        .response
+        .as_ref()
+}
+
+/// Response that delivered the top-level document now at `final_url`; the first document
+/// response when none matches (e.g. after a client-side redirect). Only documents of the
+/// main frame (the frame of the first document request) count, not iframes.
+pub fn final_document_response<'a>(
+    entries: &'a [NetEntry],
+    final_url: &str,
+) -> Option<&'a Network::Response> {
+    let main_frame = entries
+        .iter()
+        .find(|e| e.resource_type == "Document")?
+        .frame_id
+        .clone();
+    let docs = || {
+        entries
+            .iter()
+            .filter(|e| e.resource_type == "Document" && e.frame_id == main_frame)
+            .filter_map(|e| e.response.as_ref())
+    };
+    docs()
+        .rfind(|r| r.url == final_url)
+        .or_else(|| docs().next())
+}
+
+/// All headers of a CDP response as name/value pairs.
+pub fn response_headers(response: &Network::Response) -> Vec<(String, String)> {
+    response
+        .headers
+        .0
+        .as_ref()
+        .and_then(|h| h.as_object())
+        .map(|h| {
+            h.iter()
+                .map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Case-insensitive header lookup on a CDP response.
+pub fn response_header<'a>(response: &'a Network::Response, name: &str) -> Option<&'a str> {
+    response
+        .headers
+        .0
+        .as_ref()?
+        .as_object()?
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))?
+        .1
+        .as_str()
+}
+
+fn resource_type_name(t: &Network::ResourceType) -> String {
+    serde_json::to_value(t)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "Other".to_string())
+}
+
+/// Write `network.txt`: one line per request in request order, with its start offset,
+/// method, status, resource type, bytes on the wire, duration and URL.
+pub fn write_log(entries: &[NetEntry], path: &Path) -> Result<()> {
+    let origin = entries
+        .iter()
+        .map(|e| e.start)
+        .fold(f64::INFINITY, f64::min);
+    let mut out = String::from("#    start method status type             bytes     time url\n");
+    for e in entries {
+        let status = match (&e.response, &e.failed) {
+            (Some(r), _) => r.status.to_string(),
+            (None, Some(_)) => "ERR".to_string(),
+            (None, None) => "-".to_string(),
+        };
+        let time = e.end.map_or("-".to_string(), |end| {
+            format!("{:.0}ms", (end - e.start) * 1000.0)
+        });
+        write!(
+            out,
+            "{:>8.0}ms {:<6} {:>6} {:<12} {:>9} {:>8} {}",
+            (e.start - origin) * 1000.0,
+            e.request.method,
+            status,
+            e.resource_type,
+            e.encoded_bytes as u64,
+            time,
+            e.request.url
+        )?;
+        if e.from_cache {
+            out.push_str(" (cache)");
+        }
+        // Still in flight: on a timeout, usually what kept the page from going idle.
+        if e.end.is_none() && e.failed.is_none() {
+            out.push_str(" (pending)");
+        }
+        if let Some(err) = &e.failed {
+            write!(out, " failed: {err}")?;
+        }
+        out.push('\n');
+    }
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct NetworkSummary {
+    pub total_requests: usize,
+    pub failed_requests: usize,
+    pub transferred_bytes: u64,
+    pub cache_hits: usize,
+    pub cache_hit_ratio: f64,
+    pub largest: Vec<ResourceSize>,
+}
+
+#[derive(Serialize)]
+pub struct ResourceSize {
+    pub url: String,
+    pub resource_type: String,
+    pub bytes: u64,
+}
+
+/// Aggregate request counts, bytes on the wire and cache usage.
+pub fn summarize(entries: &[NetEntry]) -> NetworkSummary {
+    let total_requests = entries.len();
+    let cache_hits = entries.iter().filter(|e| e.from_cache).count();
+    let mut by_size: Vec<&NetEntry> = entries.iter().collect();
+    by_size.sort_by(|a, b| b.encoded_bytes.total_cmp(&a.encoded_bytes));
+    NetworkSummary {
+        total_requests,
+        failed_requests: entries.iter().filter(|e| e.failed.is_some()).count(),
+        transferred_bytes: entries.iter().map(|e| e.encoded_bytes as u64).sum(),
+        cache_hits,
+        cache_hit_ratio: if total_requests > 0 {
+            cache_hits as f64 / total_requests as f64
+        } else {
+            0.0
+        },
+        largest: by_size
+            .into_iter()
+            .take(5)
+            .filter(|e| e.encoded_bytes > 0.0)
+            .map(|e| ResourceSize {
+                url: e.request.url.clone(),
+                resource_type: e.resource_type.clone(),
+                bytes: e.encoded_bytes as u64,
+            })
+            .collect(),
+    }
+}
+
+/// Request phases in milliseconds, relative to the first request of the session.
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct Phases {
+    pub offset: f64,
+    pub queued: f64,
+    pub dns: f64,
+    pub connect: f64,
+    pub ttfb: f64,
+    pub download: f64,
+}
+
+impl Phases {
+    pub fn total(&self) -> f64 {
+        self.queued + self.dns + self.connect + self.ttfb + self.download
+    }
+}
+
+/// Split an entry into waterfall phases. `origin` is the monotonic start of the session.
+pub fn phases(entry: &NetEntry, origin: f64) -> Phases {
+    let ms = |a: f64, b: f64| if a >= 0.0 && b >= a { b - a } else { 0.0 };
+    let offset = (entry.start - origin) * 1000.0;
+    let end_ms = entry.end.map(|e| (e - entry.start) * 1000.0).unwrap_or(0.0);
+    let timing = entry.response.as_ref().and_then(|r| r.timing.as_ref());
+    match timing {
+        Some(t) => {
+            let blocked = ((t.request_time - entry.start) * 1000.0).max(0.0);
+            let dns = ms(t.dns_start, t.dns_end);
+            let connect = ms(t.connect_start, t.connect_end);
+            // Anything before sendStart that wasn't DNS or connect is stall time.
+            let stalled = (t.send_start - dns - connect).max(0.0);
+            let ttfb = ms(t.send_start.max(0.0), t.receive_headers_end);
+            let download = (end_ms - blocked - t.receive_headers_end).max(0.0);
+            Phases {
+                offset,
+                queued: blocked + stalled,
+                dns,
+                connect,
+                ttfb,
+                download,
+            }
+        }
+        None => Phases {
+            offset,
+            download: end_ms.max(0.0),
+            ..Default::default()
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct Waterfall<'a> {
+    /// From the first request's start to the last one's end.
+    span_ms: f64,
+    requests: Vec<WaterfallRow<'a>>,
+}
+
+#[derive(Serialize)]
+struct WaterfallRow<'a> {
+    url: &'a str,
+    method: &'a str,
+    resource_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    from_cache: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failed: Option<&'a str>,
+    bytes: u64,
+    #[serde(flatten)]
+    phases: Phases,
+    total: f64,
+}
+
+/// Write the waterfall as `waterfall.json`: every request's phases in milliseconds, in
+/// request order, for tooling that wants the numbers rather than the picture.
+pub fn write_waterfall_json(entries: &[NetEntry], path: &Path) -> Result<()> {
+    let origin = entries
+        .iter()
+        .map(|e| e.start)
+        .fold(f64::INFINITY, f64::min);
+    // Sub-millisecond precision is noise at this scale.
+    let round = |v: f64| (v * 10.0).round() / 10.0;
+    let requests: Vec<WaterfallRow> = entries
+        .iter()
+        .map(|e| {
+            let p = phases(e, origin);
+            WaterfallRow {
+                url: &e.request.url,
+                method: &e.request.method,
+                resource_type: &e.resource_type,
+                status: e.response.as_ref().map(|r| r.status),
+                from_cache: e.from_cache,
+                failed: e.failed.as_deref(),
+                bytes: e.encoded_bytes as u64,
+                phases: Phases {
+                    offset: round(p.offset),
+                    queued: round(p.queued),
+                    dns: round(p.dns),
+                    connect: round(p.connect),
+                    ttfb: round(p.ttfb),
+                    download: round(p.download),
+                },
+                total: round(p.total()),
+            }
+        })
+        .collect();
+    let span_ms = requests
+        .iter()
+        .map(|r| r.phases.offset + r.total)
+        .fold(0.0_f64, f64::max);
+    let file = std::fs::File::create(path)?;
+    serde_json::to_writer_pretty(
+        std::io::BufWriter::new(file),
+        &Waterfall {
+            span_ms: round(span_ms),
+            requests,
+        },
+    )?;
+    Ok(())
+}
+
+const ROW_H: f64 = 16.0;
+const LABEL_W: f64 = 380.0;
+const CHART_W: f64 = 820.0;
+const MAX_ROWS: usize = 400;
+const PHASE_COLORS: &[(&str, &str)] = &[
+    ("queued", "#b0b0b0"),
+    ("dns", "#1f9e89"),
+    ("connect", "#f28e2b"),
+    ("ttfb", "#59a14f"),
+    ("download", "#4e79a7"),
+];
+
+/// Render a request timing waterfall as a standalone SVG.
+pub fn write_waterfall_svg(entries: &[NetEntry], path: &Path) -> Result<()> {
+    let origin = entries
+        .iter()
+        .map(|e| e.start)
+        .fold(f64::INFINITY, f64::min);
+    let rows: Vec<(&NetEntry, Phases)> = entries
+        .iter()
+        .take(MAX_ROWS)
+        .map(|e| (e, phases(e, origin)))
+        .collect();
+    let span = rows
+        .iter()
+        .map(|(_, p)| p.offset + p.total())
+        .fold(1.0_f64, f64::max);
+    let scale = CHART_W / span;
+    let top = 40.0;
+    let height = top + ROW_H * rows.len() as f64 + 10.0;
+    let width = LABEL_W + CHART_W + 20.0;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="monospace" font-size="11">"#
+    )?;
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    for (i, (name, color)) in PHASE_COLORS.iter().enumerate() {
+        let x = 10.0 + i as f64 * 90.0;
+        writeln!(
+            svg,
+            r#"<rect x="{x}" y="8" width="10" height="10" fill="{color}"/><text x="{}" y="17">{name}</text>"#,
+            x + 14.0
+        )?;
+    }
+    writeln!(
+        svg,
+        r#"<text x="{}" y="17">total {:.0} ms, {} requests</text>"#,
+        LABEL_W,
+        span,
+        entries.len()
+    )?;
+    let ticks = 10;
+    for t in 0..=ticks {
+        let x = LABEL_W + CHART_W * t as f64 / ticks as f64;
+        writeln!(
+            svg,
+            r##"<line x1="{x}" y1="{}" x2="{x}" y2="{height}" stroke="#eee"/><text x="{x}" y="{}" font-size="9">{:.0}</text>"##,
+            top - 6.0,
+            top - 8.0,
+            span * t as f64 / ticks as f64
+        )?;
+    }
+    for (row, (entry, p)) in rows.iter().enumerate() {
+        let y = top + row as f64 * ROW_H;
+        let status = entry
+            .response
+            .as_ref()
+            .map(|r| r.status.to_string())
+            .unwrap_or_else(|| if entry.failed.is_some() { "ERR" } else { "..." }.to_string());
+        let mut label = format!("{} {}", status, entry.request.url);
+        if label.len() > 60 {
+            let cut = (0..=57)
+                .rev()
+                .find(|&i| label.is_char_boundary(i))
+                .unwrap_or(0);
+            label.truncate(cut);
+            label.push_str("...");
+        }
+        writeln!(
+            svg,
+            r#"<text x="4" y="{}">{}</text>"#,
+            y + ROW_H - 4.0,
+            xml_escape(&label)
+        )?;
+        let mut x = LABEL_W + p.offset * scale;
+        let parts = [p.queued, p.dns, p.connect, p.ttfb, p.download];
+        for ((_, color), dur) in PHASE_COLORS.iter().zip(parts) {
+            let w = dur * scale;
+            if w > 0.0 {
+                writeln!(
+                    svg,
+                    r#"<rect x="{x:.1}" y="{:.1}" width="{w:.1}" height="{:.1}" fill="{color}"/>"#,
+                    y + 3.0,
+                    ROW_H - 6.0
+                )?;
+            }
+            x += w;
+        }
+    }
+    writeln!(svg, "</svg>")?;
+    std::fs::write(path, svg)?;
+    Ok(())
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    fn event(method: &str, params: Value) -> Event {
+        serde_json::from_value(json!({ "method": method, "params": params })).unwrap()
+    }
+
+    fn response(url: &str, status: u32, timing: Option<Value>) -> Value {
+        let mut r = json!({
+            "url": url,
+            "status": status,
+            "statusText": "",
+            "headers": {},
+            "mimeType": "text/html",
+            "connectionReused": false,
+            "connectionId": 1,
+            "encodedDataLength": 0,
+            "securityState": "secure",
+        });
+        if let Some(t) = timing {
+            r["timing"] = t;
+        }
+        r
+    }
+
+    fn will_be_sent(id: &str, url: &str, at: f64, redirect: Option<Value>) -> Event {
+        let mut p = json!({
+            "requestId": id,
+            "loaderId": "L",
+            "documentURL": url,
+            "request": {
+                "url": url,
+                "method": "GET",
+                "headers": {},
+                "initialPriority": "VeryHigh",
+                "referrerPolicy": "no-referrer",
+            },
+            "timestamp": at,
+            "wallTime": 1.7e9 + at,
+            "initiator": { "type": "other" },
+            "type": "Document",
+        });
+        if let Some(r) = redirect {
+            p["redirectResponse"] = r;
+        }
+        event("Network.requestWillBeSent", p)
+    }
+
+    fn entry(start: f64, end: Option<f64>, timing: Option<Value>) -> NetEntry {
+        let mut st = State::default();
+        st.record(&will_be_sent("1", "https://example.com/", start, None));
+        st.entries[0].response =
+            Some(serde_json::from_value(response("https://example.com/", 200, timing)).unwrap());
+        st.entries[0].end = end;
+        st.entries.remove(0)
+    }
+
+    #[test]
+    fn redirect_hops_are_separate_entries() {
+        let mut st = State::default();
+        st.record(&will_be_sent("1", "http://example.com/", 10.0, None));
+        st.record(&will_be_sent(
+            "1",
+            "https://example.com/",
+            10.2,
+            Some(response("http://example.com/", 301, None)),
+        ));
+        st.record(&event(
+            "Network.responseReceived",
+            json!({
+                "requestId": "1",
+                "loaderId": "L",
+                "timestamp": 10.4,
+                "type": "Document",
+                "response": response("https://example.com/", 200, None),
+            }),
+        ));
+        st.record(&event(
+            "Network.loadingFinished",
+            json!({ "requestId": "1", "timestamp": 10.5, "encodedDataLength": 2048.0 }),
+        ));
+
+        assert_eq!(st.entries.len(), 2);
+        let (hop, page) = (&st.entries[0], &st.entries[1]);
+        assert_eq!(hop.request.url, "http://example.com/");
+        assert_eq!(hop.response.as_ref().unwrap().status, 301);
+        assert_eq!(hop.end, Some(10.2));
+        assert_eq!(page.resource_type, "Document");
+        assert_eq!(page.response.as_ref().unwrap().status, 200);
+        assert_eq!(page.end, Some(10.5));
+        assert_eq!(page.encoded_bytes, 2048.0);
+    }
+
+    #[test]
+    fn phases_without_timing_are_all_download() {
+        let p = phases(&entry(1.5, Some(1.75), None), 1.0);
+        assert_eq!(p.offset, 500.0);
+        assert_eq!(p.download, 250.0);
+        assert_eq!(p.total(), 250.0);
+    }
+
+    #[test]
+    fn phases_split_resource_timing() {
+        let timing = json!({
+            "requestTime": 1.0,
+            "proxyStart": -1, "proxyEnd": -1,
+            "dnsStart": 0, "dnsEnd": 20,
+            "connectStart": 20, "connectEnd": 50,
+            "sslStart": 30, "sslEnd": 50,
+            "workerStart": -1, "workerReady": -1,
+            "workerFetchStart": -1, "workerRespondWithSettled": -1,
+            "sendStart": 50, "sendEnd": 51,
+            "pushStart": 0, "pushEnd": 0,
+            "receiveHeadersEnd": 150,
+        });
+        let p = phases(&entry(1.0, Some(1.2), Some(timing)), 1.0);
+        assert_eq!(p.offset, 0.0);
+        assert_eq!(p.dns, 20.0);
+        assert_eq!(p.connect, 30.0);
+        assert_eq!(p.ttfb, 100.0);
+        assert_eq!(p.queued, 0.0);
+        assert!((p.download - 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn xml_escape_escapes_markup() {
+        assert_eq!(
+            xml_escape(r#"200 https://x/?a=1&b=<"c">"#),
+            "200 https://x/?a=1&amp;b=&lt;&quot;c&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn summary_counts_bytes_cache_and_failures() {
+        let sized = |bytes: f64, from_cache: bool, failed: bool| {
+            let mut e = entry(1.0, Some(1.1), None);
+            e.encoded_bytes = bytes;
+            e.from_cache = from_cache;
+            e.failed = failed.then(|| "net::ERR_FAILED".to_string());
+            e
+        };
+        let entries = [
+            sized(100.0, false, false),
+            sized(0.0, true, false),
+            sized(5000.0, false, false),
+            sized(0.0, false, true),
+        ];
+        let s = summarize(&entries);
+        assert_eq!(s.total_requests, 4);
+        assert_eq!(s.failed_requests, 1);
+        assert_eq!(s.transferred_bytes, 5100);
+        assert_eq!(s.cache_hits, 1);
+        assert_eq!(s.cache_hit_ratio, 0.25);
+        let largest: Vec<u64> = s.largest.iter().map(|r| r.bytes).collect();
+        assert_eq!(largest, [5000, 100]);
+    }
+
+    #[test]
+    fn summary_of_nothing() {
+        let s = summarize(&[]);
+        assert_eq!(s.total_requests, 0);
+        assert_eq!(s.cache_hit_ratio, 0.0);
+        assert!(s.largest.is_empty());
+    }
+
+    fn received(st: &mut State, id: &str, at: f64, response: Value) {
+        st.record(&event(
+            "Network.responseReceived",
+            json!({
+                "requestId": id,
+                "loaderId": "L",
+                "timestamp": at,
+                "type": "Document",
+                "response": response,
+            }),
+        ));
+    }
+
+    #[test]
+    fn document_response_follows_redirects_from_an_index() {
+        let mut st = State::default();
+        st.record(&will_be_sent("1", "http://example.com/", 1.0, None));
+        st.record(&will_be_sent(
+            "1",
+            "https://example.com/",
+            1.1,
+            Some(response("http://example.com/", 301, None)),
+        ));
+        received(
+            &mut st,
+            "1",
+            1.2,
+            response("https://example.com/", 200, None),
+        );
+        st.record(&will_be_sent("2", "https://example.com/", 5.0, None));
+        let mut throttled = response("https://example.com/", 429, None);
+        throttled["headers"] = json!({ "Retry-After": "120" });
+        received(&mut st, "2", 5.1, throttled);
+
+        assert_eq!(document_response(&st.entries, 0).unwrap().status, 200);
+        let retry = document_response(&st.entries, 2).unwrap();
+        assert_eq!(retry.status, 429);
+        assert_eq!(response_header(retry, "retry-after"), Some("120"));
+        assert_eq!(response_header(retry, "content-type"), None);
+        assert!(document_response(&st.entries, 3).is_none());
+    }
+
+    #[test]
+    fn final_document_response_matches_the_final_url() {
+        let mut st = State::default();
+        st.record(&will_be_sent("1", "https://example.com/", 1.0, None));
+        received(
+            &mut st,
+            "1",
+            1.1,
+            response("https://example.com/", 200, None),
+        );
+        st.record(&will_be_sent("2", "https://example.com/next", 2.0, None));
+        received(
+            &mut st,
+            "2",
+            2.1,
+            response("https://example.com/next", 403, None),
+        );
+
+        let next = final_document_response(&st.entries, "https://example.com/next");
+        assert_eq!(next.unwrap().status, 403);
+        // A URL no document was served from (client-side redirect): the first one.
+        let other = final_document_response(&st.entries, "https://example.com/#/app");
+        assert_eq!(other.unwrap().status, 200);
+        assert!(final_document_response(&[], "https://example.com/").is_none());
+    }
+
+    #[test]
+    fn response_headers_lists_every_pair() {
+        let mut r = response("https://example.com/", 200, None);
+        r["headers"] = json!({ "Content-Type": "text/html", "Server": "cloudflare" });
+        let r: Network::Response = serde_json::from_value(r).unwrap();
+        let mut headers = response_headers(&r);
+        headers.sort();
+        assert_eq!(
+            headers,
+            [
+                ("Content-Type".to_string(), "text/html".to_string()),
+                ("Server".to_string(), "cloudflare".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn final_document_response_ignores_iframes() {
+        let mut st = State::default();
+        for (id, url, frame, status) in [
+            ("1", "https://example.com/", "main", 200),
+            ("2", "https://ads.example/frame", "child", 404),
+        ] {
+            st.record(&will_be_sent(id, url, 1.0, None));
+            received(&mut st, id, 1.1, response(url, status, None));
+            st.entries.last_mut().unwrap().frame_id = Some(frame.to_string());
+        }
+        let r = final_document_response(&st.entries, "https://ads.example/frame");
+        assert_eq!(r.unwrap().status, 200);
+    }
+
+    #[test]
+    fn log_marks_cached_failed_and_pending_requests() {
+        let mut cached = entry(1.0, Some(1.05), None);
+        cached.from_cache = true;
+        cached.encoded_bytes = 512.0;
+        let mut failed = entry(1.1, Some(1.2), None);
+        failed.response = None;
+        failed.failed = Some("net::ERR_BLOCKED_BY_CLIENT".to_string());
+        let mut pending = entry(1.5, None, None);
+        pending.response = None;
+
+        let path = std::env::temp_dir().join(format!("ankabot-network-{}.txt", std::process::id()));
+        write_log(&[cached, failed, pending], &path).unwrap();
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("   200 Document"), "{}", lines[1]);
+        assert!(lines[1].contains("      512     50ms"), "{}", lines[1]);
+        assert!(lines[1].ends_with("https://example.com/ (cache)"));
+        assert!(lines[2].contains("   ERR "), "{}", lines[2]);
+        assert!(lines[2].ends_with(" failed: net::ERR_BLOCKED_BY_CLIENT"));
+        assert!(
+            lines[3].trim_start().starts_with("500ms GET"),
+            "{}",
+            lines[3]
+        );
+        assert!(lines[3].ends_with(" (pending)"));
+    }
+
+    #[test]
+    fn wait_until_wakes_on_events() {
+        let capture = NetworkCapture {
+            state: Default::default(),
+            changed: Default::default(),
+        };
+        capture
+            .state
+            .lock()
+            .unwrap()
+            .record(&will_be_sent("1", "https://example.com/", 1.0, None));
+        let (sink, notify) = (capture.state.clone(), capture.changed.clone());
+        let feeder = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            sink.lock()
+                .unwrap()
+                .record(&will_be_sent("2", "https://example.com/api", 2.0, None));
+            notify.notify_all();
+        });
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(10);
+        // Entries before `from` don't count.
+        assert!(capture.wait_until(1, deadline, |new| new.len() == 1));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        feeder.join().unwrap();
+
+        let soon = Instant::now() + Duration::from_millis(50);
+        assert!(!capture.wait_until(0, soon, |all| all.len() > 2));
+    }
+}
@@ -0,0 +1,36 @@
+//! Completion webhooks (`--notify-webhook URL`).
+
+use anyhow::{anyhow, Result};
+use std::{path::Path, time::Duration};
+
+/// POST the run's result.json (success output or timeout report) to `webhook`, retrying
+/// with exponential backoff (1s, 2s, 4s, …) on connection errors and non-2xx responses.
+pub async fn post_result(webhook: &str, result_json: &Path, retries: u32) -> Result<()> {
+    let body = std::fs::read(result_json)?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()?;
+    let mut last_err = anyhow!("webhook not attempted");
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(1 << (attempt - 1).min(5))).await;
+        }
+        let res = client
+            .post(webhook)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("x-ankabot-result", result_json.display().to_string())
+            .body(body.clone())
+            .send()
+            .await;
+        match res {
+            Ok(r) if r.status().is_success() => return Ok(()),
+            Ok(r) => last_err = anyhow!("webhook returned {}", r.status()),
+            Err(e) => last_err = e.into(),
+        }
+    }
+    Err(last_err.context(format!(
+        "notifying {} after {} attempts",
+        webhook,
+        retries + 1
+    )))
+}
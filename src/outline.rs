@@ -0,0 +1,202 @@
+//! PDF bookmarks from the page's h1–h3 headings.
+//!
+//! Chrome only records where an element landed in the PDF when something links to it: for
+//! every `<a href="#id">` it writes a named destination (page and position) for the target.
+//! So before printing, each heading gets an id if it has none and a hidden link pointing
+//! at it; afterwards the ids and links are removed again, and the outline is built from
+//! the destinations. Headings that weren't printed (hidden in print, or outside
+//! `--pdf-page-ranges`) get no destination and are left out.
+
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Document, Object, ObjectId};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub struct Heading {
+    /// 1–3, from the tag name.
+    level: u8,
+    text: String,
+    /// Name of the destination Chrome writes for it.
+    dest: String,
+}
+
+const PREPARE_JS: &str = r#"(() => {
+  const links = document.createElement('div');
+  links.id = '__ankabot_outline';
+  links.hidden = true;
+  const headings = [];
+  document.querySelectorAll('h1, h2, h3').forEach((h, i) => {
+    const text = (h.innerText || h.textContent || '').replace(/\s+/g, ' ').trim().slice(0, 200);
+    if (!text) return;
+    if (!h.id) {
+      h.id = '__ankabot_h' + i;
+      h.setAttribute('data-ankabot-outline-id', '');
+    }
+    // Chrome names the destination after the fragment as URL parsing escapes it.
+    const frag = h.id.replace(/[^\x21-\x7e]|["<>`]/gu, (c) => encodeURIComponent(c));
+    const a = document.createElement('a');
+    a.href = '#' + frag;
+    links.appendChild(a);
+    headings.push({ level: +h.tagName[1], text, dest: frag });
+  });
+  document.body.appendChild(links);
+  return JSON.stringify(headings);
+})()"#;
+
+const CLEANUP_JS: &str = r#"(() => {
+  document.getElementById('__ankabot_outline')?.remove();
+  document.querySelectorAll('[data-ankabot-outline-id]').forEach((h) => {
+    h.removeAttribute('id');
+    h.removeAttribute('data-ankabot-outline-id');
+  });
+})()"#;
+
+/// Mark the headings for printing and return them in document order.
+pub fn prepare(tab: &headless_chrome::Tab) -> Result<Vec<Heading>> {
+    let json = tab
+        .evaluate(PREPARE_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "[]".to_string());
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Undo [`prepare`].
+pub fn cleanup(tab: &headless_chrome::Tab) -> Result<()> {
+    tab.evaluate(CLEANUP_JS, false)?;
+    Ok(())
+}
+
+/// Add an outline of `headings` to the printed PDF. Returns the bytes unchanged when
+/// none of them made it into the PDF.
+pub fn add(bytes: Vec<u8>, headings: &[Heading]) -> Result<Vec<u8>> {
+    if headings.is_empty() {
+        return Ok(bytes);
+    }
+    let mut doc = Document::load_mem(&bytes).context("parsing the printed PDF")?;
+    let dests = named_destinations(&doc);
+    let placed: Vec<(&Heading, Object)> = headings
+        .iter()
+        .filter_map(|h| Some((h, dests.get(h.dest.as_bytes())?.clone())))
+        .collect();
+    if placed.is_empty() {
+        return Ok(bytes);
+    }
+
+    let root = doc.new_object_id();
+    let ids: Vec<ObjectId> = placed.iter().map(|_| doc.new_object_id()).collect();
+    // Nest each heading under the closest earlier heading of a higher level.
+    let mut parents = Vec::with_capacity(placed.len());
+    let mut stack: Vec<usize> = Vec::new();
+    for (i, (h, _)) in placed.iter().enumerate() {
+        while stack.last().is_some_and(|&p| placed[p].0.level >= h.level) {
+            stack.pop();
+        }
+        parents.push(stack.last().copied());
+        stack.push(i);
+    }
+    let children = |parent: Option<usize>| -> Vec<usize> {
+        (0..placed.len())
+            .filter(|&i| parents[i] == parent)
+            .collect()
+    };
+
+    for (i, (h, dest)) in placed.iter().enumerate() {
+        let siblings = children(parents[i]);
+        let pos = siblings.iter().position(|&s| s == i).unwrap_or(0);
+        let kids = children(Some(i));
+        let mut item = dictionary! {
+            "Title" => crate::pdf::text_string(&h.text),
+            "Parent" => parents[i].map_or(root, |p| ids[p]),
+            "Dest" => dest.clone(),
+        };
+        if pos > 0 {
+            item.set("Prev", ids[siblings[pos - 1]]);
+        }
+        if let Some(&next) = siblings.get(pos + 1) {
+            item.set("Next", ids[next]);
+        }
+        if let (Some(&first), Some(&last)) = (kids.first(), kids.last()) {
+            item.set("First", ids[first]);
+            item.set("Last", ids[last]);
+            // Positive: open, showing the direct children.
+            item.set("Count", kids.len() as i64);
+        }
+        doc.objects.insert(ids[i], Object::Dictionary(item));
+    }
+    let top = children(None);
+    doc.objects.insert(
+        root,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => ids[top[0]],
+            "Last" => ids[top[top.len() - 1]],
+            "Count" => top.len() as i64,
+        }),
+    );
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Outlines", root);
+    catalog.set("PageMode", "UseOutlines");
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out).context("serializing the PDF")?;
+    Ok(out)
+}
+
+/// Named destinations, from the catalog's `/Dests` dictionary or its `/Names` tree.
+fn named_destinations(doc: &Document) -> HashMap<Vec<u8>, Object> {
+    let mut dests = HashMap::new();
+    let Ok(catalog) = doc.catalog() else {
+        return dests;
+    };
+    let resolve = |o: &Object| -> Option<Object> {
+        let o = match o {
+            Object::Reference(id) => doc.get_object(*id).ok()?,
+            o => o,
+        };
+        // A destination is an array, or a dictionary holding one under /D.
+        match o {
+            Object::Array(_) => Some(o.clone()),
+            Object::Dictionary(d) => d.get(b"D").ok().cloned(),
+            _ => None,
+        }
+    };
+    if let Ok(d) = catalog
+        .get(b"Dests")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+    {
+        for (name, value) in d.iter() {
+            if let Some(dest) = resolve(value) {
+                dests.insert(name.clone(), dest);
+            }
+        }
+    }
+    let tree = catalog
+        .get(b"Names")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|names| names.get(b"Dests"))
+        .and_then(|o| doc.dereference(o))
+        .map(|(_, o)| o.clone());
+    let mut pending: Vec<Object> = tree.into_iter().collect();
+    // Name trees: /Names [key value …] at the leaves, /Kids below the root.
+    while let Some(node) = pending.pop() {
+        let Ok(node) = node.as_dict() else { continue };
+        if let Ok(pairs) = node.get(b"Names").and_then(Object::as_array) {
+            for kv in pairs.chunks_exact(2) {
+                if let (Object::String(name, _), Some(dest)) = (&kv[0], resolve(&kv[1])) {
+                    dests.insert(name.clone(), dest);
+                }
+            }
+        }
+        if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+            pending.extend(
+                kids.iter()
+                    .filter_map(|k| doc.dereference(k).ok().map(|(_, o)| o.clone())),
+            );
+        }
+    }
+    dests
+}
@@ -0,0 +1,563 @@
+//! How Chrome prints PDFs (paper, margins, headers) and post-processing of the result
+//! (document metadata, stamps, encryption and permissions).
+
+use crate::Cli;
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use headless_chrome::types::PrintToPdfOptions;
+use lopdf::{
+    content::{Content, Operation},
+    dictionary,
+    encryption::crypt_filters::{Aes128CryptFilter, CryptFilter},
+    Dictionary, Document, EncryptionState, EncryptionVersion, Object, ObjectId, Permissions,
+    Stream, StringFormat,
+};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, path::Path, sync::Arc};
+
+/// Actions `--pdf-restrict` can withhold from readers who only know the user password.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PdfRestriction {
+    Print,
+    Copy,
+    Modify,
+    Annotate,
+}
+
+impl PdfRestriction {
+    fn permissions(self) -> Permissions {
+        match self {
+            Self::Print => Permissions::PRINTABLE | Permissions::PRINTABLE_IN_HIGH_QUALITY,
+            Self::Copy => Permissions::COPYABLE,
+            Self::Modify => Permissions::MODIFIABLE | Permissions::ASSEMBLABLE,
+            Self::Annotate => Permissions::ANNOTABLE | Permissions::FILLABLE,
+        }
+    }
+}
+
+/// `--paper` sizes; Chrome's own default is Letter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PaperSize {
+    Letter,
+    Legal,
+    Tabloid,
+    A3,
+    A4,
+    A5,
+}
+
+impl PaperSize {
+    /// Portrait width and height in inches.
+    fn inches(self) -> (f64, f64) {
+        match self {
+            Self::Letter => (8.5, 11.0),
+            Self::Legal => (8.5, 14.0),
+            Self::Tabloid => (11.0, 17.0),
+            Self::A3 => (11.69, 16.54),
+            Self::A4 => (8.27, 11.69),
+            Self::A5 => (5.83, 8.27),
+        }
+    }
+}
+
+/// `--pdf-margin`: one length for all sides or four (top,right,bottom,left), each a
+/// number with an optional `in` (default), `mm`, `cm` or `px` unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Margins {
+    top: f64,
+    right: f64,
+    bottom: f64,
+    left: f64,
+}
+
+impl std::str::FromStr for Margins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let sides: Vec<f64> = s.split(',').map(inches).collect::<Result<_, _>>()?;
+        match sides[..] {
+            [all] => Ok(Margins {
+                top: all,
+                right: all,
+                bottom: all,
+                left: all,
+            }),
+            [top, right, bottom, left] => Ok(Margins {
+                top,
+                right,
+                bottom,
+                left,
+            }),
+            _ => Err(format!(
+                "expected one margin or four (top,right,bottom,left), got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// `"10mm"` -> inches.
+fn inches(len: &str) -> Result<f64, String> {
+    let len = len.trim();
+    let (num, per_inch) = [("mm", 25.4), ("cm", 2.54), ("px", 96.0), ("in", 1.0)]
+        .iter()
+        .find_map(|(unit, per)| len.strip_suffix(unit).map(|n| (n, *per)))
+        .unwrap_or((len, 1.0));
+    match num.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok(n / per_inch),
+        _ => Err(format!("invalid length '{}' (e.g. 0.5in, 10mm)", len)),
+    }
+}
+
+/// `--pdf-scale`: Chrome accepts 0.1 to 2.
+pub fn parse_scale(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if (0.1..=2.0).contains(&v) => Ok(v),
+        _ => Err(format!("expected a scale between 0.1 and 2, got '{}'", s)),
+    }
+}
+
+/// `--pdf-page-ranges`: e.g. `1-5, 8, 11-13`.
+pub fn parse_page_ranges(s: &str) -> Result<String, String> {
+    let valid = s.split(',').all(|part| {
+        let part = part.trim();
+        let (from, to) = part.split_once('-').unwrap_or((part, part));
+        let (from, to) = (from.trim(), to.trim());
+        match (from.parse::<u32>(), to.parse::<u32>()) {
+            (Ok(a), Ok(b)) => a >= 1 && a <= b,
+            // Open-ended ranges: "-3" (up to page 3) and "5-" (from page 5).
+            (Err(_), Ok(b)) => from.is_empty() && b >= 1,
+            (Ok(a), Err(_)) => to.is_empty() && a >= 1,
+            _ => false,
+        }
+    });
+    if valid {
+        Ok(s.to_string())
+    } else {
+        Err(format!("expected page ranges like '1-5,8', got '{}'", s))
+    }
+}
+
+/// `--pdf-media`: which CSS media type the page is laid out for while it is printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PdfMedia {
+    /// The site's print stylesheet (`@media print`), as Chrome prints by default.
+    Print,
+    /// The on-screen layout.
+    Screen,
+}
+
+/// Resolves after two animation frames, once scripts watching `matchMedia` have reacted.
+const SETTLE_JS: &str =
+    "new Promise((r) => requestAnimationFrame(() => requestAnimationFrame(() => r(true))))";
+
+/// Emulate `--pdf-media` for the print, so `matchMedia('print')` listeners see it and
+/// relayout before Chrome paginates; `None` restores the normal screen media.
+pub fn emulate_media(tab: &headless_chrome::Tab, media: Option<PdfMedia>) -> Result<()> {
+    use headless_chrome::protocol::cdp::Emulation::SetEmulatedMedia;
+    let name = match media {
+        Some(PdfMedia::Print) => "print",
+        Some(PdfMedia::Screen) => "screen",
+        None => "",
+    };
+    tab.call_method(SetEmulatedMedia {
+        media: Some(name.to_string()),
+        features: None,
+    })?;
+    if media.is_some() {
+        tab.evaluate(SETTLE_JS, true)?;
+    }
+    Ok(())
+}
+
+/// Chrome's print settings from the `--paper`, `--landscape`, `--pdf-*` flags. Without
+/// any of them this is the zero-margin output ankabot always printed.
+pub fn print_options(args: &Cli) -> Result<PrintToPdfOptions> {
+    let read = |p: &Option<std::path::PathBuf>| -> Result<Option<String>> {
+        p.as_ref()
+            .map(|p| {
+                std::fs::read_to_string(p)
+                    .with_context(|| format!("reading PDF template {}", p.display()))
+            })
+            .transpose()
+    };
+    let header = read(&args.pdf_header_template)?;
+    let footer = read(&args.pdf_footer_template)?;
+    let header_footer = header.is_some() || footer.is_some();
+    // Headers and footers are drawn inside the margins, so they need some by default.
+    let margins = args.pdf_margin.unwrap_or(if header_footer {
+        Margins {
+            top: 0.6,
+            right: 0.4,
+            bottom: 0.6,
+            left: 0.4,
+        }
+    } else {
+        Margins {
+            top: 0.0,
+            right: 0.0,
+            bottom: 0.0,
+            left: 0.0,
+        }
+    });
+    let paper = args.paper.map(PaperSize::inches);
+    Ok(PrintToPdfOptions {
+        landscape: args.landscape.then_some(true),
+        display_header_footer: header_footer.then_some(true),
+        print_background: Some(true),
+        scale: args.pdf_scale,
+        paper_width: paper.map(|(w, _)| w),
+        paper_height: paper.map(|(_, h)| h),
+        margin_top: Some(margins.top),
+        margin_bottom: Some(margins.bottom),
+        margin_left: Some(margins.left),
+        margin_right: Some(margins.right),
+        page_ranges: args.pdf_page_ranges.clone(),
+        // An empty template suppresses Chrome's default date/title header or URL footer.
+        header_template: header_footer.then(|| header.unwrap_or_else(|| "<span></span>".into())),
+        footer_template: header_footer.then(|| footer.unwrap_or_else(|| "<span></span>".into())),
+        // An explicit --paper wins over the page's CSS @page size.
+        prefer_css_page_size: Some(args.paper.is_none()),
+        ..Default::default()
+    })
+}
+
+/// Where `--stamp` text goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StampStyle {
+    /// A small line along the bottom edge
+    Footer,
+    /// Large translucent text across the middle
+    Diagonal,
+}
+
+/// Expand the `{timestamp}` (UTC, RFC 3339) and `{url}` placeholders of a `--stamp`.
+pub fn stamp_text(template: &str, url: &str) -> String {
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    template.replace("{timestamp}", &now).replace("{url}", url)
+}
+
+/// Apply the PDF options in `args` to a printed PDF of `url` in place. Returns whether the
+/// file was encrypted.
+pub fn postprocess(args: &Cli, path: &Path, url: &str) -> Result<bool> {
+    let bytes = std::fs::read(path).with_context(|| format!("loading {}", path.display()))?;
+    let (out, encrypted) = postprocess_bytes(args, bytes, url)?;
+    std::fs::write(path, out).with_context(|| format!("writing {}", path.display()))?;
+    Ok(encrypted)
+}
+
+/// [`postprocess`] on PDF bytes held in memory.
+pub fn postprocess_bytes(args: &Cli, bytes: Vec<u8>, url: &str) -> Result<(Vec<u8>, bool)> {
+    let encrypting = args.pdf_password.is_some() || args.pdf_owner_password.is_some();
+    let mut doc = Document::load_mem(&bytes).context("parsing the printed PDF")?;
+    set_info(&mut doc, args, url)?;
+    if let Some(template) = &args.stamp {
+        stamp(&mut doc, &stamp_text(template, url), args.stamp_style)?;
+    }
+    if args.pdfa {
+        crate::pdfa::convert(&mut doc)?;
+    }
+    // Encryption goes last so it covers everything added above.
+    if encrypting {
+        encrypt(&mut doc, args)?;
+    }
+    let mut out = Vec::new();
+    doc.save_to(&mut out).context("serializing the PDF")?;
+    Ok((out, encrypting))
+}
+
+/// Fill in the document information dictionary for DMS indexing: Title (Chrome's, from
+/// the page `<title>`, unless `--pdf-title`), Subject (the source URL unless
+/// `--pdf-subject`), Author, Keywords and the capture time.
+fn set_info(doc: &mut Document, args: &Cli, url: &str) -> Result<()> {
+    let id = match doc.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(id) => id,
+        Err(_) => {
+            let id = doc.add_object(Dictionary::new());
+            doc.trailer.set("Info", id);
+            id
+        }
+    };
+    let info = doc.get_object_mut(id).and_then(Object::as_dict_mut)?;
+    let has_title = matches!(info.get(b"Title"), Ok(Object::String(t, _)) if !t.is_empty());
+    match &args.pdf_title {
+        Some(title) => info.set("Title", text_string(title)),
+        None if !has_title => info.set("Title", text_string(url)),
+        None => {}
+    }
+    info.set(
+        "Subject",
+        text_string(args.pdf_subject.as_deref().unwrap_or(url)),
+    );
+    if let Some(author) = &args.pdf_author {
+        info.set("Author", text_string(author));
+    }
+    if !args.pdf_keywords.is_empty() {
+        info.set("Keywords", text_string(&args.pdf_keywords.join(", ")));
+    }
+    let now = chrono::Utc::now()
+        .format("D:%Y%m%d%H%M%S+00'00'")
+        .to_string();
+    info.set("CreationDate", Object::string_literal(now.clone()));
+    info.set("ModDate", Object::string_literal(now));
+    Ok(())
+}
+
+/// A PDF text string: PDFDocEncoding-compatible ASCII as is, anything else as UTF-16BE.
+pub fn text_string(s: &str) -> Object {
+    if s.is_ascii() {
+        return Object::string_literal(s);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(s.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// Read a PDF text string back: UTF-16BE with a byte order mark, else PDFDocEncoding
+/// (Latin-1 for the characters that matter here).
+pub fn decode_text(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => bytes.iter().map(|&b| char::from(b)).collect(),
+    }
+}
+
+/// Overlay `text` on every page as a form XObject drawn after the page's own content.
+fn stamp(doc: &mut Document, text: &str, style: StampStyle) -> Result<()> {
+    let font = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let translucent = doc.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => 0.22,
+        "CA" => 0.22,
+    });
+    // Helvetica has no glyphs outside WinAnsi (roughly Latin-1).
+    let bytes: Vec<u8> = text
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect();
+    for page_id in doc.get_pages().into_values().collect::<Vec<_>>() {
+        let [x0, y0, x1, y1] = media_box(doc, page_id);
+        let (w, h) = (x1 - x0, y1 - y0);
+        // Rough Helvetica advance: half the font size per character.
+        let advance = 0.5 * bytes.len().max(1) as f32;
+        let ops = match style {
+            StampStyle::Footer => {
+                let size = 8.0_f32.min((w - 36.0) / advance).max(4.0);
+                vec![
+                    Operation::new("q", vec![]),
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), size.into()]),
+                    Operation::new("g", vec![0.3.into()]),
+                    Operation::new("Td", vec![(x0 + 18.0).into(), (y0 + 10.0).into()]),
+                    Operation::new("Tj", vec![Object::string_literal(bytes.clone())]),
+                    Operation::new("ET", vec![]),
+                    Operation::new("Q", vec![]),
+                ]
+            }
+            StampStyle::Diagonal => {
+                let diag = (w * w + h * h).sqrt();
+                let size = (0.8 * diag / advance).min(72.0);
+                let (cos, sin) = (w / diag, h / diag);
+                let half = advance * size / 2.0;
+                let (tx, ty) = (x0 + w / 2.0 - cos * half, y0 + h / 2.0 - sin * half);
+                vec![
+                    Operation::new("q", vec![]),
+                    Operation::new("gs", vec!["GS1".into()]),
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), size.into()]),
+                    Operation::new("g", vec![0.0.into()]),
+                    Operation::new(
+                        "Tm",
+                        vec![
+                            cos.into(),
+                            sin.into(),
+                            (-sin).into(),
+                            cos.into(),
+                            tx.into(),
+                            ty.into(),
+                        ],
+                    ),
+                    Operation::new("Tj", vec![Object::string_literal(bytes.clone())]),
+                    Operation::new("ET", vec![]),
+                    Operation::new("Q", vec![]),
+                ]
+            }
+        };
+        let form = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![x0.into(), y0.into(), x1.into(), y1.into()],
+                "Resources" => dictionary! {
+                    "Font" => dictionary! { "F1" => font },
+                    "ExtGState" => dictionary! { "GS1" => translucent },
+                },
+            },
+            Content { operations: ops }.encode()?,
+        );
+        let form_id = doc.add_object(form);
+        doc.add_xobject(page_id, "AnkabotStamp", form_id)?;
+        // Wrap the original content in q/Q so its graphics state can't leak into the stamp.
+        let contents = doc.get_page_contents(page_id);
+        let open = doc.add_object(Stream::new(dictionary! {}, b"q\n".to_vec()));
+        let close = doc.add_object(Stream::new(
+            dictionary! {},
+            b"\nQ\nq /AnkabotStamp Do Q\n".to_vec(),
+        ));
+        let mut wrapped = vec![Object::Reference(open)];
+        wrapped.extend(contents.into_iter().map(Object::Reference));
+        wrapped.push(Object::Reference(close));
+        doc.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)?
+            .set("Contents", wrapped);
+    }
+    Ok(())
+}
+
+/// The page's MediaBox (own or inherited), US Letter when absent.
+fn media_box(doc: &Document, page_id: ObjectId) -> [f32; 4] {
+    let mut node = doc.get_dictionary(page_id).ok();
+    while let Some(dict) = node {
+        if let Ok(Object::Array(b)) = dict.get(b"MediaBox") {
+            let nums: Vec<f32> = b.iter().filter_map(|o| o.as_float().ok()).collect();
+            if let [x0, y0, x1, y1] = nums[..] {
+                return [x0, y0, x1, y1];
+            }
+        }
+        node = dict
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .ok();
+    }
+    [0.0, 0.0, 612.0, 792.0]
+}
+
+/// AES-128 (PDF 1.6, V4/R4) encryption with the user/owner passwords and restrictions.
+fn encrypt(doc: &mut Document, args: &Cli) -> Result<()> {
+    ensure_id(doc);
+    let user = args.pdf_password.clone().unwrap_or_default();
+    // Without an explicit owner password, nobody can lift the restrictions.
+    let owner = match &args.pdf_owner_password {
+        Some(p) => p.clone(),
+        None => random_password()?,
+    };
+    let permissions = args
+        .pdf_restrict
+        .iter()
+        .fold(Permissions::all(), |p, r| p - r.permissions());
+    let filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+    let state = EncryptionState::try_from(EncryptionVersion::V4 {
+        document: doc,
+        encrypt_metadata: true,
+        crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), filter)]),
+        stream_filter: b"StdCF".to_vec(),
+        string_filter: b"StdCF".to_vec(),
+        owner_password: &owner,
+        user_password: &user,
+        permissions,
+    })
+    .map_err(|e| anyhow!("preparing PDF encryption: {}", e))?;
+    doc.encrypt(&state)
+        .map_err(|e| anyhow!("encrypting PDF: {}", e))?;
+    Ok(())
+}
+
+/// Encryption's key derivation and PDF/A both need the trailer's file identifier; add one
+/// if Chrome left it out.
+pub fn ensure_id(doc: &mut Document) {
+    if doc.trailer.get(b"ID").is_ok() {
+        return;
+    }
+    let id = Sha256::digest(format!("{:?}", std::time::SystemTime::now()))[..16].to_vec();
+    doc.trailer.set(
+        "ID",
+        Object::Array(vec![
+            Object::string_literal(id.clone()),
+            Object::string_literal(id),
+        ]),
+    );
+}
+
+/// 128 bits from the OS CSPRNG, hex-encoded.
+fn random_password() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("generating an owner password: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn page_ranges_accept_lists_and_open_ends() {
+        for ok in ["1", "1-5", "1-5, 8, 11-13", "3-3", "-3", "5-", " 2 - 4 "] {
+            assert_eq!(parse_page_ranges(ok).as_deref(), Ok(ok), "{ok}");
+        }
+    }
+
+    #[test]
+    fn page_ranges_reject_bad_ranges() {
+        for bad in [
+            "", "0", "0-3", "5-2", "1,,2", "1,", ",1", "-", "a", "1-b", "-0", "1.5",
+        ] {
+            assert!(parse_page_ranges(bad).is_err(), "{bad} accepted");
+        }
+    }
+
+    #[test]
+    fn lengths_convert_to_inches() {
+        assert!(close(inches("0.5").unwrap(), 0.5));
+        assert!(close(inches("0.5in").unwrap(), 0.5));
+        assert!(close(inches("25.4mm").unwrap(), 1.0));
+        assert!(close(inches(" 2.54cm ").unwrap(), 1.0));
+        assert!(close(inches("48px").unwrap(), 0.5));
+        assert!(close(inches("0").unwrap(), 0.0));
+    }
+
+    #[test]
+    fn lengths_reject_negative_and_unknown_units() {
+        for bad in ["-1mm", "1pt", "mm", "", "ten"] {
+            assert!(inches(bad).is_err(), "{bad} accepted");
+        }
+    }
+
+    #[test]
+    fn margins_take_one_or_four_sides() {
+        let all: Margins = "10mm".parse().unwrap();
+        assert!(close(all.top, 10.0 / 25.4) && all.top == all.left && all.top == all.bottom);
+        let m: Margins = "1in,2cm,0,96px".parse().unwrap();
+        assert!(close(m.top, 1.0));
+        assert!(close(m.right, 2.0 / 2.54));
+        assert!(close(m.bottom, 0.0));
+        assert!(close(m.left, 1.0));
+        assert!("1,2".parse::<Margins>().is_err());
+        assert!("1,2,3,4,5".parse::<Margins>().is_err());
+        assert!("1,,2,3".parse::<Margins>().is_err());
+    }
+
+    #[test]
+    fn scale_is_bounded() {
+        assert_eq!(parse_scale("0.1"), Ok(0.1));
+        assert_eq!(parse_scale("2"), Ok(2.0));
+        for bad in ["0.05", "2.5", "0", "x"] {
+            assert!(parse_scale(bad).is_err(), "{bad} accepted");
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! `--pdfa`: turn Chrome's PDF into PDF/A-2b for long-term archiving.
+//!
+//! Chrome (Skia) already embeds a subset of every font it draws with, so fonts are checked
+//! rather than embedded here. What's missing is added: an sRGB output intent (the pages
+//! paint in DeviceRGB), XMP metadata mirroring the document information dictionary, a file
+//! identifier, and the print flag on annotations.
+
+use anyhow::{bail, Result};
+use lopdf::{dictionary, Document, Object, Stream};
+
+const SRGB: &str = "sRGB IEC61966-2.1";
+
+/// Convert `doc` in place. Fails when a font isn't embedded, which can't be repaired here.
+pub fn convert(doc: &mut Document) -> Result<()> {
+    let missing = unembedded_fonts(doc);
+    if !missing.is_empty() {
+        bail!(
+            "PDF/A needs embedded fonts; not embedded: {}",
+            missing.join(", ")
+        );
+    }
+    crate::pdf::ensure_id(doc);
+    // A comment of high bytes right after the header marks the file as binary.
+    doc.binary_mark = vec![0xE2, 0xE3, 0xCF, 0xD3];
+    doc.trailer.remove(b"Encrypt");
+
+    let mut icc = Stream::new(dictionary! { "N" => 3 }, srgb_icc());
+    icc.allows_compression = false;
+    let icc = doc.add_object(icc);
+    let intent = doc.add_object(dictionary! {
+        "Type" => "OutputIntent",
+        "S" => "GTS_PDFA1",
+        "OutputConditionIdentifier" => Object::string_literal(SRGB),
+        "Info" => Object::string_literal(SRGB),
+        "DestOutputProfile" => icc,
+    });
+
+    let xmp = xmp(&info(doc));
+    let mut metadata = Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        xmp.into_bytes(),
+    );
+    // Kept uncompressed so the XMP can be found by byte scanning, as the format intends.
+    metadata.allows_compression = false;
+    let metadata = doc.add_object(metadata);
+
+    let catalog = doc.catalog_mut()?;
+    catalog.set("OutputIntents", vec![Object::Reference(intent)]);
+    catalog.set("Metadata", metadata);
+
+    print_annotations(doc);
+    Ok(())
+}
+
+/// Fonts with no embedded font program (standard-14 fonts, or a descriptor without a
+/// FontFile). Type 3 fonts carry their glyphs inline and are fine.
+fn unembedded_fonts(doc: &Document) -> Vec<String> {
+    let mut missing = Vec::new();
+    for obj in doc.objects.values() {
+        let Ok(dict) = obj.as_dict() else { continue };
+        if !matches!(dict.get(b"Type"), Ok(Object::Name(n)) if n == b"Font") {
+            continue;
+        }
+        let subtype = dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .unwrap_or(b"");
+        if matches!(subtype, b"Type0" | b"Type3") {
+            // Type0 fonts are checked through their descendant CIDFont.
+            continue;
+        }
+        let embedded = dict
+            .get(b"FontDescriptor")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .map(|fd| fd.has(b"FontFile") || fd.has(b"FontFile2") || fd.has(b"FontFile3"))
+            .unwrap_or(false);
+        if !embedded {
+            let name = dict
+                .get(b"BaseFont")
+                .and_then(Object::as_name)
+                .map(|n| String::from_utf8_lossy(n).into_owned())
+                .unwrap_or_else(|_| "unnamed font".to_string());
+            missing.push(name);
+        }
+    }
+    missing.sort();
+    missing.dedup();
+    missing
+}
+
+/// PDF/A wants every annotation printable and none hidden.
+fn print_annotations(doc: &mut Document) {
+    const HIDDEN: i64 = 1 | 2 | 32; // Invisible, Hidden, NoView
+    const PRINT: i64 = 4;
+    let mut ids = Vec::new();
+    for page in doc.get_pages().into_values() {
+        let Ok(annots) = doc
+            .get_dictionary(page)
+            .and_then(|p| p.get(b"Annots"))
+            .and_then(|a| match a {
+                Object::Reference(id) => doc.get_object(*id),
+                other => Ok(other),
+            })
+            .and_then(Object::as_array)
+        else {
+            continue;
+        };
+        ids.extend(annots.iter().filter_map(|a| a.as_reference().ok()));
+    }
+    for id in ids {
+        if let Ok(dict) = doc.get_object_mut(id).and_then(Object::as_dict_mut) {
+            let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+            dict.set("F", (flags & !HIDDEN) | PRINT);
+        }
+    }
+}
+
+/// The information dictionary's text entries, decoded.
+#[derive(Default)]
+struct Info {
+    title: Option<String>,
+    author: Option<String>,
+    subject: Option<String>,
+    keywords: Option<String>,
+    creator: Option<String>,
+    producer: Option<String>,
+    created: Option<String>,
+    modified: Option<String>,
+}
+
+fn info(doc: &Document) -> Info {
+    let Some(dict) = doc
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .ok()
+    else {
+        return Info::default();
+    };
+    let text = |key: &[u8]| match dict.get(key) {
+        Ok(Object::String(bytes, _)) => Some(crate::pdf::decode_text(bytes)),
+        _ => None,
+    };
+    Info {
+        title: text(b"Title"),
+        author: text(b"Author"),
+        subject: text(b"Subject"),
+        keywords: text(b"Keywords"),
+        creator: text(b"Creator"),
+        producer: text(b"Producer"),
+        created: text(b"CreationDate").and_then(|d| xmp_date(&d)),
+        modified: text(b"ModDate").and_then(|d| xmp_date(&d)),
+    }
+}
+
+/// `D:20240101120000+01'00'` -> `2024-01-01T12:00:00+01:00`.
+fn xmp_date(pdf: &str) -> Option<String> {
+    let s = pdf.strip_prefix("D:").unwrap_or(pdf);
+    let digits: String = s.chars().take_while(char::is_ascii_digit).collect();
+    if digits.len() < 4 {
+        return None;
+    }
+    let part = |from: usize, default: &'static str| digits.get(from..from + 2).unwrap_or(default);
+    let zone = &s[digits.len()..];
+    let tz = match zone.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let z: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+            format!(
+                "{}{}:{}",
+                sign,
+                z.get(0..2).unwrap_or("00"),
+                z.get(2..4).unwrap_or("00")
+            )
+        }
+        _ => "Z".to_string(),
+    };
+    Some(format!(
+        "{}-{}-{}T{}:{}:{}{}",
+        &digits[0..4],
+        part(4, "01"),
+        part(6, "01"),
+        part(8, "00"),
+        part(10, "00"),
+        part(12, "00"),
+        tz
+    ))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// XMP packet with the PDF/A identification and the same values as the info dictionary,
+/// which PDF/A requires to agree.
+fn xmp(info: &Info) -> String {
+    let mut props = String::new();
+    let alt = |name: &str, v: &str| {
+        format!(
+            "   <{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>\n",
+            name,
+            escape(v)
+        )
+    };
+    let simple = |name: &str, v: &str| format!("   <{0}>{1}</{0}>\n", name, escape(v));
+    if let Some(v) = &info.title {
+        props += &alt("dc:title", v);
+    }
+    if let Some(v) = &info.author {
+        props += &format!(
+            "   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n",
+            escape(v)
+        );
+    }
+    if let Some(v) = &info.subject {
+        props += &alt("dc:description", v);
+    }
+    if let Some(v) = &info.keywords {
+        props += &simple("pdf:Keywords", v);
+    }
+    if let Some(v) = &info.producer {
+        props += &simple("pdf:Producer", v);
+    }
+    if let Some(v) = &info.creator {
+        props += &simple("xmp:CreatorTool", v);
+    }
+    if let Some(v) = &info.created {
+        props += &simple("xmp:CreateDate", v);
+    }
+    if let Some(v) = &info.modified {
+        props += &simple("xmp:ModifyDate", v);
+        props += &simple("xmp:MetadataDate", v);
+    }
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:pdf="http://ns.adobe.com/pdf/1.3/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">
+   <pdfaid:part>2</pdfaid:part>
+   <pdfaid:conformance>B</pdfaid:conformance>
+{props}  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        props = props
+    )
+}
+
+/// A compact ICC v2 display profile for sRGB: D50-adapted primaries and the sRGB transfer
+/// curve sampled at 1024 points.
+fn srgb_icc() -> Vec<u8> {
+    fn s15(v: f64) -> [u8; 4] {
+        ((v * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut t = b"XYZ \0\0\0\0".to_vec();
+        for v in [x, y, z] {
+            t.extend(s15(v));
+        }
+        t
+    }
+    fn text(s: &str) -> Vec<u8> {
+        let mut t = b"text\0\0\0\0".to_vec();
+        t.extend(s.as_bytes());
+        t.push(0);
+        t
+    }
+    fn desc(s: &str) -> Vec<u8> {
+        let mut t = b"desc\0\0\0\0".to_vec();
+        t.extend((s.len() as u32 + 1).to_be_bytes());
+        t.extend(s.as_bytes());
+        t.push(0);
+        // Empty Unicode and ScriptCode descriptions.
+        t.extend([0u8; 8]);
+        t.extend([0u8; 3]);
+        t.extend([0u8; 67]);
+        t
+    }
+    let mut curve = b"curv\0\0\0\0".to_vec();
+    const POINTS: u32 = 1024;
+    curve.extend(POINTS.to_be_bytes());
+    for i in 0..POINTS {
+        let v = f64::from(i) / f64::from(POINTS - 1);
+        let linear = if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        };
+        curve.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", desc(SRGB)),
+        (b"cprt", text("No copyright, use freely")),
+        (b"wtpt", xyz(0.9642, 1.0, 0.8249)),
+        (b"rXYZ", xyz(0.4361, 0.2225, 0.0139)),
+        (b"gXYZ", xyz(0.3851, 0.7169, 0.0971)),
+        (b"bXYZ", xyz(0.1431, 0.0606, 0.7141)),
+        (b"rTRC", curve),
+    ];
+    // The three TRC tags share one curve.
+    let table_len = 4 + 12 * (tags.len() + 2);
+    let mut offset = 128 + table_len;
+    let mut table = ((tags.len() + 2) as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    for (sig, body) in &tags {
+        let entry = |sig: &[u8; 4], table: &mut Vec<u8>| {
+            table.extend(sig);
+            table.extend((offset as u32).to_be_bytes());
+            table.extend((body.len() as u32).to_be_bytes());
+        };
+        entry(sig, &mut table);
+        if *sig == b"rTRC" {
+            entry(b"gTRC", &mut table);
+            entry(b"bTRC", &mut table);
+        }
+        data.extend(body);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset = 128 + table_len + data.len();
+    }
+
+    let size = 128 + table.len() + data.len();
+    let mut header = Vec::with_capacity(128);
+    header.extend((size as u32).to_be_bytes());
+    header.extend([0u8; 4]); // CMM
+    header.extend([0x02, 0x10, 0, 0]); // version 2.1
+    header.extend(b"mntrRGB XYZ ");
+    for v in [2024u16, 1, 1, 0, 0, 0] {
+        header.extend(v.to_be_bytes());
+    }
+    header.extend(b"acsp");
+    header.extend([0u8; 24]); // platform, flags, manufacturer, model, attributes
+    header.extend([0u8; 4]); // perceptual intent
+    header.extend(s15(0.9642));
+    header.extend(s15(1.0));
+    header.extend(s15(0.8249));
+    header.resize(128, 0);
+
+    let mut icc = header;
+    icc.extend(table);
+    icc.extend(data);
+    icc
+}
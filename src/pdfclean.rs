@@ -0,0 +1,80 @@
+//! `--pdf-clean`: a print stylesheet for readable archival PDFs without per-site CSS.
+//!
+//! Applied after the screenshot and before printing, and undone right after printing, so
+//! later captures (the MHTML archive, extraction) see the page as rendered. Everything is
+//! under `@media print`, except that collapsed `<details>` and accordions are opened.
+
+use anyhow::Result;
+
+const CLEAN_JS: &str = r#"(() => {
+  for (const d of document.querySelectorAll('details:not([open])')) {
+    d.open = true;
+    d.setAttribute('data-ankabot-opened', '');
+  }
+  // Accordions: the panel a collapsed toggle controls.
+  for (const t of document.querySelectorAll('[aria-expanded="false"][aria-controls]')) {
+    for (const id of t.getAttribute('aria-controls').split(/\s+/)) {
+      const panel = id && document.getElementById(id);
+      if (panel) {
+        if (panel.hasAttribute('hidden')) {
+          panel.removeAttribute('hidden');
+          panel.setAttribute('data-ankabot-unhidden', '');
+        }
+        panel.setAttribute('data-ankabot-expand', '');
+      }
+    }
+  }
+  // Fixed overlays (cookie banners, sticky bars, chat widgets) would print on every page.
+  for (const el of document.body ? document.body.querySelectorAll('*') : []) {
+    const pos = getComputedStyle(el).position;
+    if (pos === 'fixed') el.setAttribute('data-ankabot-fixed', '');
+    else if (pos === 'sticky') el.setAttribute('data-ankabot-sticky', '');
+  }
+  const style = document.createElement('style');
+  style.id = '__ankabot_pdf_clean';
+  style.textContent = `@media print {
+  nav, aside, footer, body > header, [role="navigation"], [role="banner"],
+  [role="contentinfo"], [role="complementary"], [role="search"], [aria-modal="true"],
+  [data-ankabot-fixed], .adsbygoogle, ins.adsbygoogle, [id^="google_ads"],
+  [id^="div-gpt-ad"], iframe[src*="doubleclick"], iframe[src*="googlesyndication"],
+  #onetrust-consent-sdk, .fc-consent-root, #CybotCookiebotDialog,
+  video, audio, button, input, select, textarea {
+    display: none !important;
+  }
+  [data-ankabot-sticky] { position: static !important; }
+  [data-ankabot-expand] {
+    display: block !important; height: auto !important; max-height: none !important;
+    overflow: visible !important; visibility: visible !important; opacity: 1 !important;
+  }
+  html, body { height: auto !important; overflow: visible !important; }
+  img, svg, canvas { max-width: 100% !important; height: auto; }
+  figure, img, svg, pre, blockquote, table, tr { break-inside: avoid; }
+  h1, h2, h3, h4, h5, h6 { break-after: avoid; }
+  p { orphans: 3; widows: 3; }
+}`;
+  (document.head || document.documentElement).appendChild(style);
+})()"#;
+
+const CLEANUP_JS: &str = r#"(() => {
+  document.getElementById('__ankabot_pdf_clean')?.remove();
+  for (const d of document.querySelectorAll('details[data-ankabot-opened]')) d.open = false;
+  for (const el of document.querySelectorAll('[data-ankabot-unhidden]')) {
+    el.setAttribute('hidden', '');
+  }
+  const marks = ['opened', 'unhidden', 'expand', 'fixed', 'sticky'].map((m) => 'data-ankabot-' + m);
+  for (const el of document.querySelectorAll(marks.map((m) => `[${m}]`).join(','))) {
+    for (const m of marks) el.removeAttribute(m);
+  }
+})()"#;
+
+/// Open collapsed content and install the print stylesheet.
+pub fn apply(tab: &headless_chrome::Tab) -> Result<()> {
+    tab.evaluate(CLEAN_JS, false)?;
+    Ok(())
+}
+
+/// Undo [`apply`].
+pub fn cleanup(tab: &headless_chrome::Tab) -> Result<()> {
+    tab.evaluate(CLEANUP_JS, false)?;
+    Ok(())
+}
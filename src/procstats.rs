@@ -0,0 +1,100 @@
+//! Chrome's own resource use during a render: the launched browser and every process it
+//! forked, sampled from /proc every 250 ms, reported as peak memory and CPU seconds in
+//! `diagnostics.chrome_process`, for sizing worker containers.
+//!
+//! Linux only; elsewhere there is nothing to sample and the block is left out. CPU time of
+//! a renderer that exits between samples is counted up to its last sample.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const INTERVAL: Duration = Duration::from_millis(250);
+/// USER_HZ, the unit of /proc/<pid>/stat CPU times: 100 on every Linux ABI.
+const TICKS_PER_SECOND: f64 = 100.0;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProcessUsage {
+    /// Highest total resident memory of the process tree.
+    pub peak_rss_mb: u64,
+    /// User plus system CPU time across the tree.
+    pub cpu_seconds: f64,
+    /// Most processes alive at once (browser, GPU, network, renderers, …).
+    pub peak_processes: usize,
+    pub samples: usize,
+}
+
+pub struct Sampler {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Option<ProcessUsage>>,
+}
+
+impl Sampler {
+    /// Start sampling `pid` and its descendants in the background.
+    pub fn start(pid: u32) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let handle = std::thread::spawn(move || {
+            let mut peak_rss_kb = 0;
+            let mut peak_processes = 0;
+            let mut samples = 0;
+            let mut cpu_ticks: HashMap<u32, u64> = HashMap::new();
+            loop {
+                let mut rss_kb = 0;
+                let mut alive = 0;
+                for p in std::iter::once(pid).chain(crate::cgroup::descendants(pid)) {
+                    let Some((ticks, rss)) = sample(p) else {
+                        continue;
+                    };
+                    alive += 1;
+                    rss_kb += rss;
+                    cpu_ticks.insert(p, ticks);
+                }
+                if alive > 0 {
+                    samples += 1;
+                    peak_rss_kb = peak_rss_kb.max(rss_kb);
+                    peak_processes = peak_processes.max(alive);
+                }
+                if flag.load(Ordering::Relaxed) {
+                    break;
+                }
+                std::thread::sleep(INTERVAL);
+            }
+            (samples > 0).then(|| ProcessUsage {
+                peak_rss_mb: peak_rss_kb / 1024,
+                cpu_seconds: (cpu_ticks.values().sum::<u64>() as f64 / TICKS_PER_SECOND * 100.0)
+                    .round()
+                    / 100.0,
+                peak_processes,
+                samples,
+            })
+        });
+        Self { stop, handle }
+    }
+
+    /// Take a last sample and stop.
+    pub fn finish(self) -> Option<ProcessUsage> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().ok().flatten()
+    }
+}
+
+/// (utime + stime in ticks, VmRSS in kB) of one process.
+fn sample(pid: u32) -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // Fields resume after the command name's closing parenthesis: state is the first,
+    // utime and stime are the 12th and 13th.
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    let ticks = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let rss = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmRSS:"))
+        .and_then(|v| v.split_whitespace().next())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    Some((ticks, rss))
+}
@@ -0,0 +1,93 @@
+//! Terminal dashboard for batch/crawl runs: one line per worker plus an overall bar.
+//!
+//! Drawn on stderr only when it is a terminal (and `--no-tui` is not set), so stdout
+//! stays a clean list of result.json paths.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{
+    io::IsTerminal,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+pub struct Progress {
+    overall: ProgressBar,
+    workers: Vec<ProgressBar>,
+    ok: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Progress {
+    pub fn new(total: u64, workers: usize, no_tui: bool) -> Self {
+        let enabled = !no_tui && std::io::stderr().is_terminal();
+        let mp = MultiProgress::with_draw_target(if enabled {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        });
+        let overall = mp.add(ProgressBar::new(total));
+        overall.set_style(
+            ProgressStyle::with_template(
+                "{bar:30.cyan/blue} {pos}/{len} {msg} [{elapsed_precise}, eta {eta}]",
+            )
+            .expect("valid template")
+            .progress_chars("=> "),
+        );
+        overall.set_message("ok 0, failed 0");
+        let worker_style =
+            ProgressStyle::with_template("{spinner} w{prefix} {wide_msg}").expect("valid template");
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let pb = mp.add(ProgressBar::new_spinner());
+                pb.set_style(worker_style.clone());
+                pb.set_prefix((i + 1).to_string());
+                pb.set_message("idle");
+                if enabled {
+                    pb.enable_steady_tick(Duration::from_millis(120));
+                }
+                pb
+            })
+            .collect();
+        Self {
+            overall,
+            workers,
+            ok: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// Upper bound on the work for the ETA; grows as a crawl discovers URLs.
+    pub fn set_total(&self, total: u64) {
+        self.overall.set_length(total);
+    }
+
+    pub fn start(&self, worker: usize, url: &str) {
+        if let Some(pb) = self.workers.get(worker) {
+            pb.set_message(url.to_string());
+        }
+    }
+
+    pub fn finish(&self, worker: usize, ok: bool) {
+        if ok {
+            self.ok.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(pb) = self.workers.get(worker) {
+            pb.set_message("idle");
+        }
+        self.overall.set_message(format!(
+            "ok {}, failed {}",
+            self.ok.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed)
+        ));
+        self.overall.inc(1);
+    }
+
+    pub fn done(&self) {
+        for pb in &self.workers {
+            pb.finish_and_clear();
+        }
+        self.overall.finish();
+    }
+}
@@ -0,0 +1,207 @@
+//! `ankabot prune`: retention for `--out-root`. Runs are found through the run index, so
+//! only directories ankabot recorded are ever deleted; their `--archive` files go with
+//! them and the index forgets them.
+
+use crate::index::{IndexedRun, RunIndex};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub struct Policy {
+    /// Remove runs created longer ago than this.
+    pub older_than: Option<Duration>,
+    /// Keep only the newest N runs of each host.
+    pub max_runs_per_host: Option<usize>,
+    /// List what would be removed without removing it.
+    pub dry_run: bool,
+}
+
+/// Apply `policy` to the runs indexed under `out_root`. Removed run directories are
+/// printed to stdout, a summary to stderr.
+pub fn run(out_root: &Path, policy: &Policy) -> Result<()> {
+    let index = RunIndex::open(out_root)?;
+    let doomed = select(index.all()?, policy, chrono::Utc::now());
+
+    let mut bytes = 0;
+    for run in &doomed {
+        let dir = PathBuf::from(&run.run_dir);
+        let mut paths = vec![dir.clone()];
+        for ext in ["zip", "tar.gz"] {
+            paths.push(with_suffix(&dir, ext));
+        }
+        for path in paths.iter().filter(|p| p.exists()) {
+            bytes += size_of(path);
+            if !policy.dry_run {
+                if path.is_dir() {
+                    std::fs::remove_dir_all(path)
+                } else {
+                    std::fs::remove_file(path)
+                }
+                .with_context(|| format!("removing {}", path.display()))?;
+            }
+        }
+        if !policy.dry_run {
+            index.forget(&run.run_dir)?;
+        }
+        println!("{}", run.run_dir);
+    }
+    eprintln!(
+        "{} {} run(s), {:.1} MB",
+        if policy.dry_run {
+            "would remove"
+        } else {
+            "removed"
+        },
+        doomed.len(),
+        bytes as f64 / 1_048_576.0
+    );
+    Ok(())
+}
+
+/// The runs `policy` removes, out of `runs` listed newest first.
+fn select(
+    runs: Vec<IndexedRun>,
+    policy: &Policy,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<IndexedRun> {
+    let cutoff = policy
+        .older_than
+        .map(|d| now - chrono::Duration::from_std(d).unwrap_or_default());
+
+    let mut per_host: HashMap<String, usize> = HashMap::new();
+    let mut doomed: Vec<IndexedRun> = Vec::new();
+    // Newest first, so the per-host count keeps the latest runs.
+    for run in runs {
+        let seen = per_host.entry(host_of(&run.url)).or_default();
+        *seen += 1;
+        let too_many = policy.max_runs_per_host.is_some_and(|max| *seen > max);
+        let too_old = cutoff.is_some_and(|cutoff| {
+            chrono::DateTime::parse_from_rfc3339(&run.created_at)
+                .is_ok_and(|created| created < cutoff)
+        });
+        if too_many || too_old {
+            doomed.push(run);
+        }
+    }
+    doomed
+}
+
+fn host_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+        .unwrap_or_default()
+}
+
+/// `dir` with `.ext` appended to its name, where `--archive` puts its file.
+fn with_suffix(dir: &Path, ext: &str) -> PathBuf {
+    let mut name = dir.as_os_str().to_owned();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+/// Total size of a file or of the files below a directory.
+fn size_of(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| size_of(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(url: &str, created_at: &str) -> IndexedRun {
+        IndexedRun {
+            id: 0,
+            url: url.to_string(),
+            final_url: None,
+            created_at: created_at.to_string(),
+            run_dir: format!("{url} {created_at}"),
+            http_status: Some(200),
+            exit_code: 0,
+            content_hash: None,
+            tags: Default::default(),
+            status: Some("ok".to_string()),
+            wait_branch: None,
+            waf_detected: None,
+            anti_bot_vendor: None,
+            elapsed_ms: None,
+            artifacts: Default::default(),
+        }
+    }
+
+    fn dirs(runs: &[IndexedRun]) -> Vec<&str> {
+        runs.iter().map(|r| r.run_dir.as_str()).collect()
+    }
+
+    fn now() -> chrono::DateTime<chrono::Utc> {
+        "2026-03-10T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn keeps_the_newest_runs_of_each_host() {
+        let runs = vec![
+            run("https://a.example/x", "2026-03-10T11:00:00Z"),
+            run("https://B.example/", "2026-03-10T10:00:00Z"),
+            run("https://a.example/y", "2026-03-09T11:00:00Z"),
+            run("https://b.example/", "2026-03-08T10:00:00Z"),
+            run("https://a.example/z", "2026-03-07T11:00:00Z"),
+        ];
+        let policy = Policy {
+            older_than: None,
+            max_runs_per_host: Some(1),
+            dry_run: false,
+        };
+        assert_eq!(
+            dirs(&select(runs, &policy, now())),
+            [
+                "https://a.example/y 2026-03-09T11:00:00Z",
+                "https://b.example/ 2026-03-08T10:00:00Z",
+                "https://a.example/z 2026-03-07T11:00:00Z",
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_runs_older_than_the_cutoff() {
+        let runs = vec![
+            run("https://a.example/", "2026-03-10T11:00:00+00:00"),
+            run("https://a.example/", "2026-03-09T13:00:00+01:00"),
+            run("https://a.example/", "2026-03-09T11:00:00Z"),
+            run("https://a.example/", "not a date"),
+        ];
+        let policy = Policy {
+            older_than: Some(Duration::from_secs(86400)),
+            max_runs_per_host: None,
+            dry_run: false,
+        };
+        assert_eq!(
+            dirs(&select(runs, &policy, now())),
+            ["https://a.example/ 2026-03-09T11:00:00Z"]
+        );
+    }
+
+    #[test]
+    fn either_limit_removes_a_run() {
+        let runs = vec![
+            run("https://a.example/", "2026-03-10T11:00:00Z"),
+            run("https://a.example/", "2026-03-10T10:00:00Z"),
+            run("https://b.example/", "2026-01-01T00:00:00Z"),
+        ];
+        let policy = Policy {
+            older_than: Some(Duration::from_secs(7 * 86400)),
+            max_runs_per_host: Some(1),
+            dry_run: true,
+        };
+        assert_eq!(select(runs, &policy, now()).len(), 2);
+    }
+}
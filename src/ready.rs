@@ -0,0 +1,315 @@
+//! Readiness waits as Promises in the page. Each wait re-checks its condition only when
+//! something it depends on happens: readyState changes, DOM mutations, tracked requests
+//! starting or ending (see `build_instrument_js`), image and font loads, transitions. A
+//! timer covers "quiet for N ms" conditions. Rust awaits the Promise with one
+//! `Runtime.evaluate` instead of sleep-polling over CDP, so a fast page is seen ready as
+//! soon as it is, and a slow one costs no round trips while it loads.
+//!
+//! The Promise settles on its own after at most [`SLICE`]. A longer evaluate would trip
+//! the DevTools connection's idle timeout on a quiet page. State that must outlive a slice
+//! (since when a heuristic has held, when a box last moved) is kept in the page under the
+//! wait's key. A navigation during a wait (a scripted redirect) destroys the context, and
+//! the wait carries on in the new document.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Longest single `Runtime.evaluate`; well under the transport's 30 s idle timeout.
+const SLICE: Duration = Duration::from_secs(5);
+
+/// `(spec, key, timeoutMs, probe) => Promise<JSON {done, value}>`. Specs by `kind`:
+/// - `ready`: `readyState` (`complete`|`interactive`), `idleMs`, `threshold`, `minChars`,
+///   `domQuietMs` (or null); resolves to the wait branch.
+/// - `idle`: `idleMs`, `threshold`; tracked requests quiet.
+/// - `assets`: every image complete and fonts loaded.
+/// - `selectors`: `sels`, `mode` (`any`|`all`), `visible`, `stableMs` (or null); resolves to
+///   the selector that matched in `any` mode. Unfinished, the value is each selector's
+///   state: `ready`, `missing`, `hidden` or `moving`.
+/// - `xpath`: `xpath`.
+/// - `js`: `probe()` truthy; the value is `{value}` or, if it threw, `{error}`. A plain
+///   variable assignment raises no event, so this one also re-checks every 100 ms.
+///
+/// A selector or XPath that doesn't parse ends the wait at once with `{invalid}`: the
+/// selector, or the parse error.
+const WAIT_JS: &str = r#"((spec, key, timeout, probe) => new Promise((resolve) => {
+  const a = window.__ankabot || { pending: 0, lastMutation: 0, netChanged: 0 };
+  const waits = window.__ankabotWaits || (window.__ankabotWaits = {});
+  const st = waits[key] || (waits[key] = { boxes: {} });
+  const cleanups = [];
+  let settled = false, busy = false, wake = null, queued = null, lastRun = -1e9, value = null;
+  const finish = (done) => {
+    if (settled) return;
+    settled = true;
+    clearTimeout(limit); clearTimeout(wake); clearTimeout(queued);
+    for (const f of cleanups) f();
+    if (done) delete waits[key];
+    resolve(JSON.stringify({ done, value }));
+  };
+  const limit = setTimeout(() => finish(false), timeout);
+  const apply = (r) => {
+    if (settled) return;
+    value = r.value === undefined ? null : r.value;
+    if (r.done) return finish(true);
+    clearTimeout(wake);
+    if (r.next != null) wake = setTimeout(run, Math.max(0, r.next));
+  };
+  const run = () => {
+    if (settled || busy) return;
+    lastRun = performance.now();
+    const r = check(lastRun);
+    if (!(r instanceof Promise)) return apply(r);
+    // An async check: events arriving meanwhile are covered by its `next`.
+    busy = true;
+    r.then((r) => { busy = false; apply(r); });
+  };
+  // Bursts of events (a framework rendering) are coalesced into one check per 50 ms.
+  const kick = () => {
+    if (queued || settled) return;
+    queued = setTimeout(() => { queued = null; run(); },
+      Math.max(0, 50 - (performance.now() - lastRun)));
+  };
+  const on = (target, ev, capture) => {
+    if (!target) return;
+    target.addEventListener(ev, kick, capture);
+    cleanups.push(() => target.removeEventListener(ev, kick, capture));
+  };
+  const observe = (attributes) => {
+    const mo = new MutationObserver(kick);
+    mo.observe(document, { subtree: true, childList: true, characterData: true, attributes });
+    cleanups.push(() => mo.disconnect());
+  };
+  const onNet = () => {
+    if (!a.netListeners) return;
+    a.netListeners.add(kick);
+    cleanups.push(() => a.netListeners.delete(kick));
+  };
+  const idleFor = (now, threshold) => a.pending <= threshold ? now - a.netChanged : -1;
+  let check;
+  switch (spec.kind) {
+    case 'ready':
+      check = (now) => {
+        const next = [];
+        const quietFor = now - a.lastMutation;
+        const domQuiet = spec.domQuietMs == null || quietFor >= spec.domQuietMs;
+        if (!domQuiet) next.push(spec.domQuietMs - quietFor);
+        const rs = document.readyState;
+        const readyOk = spec.readyState === 'interactive' ? rs !== 'loading' : rs === 'complete';
+        if (readyOk && domQuiet) {
+          return { done: true, value: spec.domQuietMs == null ? 'ready_state' : 'dom_quiet' };
+        }
+        const idle = idleFor(now, spec.threshold);
+        if (idle >= spec.idleMs && domQuiet) return { done: true, value: 'network_idle' };
+        if (idle >= 0 && idle < spec.idleMs) next.push(spec.idleMs - idle);
+        const text = document.body ? document.body.innerText.length : 0;
+        if (text >= spec.minChars && document.querySelector('main,article,#app,#root')) {
+          if (st.heuristic == null) st.heuristic = now;
+          if (now - st.heuristic >= 600 && domQuiet) return { done: true, value: 'heuristic' };
+          if (now - st.heuristic < 600) next.push(st.heuristic + 600 - now);
+        } else {
+          st.heuristic = null;
+        }
+        return { done: false, next: next.length ? Math.min(...next) : null };
+      };
+      on(document, 'readystatechange');
+      on(window, 'load');
+      observe(false);
+      onNet();
+      break;
+    case 'idle':
+      check = (now) => {
+        const idle = idleFor(now, spec.threshold);
+        if (idle >= spec.idleMs) return { done: true };
+        return { done: false, next: idle >= 0 ? spec.idleMs - idle : null };
+      };
+      onNet();
+      break;
+    case 'assets':
+      check = () => {
+        const images = Array.from(document.images).filter((i) => !i.complete).length;
+        const fonts = !document.fonts || document.fonts.status === 'loaded';
+        return { done: images === 0 && fonts, value: { images, fonts } };
+      };
+      // load and error don't bubble, but reach a capturing listener on the document.
+      on(document, 'load', true);
+      on(document, 'error', true);
+      on(document.fonts, 'loadingdone');
+      on(document.fonts, 'loadingerror');
+      observe(false);
+      break;
+    case 'selectors':
+      check = (now) => {
+        const next = [];
+        const states = spec.sels.map((sel) => {
+          let el;
+          try { el = document.querySelector(sel); } catch (e) { return 'invalid'; }
+          if (!el) { delete st.boxes[sel]; return 'missing'; }
+          if (!spec.visible && spec.stableMs == null) return 'ready';
+          const r = el.getBoundingClientRect();
+          const box = [r.x + scrollX, r.y + scrollY, r.width, r.height].join();
+          const prev = st.boxes[sel];
+          if (!prev || prev.box !== box) st.boxes[sel] = { box, since: now };
+          const shown = r.width > 0 && r.height > 0 && (el.checkVisibility
+            ? el.checkVisibility({ opacityProperty: true, visibilityProperty: true })
+            : getComputedStyle(el).display !== 'none' &&
+              getComputedStyle(el).visibility !== 'hidden');
+          if (spec.visible && !shown) return 'hidden';
+          if (spec.stableMs != null) {
+            const held = now - st.boxes[sel].since;
+            if (held < spec.stableMs) { next.push(spec.stableMs - held); return 'moving'; }
+          }
+          return 'ready';
+        });
+        const invalid = states.indexOf('invalid');
+        if (invalid >= 0) return { done: true, value: { invalid: spec.sels[invalid] } };
+        const first = states.indexOf('ready');
+        if (spec.mode === 'any' && first >= 0) return { done: true, value: spec.sels[first] };
+        if (spec.mode === 'all' && states.every((s) => s === 'ready')) return { done: true };
+        return { done: false, value: states, next: next.length ? Math.min(...next) : null };
+      };
+      observe(true);
+      if (spec.visible || spec.stableMs != null) {
+        on(window, 'resize');
+        on(document, 'load', true);
+        on(document, 'transitionend', true);
+        on(document, 'animationend', true);
+      }
+      break;
+    case 'xpath':
+      check = () => {
+        try {
+          const r = document.evaluate(spec.xpath, document, null,
+            XPathResult.FIRST_ORDERED_NODE_TYPE, null);
+          return { done: r.singleNodeValue !== null };
+        } catch (e) {
+          return { done: true, value: { invalid: e.message } };
+        }
+      };
+      observe(true);
+      break;
+    case 'js':
+      check = async () => {
+        let v;
+        try { v = await probe(); } catch (e) { return { done: false, value: { error: String(e) }, next: 100 }; }
+        // Values JSON can't carry (DOM nodes, cycles) are reported as their string form.
+        let val = v === undefined ? null : v;
+        try { JSON.stringify(val); } catch (e) { val = String(v); }
+        return { done: !!v, value: { value: val }, next: 100 };
+      };
+      observe(true);
+      onNet();
+      break;
+    default:
+      check = () => ({ done: false, value: 'unknown wait ' + spec.kind });
+  }
+  run();
+}))"#;
+
+#[derive(Deserialize)]
+struct Slice {
+    done: bool,
+    #[serde(default)]
+    value: Value,
+}
+
+/// How an in-page wait ended.
+pub enum Outcome {
+    /// The condition held; the wait's result.
+    Done(Value),
+    /// The deadline passed; the wait's last progress report.
+    TimedOut(Value),
+}
+
+static NEXT_KEY: AtomicU64 = AtomicU64::new(0);
+
+/// Wait in the page for `spec` (see [`WAIT_JS`]) until it holds or `deadline` passes.
+pub fn until(tab: &headless_chrome::Tab, spec: &Value, deadline: Instant) -> Result<Outcome> {
+    wait(tab, spec, "null", deadline)
+}
+
+/// Wait in the page until the JavaScript expression `expr` is truthy, awaiting it if it
+/// is a Promise. The expression is spliced into the evaluated source rather than passed
+/// to `eval`, which a page's CSP may forbid.
+pub fn until_js(tab: &headless_chrome::Tab, expr: &str, deadline: Instant) -> Result<Outcome> {
+    let probe = format!("async () => ({expr}\n)");
+    wait(tab, &serde_json::json!({ "kind": "js" }), &probe, deadline)
+}
+
+fn wait(
+    tab: &headless_chrome::Tab,
+    spec: &Value,
+    probe: &str,
+    deadline: Instant,
+) -> Result<Outcome> {
+    let key = format!("w{}", NEXT_KEY.fetch_add(1, Ordering::Relaxed));
+    let mut last = Value::Null;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(Outcome::TimedOut(last));
+        }
+        let slice = match tab.evaluate(&slice_expr(spec, &key, left, probe), true) {
+            Ok(r) => parse_slice(r.value).context("in-page wait returned no result")?,
+            // The document was replaced mid-wait; start over in the new one.
+            Err(e) if e.to_string().to_ascii_lowercase().contains("context") => {
+                std::thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if slice.done {
+            return Ok(Outcome::Done(slice.value));
+        }
+        last = slice.value;
+    }
+}
+
+/// One slice of the wait: at most [`SLICE`], or what is `left` of the deadline.
+fn slice_expr(spec: &Value, key: &str, left: Duration, probe: &str) -> String {
+    format!(
+        "({WAIT_JS})({spec}, {key:?}, {}, {probe})",
+        left.min(SLICE).as_millis()
+    )
+}
+
+/// The slice's result, which comes back JSON-encoded in a string.
+fn parse_slice(value: Option<Value>) -> Option<Slice> {
+    serde_json::from_str(value?.as_str()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn slice_is_capped() {
+        let spec = json!({ "kind": "xpath", "xpath": "//main" });
+        let long = slice_expr(&spec, "w1", Duration::from_secs(60), "null");
+        assert!(long.ends_with(r#"({"kind":"xpath","xpath":"//main"}, "w1", 5000, null)"#));
+        let short = slice_expr(&spec, "w1", Duration::from_millis(1200), "null");
+        assert!(short.ends_with(", \"w1\", 1200, null)"));
+    }
+
+    #[test]
+    fn slice_results() {
+        let done = parse_slice(Some(json!(r##"{"done":true,"value":"#app"}"##))).unwrap();
+        assert!(done.done);
+        assert_eq!(done.value, json!("#app"));
+        let pending = parse_slice(Some(json!(r#"{"done":false}"#))).unwrap();
+        assert!(!pending.done);
+        assert_eq!(pending.value, Value::Null);
+        assert!(parse_slice(None).is_none());
+        assert!(parse_slice(Some(json!({ "done": true }))).is_none());
+        assert!(parse_slice(Some(json!("not json"))).is_none());
+    }
+
+    #[test]
+    fn every_wait_kind_is_handled() {
+        for kind in ["ready", "idle", "assets", "selectors", "xpath", "js"] {
+            assert!(WAIT_JS.contains(&format!("case '{kind}':")), "{kind}");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! `ankabot runs list|show`: query the run index in `<out_root>/ankabot.db` instead of
+//! walking the run directories.
+
+use crate::index::{IndexedRun, RunIndex};
+use anyhow::{bail, Result};
+use std::path::Path;
+
+pub struct Filter {
+    pub url: Option<String>,
+    pub status: Option<String>,
+    /// RFC 3339.
+    pub since: Option<String>,
+    pub limit: usize,
+}
+
+/// Print the matching runs, newest first: one line each, or one JSON object per line.
+pub fn list(out_root: &Path, filter: &Filter, json: bool) -> Result<()> {
+    let runs = RunIndex::open(out_root)?.list(
+        filter.url.as_deref(),
+        filter.status.as_deref(),
+        filter.since.as_deref(),
+        filter.limit,
+    )?;
+    for run in &runs {
+        if json {
+            println!("{}", serde_json::to_string(run)?);
+        } else {
+            println!("{}", line(run));
+        }
+    }
+    Ok(())
+}
+
+/// Print one run's index record as JSON: run `id`, else the run of `url` nearest to `at`
+/// (the latest without it), else the latest run.
+pub fn show(out_root: &Path, id: Option<i64>, url: Option<&str>, at: Option<&str>) -> Result<()> {
+    let index = RunIndex::open(out_root)?;
+    let run = match (id, url) {
+        (Some(id), _) => index.get(id)?,
+        (None, Some(url)) => index.nearest(url, at)?,
+        (None, None) => index.list(None, None, None, 1)?.pop(),
+    };
+    let Some(run) = run else {
+        match (id, url) {
+            (Some(id), _) => bail!("no run with id {}", id),
+            (None, Some(url)) => bail!("no runs of {}", url),
+            (None, None) => bail!("no runs in {}", out_root.display()),
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&run)?);
+    Ok(())
+}
+
+fn line(run: &IndexedRun) -> String {
+    format!(
+        "{:>5}  {}  {:<7}  {:>3}  {:<10}  {}  {}",
+        run.id,
+        run.created_at,
+        run.status.as_deref().unwrap_or("-"),
+        run.http_status.map_or("-".to_string(), |s| s.to_string()),
+        run.wait_branch.as_deref().unwrap_or("-"),
+        run.url,
+        run.run_dir
+    )
+}
@@ -0,0 +1,232 @@
+//! Politeness scheduler for batch/crawl runs: per-host FIFO queues, a cap on in-flight
+//! renders per host (`--host-concurrency`, default 1) and an overall worker pool
+//! (`--workers`). Higher-priority jobs go first; hosts otherwise take turns.
+
+use crate::{run_one, Cli, RunRecord};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use tokio::task::JoinSet;
+
+struct Queued<J> {
+    priority: i32,
+    job: J,
+}
+
+/// The queueing half of [`Pool`]: which job runs next, on which worker, without
+/// running anything.
+struct Queues<J> {
+    queues: HashMap<String, VecDeque<Queued<J>>>,
+    /// Hosts in the order they were last served, for round-robin between equal priorities.
+    turn: VecDeque<String>,
+    in_flight: HashMap<String, usize>,
+    per_host: usize,
+    free_workers: Vec<usize>,
+}
+
+impl<J> Queues<J> {
+    fn new(workers: usize, per_host: usize) -> Self {
+        Self {
+            queues: HashMap::new(),
+            turn: VecDeque::new(),
+            in_flight: HashMap::new(),
+            per_host: per_host.max(1),
+            free_workers: (0..workers.max(1)).rev().collect(),
+        }
+    }
+
+    /// Within a host, jobs keep FIFO order per priority.
+    fn push(&mut self, host: String, priority: i32, job: J) {
+        let q = self.queues.entry(host.clone()).or_default();
+        let at = q
+            .iter()
+            .position(|j| j.priority < priority)
+            .unwrap_or(q.len());
+        q.insert(at, Queued { priority, job });
+        if !self.turn.contains(&host) {
+            self.turn.push_back(host);
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.queues.values().map(|q| q.len()).sum()
+    }
+
+    /// The next job that may start, with the worker and host it occupies until
+    /// [`Queues::release`].
+    fn start_next(&mut self) -> Option<(usize, String, J)> {
+        if self.free_workers.is_empty() {
+            return None;
+        }
+        let host = self.pick_host()?;
+        let job = self.queues.get_mut(&host)?.pop_front()?.job;
+        let worker = self.free_workers.pop()?;
+        *self.in_flight.entry(host.clone()).or_default() += 1;
+        self.turn.retain(|h| h != &host);
+        self.turn.push_back(host.clone());
+        Some((worker, host, job))
+    }
+
+    fn release(&mut self, worker: usize, host: &str) {
+        self.free_workers.push(worker);
+        if let Some(n) = self.in_flight.get_mut(host) {
+            *n = n.saturating_sub(1);
+        }
+    }
+
+    /// The host whose head job has the highest priority among hosts under their cap;
+    /// ties go to the host served least recently.
+    fn pick_host(&self) -> Option<String> {
+        self.turn
+            .iter()
+            .filter(|h| self.in_flight.get(*h).copied().unwrap_or(0) < self.per_host)
+            .filter_map(|h| Some((h, self.queues.get(h)?.front()?.priority)))
+            .fold(None, |best: Option<(&String, i32)>, (h, p)| match best {
+                Some((_, bp)) if bp >= p => best,
+                _ => Some((h, p)),
+            })
+            .map(|(h, _)| h.clone())
+    }
+}
+
+/// A finished job handed back to the driver.
+pub struct Done<T> {
+    pub worker: usize,
+    pub meta: T,
+    pub result: Result<RunRecord>,
+}
+
+pub struct Pool<T> {
+    queues: Queues<(Cli, T)>,
+    workers: usize,
+    running: JoinSet<(usize, String, T, Result<RunRecord>)>,
+}
+
+impl<T: Send + 'static> Pool<T> {
+    pub fn new(workers: usize, per_host: usize) -> Self {
+        Self {
+            queues: Queues::new(workers, per_host),
+            workers: workers.max(1),
+            running: JoinSet::new(),
+        }
+    }
+
+    /// Queue a render of `args.url`.
+    pub fn push(&mut self, priority: i32, args: Cli, meta: T) {
+        let host = host_of(args.url());
+        self.queues.push(host, priority, (args, meta));
+    }
+
+    /// Jobs queued but not started.
+    pub fn queued(&self) -> usize {
+        self.queues.queued()
+    }
+
+    /// Start queued jobs while workers are free. `on_start` sees (worker, url) of each.
+    pub fn fill(&mut self, mut on_start: impl FnMut(usize, &str)) {
+        while let Some((worker, host, (mut args, meta))) = self.queues.start_next() {
+            if self.workers > 1 {
+                // Chrome locks its profile directory, so concurrent renders can't share
+                // one: each worker slot keeps its own, e.g. `<profile>/w2`.
+                args.user_data_dir = Some(
+                    crate::profile_dir(&args.profile, args.user_data_dir.clone())
+                        .join(format!("w{worker}")),
+                );
+            }
+            on_start(worker, args.url());
+            self.running.spawn(async move {
+                // A panic fails its own job instead of the whole run.
+                let result = match tokio::spawn(async move { run_one(&args).await }).await {
+                    Ok(result) => result,
+                    Err(e) => Err(anyhow!("render task failed: {}", e)),
+                };
+                (worker, host, meta, result)
+            });
+        }
+    }
+
+    /// Wait for the next job to finish; None when nothing is running.
+    pub async fn next_done(&mut self) -> Option<Done<T>> {
+        let joined = self.running.join_next().await?;
+        // The task itself only awaits the render, so it can't panic.
+        let (worker, host, meta, result) = joined.expect("scheduler task failed");
+        self.queues.release(worker, &host);
+        Some(Done {
+            worker,
+            meta,
+            result,
+        })
+    }
+}
+
+fn host_of(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(q: &mut Queues<&'static str>) -> Vec<&'static str> {
+        let mut started = Vec::new();
+        while let Some((worker, host, job)) = q.start_next() {
+            q.release(worker, &host);
+            started.push(job);
+        }
+        started
+    }
+
+    #[test]
+    fn hosts_take_turns() {
+        let mut q = Queues::new(1, 1);
+        q.push("a".into(), 0, "a1");
+        q.push("a".into(), 0, "a2");
+        q.push("a".into(), 0, "a3");
+        q.push("b".into(), 0, "b1");
+        q.push("b".into(), 0, "b2");
+        assert_eq!(drain(&mut q), ["a1", "b1", "a2", "b2", "a3"]);
+        assert_eq!(q.queued(), 0);
+    }
+
+    #[test]
+    fn priority_goes_first_and_keeps_fifo_within_a_level() {
+        let mut q = Queues::new(1, 1);
+        q.push("a".into(), 0, "a-low");
+        q.push("a".into(), 5, "a-high1");
+        q.push("b".into(), 1, "b-mid");
+        q.push("a".into(), 5, "a-high2");
+        assert_eq!(drain(&mut q), ["a-high1", "a-high2", "b-mid", "a-low"]);
+    }
+
+    #[test]
+    fn host_cap_holds_back_a_busy_host() {
+        let mut q = Queues::new(4, 1);
+        q.push("a".into(), 0, "a1");
+        q.push("a".into(), 0, "a2");
+        q.push("b".into(), 0, "b1");
+        let (w1, h1, j1) = q.start_next().unwrap();
+        let (_, _, j2) = q.start_next().unwrap();
+        assert_eq!((j1, j2), ("a1", "b1"));
+        // Workers are free, but host `a` is at its cap.
+        assert!(q.start_next().is_none());
+        q.release(w1, &h1);
+        assert_eq!(q.start_next().unwrap().2, "a2");
+    }
+
+    #[test]
+    fn worker_pool_limits_concurrency() {
+        let mut q = Queues::new(2, 3);
+        for job in ["a1", "a2", "a3"] {
+            q.push("a".into(), 0, job);
+        }
+        let (w1, h1, _) = q.start_next().unwrap();
+        let (w2, _, _) = q.start_next().unwrap();
+        assert_ne!(w1, w2);
+        assert!(q.start_next().is_none());
+        q.release(w1, &h1);
+        let (w3, _, job) = q.start_next().unwrap();
+        assert_eq!((w3, job), (w1, "a3"));
+    }
+}
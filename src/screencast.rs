@@ -0,0 +1,173 @@
+//! `--screencast`: a video of the session from navigation until the page is captured,
+//! recorded with `Page.startScreencast`.
+//!
+//! Chrome pushes a JPEG whenever the page repaints (nothing while it is static), so frames
+//! carry their own timestamps and the video is variable-frame-rate. Encoding to
+//! `session.webm` is done by `ffmpeg` from `PATH`; without it, or if it fails, the frames
+//! are kept in `screencast/` with an `index.json` instead.
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use headless_chrome::browser::tab::EventListener;
+use headless_chrome::protocol::cdp::{types::Event, Page::StartScreencastFormatOption};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+const QUALITY: u32 = 70;
+/// Bounds the disk a long session can take; later frames are acknowledged and dropped.
+const MAX_FRAMES: usize = 3000;
+/// How long the last frame stays on screen, in seconds.
+const LAST_FRAME_S: f64 = 1.0;
+
+type Listener = Weak<dyn EventListener<Event> + Send + Sync>;
+
+#[derive(Serialize)]
+struct Frame {
+    /// Seconds since the first frame.
+    t: f64,
+    file: String,
+}
+
+pub struct Screencast {
+    tab: Arc<headless_chrome::Tab>,
+    listener: Listener,
+    worker: JoinHandle<Vec<Frame>>,
+    frames_dir: PathBuf,
+}
+
+impl Screencast {
+    /// Start recording `tab` at most `width`x`height`; frames go to `frames_dir`.
+    pub fn start(
+        tab: Arc<headless_chrome::Tab>,
+        frames_dir: &Path,
+        width: u32,
+        height: u32,
+    ) -> Result<Screencast> {
+        std::fs::create_dir_all(frames_dir)?;
+        let (tx, rx) = mpsc::channel::<(u32, String, Option<f64>)>();
+        let listener = tab.add_event_listener(Arc::new(move |event: &Event| {
+            if let Event::PageScreencastFrame(e) = event {
+                let p = &e.params;
+                let _ = tx.send((p.session_id, p.data.clone(), p.metadata.timestamp));
+            }
+        }))?;
+
+        // Acks are CDP calls, so they are made here rather than on the event thread.
+        let acker = tab.clone();
+        let out = frames_dir.to_path_buf();
+        let worker = std::thread::spawn(move || {
+            let started = Instant::now();
+            let mut first: Option<f64> = None;
+            let mut frames = Vec::new();
+            for (session_id, data, timestamp) in rx {
+                let _ = acker.ack_screencast(session_id);
+                if frames.len() >= MAX_FRAMES {
+                    continue;
+                }
+                let Ok(jpeg) = base64::engine::general_purpose::STANDARD.decode(data) else {
+                    continue;
+                };
+                let ts = timestamp.unwrap_or_else(|| started.elapsed().as_secs_f64());
+                let t = ts - *first.get_or_insert(ts);
+                let file = format!("{:06}.jpg", frames.len() + 1);
+                if std::fs::write(out.join(&file), jpeg).is_ok() {
+                    frames.push(Frame { t, file });
+                }
+            }
+            frames
+        });
+
+        tab.start_screencast(
+            Some(StartScreencastFormatOption::Jpeg),
+            Some(QUALITY),
+            Some(width),
+            Some(height),
+            None,
+        )?;
+        Ok(Screencast {
+            tab,
+            listener,
+            worker,
+            frames_dir: frames_dir.to_path_buf(),
+        })
+    }
+
+    /// Stop recording and encode `video`. Returns the video, or the frame index when the
+    /// frames couldn't be encoded; `None` when Chrome sent no frames.
+    pub fn finish(self, video: &Path) -> Result<Option<PathBuf>> {
+        let _ = self.tab.stop_screencast();
+        // Dropping the listener closes the channel, which ends the worker.
+        self.tab.remove_event_listener(&self.listener)?;
+        let frames = self
+            .worker
+            .join()
+            .map_err(|_| anyhow!("screencast thread panicked"))?;
+        if frames.is_empty() {
+            let _ = std::fs::remove_dir_all(&self.frames_dir);
+            return Ok(None);
+        }
+
+        match encode(&self.frames_dir, &frames, video) {
+            Ok(()) => {
+                let _ = std::fs::remove_dir_all(&self.frames_dir);
+                Ok(Some(video.to_path_buf()))
+            }
+            Err(e) => {
+                eprintln!(
+                    "warning: screencast not encoded ({:#}); frames kept in {}",
+                    e,
+                    self.frames_dir.display()
+                );
+                let index = self.frames_dir.join("index.json");
+                std::fs::write(&index, serde_json::to_vec_pretty(&frames)?)?;
+                Ok(Some(index))
+            }
+        }
+    }
+}
+
+/// Encode the frames, each shown until the next one arrived, with ffmpeg's concat demuxer.
+fn encode(dir: &Path, frames: &[Frame], video: &Path) -> Result<()> {
+    let mut list = String::from("ffconcat version 1.0\n");
+    for (i, f) in frames.iter().enumerate() {
+        let next = frames.get(i + 1).map_or(f.t + LAST_FRAME_S, |n| n.t);
+        list.push_str(&format!(
+            "file '{}'\nduration {:.3}\n",
+            f.file,
+            (next - f.t).max(0.001)
+        ));
+    }
+    // The concat demuxer ignores the last entry's duration unless the file is repeated.
+    if let Some(last) = frames.last() {
+        list.push_str(&format!("file '{}'\n", last.file));
+    }
+    std::fs::write(dir.join("frames.ffconcat"), list)?;
+
+    let out = std::process::Command::new("ffmpeg")
+        .current_dir(dir)
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "concat",
+            "-i",
+            "frames.ffconcat",
+        ])
+        // VP8 needs even dimensions; frames can change size if the viewport does.
+        .args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2,format=yuv420p"])
+        .args(["-c:v", "libvpx", "-b:v", "1M", "-crf", "10"])
+        .arg(video)
+        .output()
+        .context("running ffmpeg")?;
+    if !out.status.success() {
+        return Err(anyhow!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,234 @@
+//! `ankabot serve`: HTTP API over the stored runs in the output root.
+//!
+//! * `GET /diff?url=…[&from=…][&to=…]` — structured diff between the stored runs of `url`
+//!   nearest to `from` and `to` (defaults: the latest run and the one before it).
+//! * `GET /capture?url=…[&artifact=html|screenshot|pdf|mhtml]` — render `url` with the
+//!   serve command's capture flags and return the artifacts in the response (base64 in
+//!   JSON, or one artifact as raw bytes) without touching the output root.
+
+use crate::{capture_in_memory, diff, index::RunIndex, Artifact, Cli, ScreenshotFormat};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, path::Path, sync::Arc};
+
+/// `args` carries the output root and the capture flags applied to `/capture` requests.
+pub async fn serve(args: Cli, listen: SocketAddr) -> Result<()> {
+    let args = Arc::new(args);
+    let make_svc = make_service_fn(move |_conn| {
+        let args = args.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let args = args.clone();
+                async move { Ok::<_, Infallible>(handle(args, req).await) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&listen)?.serve(make_svc);
+    println!("listening on http://{}", server.local_addr());
+    server.await?;
+    Ok(())
+}
+
+/// An API failure with the HTTP status it maps to.
+struct ApiError(StatusCode, String);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))
+    }
+}
+
+async fn handle(args: Arc<Cli>, req: Request<Body>) -> Response<Body> {
+    let query: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let res = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/diff") => diff_handler(&args.out_root, &query).map(|b| (JSON, b)),
+        (&Method::GET, "/capture") => capture_handler(args, &query).await,
+        _ => Err(ApiError(StatusCode::NOT_FOUND, "not found".to_string())),
+    };
+    match res {
+        Ok((content_type, body)) => Response::builder()
+            .header("content-type", content_type)
+            .body(Body::from(body))
+            .unwrap(),
+        Err(ApiError(status, msg)) => {
+            json_response(status, json(&serde_json::json!({ "error": msg })))
+        }
+    }
+}
+
+fn diff_handler(root: &Path, query: &HashMap<String, String>) -> Result<Vec<u8>, ApiError> {
+    let url = query
+        .get("url")
+        .ok_or_else(|| ApiError(StatusCode::BAD_REQUEST, "missing url".to_string()))?;
+    let at = |key: &str| -> Result<Option<String>, ApiError> {
+        query
+            .get(key)
+            .map(|s| parse_time(s).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string())))
+            .transpose()
+    };
+    let (from_at, to_at) = (at("from")?, at("to")?);
+
+    let index = RunIndex::open(root)?;
+    let not_found = || {
+        ApiError(
+            StatusCode::NOT_FOUND,
+            format!("fewer than two runs of {}", url),
+        )
+    };
+    let to = index
+        .nearest(url, to_at.as_deref())?
+        .ok_or_else(not_found)?;
+    let from = match &from_at {
+        Some(t) => index.nearest(url, Some(t))?,
+        None => index.previous(url, to.id)?,
+    }
+    .ok_or_else(not_found)?;
+    Ok(json(&diff::diff_runs(from, to)?))
+}
+
+async fn capture_handler(
+    args: Arc<Cli>,
+    query: &HashMap<String, String>,
+) -> Result<(&'static str, Vec<u8>), ApiError> {
+    use base64::Engine as _;
+
+    let url = query
+        .get("url")
+        .ok_or_else(|| ApiError(StatusCode::BAD_REQUEST, "missing url".to_string()))?
+        .clone();
+    let only = query
+        .get("artifact")
+        .map(|a| {
+            parse_artifact(a, args.screenshot_format).ok_or_else(|| {
+                ApiError(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "unknown artifact '{}' (html, screenshot, {}, pdf, mhtml)",
+                        a,
+                        shot_name(args.screenshot_format)
+                    ),
+                )
+            })
+        })
+        .transpose()?;
+
+    let capture = tokio::task::spawn_blocking(move || capture_in_memory(&url, &args, true))
+        .await
+        .map_err(|e| anyhow!("capture task failed: {}", e))?
+        .map_err(|e| {
+            let msg = format!("{:#}", e);
+            let status = if msg.contains("timeout") || msg.contains("EventNeverCame") {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::BAD_GATEWAY
+            };
+            ApiError(status, msg)
+        })?;
+
+    if let Some(wanted) = only {
+        return capture
+            .artifacts
+            .into_iter()
+            .find(|(a, _)| *a == wanted)
+            .map(|(a, bytes)| (a.content_type(), bytes))
+            .ok_or_else(|| {
+                ApiError(
+                    StatusCode::BAD_GATEWAY,
+                    format!("{} was not captured", wanted.file_name()),
+                )
+            });
+    }
+    let artifacts: serde_json::Map<String, serde_json::Value> = capture
+        .artifacts
+        .iter()
+        .map(|(a, bytes)| {
+            (
+                a.file_name().to_string(),
+                serde_json::json!({
+                    "content_type": a.content_type(),
+                    "bytes": bytes.len(),
+                    "base64": base64::engine::general_purpose::STANDARD.encode(bytes),
+                }),
+            )
+        })
+        .collect();
+    Ok((
+        JSON,
+        json(&serde_json::json!({
+        "final_url": capture.final_url,
+        "wait_branch": capture.wait_branch,
+        "elapsed_ms": capture.elapsed_ms,
+        "pdf_encrypted": capture.pdf_encrypted,
+        "artifacts": artifacts,
+        "warnings": capture.warnings,
+        })),
+    ))
+}
+
+fn shot_name(shot: ScreenshotFormat) -> String {
+    shot.to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// `screenshot`, or the name of the format the server captures screenshots in.
+fn parse_artifact(name: &str, shot: ScreenshotFormat) -> Option<Artifact> {
+    Some(match name {
+        "html" => Artifact::Html,
+        "screenshot" => Artifact::Screenshot(shot),
+        _ if name == shot_name(shot) => Artifact::Screenshot(shot),
+        "pdf" => Artifact::Pdf,
+        "mhtml" => Artifact::Mhtml,
+        _ => return None,
+    })
+}
+
+/// Accept RFC 3339, `YYYY-MM-DD[THH:MM[:SS]]` (UTC) or a run-directory stamp
+/// `YYYYmmdd-HHMMSS` (local time); returns RFC 3339 UTC.
+pub fn parse_time(s: &str) -> Result<String> {
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+    let utc = |t: chrono::DateTime<Utc>| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(utc(t.with_timezone(&Utc)));
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(t) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Ok(utc(Utc.from_utc_datetime(&t)));
+        }
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(utc(Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap())));
+    }
+    if let Ok(t) = NaiveDateTime::parse_from_str(s, "%Y%m%d-%H%M%S") {
+        if let Some(local) = chrono::Local.from_local_datetime(&t).earliest() {
+            return Ok(utc(local.with_timezone(&Utc)));
+        }
+    }
+    Err(anyhow!("unrecognised time '{}'", s))
+}
+
+fn json<T: serde::Serialize>(v: &T) -> Vec<u8> {
+    serde_json::to_vec_pretty(v).unwrap_or_default()
+}
+
+const JSON: &str = "application/json";
+
+fn json_response(status: StatusCode, body: Vec<u8>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", JSON)
+        .body(Body::from(body))
+        .unwrap()
+}
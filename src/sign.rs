@@ -0,0 +1,202 @@
+//! `--sign key.pem`: a signed manifest of the run, so a capture can be shown not to have
+//! been altered afterwards.
+//!
+//! `manifest.json` lists the sha256 of every artifact (everything in the run directory, plus
+//! debug artifacts a timeout report points to elsewhere) with the capture metadata and the
+//! signer's public key. `manifest.sig` is the raw Ed25519 signature over the exact bytes of
+//! `manifest.json`, so it verifies with stock OpenSSL:
+//!
+//! ```text
+//! openssl pkey -in key.pem -pubout -out pub.pem
+//! openssl pkeyutl -verify -pubin -inkey pub.pem -rawin -in manifest.json -sigfile manifest.sig
+//! ```
+
+use crate::hash;
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use ed25519_dalek::{pkcs8::DecodePrivateKey, Signer, SigningKey};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "manifest.json";
+const SIGNATURE: &str = "manifest.sig";
+
+#[derive(Serialize)]
+struct Manifest {
+    tool: String,
+    signed_at: String,
+    input_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    final_url: Option<String>,
+    /// `ok`, or the report status (`timeout`, `blocked`).
+    status: String,
+    exit_code: i32,
+    algorithm: &'static str,
+    /// Base64 of the raw 32-byte Ed25519 public key.
+    public_key: String,
+    artifacts: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    /// Relative to the run directory, or absolute for files outside it.
+    path: String,
+    bytes: u64,
+    sha256: String,
+}
+
+/// Read an Ed25519 private key in PKCS#8 PEM (`openssl genpkey -algorithm ed25519`).
+pub fn load_key(path: &Path) -> Result<SigningKey> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("reading signing key {}", path.display()))?;
+    SigningKey::from_pkcs8_pem(&pem).map_err(|e| {
+        anyhow::anyhow!(
+            "{}: not an Ed25519 PKCS#8 private key ({})",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Write `manifest.json` and `manifest.sig` next to `result_json`; returns their paths.
+pub fn sign_run(
+    key: &SigningKey,
+    input_url: &str,
+    result_json: &Path,
+    exit_code: i32,
+) -> Result<[PathBuf; 2]> {
+    let run_dir = result_json.parent().unwrap_or(Path::new("."));
+    let result: serde_json::Value = serde_json::from_slice(&std::fs::read(result_json)?)?;
+
+    let mut files = Vec::new();
+    collect_files(run_dir, &mut files)?;
+    files.retain(|p| !p.ends_with(MANIFEST) && !p.ends_with(SIGNATURE));
+    // Timeout reports keep their debug artifacts outside the run directory.
+    if let Some(artifacts) = result.get("artifacts").and_then(|a| a.as_object()) {
+        for p in artifacts
+            .values()
+            .filter_map(|v| v.as_str())
+            .map(PathBuf::from)
+        {
+            if p.is_file() && !p.starts_with(run_dir) && !files.contains(&p) {
+                files.push(p);
+            }
+        }
+    }
+    files.sort();
+
+    let mut artifacts = Vec::new();
+    for file in files {
+        let bytes = std::fs::read(&file).with_context(|| format!("hashing {}", file.display()))?;
+        artifacts.push(Entry {
+            path: file
+                .strip_prefix(run_dir)
+                .unwrap_or(&file)
+                .display()
+                .to_string(),
+            bytes: bytes.len() as u64,
+            sha256: hash::sha256_hex(&bytes),
+        });
+    }
+
+    let manifest = Manifest {
+        tool: format!("ankabot {}", env!("CARGO_PKG_VERSION")),
+        signed_at: chrono::Utc::now().to_rfc3339(),
+        input_url: input_url.to_string(),
+        final_url: result
+            .get("final_url")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        status: result
+            .get("status")
+            .and_then(|v| v.as_str())
+            .unwrap_or("ok")
+            .to_string(),
+        exit_code,
+        algorithm: "ed25519",
+        public_key: base64::engine::general_purpose::STANDARD
+            .encode(key.verifying_key().as_bytes()),
+        artifacts,
+    };
+    let bytes = serde_json::to_vec_pretty(&manifest)?;
+    let signature = key.sign(&bytes);
+    let manifest_path = run_dir.join(MANIFEST);
+    let signature_path = run_dir.join(SIGNATURE);
+    std::fs::write(&manifest_path, &bytes)?;
+    std::fs::write(&signature_path, signature.to_bytes())?;
+    Ok([manifest_path, signature_path])
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    /// Check `manifest.sig` against the embedded public key and every listed sha256
+    /// against the file on disk, the way a recipient would.
+    fn verify(run_dir: &Path) -> Result<(), String> {
+        let bytes = std::fs::read(run_dir.join(MANIFEST)).unwrap();
+        let sig = std::fs::read(run_dir.join(SIGNATURE)).unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(manifest["public_key"].as_str().unwrap())
+            .unwrap();
+        let key = VerifyingKey::from_bytes(&key.try_into().unwrap()).unwrap();
+        let sig = Signature::from_slice(&sig).unwrap();
+        key.verify(&bytes, &sig).map_err(|e| e.to_string())?;
+        for a in manifest["artifacts"].as_array().unwrap() {
+            let path = run_dir.join(a["path"].as_str().unwrap());
+            let actual = hash::file_sha256(&path).unwrap();
+            if actual != a["sha256"] {
+                return Err(format!("{} changed", path.display()));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn signed_runs_verify_until_an_artifact_changes() {
+        let run_dir = std::env::temp_dir().join(format!("ankabot-sign-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&run_dir);
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let result_json = run_dir.join("result.json");
+        std::fs::write(&result_json, r#"{"final_url": "https://example.com/"}"#).unwrap();
+        std::fs::write(run_dir.join("dom.html"), "<html>original</html>").unwrap();
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let [manifest, _] = sign_run(&key, "https://example.com", &result_json, 0).unwrap();
+        assert_eq!(verify(&run_dir), Ok(()));
+        let listed: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest).unwrap()).unwrap();
+        let paths: Vec<&str> = listed["artifacts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["dom.html", "result.json"]);
+
+        std::fs::write(run_dir.join("dom.html"), "<html>altered</html>").unwrap();
+        assert!(verify(&run_dir).unwrap_err().ends_with("dom.html changed"));
+
+        // Re-pointing the manifest at the altered file breaks the signature instead.
+        let text = std::fs::read_to_string(&manifest).unwrap();
+        let altered = hash::sha256_hex("<html>altered</html>");
+        let original = hash::sha256_hex("<html>original</html>");
+        std::fs::write(&manifest, text.replace(&original, &altered)).unwrap();
+        assert!(verify(&run_dir).is_err_and(|e| !e.contains("changed")));
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+}
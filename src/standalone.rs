@@ -0,0 +1,424 @@
+//! Self-contained copy of the saved DOM (`--standalone`): stylesheets inlined, images and
+//! fonts turned into data URIs, scripts removed and remaining links made absolute, so
+//! `page.standalone.html` renders offline from a single file.
+
+use crate::article::strip_scripts;
+use anyhow::Result;
+use base64::Engine as _;
+use std::{collections::HashMap, path::Path, time::Duration};
+
+/// Resources larger than this are linked (absolute URL) instead of inlined.
+const MAX_INLINE_BYTES: usize = 8 * 1024 * 1024;
+/// Depth limit for nested `@import`s.
+const MAX_IMPORT_DEPTH: u32 = 4;
+
+pub async fn write_standalone(html_path: &Path, base: &str, out_path: &Path) -> Result<()> {
+    let html = std::fs::read_to_string(html_path)?;
+    let base = url::Url::parse(base)?;
+    let mut inliner = Inliner::new()?;
+    let out = inliner.rewrite_html(&strip_scripts(&html), &base).await;
+    std::fs::write(out_path, out)?;
+    Ok(())
+}
+
+struct Inliner {
+    client: reqwest::Client,
+    /// URL -> (mime type, body); None when the fetch failed or was too large.
+    cache: HashMap<String, Option<(String, Vec<u8>)>>,
+}
+
+impl Inliner {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .user_agent(ua_generator::ua::spoof_ua())
+                .timeout(Duration::from_secs(15))
+                .build()?,
+            cache: HashMap::new(),
+        })
+    }
+
+    async fn fetch(&mut self, url: &url::Url) -> Option<(String, Vec<u8>)> {
+        let key = url.to_string();
+        if let Some(hit) = self.cache.get(&key) {
+            return hit.clone();
+        }
+        let fetched = async {
+            let resp = self.client.get(url.clone()).send().await.ok()?;
+            if !resp.status().is_success() {
+                return None;
+            }
+            let mime = resp
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.split(';').next().unwrap_or(v).trim().to_string())
+                .unwrap_or_else(|| guess_mime(url.path()).to_string());
+            let body = resp.bytes().await.ok()?;
+            (body.len() <= MAX_INLINE_BYTES).then(|| (mime, body.to_vec()))
+        }
+        .await;
+        self.cache.insert(key, fetched.clone());
+        fetched
+    }
+
+    /// `href` as a data URI, or as an absolute URL when it can't be fetched.
+    async fn data_uri(&mut self, href: &str, base: &url::Url) -> String {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with("data:") || href.starts_with('#') {
+            return href.to_string();
+        }
+        let Ok(url) = base.join(href) else {
+            return href.to_string();
+        };
+        match self.fetch(&url).await {
+            Some((mime, body)) => format!(
+                "data:{};base64,{}",
+                mime,
+                base64::engine::general_purpose::STANDARD.encode(body)
+            ),
+            None => url.to_string(),
+        }
+    }
+
+    async fn rewrite_html(&mut self, html: &str, base: &url::Url) -> String {
+        let lower = html.to_ascii_lowercase();
+        let mut out = String::with_capacity(html.len());
+        let mut i = 0;
+        while let Some(lt) = lower[i..].find('<').map(|p| i + p) {
+            out.push_str(&html[i..lt]);
+            if lower[lt..].starts_with("<!--") {
+                let end = lower[lt..]
+                    .find("-->")
+                    .map(|e| lt + e + 3)
+                    .unwrap_or(html.len());
+                out.push_str(&html[lt..end]);
+                i = end;
+                continue;
+            }
+            let end = lower[lt..]
+                .find('>')
+                .map(|e| lt + e + 1)
+                .unwrap_or(html.len());
+            i = end;
+            let Some(mut tag) = Tag::parse(&html[lt..end]) else {
+                out.push_str(&html[lt..end]);
+                continue;
+            };
+            match tag.name.as_str() {
+                "base" => continue,
+                "meta"
+                    if tag
+                        .get("http-equiv")
+                        .is_some_and(|v| v.eq_ignore_ascii_case("content-security-policy")) =>
+                {
+                    continue
+                }
+                "style" => {
+                    let close = lower[i..]
+                        .find("</style")
+                        .map(|e| i + e)
+                        .unwrap_or(html.len());
+                    let css = self.rewrite_css(&html[i..close], base, 0).await;
+                    out.push_str(&tag.render());
+                    out.push_str(&css);
+                    i = close;
+                    continue;
+                }
+                "link" => {
+                    let rel = tag.get("rel").unwrap_or_default().to_ascii_lowercase();
+                    let rels: Vec<&str> = rel.split_whitespace().collect();
+                    if rels.contains(&"stylesheet") {
+                        if let Some(style) = self.inline_stylesheet(&tag, base).await {
+                            out.push_str(&style);
+                            continue;
+                        }
+                    } else if rels.iter().any(|r| {
+                        matches!(
+                            *r,
+                            "preload"
+                                | "modulepreload"
+                                | "prefetch"
+                                | "preconnect"
+                                | "dns-prefetch"
+                        )
+                    }) {
+                        continue;
+                    } else if rels.contains(&"icon") {
+                        if let Some(href) = tag.get("href") {
+                            let uri = self.data_uri(&href, base).await;
+                            tag.set("href", &uri);
+                        }
+                    }
+                }
+                // <picture> sources would override the inlined <img>; video sources stay linked.
+                "source" => {
+                    tag.remove("srcset");
+                    tag.remove("sizes");
+                }
+                "img" | "input" => {
+                    if tag.get("src").is_none_or(|s| s.trim().is_empty()) {
+                        if let Some(first) = tag.get("srcset").and_then(|s| first_candidate(&s)) {
+                            tag.set("src", &first);
+                        }
+                    }
+                    if let Some(src) = tag.get("src") {
+                        let uri = self.data_uri(&src, base).await;
+                        tag.set("src", &uri);
+                    }
+                    // The inlined src is what the page showed; drop responsive candidates.
+                    tag.remove("srcset");
+                    tag.remove("sizes");
+                    tag.remove("loading");
+                }
+                "video" => {
+                    if let Some(poster) = tag.get("poster") {
+                        let uri = self.data_uri(&poster, base).await;
+                        tag.set("poster", &uri);
+                    }
+                }
+                _ => {}
+            }
+            if let Some(style) = tag.get("style") {
+                let css = self.rewrite_css(&style, base, MAX_IMPORT_DEPTH).await;
+                tag.set("style", &css);
+            }
+            tag.attrs.retain(|(name, _)| !name.starts_with("on"));
+            for name in ["href", "src", "action", "poster", "data"] {
+                if let Some(value) = tag.get(name) {
+                    tag.set(name, &absolutize(&value, base));
+                }
+            }
+            out.push_str(&tag.render());
+        }
+        out.push_str(&html[i..]);
+        out
+    }
+
+    /// Fetch a `<link rel=stylesheet>` and return it as a `<style>` element.
+    async fn inline_stylesheet(&mut self, tag: &Tag, base: &url::Url) -> Option<String> {
+        let url = base.join(tag.get("href")?.trim()).ok()?;
+        let (_, body) = self.fetch(&url).await?;
+        let css = self
+            .rewrite_css(&String::from_utf8_lossy(&body), &url, 0)
+            .await;
+        let media = tag
+            .get("media")
+            .map(|m| format!(" media=\"{}\"", escape_attr(&m)))
+            .unwrap_or_default();
+        Some(format!("<style{}>\n{}\n</style>", media, css))
+    }
+
+    /// Inline `@import`s and turn every `url(...)` into a data URI, resolved against `base`.
+    async fn rewrite_css(&mut self, css: &str, base: &url::Url, depth: u32) -> String {
+        let mut out = String::with_capacity(css.len());
+        let lower = css.to_ascii_lowercase();
+        let mut i = 0;
+        loop {
+            let next_url = lower[i..].find("url(").map(|p| i + p);
+            let next_import = (depth < MAX_IMPORT_DEPTH)
+                .then(|| lower[i..].find("@import").map(|p| i + p))
+                .flatten();
+            match (next_import, next_url) {
+                (Some(imp), url_at) if url_at.is_none_or(|u| imp < u) => {
+                    let end = lower[imp..]
+                        .find(';')
+                        .map(|e| imp + e + 1)
+                        .unwrap_or(css.len());
+                    out.push_str(&css[i..imp]);
+                    i = end;
+                    let stmt = &css[imp + "@import".len()..end];
+                    let target = import_target(stmt);
+                    let fetched = match target.and_then(|t| base.join(&t).ok()) {
+                        Some(u) => self.fetch(&u).await.map(|(_, b)| (u, b)),
+                        None => None,
+                    };
+                    match fetched {
+                        Some((u, body)) => {
+                            let nested = String::from_utf8_lossy(&body).into_owned();
+                            let inlined = Box::pin(self.rewrite_css(&nested, &u, depth + 1)).await;
+                            out.push_str(&inlined);
+                        }
+                        None => out.push_str(&css[imp..end]),
+                    }
+                }
+                (_, Some(u)) => {
+                    let open = u + "url(".len();
+                    let close = css[open..].find(')').map(|e| open + e).unwrap_or(css.len());
+                    out.push_str(&css[i..open]);
+                    let raw = css[open..close]
+                        .trim()
+                        .trim_matches(|c| c == '"' || c == '\'');
+                    let uri = self.data_uri(raw, base).await;
+                    out.push('"');
+                    out.push_str(&uri.replace('"', "%22"));
+                    out.push('"');
+                    i = close;
+                }
+                _ => break,
+            }
+        }
+        out.push_str(&css[i..]);
+        out
+    }
+}
+
+/// A start tag split into its name and attributes (raw values, entities untouched).
+struct Tag {
+    name: String,
+    attrs: Vec<(String, Option<String>)>,
+    self_closing: bool,
+}
+
+impl Tag {
+    fn parse(raw: &str) -> Option<Tag> {
+        let body = raw
+            .strip_prefix('<')?
+            .strip_suffix('>')
+            .unwrap_or(&raw[1..]);
+        let self_closing = body.trim_end().ends_with('/');
+        let body = body.trim_end().trim_end_matches('/');
+        let name_end = body
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(body.len());
+        let name = body[..name_end].to_ascii_lowercase();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return None;
+        }
+        let mut attrs = Vec::new();
+        let mut rest = body[name_end..].trim_start();
+        while !rest.is_empty() {
+            let key_end = rest
+                .find(|c: char| c.is_ascii_whitespace() || c == '=')
+                .unwrap_or(rest.len());
+            let key = rest[..key_end].to_ascii_lowercase();
+            rest = rest[key_end..].trim_start();
+            let value = if let Some(after) = rest.strip_prefix('=') {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let close = after[1..].find(q).map(|e| e + 1).unwrap_or(after.len());
+                        (&after[1..close], after.get(close + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace())
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = remaining.trim_start();
+                Some(value.to_string())
+            } else {
+                None
+            };
+            if !key.is_empty() {
+                attrs.push((key, value));
+            } else if !rest.is_empty() {
+                rest = &rest[1..];
+            }
+        }
+        Some(Tag {
+            name,
+            attrs,
+            self_closing,
+        })
+    }
+
+    /// Attribute value with `&amp;` decoded.
+    fn get(&self, name: &str) -> Option<String> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == name)
+            .and_then(|(_, v)| v.as_ref())
+            .map(|v| v.replace("&amp;", "&"))
+    }
+
+    /// Set an attribute from an unescaped value.
+    fn set(&mut self, name: &str, value: &str) {
+        let escaped = escape_attr(value);
+        match self.attrs.iter_mut().find(|(k, _)| k == name) {
+            Some((_, v)) => *v = Some(escaped),
+            None => self.attrs.push((name.to_string(), Some(escaped))),
+        }
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.attrs.retain(|(k, _)| k != name);
+    }
+
+    fn render(&self) -> String {
+        let mut out = format!("<{}", self.name);
+        for (k, v) in &self.attrs {
+            match v {
+                Some(v) => out.push_str(&format!(" {}=\"{}\"", k, v.replace('"', "&quot;"))),
+                None => out.push_str(&format!(" {}", k)),
+            }
+        }
+        out.push_str(if self.self_closing { " />" } else { ">" });
+        out
+    }
+}
+
+fn escape_attr(v: &str) -> String {
+    v.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+fn absolutize(value: &str, base: &url::Url) -> String {
+    let v = value.trim();
+    if v.is_empty()
+        || v.starts_with('#')
+        || v.starts_with("data:")
+        || v.starts_with("javascript:")
+        || v.starts_with("mailto:")
+        || v.starts_with("tel:")
+    {
+        return value.to_string();
+    }
+    base.join(v)
+        .map(|u| u.to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+fn first_candidate(srcset: &str) -> Option<String> {
+    srcset
+        .split(',')
+        .next()
+        .and_then(|c| c.split_whitespace().next())
+        .map(|s| s.to_string())
+}
+
+/// The URL of an `@import` statement body (`url(x)`, `"x"` or `'x'`).
+fn import_target(stmt: &str) -> Option<String> {
+    let s = stmt.trim().trim_end_matches(';').trim_end();
+    let inner = if s.to_ascii_lowercase().starts_with("url(") {
+        &s[4..s.find(')')?]
+    } else {
+        s.split_whitespace().next()?
+    };
+    Some(
+        inner
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string(),
+    )
+}
+
+fn guess_mime(path: &str) -> &'static str {
+    let ext = path.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "css" => "text/css",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        _ => "application/octet-stream",
+    }
+}
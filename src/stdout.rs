@@ -0,0 +1,86 @@
+//! `--output stdout`: render without touching the filesystem and print the capture as one
+//! JSON document, for read-only environments such as serverless functions.
+//!
+//! The HTML is inlined as a string, the screenshot and PDF as base64. With
+//! `--embed-max-bytes`, an artifact over the limit keeps its size and content type but
+//! loses its data, and a warning says so.
+
+use crate::{capture_in_memory, Artifact, Cli};
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Document {
+    input_url: String,
+    final_url: String,
+    wait_branch: String,
+    elapsed_ms: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pdf_encrypted: bool,
+    html: Option<Embedded>,
+    screenshot: Option<Embedded>,
+    pdf: Option<Embedded>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Embedded {
+    content_type: &'static str,
+    bytes: usize,
+    /// The HTML as text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    /// The screenshot or PDF.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base64: Option<String>,
+}
+
+/// Capture `args.url()` and print the document; returns the process exit code.
+pub async fn run(args: &Cli) -> Result<i32> {
+    let url = args.url().to_string();
+    let owned = args.clone();
+    let capture = tokio::task::spawn_blocking(move || capture_in_memory(&url, &owned, false))
+        .await
+        .map_err(|e| anyhow!("capture task failed: {}", e))??;
+
+    let mut doc = Document {
+        input_url: args.url().to_string(),
+        final_url: capture.final_url,
+        wait_branch: capture.wait_branch,
+        elapsed_ms: capture.elapsed_ms,
+        pdf_encrypted: capture.pdf_encrypted,
+        html: None,
+        screenshot: None,
+        pdf: None,
+        warnings: capture.warnings,
+    };
+    for (artifact, bytes) in capture.artifacts {
+        let mut embedded = Embedded {
+            content_type: artifact.content_type(),
+            bytes: bytes.len(),
+            text: None,
+            base64: None,
+        };
+        if args.embed_max_bytes.is_some_and(|max| bytes.len() > max) {
+            doc.warnings.push(format!(
+                "{} left out: {} bytes is over --embed-max-bytes",
+                artifact.file_name(),
+                bytes.len()
+            ));
+        } else if artifact == Artifact::Html {
+            embedded.text = Some(String::from_utf8_lossy(&bytes).into_owned());
+        } else {
+            embedded.base64 = Some(base64::engine::general_purpose::STANDARD.encode(&bytes));
+        }
+        match artifact {
+            Artifact::Html => doc.html = Some(embedded),
+            Artifact::Screenshot(_) => doc.screenshot = Some(embedded),
+            Artifact::Pdf => doc.pdf = Some(embedded),
+            Artifact::Mhtml => {}
+        }
+    }
+    println!("{}", serde_json::to_string(&doc)?);
+    Ok(0)
+}
@@ -0,0 +1,272 @@
+//! Per-URL summary of a batch/crawl run (`summary.csv` + `summary.jsonl`).
+
+use anyhow::Result;
+use serde::Serialize;
+use std::{io::Write as _, path::Path};
+
+#[derive(Serialize, Default, Clone)]
+pub struct SummaryRow {
+    pub url: String,
+    pub outcome: String,
+    pub exit_code: i32,
+    pub final_url: Option<String>,
+    pub http_status: Option<u64>,
+    pub elapsed_ms: Option<u64>,
+    pub wait_branch: Option<String>,
+    pub waf_detected: Option<bool>,
+    pub run_dir: Option<String>,
+    pub html_path: Option<String>,
+    pub screenshot_path: Option<String>,
+    pub pdf_path: Option<String>,
+    pub error: Option<String>,
+    /// `--tag` values, as a JSON object in the CSV.
+    pub tags: serde_json::Value,
+}
+
+const COLUMNS: &[&str] = &[
+    "url",
+    "outcome",
+    "exit_code",
+    "final_url",
+    "http_status",
+    "elapsed_ms",
+    "wait_branch",
+    "waf_detected",
+    "run_dir",
+    "html_path",
+    "screenshot_path",
+    "pdf_path",
+    "error",
+    "tags",
+];
+
+impl SummaryRow {
+    /// Build a row from a written result.json (success output, timeout or blocked report).
+    pub fn from_result(url: &str, exit_code: i32, result: &serde_json::Value) -> Self {
+        let s = |k: &str| result.get(k).and_then(|v| v.as_str()).map(String::from);
+        let status = result.get("status").and_then(|v| v.as_str());
+        // Both reports list their files under `artifacts`.
+        let report = matches!(status, Some("timeout" | "blocked"));
+        let outcome = match exit_code {
+            0 => "ok",
+            2 => "timeout",
+            3 => "golden_fail",
+            4 => "blocked",
+            _ => "error",
+        };
+        SummaryRow {
+            url: url.to_string(),
+            outcome: outcome.to_string(),
+            exit_code,
+            final_url: s("final_url").or_else(|| s("url")),
+            http_status: result.get("http_status").and_then(|v| v.as_u64()),
+            elapsed_ms: result.get("elapsed_ms").and_then(|v| v.as_u64()),
+            wait_branch: s("wait_branch"),
+            waf_detected: if status == Some("blocked") {
+                Some(true)
+            } else {
+                result.get("waf_detected").and_then(|v| v.as_bool())
+            },
+            run_dir: s("run_dir"),
+            html_path: if report {
+                result
+                    .pointer("/artifacts/html")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            } else {
+                s("html_path")
+            },
+            screenshot_path: if report {
+                result
+                    .pointer("/artifacts/screenshot")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            } else {
+                s("screenshot_path")
+            },
+            pdf_path: if report {
+                result
+                    .pointer("/artifacts/pdf")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            } else {
+                s("pdf_path")
+            },
+            error: None,
+            tags: result.get("tags").cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn failed(url: &str, error: &str) -> Self {
+        SummaryRow {
+            url: url.to_string(),
+            outcome: "error".to_string(),
+            exit_code: 1,
+            error: Some(error.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        vec![
+            self.url.clone(),
+            self.outcome.clone(),
+            self.exit_code.to_string(),
+            opt(&self.final_url),
+            self.http_status.map(|v| v.to_string()).unwrap_or_default(),
+            self.elapsed_ms.map(|v| v.to_string()).unwrap_or_default(),
+            opt(&self.wait_branch),
+            self.waf_detected.map(|v| v.to_string()).unwrap_or_default(),
+            opt(&self.run_dir),
+            opt(&self.html_path),
+            opt(&self.screenshot_path),
+            opt(&self.pdf_path),
+            opt(&self.error),
+            if self.tags.is_null() {
+                String::new()
+            } else {
+                self.tags.to_string()
+            },
+        ]
+    }
+}
+
+/// Exit code of a whole batch or crawl: the highest of its rows', so any failed, timed
+/// out, golden-failed or blocked page makes it nonzero.
+pub fn exit_code(rows: &[SummaryRow]) -> i32 {
+    rows.iter().map(|r| r.exit_code).max().unwrap_or(0)
+}
+
+/// Write `summary.csv` and `summary.jsonl` into `dir`.
+pub fn write(dir: &Path, rows: &[SummaryRow]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut csv = std::fs::File::create(dir.join("summary.csv"))?;
+    writeln!(csv, "{}", COLUMNS.join(","))?;
+    for row in rows {
+        let line: Vec<String> = row.csv_fields().iter().map(|f| csv_escape(f)).collect();
+        writeln!(csv, "{}", line.join(","))?;
+    }
+    let mut jsonl = std::fs::File::create(dir.join("summary.jsonl"))?;
+    for row in rows {
+        writeln!(jsonl, "{}", serde_json::to_string(row)?)?;
+    }
+    Ok(())
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn csv_escape_quotes_only_when_needed() {
+        assert_eq!(csv_escape("https://example.com/"), "https://example.com/");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn row_from_success_output() {
+        let result = json!({
+            "url": "https://example.com/",
+            "final_url": "https://example.com/home",
+            "http_status": 200,
+            "elapsed_ms": 1234,
+            "wait_branch": "selector",
+            "waf_detected": false,
+            "run_dir": "out/run",
+            "html_path": "out/run/page.html",
+            "screenshot_path": "out/run/page.png",
+        });
+        let row = SummaryRow::from_result("https://example.com/", 0, &result);
+        assert_eq!(row.outcome, "ok");
+        assert_eq!(row.final_url.as_deref(), Some("https://example.com/home"));
+        assert_eq!(row.http_status, Some(200));
+        assert_eq!(row.elapsed_ms, Some(1234));
+        assert_eq!(row.wait_branch.as_deref(), Some("selector"));
+        assert_eq!(row.waf_detected, Some(false));
+        assert_eq!(row.html_path.as_deref(), Some("out/run/page.html"));
+        assert_eq!(row.pdf_path, None);
+    }
+
+    #[test]
+    fn row_from_timeout_report_reads_artifacts() {
+        let result = json!({
+            "status": "timeout",
+            "url": "https://example.com/slow",
+            "artifacts": { "html": "out/run/page.html", "screenshot": "out/run/page.png" },
+        });
+        let row = SummaryRow::from_result("https://example.com/slow", 2, &result);
+        assert_eq!(row.outcome, "timeout");
+        assert_eq!(row.final_url.as_deref(), Some("https://example.com/slow"));
+        assert_eq!(row.html_path.as_deref(), Some("out/run/page.html"));
+        assert_eq!(row.screenshot_path.as_deref(), Some("out/run/page.png"));
+    }
+
+    #[test]
+    fn outcome_follows_exit_code() {
+        let outcome = |code| SummaryRow::from_result("u", code, &json!({})).outcome;
+        assert_eq!(outcome(3), "golden_fail");
+        assert_eq!(outcome(1), "error");
+        assert_eq!(SummaryRow::failed("u", "boom").exit_code, 1);
+    }
+
+    #[test]
+    fn run_exit_code_is_the_worst_row() {
+        let row = |code| SummaryRow::from_result("u", code, &json!({}));
+        assert_eq!(exit_code(&[]), 0);
+        assert_eq!(exit_code(&[row(0), row(0)]), 0);
+        assert_eq!(exit_code(&[row(0), SummaryRow::failed("u", "boom")]), 1);
+        assert_eq!(exit_code(&[row(2), row(0), row(4)]), 4);
+    }
+
+    #[test]
+    fn write_emits_csv_and_jsonl() {
+        let dir = std::env::temp_dir().join(format!("ankabot-summary-{}", std::process::id()));
+        let rows = [
+            SummaryRow::from_result("https://a/", 0, &json!({ "http_status": 200 })),
+            SummaryRow::failed("https://b/", "net, down"),
+        ];
+        write(&dir, &rows).unwrap();
+        let csv = std::fs::read_to_string(dir.join("summary.csv")).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with("https://a/,ok,0,,200,"));
+        assert!(lines[2].contains(",\"net, down\""));
+        let jsonl = std::fs::read_to_string(dir.join("summary.jsonl")).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tags_are_a_json_csv_column() {
+        let tagged = SummaryRow::from_result("u", 0, &json!({ "tags": { "team": "web" } }));
+        assert_eq!(tagged.tags, json!({ "team": "web" }));
+        assert_eq!(tagged.csv_fields().last().unwrap(), r#"{"team":"web"}"#);
+        let untagged = SummaryRow::failed("u", "boom");
+        assert_eq!(untagged.csv_fields().last().unwrap(), "");
+    }
+
+    #[test]
+    fn row_from_blocked_report() {
+        let result = json!({
+            "status": "blocked",
+            "url": "https://example.com/",
+            "artifacts": { "html": "out/run/page.html" },
+        });
+        let row = SummaryRow::from_result("https://example.com/", 4, &result);
+        assert_eq!(row.outcome, "blocked");
+        assert_eq!(row.waf_detected, Some(true));
+        assert_eq!(row.html_path.as_deref(), Some("out/run/page.html"));
+    }
+}
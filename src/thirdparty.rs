@@ -0,0 +1,178 @@
+//! `third_parties.json`: every origin the page contacted, with request counts and bytes,
+//! flagged first- or third-party against the page's own site.
+//!
+//! The site is the registrable domain, approximated without a public suffix list: the
+//! last two labels of the host, or three under two-letter country codes with a generic
+//! second level (`example.co.uk`, `example.com.au`). IP addresses are their own site.
+
+use crate::network::NetEntry;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Second-level labels that countries register names under.
+const GENERIC_SLDS: &[&str] = &[
+    "co", "com", "net", "org", "gov", "edu", "ac", "ne", "or", "go",
+];
+
+#[derive(Serialize)]
+struct Inventory<'a> {
+    page_url: &'a str,
+    first_party_site: String,
+    origins: Vec<Origin>,
+}
+
+#[derive(Serialize)]
+struct Origin {
+    origin: String,
+    site: String,
+    third_party: bool,
+    requests: usize,
+    transfer_bytes: u64,
+}
+
+/// The third-party totals in result.json.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Summary {
+    pub origins: usize,
+    pub sites: usize,
+    pub requests: usize,
+    pub transfer_bytes: u64,
+}
+
+/// The registrable domain of `host`, by the heuristic above.
+pub fn site(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let n = labels.len();
+    let keep = if n >= 3 && labels[n - 1].len() == 2 && GENERIC_SLDS.contains(&labels[n - 2]) {
+        3
+    } else {
+        2
+    };
+    labels[n.saturating_sub(keep)..].join(".")
+}
+
+/// Whether a request to `url` leaves the site of `first_party`. Non-network schemes
+/// (`data:`, `blob:`, extensions) are neither.
+pub fn is_third_party(url: &url::Url, first_party: &str) -> Option<bool> {
+    if !matches!(url.scheme(), "http" | "https" | "ws" | "wss") {
+        return None;
+    }
+    Some(site(url.host_str()?) != first_party)
+}
+
+/// Write the inventory for the page at `page_url` and return the third-party totals.
+pub fn write(entries: &[NetEntry], page_url: &str, path: &Path) -> Result<Summary> {
+    let first_party = url::Url::parse(page_url)
+        .ok()
+        .and_then(|u| u.host_str().map(site))
+        .unwrap_or_default();
+    let mut origins: BTreeMap<String, Origin> = BTreeMap::new();
+    for e in entries {
+        let Ok(url) = url::Url::parse(&e.request.url) else {
+            continue;
+        };
+        let Some(third_party) = is_third_party(&url, &first_party) else {
+            continue;
+        };
+        let origin = url.origin().ascii_serialization();
+        let o = origins.entry(origin.clone()).or_insert_with(|| Origin {
+            origin,
+            site: site(url.host_str().unwrap_or_default()),
+            third_party,
+            requests: 0,
+            transfer_bytes: 0,
+        });
+        o.requests += 1;
+        o.transfer_bytes += e.encoded_bytes as u64;
+    }
+    // Third parties first, the busiest on top.
+    let mut origins: Vec<Origin> = origins.into_values().collect();
+    origins.sort_by(|a, b| {
+        b.third_party
+            .cmp(&a.third_party)
+            .then(b.requests.cmp(&a.requests))
+            .then(a.origin.cmp(&b.origin))
+    });
+    let third: Vec<&Origin> = origins.iter().filter(|o| o.third_party).collect();
+    let mut sites: Vec<&str> = third.iter().map(|o| o.site.as_str()).collect();
+    sites.sort_unstable();
+    sites.dedup();
+    let summary = Summary {
+        origins: third.len(),
+        sites: sites.len(),
+        requests: third.iter().map(|o| o.requests).sum(),
+        transfer_bytes: third.iter().map(|o| o.transfer_bytes).sum(),
+    };
+    let inventory = Inventory {
+        page_url,
+        first_party_site: first_party,
+        origins,
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&inventory)?)
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn site_is_the_registrable_domain() {
+        for (host, want) in [
+            ("example.com", "example.com"),
+            ("www.example.com", "example.com"),
+            ("cdn.static.example.com", "example.com"),
+            ("WWW.Example.COM.", "example.com"),
+            ("example.co.uk", "example.co.uk"),
+            ("www.example.co.uk", "example.co.uk"),
+            ("shop.example.com.au", "example.com.au"),
+            // Two-letter TLD without a generic second level.
+            ("www.example.de", "example.de"),
+            ("a.b.example.io", "example.io"),
+            ("localhost", "localhost"),
+            ("192.168.1.10", "192.168.1.10"),
+            ("[::1]", "[::1]"),
+            ("2001:db8::1", "2001:db8::1"),
+        ] {
+            assert_eq!(site(host), want, "{host}");
+        }
+    }
+
+    #[test]
+    fn third_party_is_a_different_site() {
+        for (url, first_party, want) in [
+            ("https://example.com/a.js", "example.com", Some(false)),
+            ("https://www.example.com/", "example.com", Some(false)),
+            (
+                "https://static.cdn.example.com/x.css",
+                "example.com",
+                Some(false),
+            ),
+            ("wss://live.example.com/socket", "example.com", Some(false)),
+            ("https://example.com.evil.net/", "example.com", Some(true)),
+            (
+                "https://www.google-analytics.com/ga.js",
+                "example.com",
+                Some(true),
+            ),
+            ("https://other.co.uk/", "example.co.uk", Some(true)),
+            ("https://img.example.co.uk/", "example.co.uk", Some(false)),
+            ("http://10.0.0.2:8080/api", "10.0.0.1", Some(true)),
+            ("http://10.0.0.1:8080/api", "10.0.0.1", Some(false)),
+            ("http://[::1]/", "[::1]", Some(false)),
+            ("data:image/png;base64,AAAA", "example.com", None),
+            ("blob:https://example.com/1234", "example.com", None),
+            ("chrome-extension://abc/script.js", "example.com", None),
+        ] {
+            let url = url::Url::parse(url).unwrap();
+            assert_eq!(is_third_party(&url, first_party), want, "{url}");
+        }
+    }
+}
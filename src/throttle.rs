@@ -0,0 +1,97 @@
+//! Rate-limit handling: when a 429/503 response carries `Retry-After`, wait as long as it
+//! asks (capped at `--max-retry-after`) and try again, recording each encounter.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// One throttled response that was waited out (or given up on).
+#[derive(Serialize, Clone, Debug)]
+pub struct ThrottleEvent {
+    /// "http" or "chrome".
+    pub path: &'static str,
+    pub url: String,
+    pub status: u16,
+    /// The raw Retry-After header value.
+    pub retry_after: String,
+    /// How long we slept before retrying; 0 when retries were exhausted.
+    pub waited_ms: u64,
+}
+
+/// The delay a throttled response asks for, or None if it isn't a 429/503 with a usable
+/// Retry-After (delta-seconds or an HTTP-date).
+pub fn retry_delay(status: u16, retry_after: Option<&str>, max: Duration) -> Option<Duration> {
+    if status != 429 && status != 503 {
+        return None;
+    }
+    let value = retry_after?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default()
+        }
+    };
+    Some(delay.min(max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: Duration = Duration::from_secs(60);
+
+    fn http_date(from_now: chrono::Duration) -> String {
+        (chrono::Utc::now() + from_now)
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+
+    #[test]
+    fn delta_seconds() {
+        assert_eq!(
+            retry_delay(429, Some("7"), MAX),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(retry_delay(503, Some(" 0 "), MAX), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn http_date_in_the_future() {
+        let delay = retry_delay(503, Some(&http_date(chrono::Duration::seconds(30))), MAX)
+            .expect("date parsed");
+        // Whole-second formatting and the clock moving on take a little off.
+        assert!(
+            delay > Duration::from_secs(27) && delay <= Duration::from_secs(30),
+            "{delay:?}"
+        );
+    }
+
+    #[test]
+    fn http_date_in_the_past_means_now() {
+        let past = http_date(chrono::Duration::seconds(-120));
+        assert_eq!(retry_delay(429, Some(&past), MAX), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn long_delays_are_capped() {
+        assert_eq!(retry_delay(429, Some("3600"), MAX), Some(MAX));
+        let later = http_date(chrono::Duration::hours(2));
+        assert_eq!(retry_delay(503, Some(&later), MAX), Some(MAX));
+    }
+
+    #[test]
+    fn unusable_header_is_ignored() {
+        assert_eq!(retry_delay(429, None, MAX), None);
+        assert_eq!(retry_delay(429, Some("soon"), MAX), None);
+        assert_eq!(retry_delay(429, Some("-5"), MAX), None);
+    }
+
+    #[test]
+    fn other_statuses_are_not_throttling() {
+        for status in [200, 302, 404, 500, 502, 504] {
+            assert_eq!(retry_delay(status, Some("5"), MAX), None, "{status}");
+        }
+    }
+}
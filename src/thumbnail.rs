@@ -0,0 +1,31 @@
+//! `thumb.png`: a small copy of the screenshot for galleries and run listings.
+//!
+//! The screenshot is scaled to `--thumbnail-width`. Tall (`--full-page`) captures keep
+//! only their top, at most twice as tall as wide, since that is what a thumbnail shows
+//! anyway.
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use std::path::Path;
+
+/// `--thumbnail-width` when none is given.
+pub const DEFAULT_WIDTH: u32 = 320;
+
+/// Write a `width` px wide PNG thumbnail of the image at `screenshot` to `out`.
+pub fn write(screenshot: &Path, width: u32, out: &Path) -> Result<()> {
+    let img =
+        image::open(screenshot).with_context(|| format!("reading {}", screenshot.display()))?;
+    let width = width.min(img.width()).max(1);
+    let scale = width as f64 / img.width() as f64;
+    // Crop before scaling so a 30000 px capture isn't resized in full.
+    let keep = ((2 * width) as f64 / scale).ceil() as u32;
+    let img = if img.height() > keep {
+        img.crop_imm(0, 0, img.width(), keep)
+    } else {
+        img
+    };
+    let height = ((img.height() as f64 * scale).round() as u32).max(1);
+    img.resize_exact(width, height, FilterType::Triangle)
+        .save_with_format(out, image::ImageFormat::Png)
+        .with_context(|| format!("writing {}", out.display()))
+}
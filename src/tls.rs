@@ -0,0 +1,298 @@
+//! The main document's TLS connection and certificate, from the response's
+//! `securityDetails`, as `tls` in result.json for certificate-expiry monitoring. The HTTP
+//! fast path only gets the peer certificate, which is read here with just enough DER.
+
+use headless_chrome::protocol::cdp::Network;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TlsInfo {
+    /// `TLS 1.3`, `QUIC`, …; the connection fields are empty (and left out) on the HTTP
+    /// fast path.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub protocol: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub cipher: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub key_exchange: String,
+    pub subject: String,
+    pub issuer: String,
+    pub san: Vec<String>,
+    /// RFC 3339.
+    pub valid_from: String,
+    pub valid_to: String,
+    /// Whole days until `valid_to`; negative once expired.
+    pub days_remaining: i64,
+    /// `compliant`, `not-compliant` or `unknown`.
+    pub certificate_transparency: String,
+}
+
+/// TLS details of `response`, or `None` for plain HTTP (and cached responses, which
+/// carry none).
+pub fn from_response(response: &Network::Response) -> Option<TlsInfo> {
+    let d = response.security_details.as_ref()?;
+    let time = |secs: f64| {
+        chrono::DateTime::from_timestamp(secs as i64, 0)
+            .unwrap_or_default()
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    };
+    let now = chrono::Utc::now().timestamp() as f64;
+    let key_exchange = match &d.key_exchange_group {
+        // TLS 1.3 leaves `key_exchange` empty and names only the group.
+        Some(group) if d.key_exchange.is_empty() => group.clone(),
+        Some(group) => format!("{} {}", d.key_exchange, group),
+        None => d.key_exchange.clone(),
+    };
+    Some(TlsInfo {
+        protocol: d.protocol.clone(),
+        cipher: d.cipher.clone(),
+        key_exchange,
+        subject: d.subject_name.clone(),
+        issuer: d.issuer.clone(),
+        san: d.san_list.clone(),
+        valid_from: time(d.valid_from),
+        valid_to: time(d.valid_to),
+        days_remaining: ((d.valid_to - now) / 86_400.0).floor() as i64,
+        certificate_transparency: serde_json::to_value(&d.certificate_transparency_compliance)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+    })
+}
+
+/// TLS details from the peer certificate (DER) of an HTTP fast path response. reqwest
+/// doesn't expose the negotiated connection, so only the certificate fields are set and
+/// `certificate_transparency` is `unknown`.
+pub fn from_certificate(der: &[u8]) -> Option<TlsInfo> {
+    let cert = Certificate::parse(der)?;
+    let time =
+        |t: chrono::DateTime<chrono::Utc>| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    Some(TlsInfo {
+        protocol: String::new(),
+        cipher: String::new(),
+        key_exchange: String::new(),
+        subject: cert.subject,
+        issuer: cert.issuer,
+        san: cert.san,
+        valid_from: time(cert.not_before),
+        valid_to: time(cert.not_after),
+        days_remaining: (cert.not_after - chrono::Utc::now())
+            .num_seconds()
+            .div_euclid(86_400),
+        certificate_transparency: "unknown".to_string(),
+    })
+}
+
+/// The fields of an X.509 certificate that [`TlsInfo`] reports.
+struct Certificate {
+    /// Common name, else organization, as Chrome names them.
+    subject: String,
+    issuer: String,
+    san: Vec<String>,
+    not_before: chrono::DateTime<chrono::Utc>,
+    not_after: chrono::DateTime<chrono::Utc>,
+}
+
+const SEQUENCE: u8 = 0x30;
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0a];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+impl Certificate {
+    fn parse(der: &[u8]) -> Option<Self> {
+        let (SEQUENCE, cert, _) = tlv(der)? else {
+            return None;
+        };
+        let (SEQUENCE, tbs, _) = tlv(cert)? else {
+            return None;
+        };
+        let mut fields = items(tbs).peekable();
+        // [0] version, absent in v1 certificates.
+        if fields.peek()?.0 == 0xa0 {
+            fields.next();
+        }
+        let _serial = fields.next()?;
+        let _signature = fields.next()?;
+        let issuer = display_name(fields.next()?.1);
+        let mut validity = items(fields.next()?.1);
+        let not_before = time(validity.next()?)?;
+        let not_after = time(validity.next()?)?;
+        let subject = display_name(fields.next()?.1);
+        let _public_key = fields.next()?;
+        // [3] extensions, after the optional unique IDs.
+        let san = fields
+            .find(|(tag, _)| *tag == 0xa3)
+            .and_then(|(_, ext)| subject_alt_names(ext))
+            .unwrap_or_default();
+        Some(Self {
+            subject,
+            issuer,
+            san,
+            not_before,
+            not_after,
+        })
+    }
+}
+
+/// One DER value off the front of `input`: its tag, its contents and what follows.
+fn tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&len, rest) = rest.split_first()?;
+    let (len, rest) = if len < 0x80 {
+        (len as usize, rest)
+    } else {
+        let n = (len & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let (bytes, rest) = rest.split_at(n);
+        (bytes.iter().fold(0, |acc, &b| acc << 8 | b as usize), rest)
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// The values inside a constructed one, as (tag, contents).
+fn items(mut body: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        let (tag, value, rest) = tlv(body)?;
+        body = rest;
+        Some((tag, value))
+    })
+}
+
+/// A Name's common name, else its organization.
+fn display_name(name: &[u8]) -> String {
+    let mut attrs = Vec::new();
+    for (_, set) in items(name) {
+        for (_, attr) in items(set) {
+            let mut parts = items(attr);
+            if let (Some((0x06, oid)), Some((tag, value))) = (parts.next(), parts.next()) {
+                attrs.push((oid, directory_string(tag, value)));
+            }
+        }
+    }
+    [OID_COMMON_NAME, OID_ORGANIZATION]
+        .iter()
+        .find_map(|want| attrs.iter().find(|(oid, _)| oid == want))
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default()
+}
+
+fn directory_string(tag: u8, value: &[u8]) -> String {
+    match tag {
+        // BMPString: UTF-16BE.
+        0x1e => char::decode_utf16(
+            value
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]])),
+        )
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect(),
+        _ => String::from_utf8_lossy(value).into_owned(),
+    }
+}
+
+/// UTCTime (`YYMMDDHHMMSSZ`) or GeneralizedTime (`YYYYMMDDHHMMSSZ`).
+fn time((tag, value): (u8, &[u8])) -> Option<chrono::DateTime<chrono::Utc>> {
+    let s = std::str::from_utf8(value).ok()?;
+    let full = match tag {
+        0x17 => {
+            let yy: u32 = s.get(..2)?.parse().ok()?;
+            format!("{}{s}", if yy >= 50 { "19" } else { "20" })
+        }
+        0x18 => s.to_string(),
+        _ => return None,
+    };
+    chrono::NaiveDateTime::parse_from_str(&full, "%Y%m%d%H%M%SZ")
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+/// DNS names and IP addresses of the subjectAltName extension, if there is one.
+fn subject_alt_names(extensions: &[u8]) -> Option<Vec<String>> {
+    let (SEQUENCE, list, _) = tlv(extensions)? else {
+        return None;
+    };
+    let ext = items(list).find_map(|(_, ext)| {
+        let mut parts = items(ext);
+        (parts.next()? == (0x06, OID_SUBJECT_ALT_NAME))
+            .then(|| parts.find(|(tag, _)| *tag == 0x04))?
+    })?;
+    let (SEQUENCE, names, _) = tlv(ext.1)? else {
+        return None;
+    };
+    Some(
+        items(names)
+            .filter_map(|(tag, value)| match tag {
+                // dNSName
+                0x82 => Some(String::from_utf8_lossy(value).into_owned()),
+                // iPAddress
+                0x87 => match value.len() {
+                    4 => {
+                        Some(std::net::Ipv4Addr::from(<[u8; 4]>::try_from(value).ok()?).to_string())
+                    }
+                    16 => Some(
+                        std::net::Ipv6Addr::from(<[u8; 16]>::try_from(value).ok()?).to_string(),
+                    ),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+
+    /// example.com (O=Example Inc), issued by "Example CA R1", valid 2024-05-01 to
+    /// 2024-07-30, with two DNS names and an IP in its subjectAltName.
+    const LEAF: &str = concat!(
+        "MIIByjCCAXCgAwIBAgICEJIwCgYIKoZIzj0EAwIwMDEWMBQGA1UECgwNRXhhbXBsZSBUcnVzdDEWMBQGA1UEAwwN",
+        "RXhhbXBsZSBDQSBSMTAeFw0yNDA1MDEwMDAwMDBaFw0yNDA3MzAyMzU5NTlaMDkxCzAJBgNVBAYTAlVTMRQwEgYD",
+        "VQQKDAtFeGFtcGxlIEluYzEUMBIGA1UEAwwLZXhhbXBsZS5jb20wWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASM",
+        "fa7WOnoJJbhdK6GJkKhRd7BlnO0S8fs5oWFU0qFKbusmaq9Q/OLtIXJqTvLeJRXpR9+j9V1uGDsxKObPPUlMo3Ew",
+        "bzAtBgNVHREEJjAkggtleGFtcGxlLmNvbYIPd3d3LmV4YW1wbGUuY29thwTLAHEHMB0GA1UdDgQWBBTsiuqQc6nF",
+        "3JZLgijpfhofkAtvpjAfBgNVHSMEGDAWgBRb9Q45r+IwR4BIS8TCG759CRgvUzAKBggqhkjOPQQDAgNIADBFAiEA",
+        "15oewbGbkZUNkLiHkO97RYMiTqQJxy2m154xPNDobGMCIG7zmkVwQPVYeCpqnO/q2NWJs9GHhG4jygSaoIaY0Dti",
+    );
+
+    fn leaf() -> Vec<u8> {
+        base64::engine::general_purpose::STANDARD
+            .decode(LEAF)
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_the_certificate_fields() {
+        let info = from_certificate(&leaf()).unwrap();
+        assert_eq!(info.subject, "example.com");
+        assert_eq!(info.issuer, "Example CA R1");
+        assert_eq!(info.san, ["example.com", "www.example.com", "203.0.113.7"]);
+        assert_eq!(info.valid_from, "2024-05-01T00:00:00Z");
+        assert_eq!(info.valid_to, "2024-07-30T23:59:59Z");
+        assert!(info.days_remaining < 0);
+        assert_eq!(info.certificate_transparency, "unknown");
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json.get("protocol").is_none());
+    }
+
+    #[test]
+    fn truncated_certificates_are_rejected() {
+        let der = leaf();
+        assert!(from_certificate(&der[..der.len() / 2]).is_none());
+        assert!(from_certificate(&[]).is_none());
+    }
+
+    #[test]
+    fn both_time_forms() {
+        let utc = time((0x17, b"491231235959Z".as_slice())).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2049-12-31T23:59:59+00:00");
+        let old = time((0x17, b"990101000000Z".as_slice())).unwrap();
+        assert_eq!(old.to_rfc3339(), "1999-01-01T00:00:00+00:00");
+        let generalized = time((0x18, b"20550101000000Z".as_slice())).unwrap();
+        assert_eq!(generalized.to_rfc3339(), "2055-01-01T00:00:00+00:00");
+    }
+}
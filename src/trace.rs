@@ -0,0 +1,112 @@
+//! `--trace`: a Chrome performance trace of the whole render (navigation, waits and
+//! capture) written as `trace.json`, loadable in chrome://tracing, Perfetto or the
+//! DevTools Performance panel.
+//!
+//! The trace records the categories the DevTools Performance panel uses, without its
+//! screenshots. Chrome hands the finished trace over as an IO stream, which is copied to
+//! the file in chunks.
+
+use anyhow::{anyhow, Context, Result};
+use headless_chrome::protocol::cdp::{types::Event, types::Method, Tracing, IO};
+use serde::Serialize;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const CATEGORIES: &[&str] = &[
+    "-*",
+    "devtools.timeline",
+    "disabled-by-default-devtools.timeline",
+    "disabled-by-default-devtools.timeline.frame",
+    "disabled-by-default-devtools.timeline.stack",
+    "disabled-by-default-v8.cpu_profiler",
+    "toplevel",
+    "blink.console",
+    "blink.user_timing",
+    "latencyInfo",
+    "loading",
+    "v8.execute",
+];
+/// How long Chrome gets to flush the trace after `Tracing.end`.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+const CHUNK: u32 = 1 << 20;
+
+/// `Tracing.start` with a raw trace config: the generated `TraceConfig` has no way to
+/// leave out its closed enums.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct StartTracing {
+    transfer_mode: &'static str,
+    stream_format: &'static str,
+    trace_config: serde_json::Value,
+}
+
+impl Method for StartTracing {
+    const NAME: &'static str = "Tracing.start";
+    type ReturnObject = Tracing::StartReturnObject;
+}
+
+/// A running trace.
+pub struct Trace {
+    stream: Arc<Mutex<Option<Result<String, ()>>>>,
+}
+
+impl Trace {
+    /// Start tracing `tab`; call before navigating.
+    pub fn start(tab: &headless_chrome::Tab) -> Result<Self> {
+        let stream = Arc::new(Mutex::new(None));
+        let sink = stream.clone();
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            if let Event::TracingComplete(e) = event {
+                *sink.lock().unwrap() = Some(e.params.stream.clone().ok_or(()));
+            }
+        }))?;
+        tab.call_method(StartTracing {
+            transfer_mode: "ReturnAsStream",
+            stream_format: "json",
+            trace_config: serde_json::json!({
+                "recordMode": "recordAsMuchAsPossible",
+                "includedCategories": CATEGORIES,
+            }),
+        })?;
+        Ok(Self { stream })
+    }
+
+    /// Stop tracing and write the trace to `path`.
+    pub fn finish(self, tab: &headless_chrome::Tab, path: &Path) -> Result<()> {
+        tab.call_method(Tracing::End(None))?;
+        let deadline = Instant::now() + FLUSH_TIMEOUT;
+        let handle = loop {
+            if let Some(done) = self.stream.lock().unwrap().take() {
+                break done.map_err(|_| anyhow!("Chrome returned no trace stream"))?;
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!("trace not flushed within {:?}", FLUSH_TIMEOUT));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        };
+        let file =
+            std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        let mut out = std::io::BufWriter::new(file);
+        loop {
+            let chunk = tab.call_method(IO::Read {
+                handle: handle.clone(),
+                offset: None,
+                size: Some(CHUNK),
+            })?;
+            if chunk.base_64_encoded == Some(true) {
+                use base64::Engine as _;
+                out.write_all(&base64::engine::general_purpose::STANDARD.decode(&chunk.data)?)?;
+            } else {
+                out.write_all(chunk.data.as_bytes())?;
+            }
+            if chunk.eof {
+                break;
+            }
+        }
+        out.flush()?;
+        tab.call_method(IO::Close { handle })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! Tracker and ad detection: request hosts matched against a bundled list of known
+//! advertising, analytics, social and session-replay domains, reported as `trackers` in
+//! result.json.
+//!
+//! A host matches an entry when it is the listed domain or a subdomain of it. The list
+//! is deliberately short and well known; it names the service behind the requests, it
+//! is not a blocklist.
+
+use crate::network::NetEntry;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Tracker {
+    pub name: &'static str,
+    pub category: &'static str,
+    pub requests: usize,
+    /// The matched hosts, as requested.
+    pub hosts: Vec<String>,
+}
+
+/// (domain, service, category).
+const TRACKERS: &[(&str, &str, &str)] = &[
+    // Advertising
+    ("doubleclick.net", "Google Ads (DoubleClick)", "advertising"),
+    ("googlesyndication.com", "Google AdSense", "advertising"),
+    ("googleadservices.com", "Google Ads", "advertising"),
+    ("adservice.google.com", "Google Ads", "advertising"),
+    ("amazon-adsystem.com", "Amazon Advertising", "advertising"),
+    ("adnxs.com", "Xandr (AppNexus)", "advertising"),
+    ("criteo.com", "Criteo", "advertising"),
+    ("criteo.net", "Criteo", "advertising"),
+    ("taboola.com", "Taboola", "advertising"),
+    ("outbrain.com", "Outbrain", "advertising"),
+    ("rubiconproject.com", "Magnite (Rubicon)", "advertising"),
+    ("pubmatic.com", "PubMatic", "advertising"),
+    ("openx.net", "OpenX", "advertising"),
+    ("casalemedia.com", "Index Exchange", "advertising"),
+    ("adsrvr.org", "The Trade Desk", "advertising"),
+    ("quantserve.com", "Quantcast", "advertising"),
+    ("scorecardresearch.com", "Comscore", "advertising"),
+    ("moatads.com", "Moat", "advertising"),
+    ("bat.bing.com", "Microsoft Advertising", "advertising"),
+    ("ads-twitter.com", "X (Twitter) Ads", "advertising"),
+    ("ads.linkedin.com", "LinkedIn Ads", "advertising"),
+    ("snap.licdn.com", "LinkedIn Insight", "advertising"),
+    ("analytics.tiktok.com", "TikTok Pixel", "advertising"),
+    ("ct.pinterest.com", "Pinterest Tag", "advertising"),
+    ("sc-static.net", "Snap Pixel", "advertising"),
+    ("yieldmo.com", "Yieldmo", "advertising"),
+    ("teads.tv", "Teads", "advertising"),
+    // Analytics
+    ("google-analytics.com", "Google Analytics", "analytics"),
+    ("analytics.google.com", "Google Analytics", "analytics"),
+    ("stats.g.doubleclick.net", "Google Analytics", "analytics"),
+    ("segment.com", "Segment", "analytics"),
+    ("segment.io", "Segment", "analytics"),
+    ("mixpanel.com", "Mixpanel", "analytics"),
+    ("amplitude.com", "Amplitude", "analytics"),
+    ("heapanalytics.com", "Heap", "analytics"),
+    ("chartbeat.com", "Chartbeat", "analytics"),
+    ("chartbeat.net", "Chartbeat", "analytics"),
+    ("newrelic.com", "New Relic", "analytics"),
+    ("nr-data.net", "New Relic", "analytics"),
+    ("omtrdc.net", "Adobe Analytics", "analytics"),
+    ("2o7.net", "Adobe Analytics", "analytics"),
+    ("demdex.net", "Adobe Audience Manager", "analytics"),
+    ("matomo.cloud", "Matomo", "analytics"),
+    ("plausible.io", "Plausible", "analytics"),
+    ("mc.yandex.ru", "Yandex Metrica", "analytics"),
+    // Session replay
+    ("clarity.ms", "Microsoft Clarity", "session_replay"),
+    ("hotjar.com", "Hotjar", "session_replay"),
+    ("hotjar.io", "Hotjar", "session_replay"),
+    ("fullstory.com", "FullStory", "session_replay"),
+    ("mouseflow.com", "Mouseflow", "session_replay"),
+    ("logrocket.com", "LogRocket", "session_replay"),
+    ("lr-ingest.io", "LogRocket", "session_replay"),
+    ("smartlook.com", "Smartlook", "session_replay"),
+    ("contentsquare.net", "Contentsquare", "session_replay"),
+    // Social
+    ("connect.facebook.net", "Meta Pixel", "social"),
+    ("facebook.com/tr", "Meta Pixel", "social"),
+    ("platform.twitter.com", "X (Twitter) widgets", "social"),
+    ("platform.linkedin.com", "LinkedIn widgets", "social"),
+    ("addthis.com", "AddThis", "social"),
+    ("sharethis.com", "ShareThis", "social"),
+    // Tag managers
+    ("googletagmanager.com", "Google Tag Manager", "tag_manager"),
+    ("tealiumiq.com", "Tealium", "tag_manager"),
+    ("tags.tiqcdn.com", "Tealium", "tag_manager"),
+    ("assets.adobedtm.com", "Adobe Launch", "tag_manager"),
+    // Customer engagement
+    ("hubspot.com", "HubSpot", "customer_engagement"),
+    ("hs-analytics.net", "HubSpot", "customer_engagement"),
+    ("intercom.io", "Intercom", "customer_engagement"),
+    ("intercomcdn.com", "Intercom", "customer_engagement"),
+    ("drift.com", "Drift", "customer_engagement"),
+    ("optimizely.com", "Optimizely", "customer_engagement"),
+];
+
+/// The listed service for a request URL, matching host suffixes, or host plus first path
+/// segment for entries with a path (`facebook.com/tr`). The most specific entry wins, so
+/// `stats.g.doubleclick.net` is analytics rather than ads.
+fn lookup(url: &url::Url) -> Option<&'static (&'static str, &'static str, &'static str)> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let matches_host = |domain: &str| host == domain || host.ends_with(&format!(".{domain}"));
+    let matching = TRACKERS
+        .iter()
+        .filter(|(pattern, _, _)| match pattern.split_once('/') {
+            Some((domain, path)) => {
+                matches_host(domain)
+                    && url
+                        .path()
+                        .trim_start_matches('/')
+                        .split('/')
+                        .next()
+                        .is_some_and(|p| p == path)
+            }
+            None => matches_host(pattern),
+        });
+    matching.max_by_key(|(pattern, _, _)| pattern.len())
+}
+
+/// The trackers among `entries`, most requests first.
+pub fn detect(entries: &[NetEntry]) -> Vec<Tracker> {
+    let mut found: BTreeMap<&'static str, Tracker> = BTreeMap::new();
+    for e in entries {
+        let Ok(url) = url::Url::parse(&e.request.url) else {
+            continue;
+        };
+        let Some(&(_, name, category)) = lookup(&url) else {
+            continue;
+        };
+        let t = found.entry(name).or_insert_with(|| Tracker {
+            name,
+            category,
+            requests: 0,
+            hosts: Vec::new(),
+        });
+        t.requests += 1;
+        let host = url.host_str().unwrap_or_default().to_string();
+        if !t.hosts.contains(&host) {
+            t.hosts.push(host);
+        }
+    }
+    let mut trackers: Vec<Tracker> = found.into_values().collect();
+    trackers.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.name.cmp(b.name)));
+    trackers
+}
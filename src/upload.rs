@@ -0,0 +1,198 @@
+//! `--upload s3://bucket/prefix` / `gs://bucket/prefix`: push each finished run to object
+//! storage.
+//!
+//! Objects are keyed `<prefix>/<run dir under --out-root>/<file>`. The artifacts go first;
+//! then result.json (or the timeout/blocked report) gets an `uploads` map of file to remote
+//! URL and is uploaded last (after the `--sign` manifest), so anything watching the bucket
+//! can treat its arrival as "run complete".
+//!
+//! Credentials come from the environment the way the cloud SDKs look for them: `AWS_*`
+//! variables, web identity and ECS/EC2 instance roles for S3 (`AWS_ENDPOINT` points at an
+//! S3-compatible store); `GOOGLE_APPLICATION_CREDENTIALS`, gcloud's application default
+//! credentials or the metadata server for GCS.
+
+use anyhow::{Context, Result};
+use object_store::{
+    aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, path::Path as ObjectPath, Attribute,
+    Attributes, ObjectStore, PutPayload,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct Target {
+    scheme: &'static str,
+    bucket: String,
+    prefix: String,
+}
+
+pub fn parse_target(s: &str) -> Result<Target, String> {
+    let url = url::Url::parse(s).map_err(|e| format!("'{s}': {e}"))?;
+    let scheme = match url.scheme() {
+        "s3" => "s3",
+        "gs" => "gs",
+        other => return Err(format!("unsupported scheme '{other}' (use s3:// or gs://)")),
+    };
+    let bucket = url
+        .host_str()
+        .filter(|b| !b.is_empty())
+        .ok_or_else(|| format!("'{s}' has no bucket"))?
+        .to_string();
+    Ok(Target {
+        scheme,
+        bucket,
+        prefix: url.path().trim_matches('/').to_string(),
+    })
+}
+
+/// A connected bucket.
+pub struct Uploader {
+    target: Target,
+    store: Arc<dyn ObjectStore>,
+}
+
+impl Uploader {
+    pub fn new(target: &Target) -> Result<Self> {
+        let store: Arc<dyn ObjectStore> = match target.scheme {
+            "s3" => Arc::new(
+                AmazonS3Builder::from_env()
+                    .with_bucket_name(&target.bucket)
+                    .build()?,
+            ),
+            _ => Arc::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_bucket_name(&target.bucket)
+                    .build()?,
+            ),
+        };
+        Ok(Self {
+            target: target.clone(),
+            store,
+        })
+    }
+
+    /// Upload every file below `run_dir` except `result_json`, then record their URLs in
+    /// `result` and rewrite `result_json` with it. Upload `result_json` itself last, with
+    /// [`Uploader::upload_run_file`].
+    pub async fn upload_artifacts(
+        &self,
+        out_root: &Path,
+        run_dir: &Path,
+        result_json: &Path,
+        result: &mut serde_json::Value,
+    ) -> Result<()> {
+        let name = key_under(out_root, run_dir);
+        let mut files = Vec::new();
+        collect_files(run_dir, &mut files)?;
+        let mut uploads = BTreeMap::new();
+        for path in files.iter().filter(|p| p.as_path() != result_json) {
+            let rel = relative(run_dir, path);
+            let url = self.put(&format!("{name}/{rel}"), path).await?;
+            uploads.insert(rel, url);
+        }
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert("uploads".into(), serde_json::to_value(&uploads)?);
+        }
+        std::fs::write(result_json, serde_json::to_string_pretty(result)?)
+            .with_context(|| format!("writing {}", result_json.display()))?;
+        Ok(())
+    }
+
+    /// Upload one more file of the run: result.json, or the `--sign` manifest.
+    pub async fn upload_run_file(
+        &self,
+        out_root: &Path,
+        run_dir: &Path,
+        path: &Path,
+    ) -> Result<String> {
+        let name = key_under(out_root, run_dir);
+        self.put(&format!("{name}/{}", relative(run_dir, path)), path)
+            .await
+    }
+
+    /// Upload a run's `--archive`, which sits next to its directory.
+    pub async fn upload_archive(&self, out_root: &Path, path: &Path) -> Result<String> {
+        self.put(&key_under(out_root, path), path).await
+    }
+
+    /// Upload `path` to `<prefix>/<rel>`; returns its URL.
+    async fn put(&self, rel: &str, path: &Path) -> Result<String> {
+        let key = ObjectPath::from_iter(
+            self.target
+                .prefix
+                .split('/')
+                .chain(rel.split('/'))
+                .filter(|part| !part.is_empty()),
+        );
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let attributes = Attributes::from_iter([(Attribute::ContentType, content_type(path))]);
+        self.store
+            .put_opts(&key, PutPayload::from(bytes), attributes.into())
+            .await
+            .with_context(|| format!("uploading {}", path.display()))?;
+        Ok(format!(
+            "{}://{}/{}",
+            self.target.scheme, self.target.bucket, key
+        ))
+    }
+}
+
+/// `path` relative to `out_root`, or just its name when it lies elsewhere (`--run-dir`).
+fn key_under(out_root: &Path, path: &Path) -> String {
+    // Run paths are canonical; --out-root may not be.
+    let root = dunce::canonicalize(out_root).unwrap_or_else(|_| out_root.to_path_buf());
+    match path.strip_prefix(&root) {
+        Ok(_) => relative(&root, path),
+        Err(_) => path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    match name.rsplit('.').next().unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "json" | "har" => "application/json",
+        "txt" | "log" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "png" => "image/png",
+        "jpg" => "image/jpeg",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "mhtml" => "multipart/related",
+        "webm" => "video/webm",
+        "epub" => "application/epub+zip",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// `dir`-relative path with forward slashes.
+fn relative(dir: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(dir).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Regular files below `dir`, sorted.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if kind.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Core Web Vitals measured in the capture itself: LCP, FCP and CLS from the
+//! instrumentation script's PerformanceObservers (`window.__ankabot.vitals`). INP needs
+//! real clicks or key presses, which a capture doesn't make, so it is only there when the
+//! page received some.
+//!
+//! These are lab values from one headless load, not field data, but they move the same
+//! way and make a capture double as a performance probe.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WebVitals {
+    /// Largest Contentful Paint, ms since navigation start.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lcp_ms: Option<u64>,
+    /// The LCP element (`img#hero`) or resource URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lcp_element: Option<String>,
+    /// First Contentful Paint, ms since navigation start.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcp_ms: Option<u64>,
+    /// Cumulative Layout Shift: the worst session window.
+    pub cls: f64,
+    /// Interaction to Next Paint: the slowest interaction, or the 98th percentile with 50
+    /// or more.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inp_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Raw {
+    lcp: Option<f64>,
+    #[serde(default)]
+    lcp_element: String,
+    fcp: Option<f64>,
+    #[serde(default)]
+    cls: f64,
+    #[serde(default)]
+    interactions: HashMap<String, f64>,
+}
+
+const COLLECT_JS: &str = "JSON.stringify((window.__ankabot && window.__ankabot.vitals) || null)";
+
+/// Read the vitals recorded so far on the current document.
+pub fn collect(tab: &headless_chrome::Tab) -> Result<WebVitals> {
+    let raw = tab
+        .evaluate(COLLECT_JS, false)?
+        .value
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default();
+    let raw: Raw = serde_json::from_str(&raw)?;
+    let mut durations: Vec<f64> = raw.interactions.into_values().collect();
+    durations.sort_by(|a, b| b.total_cmp(a));
+    // web-vitals skips one worst interaction per 50.
+    let inp = durations.get(durations.len() / 50).copied();
+    Ok(WebVitals {
+        lcp_ms: raw.lcp.map(|v| v as u64),
+        lcp_element: Some(raw.lcp_element).filter(|e| !e.is_empty()),
+        fcp_ms: raw.fcp.map(|v| v as u64),
+        cls: (raw.cls * 10_000.0).round() / 10_000.0,
+        inp_ms: inp.map(|v| v as u64),
+    })
+}
@@ -0,0 +1,230 @@
+//! Monitoring mode (`--watch --interval 15m`): re-render one URL on a schedule and keep
+//! only the runs where the page changed, with every check logged to `watch.jsonl`.
+
+use crate::{capture_one, crawl::slug, finish_run, golden, hash, Cli, WatchCompare};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// One line of `watch.jsonl`.
+#[derive(Serialize, Deserialize)]
+struct Check {
+    at: String,
+    changed: bool,
+    /// Set only when the run was kept.
+    run_dir: Option<String>,
+    text_sha256: Option<String>,
+    screenshot_sha256: Option<String>,
+    /// Word-level similarity of the visible text to the previous kept run.
+    text_similarity: Option<f64>,
+    error: Option<String>,
+}
+
+pub async fn run(args: &Cli) -> Result<i32> {
+    let watch_dir = args
+        .run_dir
+        .clone()
+        .unwrap_or_else(|| args.out_root.join(format!("watch-{}", slug(args.url()))));
+    std::fs::create_dir_all(&watch_dir)?;
+    let log_path = watch_dir.join("watch.jsonl");
+    let mut previous = last_kept(&log_path);
+
+    let mut checks = 0;
+    loop {
+        let ts = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+        let mut run_args = args.clone();
+        run_args.watch = false;
+        run_args.run_dir = Some(watch_dir.join(&ts));
+        // Unchanged runs are deleted before they are uploaded, indexed or announced.
+        let check = match capture_one(&run_args).await {
+            Ok(rec) => {
+                let mut check = check_run(args, &rec.result_json, previous.as_ref())?;
+                if check.run_dir.is_some() {
+                    if let Err(e) = finish_run(&run_args, rec).await {
+                        check.error = Some(format!("{:#}", e));
+                    }
+                }
+                check
+            }
+            Err(e) => Check {
+                at: chrono::Local::now().to_rfc3339(),
+                changed: false,
+                run_dir: None,
+                text_sha256: None,
+                screenshot_sha256: None,
+                text_similarity: None,
+                error: Some(format!("{:#}", e)),
+            },
+        };
+        if check.run_dir.is_some() {
+            previous = Some(check_snapshot(&check));
+        }
+        let mut log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+        writeln!(log, "{}", serde_json::to_string(&check)?)?;
+
+        checks += 1;
+        if args.watch_iterations > 0 && checks >= args.watch_iterations {
+            return Ok(0);
+        }
+        tokio::time::sleep(args.interval).await;
+    }
+}
+
+/// Fingerprint of the last kept run, used as the comparison baseline.
+struct Snapshot {
+    run_dir: PathBuf,
+    text_sha256: Option<String>,
+    screenshot_sha256: Option<String>,
+}
+
+fn check_snapshot(c: &Check) -> Snapshot {
+    Snapshot {
+        run_dir: PathBuf::from(c.run_dir.clone().unwrap_or_default()),
+        text_sha256: c.text_sha256.clone(),
+        screenshot_sha256: c.screenshot_sha256.clone(),
+    }
+}
+
+/// Resume from an existing log: the most recent check whose run was kept.
+fn last_kept(log_path: &Path) -> Option<Snapshot> {
+    let text = std::fs::read_to_string(log_path).ok()?;
+    text.lines()
+        .rev()
+        .filter_map(|l| serde_json::from_str::<Check>(l).ok())
+        .find(|c| c.run_dir.is_some())
+        .map(|c| check_snapshot(&c))
+}
+
+fn check_run(args: &Cli, result_json: &Path, previous: Option<&Snapshot>) -> Result<Check> {
+    let run_dir = result_json
+        .parent()
+        .ok_or_else(|| anyhow!("result.json has no run directory"))?;
+    let result: serde_json::Value = serde_json::from_slice(&std::fs::read(result_json)?)?;
+    let path = |key: &str, fallback: &str| {
+        result
+            .get(key)
+            .or_else(|| result.pointer(fallback))
+            .and_then(|v| v.as_str())
+            .map(PathBuf::from)
+    };
+    let text = path("html_path", "/artifacts/html")
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|h| golden::html_to_text(&h));
+    let text_sha256 = text.as_deref().map(hash::sha256_hex);
+    let screenshot_sha256 =
+        path("screenshot_path", "/artifacts/screenshot").and_then(|p| hash::file_sha256(&p));
+
+    let (changed, text_similarity) = match previous {
+        None => (true, None),
+        Some(prev) => {
+            let text_changed = text_sha256 != prev.text_sha256;
+            let shot_changed = screenshot_sha256 != prev.screenshot_sha256;
+            let changed = match args.watch_compare {
+                WatchCompare::Html => text_changed,
+                WatchCompare::Screenshot => shot_changed,
+                WatchCompare::Both => text_changed || shot_changed,
+            };
+            let sim = match (&text, previous_text(&prev.run_dir)) {
+                (Some(a), Some(b)) => Some(golden::similarity(a, &b)),
+                _ => None,
+            };
+            (changed, sim)
+        }
+    };
+
+    let keep = changed || args.keep_unchanged;
+    if !keep {
+        std::fs::remove_dir_all(run_dir)?;
+    }
+    Ok(Check {
+        at: chrono::Local::now().to_rfc3339(),
+        changed,
+        run_dir: keep.then(|| run_dir.display().to_string()),
+        text_sha256,
+        screenshot_sha256,
+        text_similarity,
+        error: None,
+    })
+}
+
+fn previous_text(run_dir: &Path) -> Option<String> {
+    let result: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(run_dir.join("result.json")).ok()?).ok()?;
+    let html = result
+        .get("html_path")
+        .or_else(|| result.pointer("/artifacts/html"))
+        .and_then(|v| v.as_str())?;
+    Some(golden::html_to_text(&std::fs::read_to_string(html).ok()?))
+}
+
+/// Parse "500ms", "30s", "15m", "2h", "30d" or a bare number of seconds.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid interval '{}'", s))?;
+    let secs = |per: u64| {
+        n.checked_mul(per)
+            .map(Duration::from_secs)
+            .ok_or_else(|| "interval too large".to_string())
+    };
+    match unit {
+        "ms" => Ok(Duration::from_millis(n)),
+        "" | "s" => Ok(Duration::from_secs(n)),
+        "m" => secs(60),
+        "h" => secs(3600),
+        "d" => secs(86400),
+        _ => Err(format!(
+            "invalid interval unit '{}' (use ms, s, m, h or d)",
+            unit
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_units() {
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_interval("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_interval(" 45 "), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_interval("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_interval("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_interval("30d"), Ok(Duration::from_secs(30 * 86400)));
+    }
+
+    #[test]
+    fn bad_intervals_are_rejected() {
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("m").is_err());
+        assert!(parse_interval("-5s").is_err());
+        assert!(parse_interval("1.5h").is_err());
+        let unit = parse_interval("3w").unwrap_err();
+        assert!(unit.contains("'w'"), "{unit}");
+    }
+
+    #[test]
+    fn overflowing_intervals_are_rejected() {
+        assert_eq!(
+            parse_interval("999999999999999999d"),
+            Err("interval too large".to_string())
+        );
+        assert!(parse_interval("999999999999999999h").is_err());
+        assert!(parse_interval("999999999999999999m").is_err());
+        assert_eq!(
+            parse_interval("999999999999999999s"),
+            Ok(Duration::from_secs(999_999_999_999_999_999))
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Page weight: what the page cost to load, from the recorded network entries. Builds on
+//! the network summary's totals with a breakdown by resource type and how well text was
+//! compressed; the largest resources are already in `network_summary.largest`.
+
+use crate::network::{self, NetEntry, NetworkSummary};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Most uncompressed text resources listed.
+const UNCOMPRESSED_LISTED: usize = 10;
+/// Text bodies smaller than this aren't worth compressing.
+const COMPRESSIBLE_MIN_BYTES: f64 = 1024.0;
+/// Resource types whose bodies are text and should go over the wire compressed.
+const TEXT_TYPES: &[&str] = &["Document", "Script", "Stylesheet", "XHR", "Fetch"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PageWeight {
+    pub requests: usize,
+    /// Bytes on the wire, headers included.
+    pub transfer_bytes: u64,
+    /// Body bytes after decompression.
+    pub decoded_bytes: u64,
+    /// Keyed by lower-cased resource type: `script`, `stylesheet`, `image`, `font`, `xhr`, …
+    pub by_type: BTreeMap<String, TypeWeight>,
+    /// Decoded over transferred bytes for text resources fetched from the network;
+    /// 1.0 means nothing was compressed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_compression_ratio: Option<f64>,
+    /// Text resources of 1 KiB or more served without a `Content-Encoding`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uncompressed: Vec<Resource>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TypeWeight {
+    pub requests: usize,
+    pub transfer_bytes: u64,
+    pub decoded_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression_ratio: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Resource {
+    pub url: String,
+    pub resource_type: String,
+    pub transfer_bytes: u64,
+    pub decoded_bytes: u64,
+}
+
+fn resource(e: &NetEntry) -> Resource {
+    Resource {
+        url: e.request.url.clone(),
+        resource_type: e.resource_type.clone(),
+        transfer_bytes: e.encoded_bytes as u64,
+        decoded_bytes: e.decoded_bytes as u64,
+    }
+}
+
+/// Decoded over transferred bytes, to two decimals; `None` when nothing came over the wire.
+fn ratio(decoded: f64, transfer: f64) -> Option<f64> {
+    (transfer > 0.0 && decoded > 0.0).then(|| (decoded / transfer * 100.0).round() / 100.0)
+}
+
+pub fn measure(entries: &[NetEntry], summary: &NetworkSummary) -> PageWeight {
+    let mut by_type: BTreeMap<String, TypeWeight> = BTreeMap::new();
+    // Per type, only network fetches count towards the ratio: cache hits transfer nothing.
+    let mut fetched: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+    let (mut text_decoded, mut text_transfer) = (0.0, 0.0);
+    let mut uncompressed = Vec::new();
+    for e in entries {
+        let key = e.resource_type.to_ascii_lowercase();
+        let t = by_type.entry(key.clone()).or_default();
+        t.requests += 1;
+        t.transfer_bytes += e.encoded_bytes as u64;
+        t.decoded_bytes += e.decoded_bytes as u64;
+        if e.from_cache || e.failed.is_some() || e.encoded_bytes <= 0.0 {
+            continue;
+        }
+        let f = fetched.entry(key).or_default();
+        f.0 += e.decoded_bytes;
+        f.1 += e.encoded_bytes;
+        if !TEXT_TYPES.contains(&e.resource_type.as_str()) {
+            continue;
+        }
+        text_decoded += e.decoded_bytes;
+        text_transfer += e.encoded_bytes;
+        let encoded = e
+            .response
+            .as_ref()
+            .and_then(|r| network::response_header(r, "content-encoding"))
+            .is_some_and(|v| !v.trim().is_empty() && !v.eq_ignore_ascii_case("identity"));
+        if !encoded && e.decoded_bytes >= COMPRESSIBLE_MIN_BYTES {
+            uncompressed.push(resource(e));
+        }
+    }
+    for (key, (decoded, transfer)) in fetched {
+        if let Some(t) = by_type.get_mut(&key) {
+            t.compression_ratio = ratio(decoded, transfer);
+        }
+    }
+    uncompressed.sort_by_key(|r| std::cmp::Reverse(r.decoded_bytes));
+    uncompressed.truncate(UNCOMPRESSED_LISTED);
+
+    PageWeight {
+        requests: summary.total_requests,
+        transfer_bytes: summary.transferred_bytes,
+        decoded_bytes: entries.iter().map(|e| e.decoded_bytes as u64).sum(),
+        by_type,
+        text_compression_ratio: ratio(text_decoded, text_transfer),
+        uncompressed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn entry(kind: &str, transfer: f64, decoded: f64, headers: Value) -> NetEntry {
+        let url = format!("https://example.com/{}", kind.to_ascii_lowercase());
+        NetEntry {
+            request_id: "1".into(),
+            resource_type: kind.into(),
+            frame_id: None,
+            request: serde_json::from_value(json!({
+                "url": url,
+                "method": "GET",
+                "headers": {},
+                "initialPriority": "VeryHigh",
+                "referrerPolicy": "no-referrer",
+            }))
+            .unwrap(),
+            response: Some(
+                serde_json::from_value(json!({
+                    "url": url,
+                    "status": 200,
+                    "statusText": "",
+                    "headers": headers,
+                    "mimeType": "text/plain",
+                    "connectionReused": false,
+                    "connectionId": 1,
+                    "encodedDataLength": transfer,
+                    "securityState": "secure",
+                }))
+                .unwrap(),
+            ),
+            start: 0.0,
+            wall_time: 0.0,
+            end: Some(1.0),
+            encoded_bytes: transfer,
+            decoded_bytes: decoded,
+            from_cache: false,
+            failed: None,
+        }
+    }
+
+    fn gzip(kind: &str, transfer: f64, decoded: f64) -> NetEntry {
+        entry(
+            kind,
+            transfer,
+            decoded,
+            json!({ "Content-Encoding": "gzip" }),
+        )
+    }
+
+    fn measure_all(entries: &[NetEntry]) -> PageWeight {
+        measure(entries, &network::summarize(entries))
+    }
+
+    #[test]
+    fn bytes_are_counted_per_type() {
+        let w = measure_all(&[
+            gzip("Script", 1000.0, 4000.0),
+            gzip("Script", 500.0, 1500.0),
+            entry("Image", 20_000.0, 20_000.0, json!({})),
+            gzip("Stylesheet", 300.0, 900.0),
+        ]);
+        assert_eq!(w.requests, 4);
+        assert_eq!(w.transfer_bytes, 21_800);
+        assert_eq!(w.decoded_bytes, 26_400);
+        let keys: Vec<&str> = w.by_type.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["image", "script", "stylesheet"]);
+        let script = &w.by_type["script"];
+        assert_eq!(
+            (script.requests, script.transfer_bytes, script.decoded_bytes),
+            (2, 1500, 5500)
+        );
+        let image = &w.by_type["image"];
+        assert_eq!(
+            (image.requests, image.transfer_bytes, image.decoded_bytes),
+            (1, 20_000, 20_000)
+        );
+    }
+
+    #[test]
+    fn totals_match_the_network_summary() {
+        let entries = [
+            gzip("Document", 2000.0, 8000.0),
+            gzip("Script", 700.0, 2100.0),
+        ];
+        let summary = network::summarize(&entries);
+        let w = measure(&entries, &summary);
+        assert_eq!(w.requests, summary.total_requests);
+        assert_eq!(w.transfer_bytes, summary.transferred_bytes);
+    }
+
+    #[test]
+    fn compression_ratio_per_type_and_for_text() {
+        let w = measure_all(&[
+            gzip("Script", 1000.0, 4000.0),
+            gzip("Script", 500.0, 1500.0),
+            gzip("Stylesheet", 300.0, 1000.0),
+            entry("Image", 20_000.0, 20_000.0, json!({})),
+        ]);
+        // 5500 / 1500, rounded to two decimals.
+        assert_eq!(w.by_type["script"].compression_ratio, Some(3.67));
+        assert_eq!(w.by_type["stylesheet"].compression_ratio, Some(3.33));
+        assert_eq!(w.by_type["image"].compression_ratio, Some(1.0));
+        // Images aren't text: (5500 + 1000) / (1500 + 300).
+        assert_eq!(w.text_compression_ratio, Some(3.61));
+    }
+
+    #[test]
+    fn cache_hits_and_failures_stay_out_of_the_ratio() {
+        let mut cached = gzip("Script", 10.0, 5000.0);
+        cached.from_cache = true;
+        let mut failed = gzip("Script", 100.0, 9000.0);
+        failed.failed = Some("net::ERR_ABORTED".into());
+        let w = measure_all(&[gzip("Script", 1000.0, 2000.0), cached, failed]);
+        let script = &w.by_type["script"];
+        assert_eq!(script.requests, 3);
+        assert_eq!(script.transfer_bytes, 1110);
+        assert_eq!(script.compression_ratio, Some(2.0));
+        assert_eq!(w.text_compression_ratio, Some(2.0));
+    }
+
+    #[test]
+    fn no_ratio_without_network_bytes() {
+        let mut cached = gzip("Script", 0.0, 5000.0);
+        cached.from_cache = true;
+        let w = measure_all(&[cached]);
+        assert_eq!(w.by_type["script"].compression_ratio, None);
+        assert_eq!(w.text_compression_ratio, None);
+        assert!(measure_all(&[]).by_type.is_empty());
+    }
+
+    #[test]
+    fn large_text_without_content_encoding_is_uncompressed() {
+        let w = measure_all(&[
+            entry("Script", 5000.0, 5000.0, json!({})),
+            entry(
+                "Stylesheet",
+                3000.0,
+                3000.0,
+                json!({ "content-encoding": "identity" }),
+            ),
+            entry("Script", 500.0, 500.0, json!({})),
+            gzip("Document", 1000.0, 4000.0),
+            entry("Image", 50_000.0, 50_000.0, json!({})),
+        ]);
+        let urls: Vec<&str> = w.uncompressed.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/script",
+                "https://example.com/stylesheet"
+            ]
+        );
+    }
+}
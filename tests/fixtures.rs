@@ -0,0 +1,126 @@
+#![cfg(feature = "fixtures")]
+
+use std::process::Command;
+
+#[test]
+fn static_fixture_takes_http_path() {
+    let run_dir = std::env::temp_dir().join("ankabot_fixture_static");
+    let _ = std::fs::remove_dir_all(&run_dir);
+    let output = Command::new(env!("CARGO_BIN_EXE_ankabot"))
+        .args([
+            "--run-dir",
+            run_dir.to_str().unwrap(),
+            "--target-fixture",
+            "static",
+        ])
+        .output()
+        .expect("run ankabot");
+    assert!(output.status.success(), "ankabot failed");
+    let path = std::str::from_utf8(&output.stdout).unwrap().trim();
+    let v: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).expect("json");
+    assert_eq!(v.get("http_status").and_then(|s| s.as_u64()), Some(200));
+    assert_eq!(
+        v.get("requires_javascript").and_then(|s| s.as_bool()),
+        Some(false)
+    );
+}
+
+#[test]
+fn static_fixture_with_pdf_password_renders_pdf() {
+    let run_dir = std::env::temp_dir().join("ankabot_fixture_pdf_password");
+    let _ = std::fs::remove_dir_all(&run_dir);
+    let output = Command::new(env!("CARGO_BIN_EXE_ankabot"))
+        .args([
+            "--run-dir",
+            run_dir.to_str().unwrap(),
+            "--target-fixture",
+            "static",
+            "--pdf-password",
+            "secret",
+        ])
+        .output()
+        .expect("run ankabot");
+    assert!(output.status.success(), "ankabot failed");
+    let path = std::str::from_utf8(&output.stdout).unwrap().trim();
+    let v: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).expect("json");
+    assert!(v.get("pdf_path").and_then(|p| p.as_str()).is_some());
+    assert_eq!(v.get("pdf_encrypted").and_then(|e| e.as_bool()), Some(true));
+}
+
+#[test]
+fn static_fixture_captures_hreflang_alternates() {
+    let run_dir = std::env::temp_dir().join("ankabot_fixture_alternates");
+    let _ = std::fs::remove_dir_all(&run_dir);
+    let output = Command::new(env!("CARGO_BIN_EXE_ankabot"))
+        .args([
+            "--run-dir",
+            run_dir.to_str().unwrap(),
+            "--target-fixture",
+            "static",
+            "--capture-alternates",
+        ])
+        .output()
+        .expect("run ankabot");
+    assert!(output.status.success(), "ankabot failed");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(run_dir.join("alternates.json")).unwrap())
+            .expect("json");
+    let alternates = manifest["alternates"].as_array().unwrap();
+    assert_eq!(alternates.len(), 2);
+    let de = alternates.iter().find(|a| a["hreflang"] == "de").unwrap();
+    assert_eq!(de["exit_code"].as_i64(), Some(0));
+    assert!(run_dir.join("alternates/de/result.json").exists());
+}
+
+/// Run a fixture into a fresh run directory; returns the exit code and the JSON the run
+/// printed the path of.
+fn run_fixture(name: &str, extra: &[&str]) -> (Option<i32>, serde_json::Value) {
+    let run_dir = std::env::temp_dir().join(format!("ankabot_fixture_{name}_{}", extra.len()));
+    let _ = std::fs::remove_dir_all(&run_dir);
+    let output = Command::new(env!("CARGO_BIN_EXE_ankabot"))
+        .args([
+            "--run-dir",
+            run_dir.to_str().unwrap(),
+            "--target-fixture",
+            name,
+        ])
+        .args(extra)
+        .output()
+        .expect("run ankabot");
+    let path = std::str::from_utf8(&output.stdout).unwrap().trim();
+    let v = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).expect("json");
+    (output.status.code(), v)
+}
+
+#[test]
+fn spa_fixture_reports_its_wait_branch() {
+    let (code, v) = run_fixture("spa", &[]);
+    assert_eq!(code, Some(0));
+    assert_eq!(v["requires_javascript"], true);
+    assert_eq!(v["wait_branch"], "ready_state");
+
+    let (code, v) = run_fixture("spa", &["--dom-quiet-ms", "1500"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(v["wait_branch"], "dom_quiet");
+}
+
+#[test]
+fn hang_fixture_writes_a_timeout_report() {
+    let (code, v) = run_fixture("hang", &["--max-wait-ms", "3000", "--on-timeout", "report"]);
+    assert_eq!(code, Some(2));
+    assert_eq!(v["status"], "timeout");
+    assert_eq!(v["deadline_ms"], 3000);
+    assert!(v["artifacts"]["html"].as_str().is_some());
+}
+
+#[test]
+fn challenge_fixture_writes_a_blocked_report() {
+    let (code, v) = run_fixture("challenge", &[]);
+    assert_eq!(code, Some(4));
+    assert_eq!(v["status"], "blocked");
+    assert_eq!(v["vendor"], "cloudflare");
+    assert_eq!(v["challenge"], "js_challenge");
+    assert!(v["artifacts"]["html"].as_str().is_some());
+}